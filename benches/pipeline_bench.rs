@@ -1,5 +1,3 @@
-use std::collections::BTreeMap;
-
 use async_trait::async_trait;
 use bytes::Bytes;
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
@@ -8,9 +6,9 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
 
 use confidential_ml_pipeline::{
-    ActivationDType, ActivationSpec, ForwardOutput, InferenceSchedule, Orchestrator,
-    OrchestratorConfig, OrchestratorMsg, PortSpec, RequestId, ShardManifest, StageConfig,
-    StageEndpoint, StageError, StageExecutor, StageMsg, StageRuntime, StageSpec,
+    ActivationDType, ActivationSpec, ForwardOutput, InferenceSchedule, MicroBatchTiming,
+    Orchestrator, OrchestratorConfig, OrchestratorMsg, PortSpec, RequestId, ShardManifest,
+    StageConfig, StageEndpoint, StageError, StageExecutor, StageMsg, StageRuntime, StageSpec,
 };
 
 // ---------------------------------------------------------------------------
@@ -37,24 +35,22 @@ impl StageExecutor for IdentityExecutor {
 
 fn make_test_manifest(num_stages: usize) -> ShardManifest {
     let stages = (0..num_stages)
-        .map(|i| StageSpec {
-            stage_idx: i,
-            layer_start: i * 4,
-            layer_end: (i + 1) * 4,
-            require_weight_hashes: false,
-            weight_hashes: vec![],
-            expected_measurements: BTreeMap::new(),
-            endpoint: StageEndpoint {
-                control: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 9000 + i * 10),
-                },
-                data_in: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 9001 + i * 10),
-                },
-                data_out: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 9002 + i * 10),
+        .map(|i| {
+            StageSpec::linear(
+                i,
+                i * 4..(i + 1) * 4,
+                StageEndpoint {
+                    control: PortSpec::Tcp {
+                        addr: format!("127.0.0.1:{}", 9000 + i * 10),
+                    },
+                    data_in: PortSpec::Tcp {
+                        addr: format!("127.0.0.1:{}", 9001 + i * 10),
+                    },
+                    data_out: PortSpec::Tcp {
+                        addr: format!("127.0.0.1:{}", 9002 + i * 10),
+                    },
                 },
-            },
+            )
         })
         .collect();
 
@@ -414,6 +410,9 @@ fn bench_protocol_serde(c: &mut Criterion) {
         request_id: 12345678,
         num_micro_batches: 16,
         seq_len: 512,
+        session_id: None,
+        time_budget_ms: None,
+        profile: false,
     };
     let start_req_bytes = start_req.to_bytes().unwrap();
 
@@ -433,6 +432,8 @@ fn bench_protocol_serde(c: &mut Criterion) {
 
     let request_done = StageMsg::RequestDone {
         request_id: 12345678,
+        timings: vec![MicroBatchTiming::default(); 4],
+        layers: vec![],
     };
     let request_done_bytes = request_done.to_bytes().unwrap();
 
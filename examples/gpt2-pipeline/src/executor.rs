@@ -1,5 +1,5 @@
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -7,7 +7,9 @@ use candle_core::{Device, Tensor};
 use confidential_ml_transport::{DType, OwnedTensor};
 use tracing::info;
 
-use confidential_ml_pipeline::{ForwardOutput, RequestId, StageError, StageExecutor, StageSpec};
+use confidential_ml_pipeline::{
+    ForwardOutput, RequestId, SessionId, StageError, StageExecutor, StageSpec,
+};
 
 use crate::model::{Gpt2Config, Gpt2Shard};
 
@@ -15,7 +17,7 @@ use crate::model::{Gpt2Config, Gpt2Shard};
 fn owned_to_candle_u32(t: &OwnedTensor, device: &Device) -> Result<Tensor, StageError> {
     let num_elems: usize = t.shape.iter().map(|&d| d as usize).product();
     if t.data.len() != num_elems * 4 {
-        return Err(StageError::ForwardFailed {
+        return Err(StageError::InvalidInput {
             request_id: 0,
             micro_batch: 0,
             reason: format!(
@@ -42,7 +44,7 @@ fn owned_to_candle_u32(t: &OwnedTensor, device: &Device) -> Result<Tensor, Stage
 fn owned_to_candle_f32(t: &OwnedTensor, device: &Device) -> Result<Tensor, StageError> {
     let num_elems: usize = t.shape.iter().map(|&d| d as usize).product();
     if t.data.len() != num_elems * 4 {
-        return Err(StageError::ForwardFailed {
+        return Err(StageError::InvalidInput {
             request_id: 0,
             micro_batch: 0,
             reason: format!(
@@ -97,6 +99,9 @@ pub struct Gpt2StageExecutor {
     cfg: Option<Gpt2Config>,
     is_first: bool,
     is_last: bool,
+    /// The session that owns the shard's KV cache. The shard holds a single
+    /// cache, so only one session can be open at a time.
+    active_session: Mutex<Option<SessionId>>,
 }
 
 impl Gpt2StageExecutor {
@@ -107,8 +112,19 @@ impl Gpt2StageExecutor {
             cfg: None,
             is_first: false,
             is_last: false,
+            active_session: Mutex::new(None),
         }
     }
+
+    fn shard(&self, request_id: RequestId, micro_batch: u32) -> Result<&Gpt2Shard, StageError> {
+        self.shard
+            .as_deref()
+            .ok_or_else(|| StageError::ForwardFailed {
+                request_id,
+                micro_batch,
+                reason: "shard not initialized".to_string(),
+            })
+    }
 }
 
 #[async_trait]
@@ -150,37 +166,71 @@ impl StageExecutor for Gpt2StageExecutor {
         Ok(())
     }
 
+    /// Stateless request: runs on a fresh KV cache.
     async fn forward(
         &self,
         request_id: RequestId,
         micro_batch: u32,
         inputs: Vec<OwnedTensor>,
     ) -> Result<ForwardOutput, StageError> {
-        let shard = self.shard.as_ref().ok_or_else(|| StageError::ForwardFailed {
-            request_id,
-            micro_batch,
-            reason: "shard not initialized".to_string(),
-        })?;
+        let shard = self.shard(request_id, micro_batch)?;
+        if self.active_session.lock().unwrap().is_some() {
+            return Err(StageError::ForwardFailed {
+                request_id,
+                micro_batch,
+                reason: "KV cache is held by an open session".to_string(),
+            });
+        }
+        shard.clear_cache();
+        let result = self.run_forward(shard, &inputs, request_id, micro_batch);
+        shard.clear_cache();
+        result
+    }
 
-        let input_tensor = inputs.first().ok_or_else(|| StageError::ForwardFailed {
-            request_id,
-            micro_batch,
-            reason: "no input tensor".to_string(),
+    async fn open_session(&self, session_id: SessionId) -> Result<(), StageError> {
+        let shard = self.shard.as_ref().ok_or_else(|| StageError::SessionFailed {
+            session_id,
+            reason: "shard not initialized".to_string(),
         })?;
+        let mut active = self.active_session.lock().unwrap();
+        if let Some(other) = *active {
+            return Err(StageError::SessionFailed {
+                session_id,
+                reason: format!("session {other} already holds the KV cache"),
+            });
+        }
+        shard.clear_cache();
+        *active = Some(session_id);
+        Ok(())
+    }
 
-        // Check for cache-clear sentinel: a U32 tensor with shape [0] signals new prompt.
-        if input_tensor.dtype == DType::U32 && input_tensor.shape == [0] {
-            shard.clear_cache();
-            // The actual input follows as the second tensor.
-            let actual_input = inputs.get(1).ok_or_else(|| StageError::ForwardFailed {
-                request_id,
-                micro_batch,
-                reason: "cache-clear sentinel without actual input".to_string(),
-            })?;
-            return self.run_forward(shard, actual_input, request_id, micro_batch);
+    /// Session request: the first call carries the prompt, later calls only
+    /// the new token; the KV cache holds the history.
+    async fn forward_in_session(
+        &self,
+        session_id: SessionId,
+        request_id: RequestId,
+        micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        let shard = self.shard(request_id, micro_batch)?;
+        if *self.active_session.lock().unwrap() != Some(session_id) {
+            return Err(StageError::SessionFailed {
+                session_id,
+                reason: "session does not hold the KV cache".to_string(),
+            });
         }
+        self.run_forward(shard, &inputs, request_id, micro_batch)
+    }
 
-        self.run_forward(shard, input_tensor, request_id, micro_batch)
+    async fn close_session(&self, session_id: SessionId) {
+        let mut active = self.active_session.lock().unwrap();
+        if *active == Some(session_id) {
+            if let Some(shard) = self.shard.as_ref() {
+                shard.clear_cache();
+            }
+            *active = None;
+        }
     }
 }
 
@@ -188,10 +238,16 @@ impl Gpt2StageExecutor {
     fn run_forward(
         &self,
         shard: &Gpt2Shard,
-        input_tensor: &OwnedTensor,
+        inputs: &[OwnedTensor],
         request_id: RequestId,
         micro_batch: u32,
     ) -> Result<ForwardOutput, StageError> {
+        let input_tensor = inputs.first().ok_or_else(|| StageError::InvalidInput {
+            request_id,
+            micro_batch,
+            reason: "no input tensor".to_string(),
+        })?;
+
         let device = Device::Cpu;
         let candle_input = if self.is_first {
             owned_to_candle_u32(input_tensor, &device)?
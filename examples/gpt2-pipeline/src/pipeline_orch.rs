@@ -1,11 +1,9 @@
-use std::time::Instant;
-
 use bytes::Bytes;
 use clap::Parser;
 use confidential_ml_transport::{DType, OwnedTensor};
 use tracing::info;
 
-use confidential_ml_pipeline::OrchestratorConfig;
+use confidential_ml_pipeline::{GenerationRequest, GreedySampler, OrchestratorConfig, TokenSampler};
 use confidential_ml_pipeline::ShardManifest;
 
 #[cfg(feature = "tcp-mock")]
@@ -69,16 +67,6 @@ struct Args {
 
 }
 
-/// Build a cache-clear sentinel tensor (U32 with shape [0]).
-fn cache_clear_sentinel() -> OwnedTensor {
-    OwnedTensor {
-        name: "cache_clear".to_string(),
-        dtype: DType::U32,
-        shape: vec![0],
-        data: Bytes::new(),
-    }
-}
-
 fn encode_token_ids(token_ids: &[u32]) -> OwnedTensor {
     let seq_len = token_ids.len();
     let data: Vec<u8> = token_ids.iter().flat_map(|&id| id.to_le_bytes()).collect();
@@ -90,20 +78,6 @@ fn encode_token_ids(token_ids: &[u32]) -> OwnedTensor {
     }
 }
 
-fn decode_logits(tensor: &OwnedTensor) -> u32 {
-    let values: Vec<f32> = tensor
-        .data
-        .chunks_exact(4)
-        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
-        .collect();
-    let (best_idx, _) = values
-        .iter()
-        .enumerate()
-        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-        .unwrap_or((0, &0.0));
-    best_idx as u32
-}
-
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt()
@@ -131,7 +105,7 @@ async fn main() -> anyhow::Result<()> {
     let encoding = tokenizer
         .encode(args.text.as_str(), false)
         .map_err(|e| anyhow::anyhow!("tokenization failed: {e}"))?;
-    let mut token_ids: Vec<u32> = encoding.get_ids().to_vec();
+    let token_ids: Vec<u32> = encoding.get_ids().to_vec();
 
     info!(prompt_tokens = token_ids.len(), "prompt tokenized");
 
@@ -244,53 +218,45 @@ async fn main() -> anyhow::Result<()> {
 
     print!("{}", args.text);
 
-    let mut latencies_ms: Vec<f64> = Vec::new();
-
-    for step in 0..args.max_tokens {
-        let t0 = Instant::now();
-
-        let input = if step == 0 {
-            // First step: clear cache + send full prompt
-            vec![vec![cache_clear_sentinel(), encode_token_ids(&token_ids)]]
-        } else {
-            // Subsequent steps: send only the new token (KV-cache handles history)
-            let new_token = *token_ids.last().unwrap();
-            vec![vec![encode_token_ids(&[new_token])]]
-        };
-
-        let seq_len = token_ids.len();
-        let result = orch.infer(input, seq_len as u32).await?;
-
-        let elapsed = t0.elapsed();
-        let ms = elapsed.as_secs_f64() * 1000.0;
-        latencies_ms.push(ms);
-
-        let output_tensors = &result.outputs[0];
-        let logits = &output_tensors[0];
-        let next_token = decode_logits(logits);
-
-        let decoded = tokenizer
-            .decode(&[next_token], false)
-            .map_err(|e| anyhow::anyhow!("decode failed: {e}"))?;
+    // Greedy decoding, printing each token as it is sampled. `generate` runs
+    // the steps in one session so every stage reuses its KV cache: after the
+    // prompt, only the new token is sent.
+    let mut greedy = GreedySampler::default();
+    let mut step = 0usize;
+    let mut sampler = |outputs: &[OwnedTensor]| {
+        let next_token = greedy.sample(outputs)?;
+        let decoded = tokenizer.decode(&[next_token], false).unwrap_or_default();
 
         print!("{decoded}");
         use std::io::Write;
-        std::io::stdout().flush()?;
-
-        token_ids.push(next_token);
+        let _ = std::io::stdout().flush();
 
         info!(
             step,
             next_token,
             decoded = decoded.as_str(),
-            total_tokens = token_ids.len(),
-            latency_ms = format!("{ms:.1}"),
             "generated token"
         );
-    }
+        step += 1;
+        Ok(next_token)
+    };
+
+    let request = GenerationRequest {
+        initial_input: vec![encode_token_ids(&token_ids)],
+        prompt_len: token_ids.len() as u32,
+        max_tokens: args.max_tokens as u32,
+        stop_token_ids: Vec::new(),
+    };
+    let output = orch.generate(request, &mut sampler).await?;
 
     println!();
 
+    let latencies_ms: Vec<f64> = output
+        .token_latencies
+        .iter()
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .collect();
+
     // Print latency summary — percentiles over generation tokens only (excluding prompt).
     if !latencies_ms.is_empty() {
         let prompt_ms = latencies_ms[0];
@@ -1,5 +1,3 @@
-use std::collections::BTreeMap;
-
 use async_trait::async_trait;
 use bytes::Bytes;
 use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
@@ -58,24 +56,22 @@ impl StageExecutor for DoubleExecutor {
 fn make_manifest(num_stages: usize) -> ShardManifest {
     let layers_per_stage = 4;
     let stages = (0..num_stages)
-        .map(|i| StageSpec {
-            stage_idx: i,
-            layer_start: i * layers_per_stage,
-            layer_end: (i + 1) * layers_per_stage,
-            weight_hashes: vec![],
-            require_weight_hashes: false,
-            expected_measurements: BTreeMap::new(),
-            endpoint: StageEndpoint {
-                control: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 10000 + i * 10),
-                },
-                data_in: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 10001 + i * 10),
-                },
-                data_out: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 10002 + i * 10),
+        .map(|i| {
+            StageSpec::linear(
+                i,
+                i * layers_per_stage..(i + 1) * layers_per_stage,
+                StageEndpoint {
+                    control: PortSpec::Tcp {
+                        addr: format!("127.0.0.1:{}", 10000 + i * 10),
+                    },
+                    data_in: PortSpec::Tcp {
+                        addr: format!("127.0.0.1:{}", 10001 + i * 10),
+                    },
+                    data_out: PortSpec::Tcp {
+                        addr: format!("127.0.0.1:{}", 10002 + i * 10),
+                    },
                 },
-            },
+            )
         })
         .collect();
 
@@ -0,0 +1,352 @@
+use std::time::Duration;
+
+use bytes::{Bytes, BytesMut};
+use confidential_ml_transport::OwnedTensor;
+
+use crate::error::BatchError;
+use crate::scheduler::ScheduleKind;
+use crate::simulator::{CostModel, SimulationReport};
+
+/// Chooses how many micro-batches to split a batch into.
+///
+/// Every candidate count is simulated with [`CostModel::simulate`] on costs
+/// scaled down from the whole-batch `costs`, and the fastest one that fits
+/// `memory_cap` wins.
+#[derive(Debug, Clone)]
+pub struct MicroBatchPlanner {
+    /// Costs of running the whole batch as one micro-batch: each stage's
+    /// forward time and the activation bytes of the entire batch.
+    /// Micro-batches are charged in proportion to their rows.
+    pub costs: CostModel,
+    /// Fixed cost per micro-batch and stage on top of its share of the
+    /// forward time, e.g. dispatch and framing (default: zero).
+    pub overhead: Duration,
+    /// Activation bytes one stage may buffer at once.
+    pub memory_cap: usize,
+    /// The stages' `StageConfig::prefetch_depth` (default: 1). A stage
+    /// buffers up to `2 * prefetch_depth + 2` micro-batches: queued inputs
+    /// and outputs, plus the one being forwarded and its output.
+    pub prefetch_depth: usize,
+    /// Largest micro-batch count considered (default: 64).
+    pub max_micro_batches: u32,
+    /// Schedule to simulate (default: fill-drain).
+    pub schedule: ScheduleKind,
+}
+
+/// Outcome of [`MicroBatchPlanner::plan`].
+#[derive(Debug, Clone)]
+pub struct MicroBatchPlan {
+    pub num_micro_batches: u32,
+    /// Rows in the largest micro-batch.
+    pub rows_per_micro_batch: usize,
+    /// Predicted timeline of the request.
+    pub predicted: SimulationReport,
+}
+
+impl MicroBatchPlanner {
+    /// Planner with default overhead, prefetch depth, count limit and schedule.
+    pub fn new(costs: CostModel, memory_cap: usize) -> Self {
+        Self {
+            costs,
+            overhead: Duration::ZERO,
+            memory_cap,
+            prefetch_depth: 1,
+            max_micro_batches: 64,
+            schedule: ScheduleKind::FillDrain,
+        }
+    }
+
+    /// Pick the micro-batch count for a batch of `rows` rows that minimises
+    /// the predicted makespan. Ties go to the smaller count.
+    ///
+    /// Returns `BatchError::MemoryCapTooSmall` if even one-row micro-batches
+    /// exceed `memory_cap`.
+    pub fn plan(&self, rows: usize) -> std::result::Result<MicroBatchPlan, BatchError> {
+        if rows == 0 {
+            return Err(BatchError::EmptyBatch);
+        }
+        let buffered = 2 * self.prefetch_depth.max(1) + 2;
+        let bytes_for =
+            |rows_per: usize| (self.costs.activation_bytes * rows_per).div_ceil(rows) * buffered;
+
+        let max = rows.min(self.max_micro_batches.max(1) as usize);
+        let mut best: Option<MicroBatchPlan> = None;
+        for count in 1..=max {
+            let rows_per = rows.div_ceil(count);
+            if bytes_for(rows_per) > self.memory_cap {
+                continue;
+            }
+            let costs = CostModel {
+                forward: self
+                    .costs
+                    .forward
+                    .iter()
+                    .map(|f| f.mul_f64(rows_per as f64 / rows as f64) + self.overhead)
+                    .collect(),
+                activation_bytes: (self.costs.activation_bytes * rows_per).div_ceil(rows),
+                ..self.costs.clone()
+            };
+            let predicted = costs.simulate(&self.schedule, count as u32)?;
+            if best
+                .as_ref()
+                .is_none_or(|b| predicted.makespan < b.predicted.makespan)
+            {
+                best = Some(MicroBatchPlan {
+                    num_micro_batches: count as u32,
+                    rows_per_micro_batch: rows_per,
+                    predicted,
+                });
+            }
+        }
+        best.ok_or(BatchError::MemoryCapTooSmall {
+            cap: self.memory_cap,
+            needed: bytes_for(1),
+        })
+    }
+
+    /// Plan a count for `batch` and split it accordingly.
+    pub fn split(
+        &self,
+        batch: &[OwnedTensor],
+    ) -> std::result::Result<(MicroBatchPlan, Vec<Vec<OwnedTensor>>), BatchError> {
+        let plan = self.plan(batch_rows(batch)?)?;
+        let micro_batches = split_batch(batch, plan.num_micro_batches)?;
+        Ok((plan, micro_batches))
+    }
+}
+
+/// Split tensors along dimension 0 into `num_micro_batches` micro-batches.
+///
+/// All tensors must have the same number of rows. Rows are spread as
+/// evenly as possible, earlier micro-batches taking the remainder; if there
+/// are fewer rows than micro-batches, there is one micro-batch per row.
+/// The slices share the input's buffers.
+pub fn split_batch(
+    batch: &[OwnedTensor],
+    num_micro_batches: u32,
+) -> std::result::Result<Vec<Vec<OwnedTensor>>, BatchError> {
+    let rows = batch_rows(batch)?;
+    let count = (num_micro_batches.max(1) as usize).min(rows);
+    let mut micro_batches = vec![Vec::with_capacity(batch.len()); count];
+    for tensor in batch {
+        let row_bytes = row_bytes(tensor)?;
+        let mut start = 0;
+        for (i, micro_batch) in micro_batches.iter_mut().enumerate() {
+            let len = rows / count + usize::from(i < rows % count);
+            let mut shape = tensor.shape.clone();
+            shape[0] = len as u32;
+            micro_batch.push(OwnedTensor {
+                name: tensor.name.clone(),
+                dtype: tensor.dtype,
+                shape,
+                data: tensor
+                    .data
+                    .slice(start * row_bytes..(start + len) * row_bytes),
+            });
+            start += len;
+        }
+    }
+    Ok(micro_batches)
+}
+
+/// Concatenate per-micro-batch outputs along dimension 0 into one tensor
+/// per output name, in the order the first micro-batch lists them.
+///
+/// Every micro-batch must produce the same names, each with the same dtype
+/// and the same shape apart from dimension 0.
+pub fn merge_outputs(
+    outputs: Vec<Vec<OwnedTensor>>,
+) -> std::result::Result<Vec<OwnedTensor>, BatchError> {
+    let Some(first) = outputs.first() else {
+        return Ok(Vec::new());
+    };
+    let mut merged = Vec::with_capacity(first.len());
+    for (idx, head) in first.iter().enumerate() {
+        let mismatch = |reason: String| BatchError::ShapeMismatch {
+            name: head.name.clone(),
+            reason,
+        };
+        if head.shape.is_empty() {
+            return Err(mismatch("scalar tensors cannot be merged".into()));
+        }
+        let mut rows: u32 = 0;
+        let mut data = BytesMut::new();
+        for (mb, tensors) in outputs.iter().enumerate() {
+            let tensor = match tensors.get(idx) {
+                Some(t) if t.name == head.name => t,
+                _ => tensors
+                    .iter()
+                    .find(|t| t.name == head.name)
+                    .ok_or_else(|| mismatch(format!("missing from micro-batch {mb}")))?,
+            };
+            if tensor.dtype != head.dtype || tensor.shape.get(1..) != head.shape.get(1..) {
+                return Err(mismatch(format!(
+                    "micro-batch {mb} has {:?} {:?}, expected {:?} [_, {:?}]",
+                    tensor.dtype,
+                    tensor.shape,
+                    head.dtype,
+                    &head.shape[1..]
+                )));
+            }
+            rows = rows
+                .checked_add(tensor.shape[0])
+                .ok_or_else(|| mismatch("too many rows".into()))?;
+            data.extend_from_slice(&tensor.data);
+        }
+        let mut shape = head.shape.clone();
+        shape[0] = rows;
+        merged.push(OwnedTensor {
+            name: head.name.clone(),
+            dtype: head.dtype,
+            shape,
+            data: Bytes::from(data),
+        });
+    }
+    Ok(merged)
+}
+
+/// Rows shared by every tensor in `batch`.
+fn batch_rows(batch: &[OwnedTensor]) -> std::result::Result<usize, BatchError> {
+    let mut rows = None;
+    for tensor in batch {
+        let r = *tensor
+            .shape
+            .first()
+            .ok_or_else(|| BatchError::ShapeMismatch {
+                name: tensor.name.clone(),
+                reason: "scalar tensors cannot be split".into(),
+            })? as usize;
+        match rows {
+            None => rows = Some(r),
+            Some(rows) if rows != r => {
+                return Err(BatchError::ShapeMismatch {
+                    name: tensor.name.clone(),
+                    reason: format!("has {r} rows, expected {rows}"),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    match rows {
+        Some(rows) if rows > 0 => Ok(rows),
+        _ => Err(BatchError::EmptyBatch),
+    }
+}
+
+/// Bytes per row of dimension 0, checked against the tensor's data length.
+fn row_bytes(tensor: &OwnedTensor) -> std::result::Result<usize, BatchError> {
+    let elements: usize = tensor.shape.iter().map(|&d| d as usize).product();
+    let expected = elements * tensor.dtype.element_size();
+    if tensor.data.len() != expected {
+        return Err(BatchError::ShapeMismatch {
+            name: tensor.name.clone(),
+            reason: format!(
+                "shape {:?} needs {expected} bytes, data has {}",
+                tensor.shape,
+                tensor.data.len()
+            ),
+        });
+    }
+    Ok(expected / tensor.shape[0].max(1) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::LinkCost;
+    use confidential_ml_transport::DType;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    fn tensor(name: &str, rows: u32, cols: u32) -> OwnedTensor {
+        let data: Vec<u8> = (0..rows * cols * 4).map(|b| b as u8).collect();
+        OwnedTensor {
+            name: name.into(),
+            dtype: DType::F32,
+            shape: vec![rows, cols],
+            data: Bytes::from(data),
+        }
+    }
+
+    fn planner(memory_cap: usize) -> MicroBatchPlanner {
+        let link = LinkCost {
+            latency: MS,
+            bytes_per_sec: f64::INFINITY,
+        };
+        let mut planner =
+            MicroBatchPlanner::new(CostModel::new(vec![40 * MS; 4], link, 4096), memory_cap);
+        planner.overhead = MS;
+        planner
+    }
+
+    #[test]
+    fn split_spreads_rows_and_merge_restores_batch() {
+        let batch = vec![tensor("x", 5, 3), tensor("mask", 5, 1)];
+        let parts = split_batch(&batch, 2).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0][0].shape, vec![3, 3]);
+        assert_eq!(parts[1][0].shape, vec![2, 3]);
+        assert_eq!(parts[1][1].shape, vec![2, 1]);
+
+        let merged = merge_outputs(parts).unwrap();
+        assert_eq!(merged.len(), 2);
+        for (m, b) in merged.iter().zip(&batch) {
+            assert_eq!(m.name, b.name);
+            assert_eq!(m.shape, b.shape);
+            assert_eq!(m.data, b.data);
+        }
+    }
+
+    #[test]
+    fn split_caps_count_at_rows() {
+        let parts = split_batch(&[tensor("x", 2, 2)], 8).unwrap();
+        assert_eq!(parts.len(), 2);
+    }
+
+    #[test]
+    fn split_rejects_inconsistent_batches() {
+        assert!(matches!(
+            split_batch(&[tensor("x", 4, 2), tensor("y", 3, 2)], 2),
+            Err(BatchError::ShapeMismatch { .. })
+        ));
+        let mut short = tensor("x", 4, 2);
+        short.data = short.data.slice(..8);
+        assert!(matches!(
+            split_batch(&[short], 2),
+            Err(BatchError::ShapeMismatch { .. })
+        ));
+        assert!(matches!(split_batch(&[], 2), Err(BatchError::EmptyBatch)));
+    }
+
+    #[test]
+    fn merge_rejects_mismatched_outputs() {
+        let outputs = vec![vec![tensor("y", 1, 2)], vec![tensor("y", 1, 3)]];
+        assert!(merge_outputs(outputs).is_err());
+        let outputs = vec![vec![tensor("y", 1, 2)], vec![tensor("z", 1, 2)]];
+        assert!(merge_outputs(outputs).is_err());
+    }
+
+    #[test]
+    fn plan_trades_bubble_against_overhead() {
+        let plan = planner(usize::MAX).plan(32).unwrap();
+        // One micro-batch leaves 3 of 4 stages idle; one per row pays the
+        // per-micro-batch overhead 32 times.
+        assert!(plan.num_micro_batches > 1 && plan.num_micro_batches < 32);
+        let single = planner(usize::MAX)
+            .costs
+            .simulate(&ScheduleKind::FillDrain, 1)
+            .unwrap();
+        assert!(plan.predicted.makespan < single.makespan);
+    }
+
+    #[test]
+    fn plan_respects_memory_cap() {
+        // 4096 bytes over 32 rows is 128 bytes per row, 4 buffers per stage.
+        let plan = planner(128 * 4 * 2).plan(32).unwrap();
+        assert!(plan.rows_per_micro_batch <= 2);
+        assert!(matches!(
+            planner(100).plan(32),
+            Err(BatchError::MemoryCapTooSmall { needed: 512, .. })
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::protocol::{DataError, ErrorCode, VersionRange};
+
 /// Errors arising from manifest parsing and validation.
 #[derive(Debug, thiserror::Error)]
 pub enum ManifestError {
@@ -23,6 +25,33 @@ pub enum ManifestError {
     WrongStageIndex { stage_idx: usize, actual: usize },
     #[error("stage {stage_idx} requires weight hashes but none were declared")]
     MissingRequiredWeightHashes { stage_idx: usize },
+    #[error("stage {stage_idx} links to unknown stage {linked}")]
+    UnknownLinkedStage { stage_idx: usize, linked: usize },
+    #[error("link {from} -> {to} is declared twice")]
+    DuplicateLink { from: usize, to: usize },
+    #[error("link {from} -> {to} must be listed as downstream of {from} and upstream of {to}")]
+    AsymmetricLink { from: usize, to: usize },
+    #[error("stage {stage_idx} is on a cycle")]
+    Cycle { stage_idx: usize },
+    #[error("graph must have exactly one entry stage (no upstream), found {0}")]
+    EntryStageCount(usize),
+    #[error("graph must have exactly one exit stage (no downstream), found {0}")]
+    ExitStageCount(usize),
+    #[error("stage {stage_idx} has an empty pass-through tensor name")]
+    EmptyPassThroughName { stage_idx: usize },
+    #[error("stage {stage_idx} lists pass-through tensor {name:?} twice")]
+    DuplicatePassThrough { stage_idx: usize, name: String },
+    #[error("stage {stage_idx} has an invalid tensor contract: {reason}")]
+    InvalidTensorSpec { stage_idx: usize, reason: String },
+    #[error("contract mismatch on link {from} -> {to} for tensor {name:?}: {reason}")]
+    ContractMismatch {
+        from: usize,
+        to: usize,
+        name: String,
+        reason: String,
+    },
+    #[error("stage {stage_idx} input {name:?} is not produced by any upstream stage")]
+    UnproducedInput { stage_idx: usize, name: String },
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 }
@@ -34,6 +63,66 @@ pub enum SchedulerError {
     ZeroStages,
     #[error("zero micro-batches")]
     ZeroMicroBatches,
+    #[error("zero layer chunks per stage")]
+    ZeroChunks,
+    #[error("invalid schedule for stage {stage_idx} at step {step}: {reason}")]
+    InvalidSchedule {
+        stage_idx: usize,
+        step: usize,
+        reason: String,
+    },
+    #[error("schedule not supported by the stage runtime: {0}")]
+    Unsupported(String),
+    #[error("invalid cost model: {0}")]
+    InvalidCostModel(String),
+}
+
+/// Errors from splitting a batch into micro-batches and merging outputs.
+#[derive(Debug, thiserror::Error)]
+pub enum BatchError {
+    #[error("batch has no rows")]
+    EmptyBatch,
+    #[error("tensor {name}: {reason}")]
+    ShapeMismatch { name: String, reason: String },
+    #[error("memory cap of {cap} bytes is below the {needed} bytes one-row micro-batches need")]
+    MemoryCapTooSmall { cap: usize, needed: usize },
+    #[error("scheduler error: {0}")]
+    Scheduler(#[from] SchedulerError),
+}
+
+/// Errors from partitioning layers into stages.
+#[derive(Debug, thiserror::Error)]
+pub enum PartitionError {
+    #[error("no layers to partition")]
+    NoLayers,
+    #[error("no stages to partition into")]
+    NoStages,
+    #[error("{stages} stages for only {layers} layers")]
+    TooManyStages { stages: usize, layers: usize },
+    #[error("no split into {stages} stages fits the memory limits")]
+    Infeasible { stages: usize },
+    #[error("{stages} stages but {endpoints} endpoints")]
+    EndpointCountMismatch { stages: usize, endpoints: usize },
+    #[error("manifest error: {0}")]
+    Manifest(#[from] ManifestError),
+}
+
+/// Errors from building, reading or writing a model profile.
+#[derive(Debug, thiserror::Error)]
+pub enum ProfileError {
+    #[error("samples from {actual} stages, manifest has {expected}")]
+    StageCountMismatch { expected: usize, actual: usize },
+    #[error("stage {stage_idx} reported layer {layer}, which it does not own")]
+    UnknownLayer { stage_idx: usize, layer: usize },
+    #[error("no samples for layer {0}")]
+    MissingLayer(usize),
+    /// The stage's executor does not implement `forward_profiled`.
+    #[error("stage {stage_idx} reported no per-layer samples")]
+    NoLayerSamples { stage_idx: usize },
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
 }
 
 /// Errors from a pipeline stage.
@@ -47,6 +136,32 @@ pub enum StageError {
         micro_batch: u32,
         reason: String,
     },
+    #[error("resources exhausted for request {request_id}, micro-batch {micro_batch}: {reason}")]
+    ResourceExhausted {
+        request_id: u64,
+        micro_batch: u32,
+        reason: String,
+    },
+    #[error("invalid input for request {request_id}, micro-batch {micro_batch}: {reason}")]
+    InvalidInput {
+        request_id: u64,
+        micro_batch: u32,
+        reason: String,
+    },
+    #[error("weight hash mismatch: {0}")]
+    WeightHashMismatch(String),
+    #[error("micro-batch {micro_batch} is missing pass-through tensor {name:?}")]
+    MissingPassThrough { micro_batch: u32, name: String },
+    #[error(
+        "executor output for micro-batch {micro_batch} already has pass-through tensor {name:?}"
+    )]
+    PassThroughConflict { micro_batch: u32, name: String },
+    #[error("input for micro-batch {micro_batch} breaks the stage contract: {reason}")]
+    InputContract { micro_batch: u32, reason: String },
+    #[error("output for micro-batch {micro_batch} breaks the stage contract: {reason}")]
+    OutputContract { micro_batch: u32, reason: String },
+    #[error("session {session_id} failed: {reason}")]
+    SessionFailed { session_id: u64, reason: String },
     #[error("unexpected control message: {0}")]
     UnexpectedMessage(String),
     #[error("transport error: {0}")]
@@ -57,6 +172,38 @@ pub enum StageError {
     Protocol(String),
 }
 
+impl StageError {
+    /// Code reported to the orchestrator for a request that failed with
+    /// this error.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            StageError::ForwardFailed { .. } => ErrorCode::Forward,
+            StageError::ResourceExhausted { .. } => ErrorCode::ResourceExhausted,
+            StageError::InvalidInput { .. } => ErrorCode::InvalidInput,
+            StageError::WeightHashMismatch(_) => ErrorCode::WeightHash,
+            StageError::MissingPassThrough { .. }
+            | StageError::PassThroughConflict { .. }
+            | StageError::InputContract { .. }
+            | StageError::OutputContract { .. } => ErrorCode::Contract,
+            StageError::SessionFailed { .. } => ErrorCode::Session,
+            StageError::Transport(_) | StageError::ChannelClosed => ErrorCode::Transport,
+            StageError::UnexpectedMessage(_) | StageError::Protocol(_) => ErrorCode::Protocol,
+            StageError::InitFailed(_) => ErrorCode::Internal,
+        }
+    }
+
+    /// The executor's own reason for a failed forward pass, if this error
+    /// carries one.
+    pub fn details(&self) -> Option<&str> {
+        match self {
+            StageError::ForwardFailed { reason, .. }
+            | StageError::ResourceExhausted { reason, .. }
+            | StageError::InvalidInput { reason, .. } => Some(reason),
+            _ => None,
+        }
+    }
+}
+
 /// Top-level pipeline error.
 #[derive(Debug, thiserror::Error)]
 pub enum PipelineError {
@@ -64,14 +211,33 @@ pub enum PipelineError {
     Manifest(#[from] ManifestError),
     #[error("scheduler error: {0}")]
     Scheduler(#[from] SchedulerError),
+    #[error("batch error: {0}")]
+    Batch(#[from] BatchError),
+    #[error("partition error: {0}")]
+    Partition(#[from] PartitionError),
+    #[error("profile error: {0}")]
+    Profile(#[from] ProfileError),
     #[error("stage error: {0}")]
     Stage(#[from] StageError),
     #[error("transport error: {0}")]
     Transport(#[from] confidential_ml_transport::Error),
     #[error("stage {stage_idx} failed: {reason}")]
     StageFailed { stage_idx: usize, reason: String },
+    #[error("upstream failure: {0}")]
+    UpstreamFailed(DataError),
+    /// A stage failed the request. `code` says why and whether a retry may
+    /// help; `details` is the executor's own reason, if it gave one.
     #[error("request {request_id} failed: {reason}")]
-    RequestFailed { request_id: u64, reason: String },
+    RequestFailed {
+        request_id: u64,
+        code: ErrorCode,
+        reason: String,
+        details: Option<String>,
+    },
+    #[error("request {request_id} missed its deadline")]
+    DeadlineExceeded { request_id: u64 },
+    #[error("request {request_id} cancelled")]
+    Cancelled { request_id: u64 },
     #[error("pipeline shutting down")]
     Shutdown,
     #[error("I/O error: {0}")]
@@ -80,14 +246,41 @@ pub enum PipelineError {
     Timeout(String),
     #[error("pipeline tainted after unrecoverable timeout; re-initialize to continue")]
     Tainted,
+    #[error("recovery failed after {attempts} attempt(s): {source}")]
+    RecoveryFailed {
+        attempts: u32,
+        source: Box<PipelineError>,
+    },
     #[error("protocol error: {0}")]
     Protocol(String),
     #[error("serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
     #[error("protocol version mismatch: expected {expected}, got {actual}")]
     VersionMismatch { expected: u32, actual: u32 },
+    #[error("no common protocol version: local {local}, peer {peer}")]
+    NoCommonVersion {
+        local: VersionRange,
+        peer: VersionRange,
+    },
     #[error("control message too large: {size} bytes exceeds limit of {limit} bytes")]
     MessageTooLarge { size: usize, limit: usize },
+    #[error("too many in-flight requests (limit {limit})")]
+    TooManyInFlight { limit: usize },
+}
+
+impl PipelineError {
+    /// Whether the request may succeed if it is submitted again.
+    ///
+    /// True only for stage failures whose [`ErrorCode`] is retryable.
+    /// Timeouts, missed deadlines, cancellation, a tainted pipeline and
+    /// local errors are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            PipelineError::RequestFailed { code, .. } => code.is_retryable(),
+            PipelineError::UpstreamFailed(frame) => frame.code.is_retryable(),
+            _ => false,
+        }
+    }
 }
 
 /// Convenience alias.
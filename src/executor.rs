@@ -3,10 +3,15 @@ use confidential_ml_transport::OwnedTensor;
 
 use crate::error::StageError;
 use crate::manifest::StageSpec;
+use crate::protocol::LayerSample;
 
 /// Unique identifier for an inference request.
 pub type RequestId = u64;
 
+/// Identifier for a session: a sequence of requests that share stage-side
+/// state, such as a decoder's KV cache.
+pub type SessionId = u64;
+
 /// Output from a single forward pass (one micro-batch through one stage).
 pub struct ForwardOutput {
     /// Activation tensors to forward to the next stage (or final output for the last stage).
@@ -44,4 +49,52 @@ pub trait StageExecutor: Send + Sync {
         micro_batch: u32,
         inputs: Vec<OwnedTensor>,
     ) -> std::result::Result<ForwardOutput, StageError>;
+
+    /// Run a forward pass for a profiling request outside a session, timing
+    /// each layer.
+    ///
+    /// Returns the output and one [`LayerSample`] per layer of the stage.
+    /// Default calls [`forward`](Self::forward) and reports no samples, so
+    /// [`Orchestrator::profile`](crate::Orchestrator::profile) fails with
+    /// [`ProfileError::NoLayerSamples`](crate::ProfileError::NoLayerSamples)
+    /// for the stage.
+    async fn forward_profiled(
+        &self,
+        request_id: RequestId,
+        micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> std::result::Result<(ForwardOutput, Vec<LayerSample>), StageError> {
+        let output = self.forward(request_id, micro_batch, inputs).await?;
+        Ok((output, Vec::new()))
+    }
+
+    /// Allocate state for a new session.
+    ///
+    /// Called when the orchestrator opens a session, before any request in
+    /// it. An error marks the session failed on this stage; its requests are
+    /// rejected until it is closed. Default does nothing.
+    async fn open_session(&self, _session_id: SessionId) -> std::result::Result<(), StageError> {
+        Ok(())
+    }
+
+    /// Run a forward pass for a request that belongs to a session.
+    ///
+    /// Stateful executors read and update the session's state here. Default
+    /// ignores the session and calls [`forward`](Self::forward).
+    async fn forward_in_session(
+        &self,
+        _session_id: SessionId,
+        request_id: RequestId,
+        micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> std::result::Result<ForwardOutput, StageError> {
+        self.forward(request_id, micro_batch, inputs).await
+    }
+
+    /// Release a session's state.
+    ///
+    /// Called once per opened session: when the orchestrator closes it, when
+    /// one of its requests is aborted or fails on this stage, or when the
+    /// stage shuts down. Default does nothing.
+    async fn close_session(&self, _session_id: SessionId) {}
 }
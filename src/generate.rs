@@ -0,0 +1,270 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use confidential_ml_transport::{DType, OwnedTensor};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::error::PipelineError;
+use crate::orchestrator::Orchestrator;
+
+/// An autoregressive generation run for [`Orchestrator::generate`].
+pub struct GenerationRequest {
+    /// Input tensors for the first step, e.g. the encoded prompt.
+    pub initial_input: Vec<OwnedTensor>,
+    /// Number of tokens in `initial_input`. Each step's `seq_len` is this
+    /// plus the number of tokens generated so far.
+    pub prompt_len: u32,
+    /// Maximum number of tokens to generate.
+    pub max_tokens: u32,
+    /// Generation stops after producing any of these tokens.
+    pub stop_token_ids: Vec<u32>,
+}
+
+/// Why a generation run stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// `max_tokens` tokens were generated.
+    MaxTokens,
+    /// The sampler produced a token from `stop_token_ids`.
+    StopToken(u32),
+    /// The next step would exceed the manifest's `max_seq_len`.
+    MaxSeqLen,
+}
+
+/// Result of [`Orchestrator::generate`].
+#[derive(Debug)]
+pub struct GenerationOutput {
+    /// Generated tokens, excluding the prompt. Includes the stop token when
+    /// generation ended on one.
+    pub tokens: Vec<u32>,
+    /// Why generation stopped.
+    pub stop_reason: StopReason,
+    /// Wall-clock time of each step (pipeline round-trip plus sampling),
+    /// one entry per generated token. The first entry covers the prompt.
+    pub token_latencies: Vec<Duration>,
+    /// Total time including session open and close.
+    pub total: Duration,
+}
+
+impl GenerationOutput {
+    /// Time to the first generated token (the prompt step).
+    pub fn time_to_first_token(&self) -> Option<Duration> {
+        self.token_latencies.first().copied()
+    }
+}
+
+/// Chooses each next token from the last stage's outputs and encodes it as
+/// the next step's input.
+///
+/// Closures `FnMut(&[OwnedTensor]) -> Result<u32>` implement this trait with
+/// the default encoding.
+pub trait TokenSampler: Send {
+    /// Pick the next token from one step's output tensors.
+    fn sample(&mut self, outputs: &[OwnedTensor]) -> crate::error::Result<u32>;
+
+    /// Build the input tensors for the step after `token` was produced.
+    ///
+    /// Default: a single U32 tensor `input_ids` of shape `[1, 1]`.
+    fn encode(&mut self, token: u32) -> Vec<OwnedTensor> {
+        vec![OwnedTensor {
+            name: "input_ids".to_string(),
+            dtype: DType::U32,
+            shape: vec![1, 1],
+            data: Bytes::copy_from_slice(&token.to_le_bytes()),
+        }]
+    }
+}
+
+impl<F> TokenSampler for F
+where
+    F: FnMut(&[OwnedTensor]) -> crate::error::Result<u32> + Send,
+{
+    fn sample(&mut self, outputs: &[OwnedTensor]) -> crate::error::Result<u32> {
+        self(outputs)
+    }
+}
+
+/// Greedy decoding: the argmax of the last row of an F32 logits tensor.
+#[derive(Debug, Clone, Default)]
+pub struct GreedySampler {
+    /// Name of the logits tensor. `None` uses the first output tensor.
+    pub output_name: Option<String>,
+}
+
+impl TokenSampler for GreedySampler {
+    fn sample(&mut self, outputs: &[OwnedTensor]) -> crate::error::Result<u32> {
+        let logits = match &self.output_name {
+            Some(name) => outputs.iter().find(|t| &t.name == name),
+            None => outputs.first(),
+        }
+        .ok_or_else(|| PipelineError::Protocol("no logits tensor in step output".into()))?;
+
+        if logits.dtype != DType::F32 {
+            return Err(PipelineError::Protocol(format!(
+                "logits must be F32, got {:?}",
+                logits.dtype
+            )));
+        }
+        let vocab = logits.shape.last().copied().unwrap_or(0) as usize;
+        let values = logits.data.len() / 4;
+        if vocab == 0 || values < vocab {
+            return Err(PipelineError::Protocol(format!(
+                "logits shape {:?} has no vocabulary dimension",
+                logits.shape
+            )));
+        }
+
+        let last_row = &logits.data[(values - vocab) * 4..values * 4];
+        let (best, _) = last_row
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .enumerate()
+            .fold((0, f32::NEG_INFINITY), |best, (i, v)| {
+                if v > best.1 {
+                    (i, v)
+                } else {
+                    best
+                }
+            });
+        Ok(best as u32)
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
+    /// Run an autoregressive generation loop.
+    ///
+    /// Opens a session so stage caches carry over between steps, sends
+    /// `initial_input`, then repeatedly samples a token from the outputs
+    /// and feeds its encoding back in until a stop condition is met. The
+    /// session is closed before returning, including on error.
+    ///
+    /// Each step is a normal request and is subject to
+    /// `OrchestratorConfig::infer_timeout`.
+    pub async fn generate<S: TokenSampler>(
+        &mut self,
+        request: GenerationRequest,
+        sampler: &mut S,
+    ) -> crate::error::Result<GenerationOutput> {
+        let start = Instant::now();
+        let session = self.open_session().await?;
+
+        let result = self.generate_in_session(session, request, sampler).await;
+
+        if let Err(e) = self.close_session(session).await {
+            warn!(session, error = %e, "generate: failed to close session");
+        }
+        let (tokens, stop_reason, token_latencies) = result?;
+        let total = start.elapsed();
+        info!(
+            tokens = tokens.len(),
+            ?stop_reason,
+            ?total,
+            "orchestrator: generation complete"
+        );
+
+        Ok(GenerationOutput {
+            tokens,
+            stop_reason,
+            token_latencies,
+            total,
+        })
+    }
+
+    async fn generate_in_session<S: TokenSampler>(
+        &mut self,
+        session: u64,
+        request: GenerationRequest,
+        sampler: &mut S,
+    ) -> crate::error::Result<(Vec<u32>, StopReason, Vec<Duration>)> {
+        let max_seq_len = self.manifest().activation_spec.max_seq_len;
+        let mut tokens = Vec::new();
+        let mut latencies = Vec::new();
+        let mut input = request.initial_input;
+
+        let stop_reason = loop {
+            if tokens.len() as u32 >= request.max_tokens {
+                break StopReason::MaxTokens;
+            }
+            let seq_len = request.prompt_len + tokens.len() as u32;
+            if seq_len > max_seq_len {
+                break StopReason::MaxSeqLen;
+            }
+
+            let step_start = Instant::now();
+            let result = self.infer_in_session(session, vec![input], seq_len).await?;
+            let outputs = result.outputs.into_iter().next().unwrap_or_default();
+            let token = sampler.sample(&outputs)?;
+            latencies.push(step_start.elapsed());
+            tokens.push(token);
+            debug!(step = tokens.len() - 1, token, "generate: sampled token");
+
+            if request.stop_token_ids.contains(&token) {
+                break StopReason::StopToken(token);
+            }
+            input = sampler.encode(token);
+        };
+
+        Ok((tokens, stop_reason, latencies))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logits(shape: Vec<u32>, values: &[f32]) -> OwnedTensor {
+        OwnedTensor {
+            name: "logits".into(),
+            dtype: DType::F32,
+            shape,
+            data: Bytes::from(
+                values
+                    .iter()
+                    .flat_map(|v| v.to_le_bytes())
+                    .collect::<Vec<u8>>(),
+            ),
+        }
+    }
+
+    #[test]
+    fn greedy_picks_argmax_of_last_row() {
+        let t = logits(vec![2, 3], &[9.0, 0.0, 0.0, 0.1, 0.5, 0.2]);
+        assert_eq!(GreedySampler::default().sample(&[t]).unwrap(), 1);
+    }
+
+    #[test]
+    fn greedy_selects_named_output() {
+        let other = OwnedTensor {
+            name: "hidden".into(),
+            ..logits(vec![1, 2], &[5.0, 0.0])
+        };
+        let t = logits(vec![1, 2], &[0.0, 5.0]);
+        let mut sampler = GreedySampler {
+            output_name: Some("logits".into()),
+        };
+        assert_eq!(sampler.sample(&[other, t]).unwrap(), 1);
+    }
+
+    #[test]
+    fn greedy_rejects_non_f32() {
+        let t = OwnedTensor {
+            name: "ids".into(),
+            dtype: DType::U32,
+            shape: vec![1],
+            data: Bytes::from(vec![0u8; 4]),
+        };
+        assert!(GreedySampler::default().sample(&[t]).is_err());
+        assert!(GreedySampler::default().sample(&[]).is_err());
+    }
+
+    #[test]
+    fn closure_sampler_uses_default_encoding() {
+        let mut sampler = |_: &[OwnedTensor]| Ok(7);
+        assert_eq!(sampler.sample(&[]).unwrap(), 7);
+        let encoded = sampler.encode(7);
+        assert_eq!(encoded[0].shape, vec![1, 1]);
+        assert_eq!(encoded[0].data.as_ref(), &7u32.to_le_bytes());
+    }
+}
@@ -0,0 +1,518 @@
+use std::collections::VecDeque;
+
+use confidential_ml_transport::OwnedTensor;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::error::PipelineError;
+use crate::executor::RequestId;
+use crate::orchestrator::{InferenceResult, Orchestrator, RequestOptions};
+use crate::recovery::Recovery;
+use crate::retry::RetryAttempt;
+
+/// Number of commands that may be queued to the orchestrator task before
+/// callers are back-pressured.
+const COMMAND_QUEUE_DEPTH: usize = 256;
+
+/// Snapshot of the orchestrator task's state, returned by
+/// [`OrchestratorHandle::status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrchestratorStatus {
+    /// Requests currently inside the pipeline.
+    pub in_flight: usize,
+    /// Commands received but not yet started (waiting for an in-flight slot,
+    /// for in-flight requests to finish, or for a retry's backoff to end).
+    pub queued: usize,
+    /// Whether the pipeline is tainted and rejects further requests.
+    pub tainted: bool,
+}
+
+type Reply<R> = oneshot::Sender<crate::error::Result<R>>;
+
+/// A command sent from an [`OrchestratorHandle`] to the orchestrator task.
+enum Command {
+    Infer {
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+        options: RequestOptions,
+        /// Earlier attempts that failed and are being retried.
+        retries: Vec<RetryAttempt>,
+        reply: Reply<InferenceResult>,
+    },
+    HealthCheck {
+        reply: Reply<()>,
+    },
+    Shutdown {
+        reply: Reply<()>,
+    },
+    Status {
+        reply: Reply<OrchestratorStatus>,
+    },
+}
+
+/// Cloneable handle to an [`Orchestrator`] running on a background task.
+///
+/// The task owns the control and data channels. Handles send it commands and
+/// receive results over oneshot replies, so any number of tokio tasks can
+/// share one pipeline without a mutex. Concurrent `infer` calls are kept in
+/// flight together, up to `OrchestratorConfig::max_in_flight`.
+///
+/// Status queries are answered as soon as they arrive and health checks run
+/// alongside the requests in flight. Shutdown waits until the requests
+/// already queued or in flight have completed. Requests waiting for an
+/// in-flight slot start in order of [`RequestOptions::priority`], then
+/// arrival.
+///
+/// Infers are retried under `OrchestratorConfig::request_retry_policy` as
+/// with [`Orchestrator::infer`]. A retry waits out its backoff without
+/// holding up other commands, then queues again behind requests of the
+/// same or higher priority.
+#[derive(Clone)]
+pub struct OrchestratorHandle {
+    commands: mpsc::Sender<Command>,
+}
+
+impl OrchestratorHandle {
+    /// Move an initialized orchestrator onto a background task.
+    ///
+    /// The orchestrator should already be Ready (`init` and
+    /// `establish_data_channels` completed). The returned `JoinHandle`
+    /// resolves to the orchestrator once the task stops, which happens after
+    /// [`Self::shutdown`] or when every handle has been dropped.
+    pub fn spawn<T>(orchestrator: Orchestrator<T>) -> (Self, JoinHandle<Orchestrator<T>>)
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (commands, rx) = mpsc::channel(COMMAND_QUEUE_DEPTH);
+        let task = tokio::spawn(run_orchestrator_task(orchestrator, None, rx));
+        (Self { commands }, task)
+    }
+
+    /// Like [`Self::spawn`], but the task recovers the pipeline when it
+    /// becomes tainted.
+    ///
+    /// Once the requests in flight at the time of the taint have failed, the
+    /// next command triggers [`Orchestrator::recover`] with `recovery`'s
+    /// factory before it runs. If recovery gives up, commands fail with
+    /// `PipelineError::Tainted` and the next one tries again.
+    pub fn spawn_with_recovery<T>(
+        orchestrator: Orchestrator<T>,
+        recovery: Recovery<T>,
+    ) -> (Self, JoinHandle<Orchestrator<T>>)
+    where
+        T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (commands, rx) = mpsc::channel(COMMAND_QUEUE_DEPTH);
+        let task = tokio::spawn(run_orchestrator_task(orchestrator, Some(recovery), rx));
+        (Self { commands }, task)
+    }
+
+    /// Run an inference request. See [`Orchestrator::infer`].
+    pub async fn infer(
+        &self,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+    ) -> crate::error::Result<InferenceResult> {
+        self.infer_with_options(input_tensors, seq_len, RequestOptions::default())
+            .await
+    }
+
+    /// Run an inference request with per-request options. See
+    /// [`Orchestrator::infer_with_options`].
+    pub async fn infer_with_options(
+        &self,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+        options: RequestOptions,
+    ) -> crate::error::Result<InferenceResult> {
+        self.call(|reply| Command::Infer {
+            input_tensors,
+            seq_len,
+            options,
+            retries: Vec::new(),
+            reply,
+        })
+        .await
+    }
+
+    /// Ping every stage. See [`Orchestrator::health_check`].
+    pub async fn health_check(&self) -> crate::error::Result<()> {
+        self.call(|reply| Command::HealthCheck { reply }).await
+    }
+
+    /// Shut down the pipeline and stop the orchestrator task.
+    ///
+    /// Requests already in flight complete first; commands queued behind the
+    /// shutdown fail with `PipelineError::Shutdown`.
+    pub async fn shutdown(&self) -> crate::error::Result<()> {
+        self.call(|reply| Command::Shutdown { reply }).await
+    }
+
+    /// Query the orchestrator task's current state.
+    pub async fn status(&self) -> crate::error::Result<OrchestratorStatus> {
+        self.call(|reply| Command::Status { reply }).await
+    }
+
+    /// Send a command and wait for its reply. A stopped task surfaces as
+    /// `PipelineError::Shutdown`.
+    async fn call<R>(&self, make: impl FnOnce(Reply<R>) -> Command) -> crate::error::Result<R> {
+        let (reply, rx) = oneshot::channel();
+        self.commands
+            .send(make(reply))
+            .await
+            .map_err(|_| PipelineError::Shutdown)?;
+        rx.await.map_err(|_| PipelineError::Shutdown)?
+    }
+}
+
+/// Orchestrator task: starts commands in arrival order (waiting infers by
+/// priority) and retires in-flight requests oldest first. Commands keep
+/// being accepted while requests are in flight; a command never interrupts
+/// a read that is part-way through a frame.
+async fn run_orchestrator_task<T>(
+    mut orch: Orchestrator<T>,
+    mut recovery: Option<Recovery<T>>,
+    mut rx: mpsc::Receiver<Command>,
+) -> Orchestrator<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    // Submitted requests, in the order their outputs will arrive.
+    let mut waiting: VecDeque<Submitted> = VecDeque::new();
+    // Commands received but not yet started.
+    let mut backlog: VecDeque<Command> = VecDeque::new();
+    // Failed infers waiting out their backoff before they rejoin the backlog.
+    let mut retrying: Vec<(Instant, Command)> = Vec::new();
+    // False once every handle has been dropped; requests already in flight
+    // are still collected.
+    let mut open = true;
+
+    loop {
+        for (_, cmd) in extract_due(&mut retrying, Instant::now()) {
+            enqueue(&mut backlog, cmd);
+        }
+        while let Ok(cmd) = rx.try_recv() {
+            accept(&orch, &mut backlog, retrying.len(), cmd);
+        }
+
+        if orch.is_tainted() && waiting.is_empty() && !backlog.is_empty() {
+            if let Some(recovery) = recovery.as_mut() {
+                if let Err(e) = orch
+                    .recover(
+                        recovery.factory.as_mut(),
+                        recovery.provider.as_ref(),
+                        recovery.verifier.as_ref(),
+                    )
+                    .await
+                {
+                    warn!(error = %e, "orchestrator task: recovery failed");
+                }
+            }
+        }
+
+        if start_backlog(&mut orch, &mut backlog, &mut waiting, retrying.len()).await {
+            rx.close();
+            for cmd in backlog
+                .drain(..)
+                .chain(retrying.drain(..).map(|(_, cmd)| cmd))
+            {
+                fail_command(cmd);
+            }
+            while let Ok(cmd) = rx.try_recv() {
+                fail_command(cmd);
+            }
+            return orch;
+        }
+
+        let retry_at = retrying.iter().map(|(at, _)| *at).min();
+        let Some(request_id) = waiting.front().map(|submitted| submitted.request_id) else {
+            if !open && retry_at.is_none() {
+                break;
+            }
+            tokio::select! {
+                cmd = rx.recv(), if open => match cmd {
+                    Some(cmd) => accept(&orch, &mut backlog, retrying.len(), cmd),
+                    None => open = false,
+                },
+                () = wake_at(retry_at) => {}
+            }
+            continue;
+        };
+
+        // Outputs arrive through data_out's reader task, so waiting for them
+        // can give way to a new command without losing part of a frame. The
+        // stages' replies follow the last output at once and are read
+        // without interruption.
+        orch.forward_data_out();
+        let (wait_until, own_deadline) = orch.wait_deadline(request_id);
+        let finished = if orch.awaiting_outputs(request_id) {
+            tokio::select! {
+                cmd = rx.recv(), if open => {
+                    match cmd {
+                        Some(cmd) => accept(&orch, &mut backlog, retrying.len(), cmd),
+                        None => open = false,
+                    }
+                    None
+                }
+                received = orch.receive_outputs(request_id) => match received {
+                    Ok(()) => None,
+                    Err(e) => {
+                        orch.retire_head(request_id);
+                        Some(Err(e))
+                    }
+                },
+                () = tokio::time::sleep_until(wait_until) => {
+                    Some(Err(orch.expire(request_id, own_deadline).await))
+                }
+                () = wake_at(retry_at) => None,
+            }
+        } else {
+            match tokio::time::timeout_at(wait_until, orch.collect(request_id)).await {
+                Ok(result) => Some(result),
+                Err(_) => Some(Err(orch.expire(request_id, own_deadline).await)),
+            }
+        };
+        if let Some(result) = finished {
+            let submitted = waiting.pop_front().expect("head checked above");
+            settle(&orch, submitted, result, &mut retrying);
+        }
+    }
+
+    debug!("orchestrator task: all handles dropped, stopping");
+    orch
+}
+
+/// An infer the task has submitted and not yet answered.
+struct Submitted {
+    request_id: RequestId,
+    reply: Reply<InferenceResult>,
+    /// Inputs kept to submit the request again, or `None` if it is sent once.
+    resubmit: Option<Resubmit>,
+    /// Earlier attempts that failed and were retried.
+    retries: Vec<RetryAttempt>,
+}
+
+struct Resubmit {
+    input_tensors: Vec<Vec<OwnedTensor>>,
+    seq_len: u32,
+    options: RequestOptions,
+}
+
+/// Start what the backlog allows: health checks right away, infers in
+/// order while an in-flight slot is free, and a shutdown once everything
+/// before it, including the `retrying` infers waiting out a backoff, has
+/// finished. Returns true once the pipeline has shut down.
+async fn start_backlog<T>(
+    orch: &mut Orchestrator<T>,
+    backlog: &mut VecDeque<Command>,
+    waiting: &mut VecDeque<Submitted>,
+    retrying: usize,
+) -> bool
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    // Commands that cannot start yet, in their original order.
+    let mut blocked: VecDeque<Command> = VecDeque::new();
+    while let Some(cmd) = backlog.pop_front() {
+        match cmd {
+            Command::Status { reply } => {
+                let queued = backlog.len() + blocked.len() + retrying;
+                let _ = reply.send(Ok(status(orch, queued)));
+            }
+            Command::HealthCheck { reply } => {
+                let _ = reply.send(orch.health_check().await);
+            }
+            Command::Infer {
+                input_tensors,
+                seq_len,
+                options,
+                retries,
+                reply,
+            } if blocked.is_empty() && orch.in_flight() < orch.max_in_flight() => {
+                // As with `Orchestrator::infer`, a caller-supplied ID is
+                // never resubmitted.
+                let resubmit = (orch.request_retry_policy().max_retries > 0
+                    && options.request_id.is_none())
+                .then(|| Resubmit {
+                    input_tensors: input_tensors.clone(),
+                    seq_len,
+                    options: options.clone(),
+                });
+                match orch
+                    .submit_with_options(input_tensors, seq_len, options)
+                    .await
+                {
+                    Ok(request_id) => waiting.push_back(Submitted {
+                        request_id,
+                        reply,
+                        resubmit,
+                        retries,
+                    }),
+                    Err(e) => {
+                        let _ = reply.send(Err(e));
+                    }
+                }
+            }
+            Command::Shutdown { reply }
+                if blocked.is_empty() && waiting.is_empty() && retrying == 0 =>
+            {
+                info!("orchestrator task: shutting down");
+                let _ = reply.send(orch.shutdown().await);
+                backlog.extend(blocked);
+                return true;
+            }
+            // Nothing behind a pending shutdown may start.
+            shutdown @ Command::Shutdown { .. } => {
+                blocked.push_back(shutdown);
+                blocked.extend(backlog.drain(..));
+            }
+            // No free slot, or an earlier infer is still waiting for one.
+            infer => blocked.push_back(infer),
+        }
+    }
+    *backlog = blocked;
+    false
+}
+
+/// Take a command off the channel. Status is answered at once; everything
+/// else joins the backlog.
+fn accept<T>(orch: &Orchestrator<T>, backlog: &mut VecDeque<Command>, retrying: usize, cmd: Command)
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    match cmd {
+        Command::Status { reply } => {
+            let _ = reply.send(Ok(status(orch, backlog.len() + retrying)));
+        }
+        cmd => enqueue(backlog, cmd),
+    }
+}
+
+/// Answer a finished infer, or set it aside for another attempt if it
+/// failed with an error the retry policy allows retrying.
+fn settle<T>(
+    orch: &Orchestrator<T>,
+    submitted: Submitted,
+    result: crate::error::Result<InferenceResult>,
+    retrying: &mut Vec<(Instant, Command)>,
+) where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let Submitted {
+        request_id,
+        reply,
+        resubmit,
+        mut retries,
+    } = submitted;
+    let result = match (result, resubmit) {
+        (Ok(mut result), _) => {
+            result.retries = retries;
+            Ok(result)
+        }
+        (Err(error), Some(resubmit)) => {
+            let attempt = u32::try_from(retries.len()).unwrap_or(u32::MAX);
+            let backoff = orch.request_retry_policy().backoff_after(
+                attempt,
+                &error,
+                orch.is_tainted(),
+                resubmit.options.deadline,
+            );
+            match backoff {
+                Some(backoff) => {
+                    warn!(
+                        request_id,
+                        attempt,
+                        error = %error,
+                        backoff_ms = backoff.as_millis(),
+                        "orchestrator task: request failed, retrying"
+                    );
+                    retries.push(RetryAttempt { error, backoff });
+                    let cmd = Command::Infer {
+                        input_tensors: resubmit.input_tensors,
+                        seq_len: resubmit.seq_len,
+                        options: resubmit.options,
+                        retries,
+                        reply,
+                    };
+                    retrying.push((Instant::now() + backoff, cmd));
+                    return;
+                }
+                None => Err(error),
+            }
+        }
+        (Err(error), None) => Err(error),
+    };
+    if reply.send(result).is_err() {
+        debug!(
+            request_id,
+            "orchestrator task: caller dropped before result"
+        );
+    }
+}
+
+/// Remove and return the retries whose backoff has ended by `now`.
+fn extract_due(retrying: &mut Vec<(Instant, Command)>, now: Instant) -> Vec<(Instant, Command)> {
+    let (due, pending) = std::mem::take(retrying)
+        .into_iter()
+        .partition(|(at, _)| *at <= now);
+    *retrying = pending;
+    due
+}
+
+/// Sleep until `at`, or forever if there is nothing to wake for.
+async fn wake_at(at: Option<Instant>) {
+    match at {
+        Some(at) => tokio::time::sleep_until(at).await,
+        None => std::future::pending().await,
+    }
+}
+
+fn status<T>(orch: &Orchestrator<T>, queued: usize) -> OrchestratorStatus
+where
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    OrchestratorStatus {
+        in_flight: orch.in_flight(),
+        queued,
+        tainted: orch.is_tainted(),
+    }
+}
+
+/// Add a command to the backlog. Infer commands move ahead of
+/// lower-priority infers queued since the last control command; control
+/// commands keep their arrival order.
+fn enqueue(backlog: &mut VecDeque<Command>, cmd: Command) {
+    let priority = match &cmd {
+        Command::Infer { options, .. } => options.priority,
+        _ => {
+            backlog.push_back(cmd);
+            return;
+        }
+    };
+    let mut position = backlog.len();
+    while position > 0 {
+        match &backlog[position - 1] {
+            Command::Infer { options, .. } if options.priority < priority => position -= 1,
+            _ => break,
+        }
+    }
+    backlog.insert(position, cmd);
+}
+
+/// Reply to a command that will never run because the task is stopping.
+fn fail_command(cmd: Command) {
+    match cmd {
+        Command::Infer { reply, .. } => {
+            let _ = reply.send(Err(PipelineError::Shutdown));
+        }
+        Command::HealthCheck { reply } | Command::Shutdown { reply } => {
+            let _ = reply.send(Err(PipelineError::Shutdown));
+        }
+        Command::Status { reply } => {
+            let _ = reply.send(Err(PipelineError::Shutdown));
+        }
+    }
+}
@@ -13,29 +13,59 @@ compile_error!(
      select only production features for release builds."
 );
 
+pub mod batching;
+pub mod cancel;
 pub mod error;
 pub mod executor;
+pub mod generate;
+pub mod handle;
 pub mod manifest;
 pub mod orchestrator;
+pub mod partition;
+pub mod profile;
 pub mod protocol;
+pub mod recovery;
 pub mod relay;
+pub mod retry;
 pub mod scheduler;
+pub mod simulator;
 pub mod stage;
 #[cfg(feature = "tcp")]
 pub mod tcp;
 #[cfg(feature = "vsock")]
 pub mod vsock;
 
+pub use batching::{merge_outputs, split_batch, MicroBatchPlan, MicroBatchPlanner};
+pub use cancel::CancelToken;
 pub use confidential_ml_transport::RetryPolicy;
-pub use error::{ManifestError, PipelineError, Result, SchedulerError, StageError};
-pub use executor::{ForwardOutput, RequestId, StageExecutor};
+pub use error::{
+    BatchError, ManifestError, PartitionError, PipelineError, ProfileError, Result, SchedulerError,
+    StageError,
+};
+pub use executor::{ForwardOutput, RequestId, SessionId, StageExecutor};
+pub use generate::{GenerationOutput, GenerationRequest, GreedySampler, StopReason, TokenSampler};
+pub use handle::{OrchestratorHandle, OrchestratorStatus};
 pub use manifest::{
-    ActivationDType, ActivationSpec, PortSpec, ShardManifest, StageEndpoint, StageSpec,
+    ActivationDType, ActivationSpec, Dim, PortSpec, ShardManifest, StageEndpoint, StageSpec,
+    TensorDType, TensorSpec,
+};
+pub use orchestrator::{
+    InferenceResult, InferenceStream, Orchestrator, OrchestratorConfig, RequestOptions,
 };
-pub use orchestrator::{InferenceResult, Orchestrator, OrchestratorConfig};
+pub use partition::{partition_layers, LayerCost, Partition};
+pub use profile::{synthetic_inputs, LayerProfile, ModelProfile};
 pub use protocol::{
-    OrchestratorMsg, StageMsg, DEFAULT_MAX_CONTROL_MESSAGE_BYTES, PROTOCOL_VERSION,
+    DataError, DataFrame, ErrorCode, LayerSample, MicroBatchTiming, OrchestratorMsg, StageMsg,
+    VersionRange, DEFAULT_MAX_CONTROL_MESSAGE_BYTES, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+pub use recovery::{DataTransports, Recovery, TransportFactory};
+pub use relay::{start_relay_graph, start_relay_link, start_relay_mesh, RelayHandle};
+pub use retry::{RequestRetryPolicy, RetryAttempt};
+pub use scheduler::{
+    FillDrain, InferenceSchedule, Interleaved, OneFOneB, PipeOp, ScheduleKind, ScheduleStrategy,
+    StageSchedule,
+};
+pub use simulator::{
+    CostModel, LinkCost, SimulationComparison, SimulationReport, Span, SpanKind, StageComparison,
 };
-pub use relay::{start_relay_link, start_relay_mesh, RelayHandle};
-pub use scheduler::{InferenceSchedule, PipeOp, StageSchedule};
 pub use stage::{ControlPhaseResult, StageConfig, StageRuntime};
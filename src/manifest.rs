@@ -1,6 +1,7 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
 
-use confidential_ml_transport::ExpectedMeasurements;
+use confidential_ml_transport::{DType, ExpectedMeasurements, OwnedTensor};
 use serde::{Deserialize, Serialize};
 
 use crate::error::ManifestError;
@@ -31,9 +32,66 @@ pub struct StageSpec {
     pub weight_hashes: Vec<String>,
     /// Expected attestation measurements: register index -> hex-encoded hash.
     pub expected_measurements: BTreeMap<usize, String>,
+    /// Stages this stage receives activations from. Their tensors are
+    /// concatenated in this order before `forward`. Empty for the entry
+    /// stage, which receives the orchestrator's input.
+    ///
+    /// If no stage declares `upstream` or `downstream`, stages form a linear
+    /// chain in index order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub upstream: Vec<usize>,
+    /// Stages this stage sends its output to; each receives all of it.
+    /// Empty for the exit stage, whose output goes to the orchestrator.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub downstream: Vec<usize>,
+    /// Names of input tensors the stage forwards to its output without
+    /// handing them to the executor, e.g. an attention mask that a later
+    /// stage needs. They travel over the same encrypted data channels as
+    /// the activations and are appended after the executor's outputs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pass_through: Vec<String>,
+    /// Tensors the executor receives, not counting pass-through tensors.
+    /// Empty means undeclared: inputs are not checked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<TensorSpec>,
+    /// Tensors the executor produces. Empty means undeclared.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<TensorSpec>,
     pub endpoint: StageEndpoint,
 }
 
+/// A named tensor in a stage's input or output contract.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TensorSpec {
+    pub name: String,
+    pub dtype: TensorDType,
+    /// Dimensions, each fixed (`768`) or symbolic (`"seq"`). A symbol takes
+    /// the same value everywhere it appears in one stage's inputs and
+    /// outputs for a micro-batch.
+    pub shape: Vec<Dim>,
+}
+
+/// One dimension of a [`TensorSpec`] shape.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Dim {
+    Fixed(u32),
+    Symbol(String),
+}
+
+/// Element type of a [`TensorSpec`], mirroring the transport's `DType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TensorDType {
+    F32,
+    F64,
+    F16,
+    BF16,
+    I32,
+    I64,
+    U8,
+    U32,
+}
+
 /// Network endpoints for a stage's control and data channels.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StageEndpoint {
@@ -82,6 +140,11 @@ impl ShardManifest {
     }
 
     /// Validate that stages are contiguous, correctly indexed, and cover all layers.
+    ///
+    /// In a graph manifest the stages' layer ranges must tile
+    /// `0..total_layers` in any stage order, and the links must be mutual
+    /// and acyclic, with exactly one entry and one exit stage. Every stage
+    /// then lies on a path from the entry to the exit.
     pub fn validate(&self) -> std::result::Result<(), ManifestError> {
         if self.stages.is_empty() {
             return Err(ManifestError::EmptyStages);
@@ -104,15 +167,34 @@ impl ShardManifest {
                     end: stage.layer_end,
                 });
             }
+            stage.validate_tensors()?;
+            for (j, name) in stage.pass_through.iter().enumerate() {
+                if name.is_empty() {
+                    return Err(ManifestError::EmptyPassThroughName { stage_idx: i });
+                }
+                if stage.pass_through[..j].contains(name) {
+                    return Err(ManifestError::DuplicatePassThrough {
+                        stage_idx: i,
+                        name: name.clone(),
+                    });
+                }
+            }
+        }
+
+        // Stages in layer order: index order for a chain, any order for a graph.
+        let mut order: Vec<usize> = (0..self.stages.len()).collect();
+        if self.is_graph() {
+            self.validate_links()?;
+            order.sort_by_key(|&i| self.stages[i].layer_start);
         }
 
         // Check contiguity.
-        for i in 0..self.stages.len() - 1 {
-            let end = self.stages[i].layer_end;
-            let next_start = self.stages[i + 1].layer_start;
+        for pair in order.windows(2) {
+            let end = self.stages[pair[0]].layer_end;
+            let next_start = self.stages[pair[1]].layer_start;
             if end != next_start {
                 return Err(ManifestError::NonContiguousLayers {
-                    stage_idx: i,
+                    stage_idx: pair[0],
                     end,
                     next_start,
                 });
@@ -120,16 +202,16 @@ impl ShardManifest {
         }
 
         // Layers must start at 0.
-        if self.stages[0].layer_start != 0 {
+        if self.stages[order[0]].layer_start != 0 {
             return Err(ManifestError::LayerStartNotZero {
-                start: self.stages[0].layer_start,
+                start: self.stages[order[0]].layer_start,
             });
         }
 
         // Check total coverage.
         // Safety: `stages` is non-empty (checked above).
-        let last_end = match self.stages.last() {
-            Some(s) => s.layer_end,
+        let last_end = match order.last() {
+            Some(&i) => self.stages[i].layer_end,
             None => return Err(ManifestError::EmptyStages),
         };
         if last_end != self.total_layers {
@@ -139,11 +221,217 @@ impl ShardManifest {
             });
         }
 
+        self.validate_contracts()
+    }
+
+    /// Whether any stage declares `upstream` or `downstream` links. If not,
+    /// the stages form a linear chain.
+    pub fn is_graph(&self) -> bool {
+        self.stages
+            .iter()
+            .any(|s| !s.upstream.is_empty() || !s.downstream.is_empty())
+    }
+
+    /// Stages that `stage_idx` receives from, in concatenation order.
+    pub fn upstream_of(&self, stage_idx: usize) -> Vec<usize> {
+        if self.is_graph() {
+            self.stages[stage_idx].upstream.clone()
+        } else if stage_idx > 0 {
+            vec![stage_idx - 1]
+        } else {
+            vec![]
+        }
+    }
+
+    /// Stages that `stage_idx` sends its output to.
+    pub fn downstream_of(&self, stage_idx: usize) -> Vec<usize> {
+        if self.is_graph() {
+            self.stages[stage_idx].downstream.clone()
+        } else if stage_idx + 1 < self.stages.len() {
+            vec![stage_idx + 1]
+        } else {
+            vec![]
+        }
+    }
+
+    /// Every inter-stage data link as `(upstream, downstream)`, ordered by
+    /// upstream stage and then by its `downstream` list.
+    pub fn links(&self) -> Vec<(usize, usize)> {
+        (0..self.stages.len())
+            .flat_map(|i| self.downstream_of(i).into_iter().map(move |d| (i, d)))
+            .collect()
+    }
+
+    /// The stage that receives the orchestrator's input.
+    pub fn entry_stage(&self) -> usize {
+        (0..self.stages.len())
+            .find(|&i| self.upstream_of(i).is_empty())
+            .unwrap_or(0)
+    }
+
+    /// The stage whose output goes back to the orchestrator.
+    pub fn exit_stage(&self) -> usize {
+        (0..self.stages.len())
+            .find(|&i| self.downstream_of(i).is_empty())
+            .unwrap_or(self.stages.len().saturating_sub(1))
+    }
+
+    /// Check declared contracts across every link: each output of a stage
+    /// must be an input (or pass-through) of each downstream stage, with
+    /// the same dtype and a compatible shape, and each input must come from
+    /// exactly one upstream stage. Links where either side is undeclared
+    /// are not checked.
+    fn validate_contracts(&self) -> std::result::Result<(), ManifestError> {
+        for (from, to) in self.links() {
+            let (src, dst) = (&self.stages[from], &self.stages[to]);
+            if src.outputs.is_empty() || dst.inputs.is_empty() {
+                continue;
+            }
+            for out in &src.outputs {
+                if dst.pass_through.contains(&out.name) {
+                    continue;
+                }
+                let mismatch = |reason: String| ManifestError::ContractMismatch {
+                    from,
+                    to,
+                    name: out.name.clone(),
+                    reason,
+                };
+                let Some(input) = dst.inputs.iter().find(|i| i.name == out.name) else {
+                    return Err(mismatch(format!("not an input of stage {to}")));
+                };
+                if input.dtype != out.dtype {
+                    return Err(mismatch(format!(
+                        "{:?} output, {:?} input",
+                        out.dtype, input.dtype
+                    )));
+                }
+                if !shapes_compatible(&out.shape, &input.shape) {
+                    return Err(mismatch(format!(
+                        "shape {:?} output, {:?} input",
+                        out.shape, input.shape
+                    )));
+                }
+            }
+        }
+
+        for (to, dst) in self.stages.iter().enumerate() {
+            let upstream = self.upstream_of(to);
+            if dst.inputs.is_empty()
+                || upstream.is_empty()
+                || upstream.iter().any(|&u| self.stages[u].outputs.is_empty())
+            {
+                continue;
+            }
+            for input in &dst.inputs {
+                let mut producers = upstream.iter().filter(|&&u| {
+                    let src = &self.stages[u];
+                    src.outputs.iter().any(|o| o.name == input.name)
+                        || src.pass_through.contains(&input.name)
+                });
+                let Some(&first) = producers.next() else {
+                    return Err(ManifestError::UnproducedInput {
+                        stage_idx: to,
+                        name: input.name.clone(),
+                    });
+                };
+                if let Some(&from) = producers.next() {
+                    return Err(ManifestError::ContractMismatch {
+                        from,
+                        to,
+                        name: input.name.clone(),
+                        reason: format!("also produced by stage {first}"),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a graph manifest's links: known, unique and mutual stages, no
+    /// cycles, and one entry and one exit stage.
+    fn validate_links(&self) -> std::result::Result<(), ManifestError> {
+        let n = self.stages.len();
+        for (i, stage) in self.stages.iter().enumerate() {
+            for (links, upstream) in [(&stage.upstream, true), (&stage.downstream, false)] {
+                for (k, &linked) in links.iter().enumerate() {
+                    if linked >= n {
+                        return Err(ManifestError::UnknownLinkedStage {
+                            stage_idx: i,
+                            linked,
+                        });
+                    }
+                    let (from, to) = if upstream { (linked, i) } else { (i, linked) };
+                    if links[..k].contains(&linked) {
+                        return Err(ManifestError::DuplicateLink { from, to });
+                    }
+                    let mirrored = if upstream {
+                        &self.stages[linked].downstream
+                    } else {
+                        &self.stages[linked].upstream
+                    };
+                    if !mirrored.contains(&i) {
+                        return Err(ManifestError::AsymmetricLink { from, to });
+                    }
+                }
+            }
+        }
+
+        let entries = self.stages.iter().filter(|s| s.upstream.is_empty()).count();
+        if entries != 1 {
+            return Err(ManifestError::EntryStageCount(entries));
+        }
+        let exits = self
+            .stages
+            .iter()
+            .filter(|s| s.downstream.is_empty())
+            .count();
+        if exits != 1 {
+            return Err(ManifestError::ExitStageCount(exits));
+        }
+
+        // Kahn's algorithm: stages left with unvisited upstreams are on a cycle.
+        let mut waiting: Vec<usize> = self.stages.iter().map(|s| s.upstream.len()).collect();
+        let mut ready: Vec<usize> = (0..n).filter(|&i| waiting[i] == 0).collect();
+        let mut visited = 0;
+        while let Some(i) = ready.pop() {
+            visited += 1;
+            for &d in &self.stages[i].downstream {
+                waiting[d] -= 1;
+                if waiting[d] == 0 {
+                    ready.push(d);
+                }
+            }
+        }
+        if visited < n {
+            let stage_idx = (0..n).find(|&i| waiting[i] > 0).unwrap_or(0);
+            return Err(ManifestError::Cycle { stage_idx });
+        }
         Ok(())
     }
 }
 
 impl StageSpec {
+    /// A stage of a linear pipeline running `layers`, with no weight
+    /// hashes, measurements, graph links, pass-through tensors or tensor
+    /// contracts. Set any of those with struct update syntax.
+    pub fn linear(stage_idx: usize, layers: Range<usize>, endpoint: StageEndpoint) -> Self {
+        Self {
+            stage_idx,
+            layer_start: layers.start,
+            layer_end: layers.end,
+            require_weight_hashes: false,
+            weight_hashes: Vec::new(),
+            expected_measurements: BTreeMap::new(),
+            upstream: Vec::new(),
+            downstream: Vec::new(),
+            pass_through: Vec::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            endpoint,
+        }
+    }
+
     /// Convert hex-encoded expected measurements to the transport crate's type.
     pub fn to_expected_measurements(
         &self,
@@ -161,6 +449,152 @@ impl StageSpec {
     }
 }
 
+impl ActivationSpec {
+    /// Size in bytes of one activation tensor carrying `rows` sequences of
+    /// `seq_len` tokens.
+    pub fn tensor_bytes(&self, rows: usize, seq_len: usize) -> usize {
+        rows * seq_len * self.hidden_dim as usize * self.dtype.element_size()
+    }
+}
+
+impl StageSpec {
+    /// Check the stage's own tensor declarations: non-empty, unique names,
+    /// non-empty symbols, and no overlap with `pass_through`.
+    fn validate_tensors(&self) -> std::result::Result<(), ManifestError> {
+        let invalid = |reason: String| ManifestError::InvalidTensorSpec {
+            stage_idx: self.stage_idx,
+            reason,
+        };
+        for (side, specs) in [("input", &self.inputs), ("output", &self.outputs)] {
+            for (j, spec) in specs.iter().enumerate() {
+                if spec.name.is_empty() {
+                    return Err(invalid(format!("{side} {j} has an empty name")));
+                }
+                if specs[..j].iter().any(|s| s.name == spec.name) {
+                    return Err(invalid(format!("{side} {:?} declared twice", spec.name)));
+                }
+                if spec.shape.iter().any(|d| *d == Dim::Symbol(String::new())) {
+                    return Err(invalid(format!(
+                        "{side} {:?} has an empty symbol",
+                        spec.name
+                    )));
+                }
+                if self.pass_through.contains(&spec.name) {
+                    return Err(invalid(format!(
+                        "{side} {:?} is also a pass-through tensor",
+                        spec.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether two declared shapes can describe the same tensor: same rank, and
+/// equal wherever both dimensions are fixed.
+fn shapes_compatible(a: &[Dim], b: &[Dim]) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|pair| match pair {
+            (Dim::Fixed(x), Dim::Fixed(y)) => x == y,
+            _ => true,
+        })
+}
+
+/// Values bound to shape symbols while checking one micro-batch.
+pub(crate) type SymbolBindings = HashMap<String, u32>;
+
+/// Check `tensors` against a declared contract: exactly the declared names,
+/// each once, with the declared dtype and shape and a matching data length.
+/// Symbols are bound in `bindings` on first use. An empty contract accepts
+/// anything.
+pub(crate) fn check_contract(
+    specs: &[TensorSpec],
+    tensors: &[OwnedTensor],
+    bindings: &mut SymbolBindings,
+) -> std::result::Result<(), String> {
+    if specs.is_empty() {
+        return Ok(());
+    }
+    for tensor in tensors {
+        if !specs.iter().any(|s| s.name == tensor.name) {
+            return Err(format!("undeclared tensor {:?}", tensor.name));
+        }
+    }
+    for spec in specs {
+        let mut matching = tensors.iter().filter(|t| t.name == spec.name);
+        let tensor = matching
+            .next()
+            .ok_or_else(|| format!("missing tensor {:?}", spec.name))?;
+        if matching.next().is_some() {
+            return Err(format!("tensor {:?} appears more than once", spec.name));
+        }
+        spec.check(tensor, bindings)?;
+    }
+    Ok(())
+}
+
+impl TensorSpec {
+    fn check(
+        &self,
+        tensor: &OwnedTensor,
+        bindings: &mut SymbolBindings,
+    ) -> std::result::Result<(), String> {
+        if DType::from(self.dtype) != tensor.dtype {
+            return Err(format!(
+                "tensor {:?} is {:?}, expected {:?}",
+                self.name, tensor.dtype, self.dtype
+            ));
+        }
+        if tensor.shape.len() != self.shape.len() {
+            return Err(format!(
+                "tensor {:?} has shape {:?}, expected {:?}",
+                self.name, tensor.shape, self.shape
+            ));
+        }
+        for (i, (&actual, dim)) in tensor.shape.iter().zip(&self.shape).enumerate() {
+            let (expected, what) = match dim {
+                Dim::Fixed(n) => (*n, String::new()),
+                Dim::Symbol(sym) => (
+                    *bindings.entry(sym.clone()).or_insert(actual),
+                    format!(" ({sym})"),
+                ),
+            };
+            if actual != expected {
+                return Err(format!(
+                    "tensor {:?} dimension {i}{what} is {actual}, expected {expected}",
+                    self.name
+                ));
+            }
+        }
+        let elements: usize = tensor.shape.iter().map(|&d| d as usize).product();
+        if tensor.data.len() != elements * tensor.dtype.element_size() {
+            return Err(format!(
+                "tensor {:?} has {} bytes for shape {:?}",
+                self.name,
+                tensor.data.len(),
+                tensor.shape
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl From<TensorDType> for DType {
+    fn from(dtype: TensorDType) -> Self {
+        match dtype {
+            TensorDType::F32 => DType::F32,
+            TensorDType::F64 => DType::F64,
+            TensorDType::F16 => DType::F16,
+            TensorDType::BF16 => DType::BF16,
+            TensorDType::I32 => DType::I32,
+            TensorDType::I64 => DType::I64,
+            TensorDType::U8 => DType::U8,
+            TensorDType::U32 => DType::U32,
+        }
+    }
+}
+
 impl ActivationDType {
     /// Size of one element in bytes.
     pub const fn element_size(self) -> usize {
@@ -191,14 +625,12 @@ mod tests {
 
     fn make_manifest(num_stages: usize, layers_per_stage: usize) -> ShardManifest {
         let stages = (0..num_stages)
-            .map(|i| StageSpec {
-                stage_idx: i,
-                layer_start: i * layers_per_stage,
-                layer_end: (i + 1) * layers_per_stage,
-                require_weight_hashes: false,
-                weight_hashes: vec![],
-                expected_measurements: BTreeMap::new(),
-                endpoint: make_endpoint((9000 + i * 10) as u32),
+            .map(|i| {
+                StageSpec::linear(
+                    i,
+                    i * layers_per_stage..(i + 1) * layers_per_stage,
+                    make_endpoint((9000 + i * 10) as u32),
+                )
             })
             .collect();
 
@@ -306,13 +738,8 @@ mod tests {
     #[test]
     fn expected_measurements_conversion() {
         let stage = StageSpec {
-            stage_idx: 0,
-            layer_start: 0,
-            layer_end: 4,
-            require_weight_hashes: false,
-            weight_hashes: vec![],
             expected_measurements: BTreeMap::from([(0, "abcd1234".into()), (1, "deadbeef".into())]),
-            endpoint: make_endpoint(9000),
+            ..StageSpec::linear(0, 0..4, make_endpoint(9000))
         };
         let em = stage.to_expected_measurements().unwrap();
         assert_eq!(em.values.len(), 2);
@@ -337,6 +764,295 @@ mod tests {
         }
     }
 
+    /// Diamond: 0 -> {1, 2} -> 3, with the branches' layers out of stage order.
+    fn make_diamond() -> ShardManifest {
+        let mut m = make_manifest(4, 2);
+        let links: [(&[usize], &[usize]); 4] =
+            [(&[], &[1, 2]), (&[0], &[3]), (&[0], &[3]), (&[1, 2], &[])];
+        for (stage, (up, down)) in m.stages.iter_mut().zip(links) {
+            stage.upstream = up.to_vec();
+            stage.downstream = down.to_vec();
+        }
+        m.stages[1].layer_start = 4;
+        m.stages[1].layer_end = 6;
+        m.stages[2].layer_start = 2;
+        m.stages[2].layer_end = 4;
+        m
+    }
+
+    #[test]
+    fn linear_manifest_links() {
+        let m = make_manifest(3, 4);
+        assert!(!m.is_graph());
+        assert_eq!(m.links(), vec![(0, 1), (1, 2)]);
+        assert_eq!(m.upstream_of(0), Vec::<usize>::new());
+        assert_eq!(m.downstream_of(1), vec![2]);
+        assert_eq!((m.entry_stage(), m.exit_stage()), (0, 2));
+    }
+
+    #[test]
+    fn graph_manifest_links() {
+        let m = make_diamond();
+        m.validate().unwrap();
+        assert!(m.is_graph());
+        assert_eq!(m.links(), vec![(0, 1), (0, 2), (1, 3), (2, 3)]);
+        assert_eq!(m.upstream_of(3), vec![1, 2]);
+        assert_eq!((m.entry_stage(), m.exit_stage()), (0, 3));
+
+        let json = m.to_json().unwrap();
+        let m2 = ShardManifest::from_json(&json).unwrap();
+        assert_eq!(m2.links(), m.links());
+        // Linear manifests keep their JSON shape.
+        assert!(!make_manifest(2, 4).to_json().unwrap().contains("upstream"));
+    }
+
+    #[test]
+    fn graph_links_must_be_mutual_and_known() {
+        let mut m = make_diamond();
+        m.stages[3].upstream = vec![1];
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::AsymmetricLink { from: 2, to: 3 })
+        ));
+
+        let mut m = make_diamond();
+        m.stages[0].downstream.push(7);
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::UnknownLinkedStage {
+                stage_idx: 0,
+                linked: 7
+            })
+        ));
+
+        let mut m = make_diamond();
+        m.stages[0].downstream.push(1);
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::DuplicateLink { from: 0, to: 1 })
+        ));
+    }
+
+    #[test]
+    fn graph_rejects_cycles_and_extra_endpoints() {
+        let mut m = make_diamond();
+        // 1 -> 2 -> 1 inside the diamond.
+        m.stages[1].downstream.push(2);
+        m.stages[2].upstream.push(1);
+        m.stages[2].downstream.push(1);
+        m.stages[1].upstream.push(2);
+        assert!(matches!(m.validate(), Err(ManifestError::Cycle { .. })));
+
+        let mut m = make_diamond();
+        m.stages[1].upstream.clear();
+        m.stages[0].downstream = vec![2];
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::EntryStageCount(2))
+        ));
+
+        let mut m = make_diamond();
+        m.stages[2].downstream.clear();
+        m.stages[3].upstream = vec![1];
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::ExitStageCount(2))
+        ));
+    }
+
+    #[test]
+    fn graph_layers_must_tile_the_model() {
+        let mut m = make_diamond();
+        m.stages[2].layer_end = 3;
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::NonContiguousLayers {
+                stage_idx: 2,
+                end: 3,
+                next_start: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn pass_through_names_must_be_unique_and_non_empty() {
+        let mut m = make_manifest(3, 4);
+        m.stages[1].pass_through = vec!["mask".into(), "positions".into()];
+        m.validate().unwrap();
+        let m2 = ShardManifest::from_json(&m.to_json().unwrap()).unwrap();
+        assert_eq!(m2.stages[1].pass_through, m.stages[1].pass_through);
+        assert!(m2.stages[0].pass_through.is_empty());
+
+        m.stages[1].pass_through.push("mask".into());
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::DuplicatePassThrough { stage_idx: 1, ref name }) if name == "mask"
+        ));
+
+        m.stages[1].pass_through = vec![String::new()];
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::EmptyPassThroughName { stage_idx: 1 })
+        ));
+    }
+
+    fn spec(name: &str, dtype: TensorDType, shape: &[Dim]) -> TensorSpec {
+        TensorSpec {
+            name: name.into(),
+            dtype,
+            shape: shape.to_vec(),
+        }
+    }
+
+    fn sym(s: &str) -> Dim {
+        Dim::Symbol(s.into())
+    }
+
+    /// ids [batch, seq] -> hidden [batch, seq, 4] -> logits [batch, 10].
+    fn make_contract_manifest() -> ShardManifest {
+        let mut m = make_manifest(2, 4);
+        let hidden = spec(
+            "hidden",
+            TensorDType::F32,
+            &[sym("batch"), sym("seq"), Dim::Fixed(4)],
+        );
+        m.stages[0].inputs = vec![spec("ids", TensorDType::U32, &[sym("batch"), sym("seq")])];
+        m.stages[0].outputs = vec![hidden.clone()];
+        m.stages[1].inputs = vec![hidden];
+        m.stages[1].outputs = vec![spec(
+            "logits",
+            TensorDType::F32,
+            &[sym("batch"), Dim::Fixed(10)],
+        )];
+        m
+    }
+
+    #[test]
+    fn contracts_roundtrip_with_symbolic_shapes() {
+        let m = make_contract_manifest();
+        m.validate().unwrap();
+        let json = m.to_json().unwrap();
+        assert!(json.contains(r#""batch""#));
+        let m2 = ShardManifest::from_json(&json).unwrap();
+        assert_eq!(m2.stages[0].outputs, m.stages[0].outputs);
+        assert_eq!(m2.stages[0].inputs[0].shape[1], sym("seq"));
+        assert_eq!(m2.stages[1].outputs[0].shape[1], Dim::Fixed(10));
+    }
+
+    #[test]
+    fn contracts_must_match_across_links() {
+        let mut m = make_contract_manifest();
+        m.stages[1].inputs[0].dtype = TensorDType::F16;
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::ContractMismatch { from: 0, to: 1, ref name, .. }) if name == "hidden"
+        ));
+
+        let mut m = make_contract_manifest();
+        m.stages[1].inputs[0].shape[2] = Dim::Fixed(8);
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::ContractMismatch { from: 0, to: 1, .. })
+        ));
+
+        // A symbol on one side is compatible with a fixed size on the other.
+        let mut m = make_contract_manifest();
+        m.stages[1].inputs[0].shape[2] = sym("hidden_dim");
+        m.validate().unwrap();
+
+        let mut m = make_contract_manifest();
+        m.stages[1].inputs[0].name = "h".into();
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::ContractMismatch { .. })
+        ));
+
+        let mut m = make_contract_manifest();
+        m.stages[1]
+            .inputs
+            .push(spec("mask", TensorDType::U8, &[sym("seq")]));
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::UnproducedInput { stage_idx: 1, ref name }) if name == "mask"
+        ));
+        // Produced by passing it through stage 0.
+        m.stages[0].pass_through = vec!["mask".into()];
+        m.validate().unwrap();
+
+        // Undeclared sides are not checked.
+        let mut m = make_contract_manifest();
+        m.stages[0].outputs.clear();
+        m.stages[1].inputs[0].dtype = TensorDType::F16;
+        m.validate().unwrap();
+    }
+
+    #[test]
+    fn stage_contracts_must_be_well_formed() {
+        let mut m = make_contract_manifest();
+        let logits = m.stages[1].outputs[0].clone();
+        m.stages[1].outputs.push(logits);
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::InvalidTensorSpec { stage_idx: 1, .. })
+        ));
+
+        let mut m = make_contract_manifest();
+        m.stages[0].inputs[0].shape[0] = sym("");
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::InvalidTensorSpec { stage_idx: 0, .. })
+        ));
+
+        let mut m = make_contract_manifest();
+        m.stages[1].pass_through = vec!["hidden".into()];
+        assert!(matches!(
+            m.validate(),
+            Err(ManifestError::InvalidTensorSpec { stage_idx: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn check_contract_binds_symbols() {
+        let tensor = |name: &str, dtype: DType, shape: Vec<u32>| {
+            let len = shape.iter().product::<u32>() as usize * dtype.element_size();
+            OwnedTensor {
+                name: name.into(),
+                dtype,
+                shape,
+                data: bytes::Bytes::from(vec![0u8; len]),
+            }
+        };
+        let m = make_contract_manifest();
+        let stage = &m.stages[1];
+
+        let mut bindings = SymbolBindings::new();
+        let input = [tensor("hidden", DType::F32, vec![2, 3, 4])];
+        check_contract(&stage.inputs, &input, &mut bindings).unwrap();
+        assert_eq!(bindings["batch"], 2);
+        // "batch" is bound to 2 for the outputs.
+        let output = [tensor("logits", DType::F32, vec![3, 10])];
+        let err = check_contract(&stage.outputs, &output, &mut bindings).unwrap_err();
+        assert!(err.contains("(batch)"), "{err}");
+
+        let mut bindings = SymbolBindings::new();
+        for bad in [
+            vec![tensor("hidden", DType::F16, vec![2, 3, 4])],
+            vec![tensor("hidden", DType::F32, vec![2, 3, 5])],
+            vec![tensor("hidden", DType::F32, vec![2, 3])],
+            vec![tensor("other", DType::F32, vec![2, 3, 4])],
+            vec![],
+            input.iter().chain(&input).cloned().collect(),
+        ] {
+            assert!(check_contract(&stage.inputs, &bad, &mut bindings).is_err());
+        }
+
+        let mut short = input[0].clone();
+        short.data = short.data.slice(..8);
+        assert!(check_contract(&stage.inputs, &[short], &mut SymbolBindings::new()).is_err());
+        // No contract, no checks.
+        check_contract(&[], &[], &mut bindings).unwrap();
+    }
+
     #[test]
     fn required_weight_hashes_must_be_declared() {
         let mut m = make_manifest(1, 4);
@@ -1,18 +1,29 @@
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::Duration;
 
-use bytes::Bytes;
 use confidential_ml_transport::{
     AttestationProvider, AttestationVerifier, Message, OwnedTensor, SecureChannel, SessionConfig,
 };
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
 use tracing::{debug, info, warn};
 use zeroize::Zeroize;
 
+use crate::cancel::CancelToken;
 use crate::error::PipelineError;
-use crate::manifest::ShardManifest;
-use crate::protocol::{OrchestratorMsg, StageMsg, DEFAULT_MAX_CONTROL_MESSAGE_BYTES};
+use crate::executor::{RequestId, SessionId};
+use crate::manifest::{ShardManifest, StageSpec};
+use crate::profile::ModelProfile;
+use crate::protocol::{
+    DataError, DataFrame, Envelope, ErrorCode, LayerSample, MicroBatchTiming, OrchestratorMsg,
+    StageMsg, VersionRange, DEFAULT_MAX_CONTROL_MESSAGE_BYTES, PROTOCOL_VERSION,
+};
 use crate::relay::RelayHandle;
-use crate::stage::ERROR_SENTINEL;
+use crate::retry::{RequestRetryPolicy, RetryAttempt};
+use crate::scheduler::{ScheduleKind, ScheduleStrategy};
+use crate::stage::{send_tensors, DataFraming, END_SENTINEL, ERROR_SENTINEL};
 
 /// Configuration for the orchestrator.
 ///
@@ -33,6 +44,15 @@ pub struct OrchestratorConfig {
     pub infer_timeout: Duration,
     /// Retry policy for TCP connections (used by TCP helpers).
     pub tcp_retry_policy: confidential_ml_transport::RetryPolicy,
+    /// Backoff and attempt limit for [`Orchestrator::recover`].
+    pub recovery_policy: confidential_ml_transport::RetryPolicy,
+    /// Resubmission of requests that fail with a retryable error (default:
+    /// no retries).
+    ///
+    /// Applies to [`Orchestrator::infer`], [`Orchestrator::infer_with_options`]
+    /// and [`Orchestrator::profile`]. Session, streaming, cancellable and
+    /// `submit`/`wait` requests are never retried.
+    pub request_retry_policy: RequestRetryPolicy,
     /// How long to wait per stage for RequestDone/RequestError during drain (default: 5s).
     pub stage_drain_timeout: Duration,
     /// Overall bound on draining data_out after stages have finished (default: 2s).
@@ -50,6 +70,22 @@ pub struct OrchestratorConfig {
     /// Messages exceeding this limit are rejected before deserialization.
     /// Default: 4 MiB.
     pub max_control_message_bytes: usize,
+    /// Protocol versions offered to stages in `Init` (default: every version
+    /// this build speaks). Each stage picks the newest one it also speaks.
+    pub protocol_versions: VersionRange,
+    /// Maximum number of requests that may be in the pipeline at once (default: 4).
+    ///
+    /// Stages process requests in submission order, so keeping several in
+    /// flight lets stage `i` work on one request while stage `i + 1` is still
+    /// busy with the previous one. A pipeline with a protocol version 1
+    /// stage keeps only one request in flight.
+    pub max_in_flight: usize,
+    /// Pipeline schedule the stages run (default: fill-drain).
+    ///
+    /// Stages run inference only, one layer chunk each, so [`Self::validate`]
+    /// rejects 1F1B (it interleaves backward passes) and interleaved
+    /// schedules with more than one chunk.
+    pub schedule: ScheduleKind,
 }
 
 impl Default for OrchestratorConfig {
@@ -59,12 +95,17 @@ impl Default for OrchestratorConfig {
             health_check_timeout: Duration::from_secs(10),
             infer_timeout: Duration::from_secs(60),
             tcp_retry_policy: confidential_ml_transport::RetryPolicy::default(),
+            recovery_policy: confidential_ml_transport::RetryPolicy::default(),
+            request_retry_policy: RequestRetryPolicy::default(),
             stage_drain_timeout: Duration::from_secs(5),
             data_drain_timeout: Duration::from_secs(2),
             data_quiet_period: Duration::from_millis(200),
             shutdown_timeout: Duration::from_secs(10),
             require_measurements: true,
             max_control_message_bytes: DEFAULT_MAX_CONTROL_MESSAGE_BYTES,
+            protocol_versions: VersionRange::SUPPORTED,
+            max_in_flight: 4,
+            schedule: ScheduleKind::FillDrain,
         }
     }
 }
@@ -118,7 +159,48 @@ impl OrchestratorConfig {
                 "max_control_message_bytes must be > 0".into(),
             ));
         }
-        Ok(())
+        if self.max_in_flight == 0 {
+            return Err(PipelineError::Protocol("max_in_flight must be > 0".into()));
+        }
+        // Whether stages can run a kind does not depend on the pipeline's
+        // shape, so the smallest one will do.
+        self.schedule.generate(1, 1)?.executable_stage(0)?;
+        self.protocol_versions.validate()?;
+        self.request_retry_policy.validate()
+    }
+}
+
+/// Per-request options for [`Orchestrator::infer_with_options`] and
+/// [`Orchestrator::submit_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    /// Point by which the request must finish.
+    ///
+    /// Replaces `OrchestratorConfig::infer_timeout` while waiting for this
+    /// request, and is sent to stages so they refuse or stop work on it once
+    /// it has passed. When it expires only this request is cancelled, and
+    /// the call returns `PipelineError::DeadlineExceeded`.
+    pub deadline: Option<std::time::Instant>,
+    /// Higher values start first. [`OrchestratorHandle`](crate::OrchestratorHandle)
+    /// uses this to order requests waiting for an in-flight slot; requests
+    /// already in the pipeline run in submission order.
+    pub priority: i32,
+    /// Request ID to use instead of a generated one, for example to
+    /// correlate stage logs with the caller's own. Must not match a request
+    /// that is still in flight or whose result has not been collected.
+    pub request_id: Option<RequestId>,
+    /// Ask stages for per-layer samples, returned in
+    /// [`InferenceResult::layer_samples`].
+    pub profile: bool,
+}
+
+impl RequestOptions {
+    /// Options with a deadline `timeout` from now.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            deadline: Some(std::time::Instant::now() + timeout),
+            ..Self::default()
+        }
     }
 }
 
@@ -127,12 +209,125 @@ impl OrchestratorConfig {
 pub struct InferenceResult {
     /// Output tensors from the final stage, grouped by micro-batch.
     pub outputs: Vec<Vec<OwnedTensor>>,
+    /// The request ID sent to the stages.
+    pub request_id: RequestId,
+    /// Per-micro-batch timings reported by each stage, indexed
+    /// `[stage][micro_batch]`.
+    pub stage_timings: Vec<Vec<MicroBatchTiming>>,
+    /// Per-layer samples reported by each stage, indexed by stage. Empty
+    /// unless the request was profiled; see [`Orchestrator::profile`].
+    pub layer_samples: Vec<Vec<LayerSample>>,
+    /// Time from submission until every stage confirmed the request.
+    pub total: Duration,
+    /// Earlier attempts that failed and were retried, oldest first. Empty
+    /// unless `OrchestratorConfig::request_retry_policy` allows retries.
+    pub retries: Vec<RetryAttempt>,
 }
 
 /// Handle to a connected stage.
 struct StageHandle<T> {
     stage_idx: usize,
     control: SecureChannel<T>,
+    /// Protocol version agreed with the stage in `Init`/`Ready`.
+    protocol_version: u32,
+    /// RequestDone/RequestError replies that arrived while the orchestrator
+    /// was waiting on a different request, keyed by request ID.
+    pending_replies: HashMap<u64, StageMsg>,
+}
+
+/// A request whose inputs have been sent but whose outputs have not yet
+/// been collected from the last stage.
+///
+/// Collection progress lives here rather than in the collecting future, so
+/// a collection that is cancelled part-way can be resumed or discarded.
+struct InFlightRequest {
+    request_id: u64,
+    num_micro_batches: u32,
+    /// The request's own deadline, if it has one.
+    deadline: Option<Instant>,
+    /// Micro-batches fully received from data_out (collected or handed out
+    /// by an `InferenceStream`).
+    received: u32,
+    /// Collected micro-batch outputs, for `wait`.
+    outputs: Vec<Vec<OwnedTensor>>,
+    /// Tensors of the micro-batch currently being received.
+    partial: Vec<OwnedTensor>,
+    /// Error frame received for this request. Kept so a collection that is
+    /// resumed after it does not wait for outputs that will never come.
+    failure: Option<DataError>,
+    /// When the request was submitted, for `InferenceResult::total`.
+    submitted_at: Instant,
+}
+
+/// Position of the orchestrator's reader within the frames on data_out.
+///
+/// Kept on the orchestrator so a receive that is cancelled part-way through
+/// a tensor group resumes inside it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum GroupState {
+    /// The next frame is a group header.
+    #[default]
+    Header,
+    /// Reading a group of the request being collected.
+    Reading { remaining: u32 },
+    /// Skipping a stale group of a request that is no longer collected.
+    Skipping { remaining: u32 },
+}
+
+/// Messages a data_out reader task may buffer ahead of the orchestrator.
+const DATA_OUT_QUEUE_DEPTH: usize = 64;
+
+/// The orchestrator's end of data_out.
+enum DataOut<T> {
+    /// Read by the orchestrator itself.
+    Channel(SecureChannel<T>),
+    /// Read by a task that forwards whole messages; see
+    /// [`Orchestrator::forward_data_out`].
+    Forwarded(ForwardedDataOut),
+}
+
+/// data_out messages forwarded by a reader task. Dropping it stops the task.
+struct ForwardedDataOut {
+    messages: mpsc::Receiver<std::result::Result<Message, confidential_ml_transport::Error>>,
+    reader: JoinHandle<()>,
+}
+
+impl Drop for ForwardedDataOut {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> DataOut<T> {
+    /// Receive the next message.
+    ///
+    /// Cancel-safe only when forwarded: a receive dropped while reading in
+    /// place may have consumed part of a frame.
+    async fn recv(&mut self) -> crate::error::Result<Message> {
+        match self {
+            DataOut::Channel(channel) => channel.recv().await.map_err(PipelineError::Transport),
+            DataOut::Forwarded(forwarded) => match forwarded.messages.recv().await {
+                Some(msg) => msg.map_err(PipelineError::Transport),
+                None => Err(PipelineError::Protocol(
+                    "data_out reader stopped after a transport error".into(),
+                )),
+            },
+        }
+    }
+}
+
+/// Work a call was doing when its future was dropped.
+///
+/// Recorded while the call runs and reconciled at the start of the next
+/// call, so an abandoned future cannot leave the channels out of step.
+enum Unfinished {
+    /// Frames were being written to a control or data channel. A frame may
+    /// have been sealed but only partly written, so the channel is unusable.
+    Send,
+    /// Requests were being collected for a caller that is now gone.
+    Collect(Vec<u64>),
+    /// A timeout drain was in progress; stale frames may remain.
+    Drain,
 }
 
 /// Lifecycle state for the orchestrator.
@@ -153,7 +348,20 @@ pub struct Orchestrator<T> {
     stages: Vec<StageHandle<T>>,
     relay_handles: Vec<RelayHandle>,
     data_in: Option<SecureChannel<T>>,
-    data_out: Option<SecureChannel<T>>,
+    data_out: Option<DataOut<T>>,
+    /// Where the reader is within the frames on `data_out`.
+    data_out_group: GroupState,
+    /// Submitted requests in the order their outputs will arrive on data_out.
+    in_flight: VecDeque<InFlightRequest>,
+    /// Results collected while waiting for a different request.
+    completed: HashMap<u64, crate::error::Result<InferenceResult>>,
+    /// In-flight requests whose caller is gone (a dropped `InferenceStream`).
+    /// Their remaining outputs are still collected, then discarded.
+    detached: HashSet<u64>,
+    /// Sessions opened on every stage and not yet closed.
+    sessions: HashSet<u64>,
+    /// Set while a call is in progress; see [`Unfinished`].
+    unfinished: Option<Unfinished>,
     tainted: bool,
     state: OrchestratorState,
 }
@@ -169,6 +377,12 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
             relay_handles: Vec::new(),
             data_in: None,
             data_out: None,
+            data_out_group: GroupState::Header,
+            in_flight: VecDeque::new(),
+            completed: HashMap::new(),
+            detached: HashSet::new(),
+            sessions: HashSet::new(),
+            unfinished: None,
             tainted: false,
             state: OrchestratorState::Created,
         })
@@ -189,6 +403,106 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
         self.tainted
     }
 
+    /// Number of submitted requests whose outputs have not been collected yet.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Maximum number of requests that may be in flight at once.
+    ///
+    /// `OrchestratorConfig::max_in_flight`, or 1 once a stage has agreed on
+    /// protocol version 1, since such a stage runs one request at a time.
+    pub fn max_in_flight(&self) -> usize {
+        if self.data_version() < 2 {
+            1
+        } else {
+            self.config.max_in_flight
+        }
+    }
+
+    /// Protocol version of the data channels: the oldest version any stage
+    /// agreed in `Init`/`Ready`.
+    fn data_version(&self) -> u32 {
+        self.stages
+            .iter()
+            .map(|stage| stage.protocol_version)
+            .min()
+            .unwrap_or(PROTOCOL_VERSION)
+    }
+
+    /// Framing of the data channels.
+    fn data_framing(&self) -> DataFraming {
+        DataFraming::for_version(self.data_version())
+    }
+
+    /// Return an error if a stage speaks a protocol version without
+    /// `feature`, which needs version 2.
+    fn require_version(&self, feature: &str) -> crate::error::Result<()> {
+        match self.stages.iter().find(|stage| stage.protocol_version < 2) {
+            Some(stage) => Err(PipelineError::Protocol(format!(
+                "{feature} needs protocol version 2, but stage {} speaks version {}",
+                stage.stage_idx, stage.protocol_version
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Reconcile whatever a dropped call left behind, then return an error
+    /// unless the pipeline is Ready and not tainted.
+    fn ensure_ready(&mut self, op: &str) -> crate::error::Result<()> {
+        self.reconcile_unfinished();
+        self.check_ready(op)
+    }
+
+    /// Clean up after a call whose future was dropped before it finished.
+    ///
+    /// Requests it was collecting are detached: their outputs are still read
+    /// in order and then discarded. An interrupted send or drain leaves the
+    /// channels in an unknown state, so the pipeline is tainted.
+    fn reconcile_unfinished(&mut self) {
+        match self.unfinished.take() {
+            None => {}
+            Some(Unfinished::Collect(request_ids)) => {
+                for request_id in request_ids {
+                    if self.completed.remove(&request_id).is_some() {
+                        debug!(
+                            request_id,
+                            "orchestrator: discarding result of dropped call"
+                        );
+                    } else if self.in_flight.iter().any(|r| r.request_id == request_id) {
+                        debug!(
+                            request_id,
+                            "orchestrator: detaching request of dropped call"
+                        );
+                        self.detached.insert(request_id);
+                    }
+                }
+            }
+            Some(Unfinished::Send) => {
+                warn!("orchestrator: previous call dropped mid-send, tainting pipeline");
+                self.tainted = true;
+            }
+            Some(Unfinished::Drain) => {
+                warn!("orchestrator: previous call dropped mid-drain, tainting pipeline");
+                self.tainted = true;
+            }
+        }
+    }
+
+    /// Return an error unless the pipeline is Ready and not tainted.
+    fn check_ready(&self, op: &str) -> crate::error::Result<()> {
+        // A failed recovery leaves the orchestrator tainted but not Ready.
+        if self.tainted {
+            return Err(PipelineError::Tainted);
+        }
+        if self.state != OrchestratorState::Ready {
+            return Err(PipelineError::Protocol(format!(
+                "{op}() requires Ready state (call init() then establish_data_channels() first)"
+            )));
+        }
+        Ok(())
+    }
+
     /// Abort all relay tasks and clear the handles list.
     fn abort_and_clear_relays(&mut self) {
         for relay in &self.relay_handles {
@@ -252,6 +566,8 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
             self.stages.push(StageHandle {
                 stage_idx: i,
                 control: channel,
+                protocol_version: self.config.protocol_versions.min,
+                pending_replies: HashMap::new(),
             });
         }
 
@@ -266,23 +582,53 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
                 stage_spec_json,
                 activation_spec_json: activation_spec_json.clone(),
                 num_stages,
+                schedule: self.config.schedule,
+                protocol_versions: Some(self.config.protocol_versions),
             };
 
-            stage
-                .control
-                .send(msg.to_bytes()?)
-                .await
-                .map_err(PipelineError::Transport)?;
+            // Sent in our oldest version, which every stage we can talk to
+            // reads.
+            stage.send(&msg).await?;
         }
 
         let max_bytes = self.config.max_control_message_bytes;
+        let versions = self.config.protocol_versions;
         for stage in &mut self.stages {
-            let msg = recv_stage_msg(&mut stage.control, max_bytes).await?;
+            let Envelope { version, msg } =
+                recv_stage_envelope(&mut stage.control, max_bytes).await?;
             match msg {
-                StageMsg::Ready { stage_idx } if stage_idx == stage.stage_idx => {
-                    info!(stage = stage_idx, "orchestrator: stage ready");
+                StageMsg::Ready {
+                    stage_idx,
+                    protocol_version,
+                } if stage_idx == stage.stage_idx => {
+                    // Version 1 stages do not report a version; their
+                    // envelope's is the one they speak.
+                    if let Some(reported) = protocol_version.filter(|&v| v != version) {
+                        return Err(PipelineError::Protocol(format!(
+                            "stage {stage_idx} reported protocol version {reported} \
+                             in a version {version} Ready"
+                        )));
+                    }
+                    if !versions.contains(version) {
+                        return Err(PipelineError::VersionMismatch {
+                            expected: versions.max,
+                            actual: version,
+                        });
+                    }
+                    if version < 2 && needs_version_2(&self.manifest.stages[stage_idx]) {
+                        return Err(PipelineError::Protocol(format!(
+                            "stage {stage_idx} speaks protocol version {version}, which has no \
+                             graph links, pass-through tensors or tensor contracts"
+                        )));
+                    }
+                    stage.protocol_version = version;
+                    info!(
+                        stage = stage_idx,
+                        protocol_version = version,
+                        "orchestrator: stage ready"
+                    );
                 }
-                StageMsg::Ready { stage_idx } => {
+                StageMsg::Ready { stage_idx, .. } => {
                     return Err(PipelineError::Protocol(format!(
                         "stage {} reported Ready with wrong stage_idx {stage_idx}",
                         stage.stage_idx
@@ -332,6 +678,11 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
     /// - `data_in_transport`: orchestrator connects (initiator) to stage 0's data_in (responder)
     /// - `data_out_transport`: last stage connects (initiator) to orchestrator's acceptor (responder)
     /// - `provider`: attestation provider for the orchestrator's responder role on data_out
+    ///
+    /// In a graph manifest, stage 0 and the last stage are the manifest's
+    /// [entry](ShardManifest::entry_stage) and [exit](ShardManifest::exit_stage)
+    /// stages, and `relay_handles` carry its [links](ShardManifest::links),
+    /// e.g. from [`start_relay_graph`](crate::start_relay_graph).
     pub async fn establish_data_channels(
         &mut self,
         data_in_transport: T,
@@ -364,18 +715,15 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
             ));
         }
 
-        let num_stages = self.stages.len();
-
+        // Version 1 stages expect no data version; they use version 1.
+        let data_version = self.data_version();
         for (i, stage) in self.stages.iter_mut().enumerate() {
             let msg = OrchestratorMsg::EstablishDataChannels {
-                has_upstream: i > 0,
-                has_downstream: i < num_stages - 1,
+                has_upstream: !self.manifest.upstream_of(i).is_empty(),
+                has_downstream: !self.manifest.downstream_of(i).is_empty(),
+                data_version: (stage.protocol_version >= 2).then_some(data_version),
             };
-            stage
-                .control
-                .send(msg.to_bytes()?)
-                .await
-                .map_err(PipelineError::Transport)?;
+            stage.send(&msg).await?;
         }
 
         info!("orchestrator: sent EstablishDataChannels to all stages");
@@ -402,18 +750,22 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
         // Store relay handles but abort them if any subsequent setup step fails.
         self.relay_handles = relay_handles;
 
-        // Connect data_in to stage 0 (orchestrator = initiator, stage 0 = responder).
-        // Apply stage 0's measurements so the data channel verifies the same
-        // enclave identity as the control channel.
+        // Connect data_in to the entry stage (orchestrator = initiator, stage =
+        // responder). Apply its measurements so the data channel verifies the
+        // same enclave identity as the control channel.
+        let entry_idx = self.manifest.entry_stage();
         let mut data_in_config = self.config.session_config.clone();
-        if !self.manifest.stages[0].expected_measurements.is_empty() {
+        if !self.manifest.stages[entry_idx]
+            .expected_measurements
+            .is_empty()
+        {
             data_in_config.expected_measurements = Some(
-                self.manifest.stages[0]
+                self.manifest.stages[entry_idx]
                     .to_expected_measurements()
                     .map_err(|e| {
                         self.abort_and_clear_relays();
                         PipelineError::Protocol(format!(
-                            "invalid measurements for stage 0 data_in: {e}"
+                            "invalid measurements for stage {entry_idx} data_in: {e}"
                         ))
                     })?,
             );
@@ -432,10 +784,11 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
             })?,
         );
 
-        // Accept data_out from last stage (last stage = initiator, orchestrator = responder).
-        // Apply last stage's measurements — with mutual attestation (transport v0.4),
-        // the responder verifies the initiator's attestation during handshake.
-        let last_idx = self.manifest.stages.len() - 1;
+        // Accept data_out from the exit stage (stage = initiator, orchestrator =
+        // responder). Apply its measurements — with mutual attestation
+        // (transport v0.4), the responder verifies the initiator's attestation
+        // during handshake.
+        let last_idx = self.manifest.exit_stage();
         let mut data_out_config = self.config.session_config.clone();
         if !self.manifest.stages[last_idx]
             .expected_measurements
@@ -452,7 +805,7 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
                     })?,
             );
         }
-        self.data_out = Some(
+        self.data_out = Some(DataOut::Channel(
             SecureChannel::accept_with_attestation(
                 data_out_transport,
                 provider,
@@ -464,11 +817,12 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
                 self.abort_and_clear_relays();
                 PipelineError::Transport(e)
             })?,
-        );
+        ));
+        self.data_out_group = GroupState::Header;
 
         let max_bytes = self.config.max_control_message_bytes;
         for stage in &mut self.stages {
-            let msg = recv_stage_msg(&mut stage.control, max_bytes).await?;
+            let msg = stage.recv(max_bytes).await?;
             match msg {
                 StageMsg::DataChannelsReady { stage_idx } if stage_idx == stage.stage_idx => {
                     info!(stage = stage_idx, "orchestrator: data channels ready");
@@ -498,249 +852,1234 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
         &self.manifest
     }
 
+    /// Protocol version agreed with each connected stage, in stage order.
+    pub fn protocol_versions(&self) -> Vec<u32> {
+        self.stages.iter().map(|s| s.protocol_version).collect()
+    }
+
+    /// Retry policy used by [`Self::recover`].
+    pub(crate) fn recovery_policy(&self) -> &confidential_ml_transport::RetryPolicy {
+        &self.config.recovery_policy
+    }
+
+    /// Retry policy for failed requests.
+    pub(crate) fn request_retry_policy(&self) -> &RequestRetryPolicy {
+        &self.config.request_retry_policy
+    }
+
+    /// Drop every channel and relay and return to the Created state, so
+    /// `init()` can run again.
+    ///
+    /// Requests still in flight fail with `PipelineError::Shutdown`. Open
+    /// sessions are forgotten, since the stages' session state goes with
+    /// the connections.
+    pub(crate) fn teardown(&mut self) {
+        self.abort_and_clear_relays();
+        self.stages.clear();
+        self.data_in = None;
+        self.data_out = None;
+        self.data_out_group = GroupState::Header;
+        self.sessions.clear();
+        self.unfinished = None;
+
+        let in_flight: Vec<u64> = self.in_flight.drain(..).map(|r| r.request_id).collect();
+        for request_id in in_flight {
+            self.stash_result(request_id, Err(PipelineError::Shutdown));
+        }
+        self.detached.clear();
+
+        self.tainted = false;
+        self.state = OrchestratorState::Created;
+    }
+
+    /// Mark the pipeline tainted, e.g. after recovery gave up.
+    pub(crate) fn mark_tainted(&mut self) {
+        self.tainted = true;
+    }
+
     /// Run an inference request through the pipeline.
     ///
     /// Sends input tensors to stage 0, receives output tensors from the last stage.
-    /// If a stage fails, it sends an error sentinel on the data channel, which
-    /// unblocks the output receiver. The orchestrator then reads the actual error
-    /// from the control channel.
+    /// If a stage fails, it sends an error frame naming itself on the data
+    /// channel, which unblocks the output receiver. The orchestrator then reads
+    /// the actual error from that stage's control channel.
+    ///
+    /// Equivalent to [`Self::submit`] followed by [`Self::wait`]. Requests that
+    /// were submitted earlier and are still in flight are collected first and
+    /// their results kept for their own `wait` calls.
     ///
     /// On timeout, attempts to drain stale protocol state from channels. If drain
     /// fails (stages stuck), the pipeline is marked tainted and further calls
     /// return `PipelineError::Tainted`.
     ///
+    /// The returned future may be dropped, e.g. by an outer `select!`. If
+    /// that happens after the inputs were sent, the next call detaches the
+    /// request and its outputs are read and discarded in order. If it
+    /// happens while inputs are still being written, the channel may hold a
+    /// partial frame and the next call marks the pipeline tainted.
+    ///
     /// Subject to `OrchestratorConfig::infer_timeout`.
     pub async fn infer(
         &mut self,
         input_tensors: Vec<Vec<OwnedTensor>>,
         seq_len: u32,
     ) -> crate::error::Result<InferenceResult> {
-        if self.state != OrchestratorState::Ready {
-            return Err(PipelineError::Protocol(
-                "infer() requires Ready state (call init() then establish_data_channels() first)"
-                    .into(),
-            ));
-        }
-        if self.tainted {
-            return Err(PipelineError::Tainted);
-        }
+        self.ensure_ready("infer")?;
+        self.infer_with_retry(input_tensors, seq_len, RequestOptions::default())
+            .await
+    }
+
+    /// Run an inference request with per-request options.
+    ///
+    /// Same as [`Self::infer`], except that `options.deadline` (if set)
+    /// bounds the wait for outputs instead of
+    /// `OrchestratorConfig::infer_timeout`. Stages are told how much time is
+    /// left and refuse or stop the request once it runs out. A missed
+    /// deadline cancels only this request, as with
+    /// [`Self::infer_cancellable`], and returns
+    /// `PipelineError::DeadlineExceeded`. Input submission is never cut
+    /// short by a deadline shorter than `infer_timeout`, since an
+    /// interrupted send would taint the pipeline.
+    pub async fn infer_with_options(
+        &mut self,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+        options: RequestOptions,
+    ) -> crate::error::Result<InferenceResult> {
+        self.ensure_ready("infer_with_options")?;
+        self.infer_with_retry(input_tensors, seq_len, options).await
+    }
+
+    /// Profile the model's layers on `input_tensors`, typically from
+    /// [`synthetic_inputs`](crate::synthetic_inputs).
+    ///
+    /// Runs one request with [`RequestOptions::profile`] set. Each stage
+    /// times every layer on every micro-batch, using
+    /// [`StageExecutor::forward_profiled`](crate::StageExecutor::forward_profiled),
+    /// and reports the samples over its control channel. The samples are
+    /// combined into a [`ModelProfile`] whose
+    /// [`layer_costs`](ModelProfile::layer_costs) feed
+    /// [`partition_layers`](crate::partition_layers).
+    pub async fn profile(
+        &mut self,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+    ) -> crate::error::Result<ModelProfile> {
+        self.ensure_ready("profile")?;
+        let options = RequestOptions {
+            profile: true,
+            ..RequestOptions::default()
+        };
+        let result = self
+            .infer_with_retry(input_tensors, seq_len, options)
+            .await?;
+        Ok(ModelProfile::from_samples(
+            &self.manifest,
+            &result.layer_samples,
+            seq_len,
+        )?)
+    }
+
+    /// Run an inference request that the caller can cancel through `cancel`.
+    ///
+    /// Behaves like [`Self::infer`] until `cancel` fires. The orchestrator
+    /// then sends `AbortRequest` to every stage, and each stage stops after
+    /// its current micro-batch. The request's remaining outputs and stage
+    /// replies are drained, so the pipeline stays usable, and the call
+    /// returns `PipelineError::Cancelled`. Inputs are always sent in full:
+    /// cancellation takes effect once submission is complete. A token that
+    /// is already cancelled returns `Cancelled` without submitting.
+    ///
+    /// Requests submitted earlier are unaffected; their results are kept for
+    /// their own `wait` calls. The pipeline is only tainted if the stages do
+    /// not wind the request down within `OrchestratorConfig::infer_timeout`.
+    pub async fn infer_cancellable(
+        &mut self,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+        cancel: &CancelToken,
+    ) -> crate::error::Result<InferenceResult> {
+        self.ensure_ready("infer_cancellable")?;
 
         let request_id = rand_request_id();
-        let timeout = self.config.infer_timeout;
+        if cancel.is_cancelled() {
+            return Err(PipelineError::Cancelled { request_id });
+        }
+        let deadline = Instant::now() + self.config.infer_timeout;
+
+        self.unfinished = Some(Unfinished::Collect(vec![request_id]));
+        let result = self
+            .infer_cancellable_inner(request_id, input_tensors, seq_len, deadline, cancel)
+            .await;
+        self.finish_call();
+        result
+    }
 
-        match tokio::time::timeout(
-            timeout,
-            self.infer_inner(request_id, input_tensors, seq_len),
+    async fn infer_cancellable_inner(
+        &mut self,
+        request_id: u64,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+        deadline: Instant,
+        cancel: &CancelToken,
+    ) -> crate::error::Result<InferenceResult> {
+        let waited = match tokio::time::timeout_at(
+            deadline,
+            self.submit_inner(request_id, None, None, false, input_tensors, seq_len),
         )
         .await
         {
-            Ok(result) => result,
-            Err(_) => {
+            Ok(Ok(())) => {
+                tokio::select! {
+                    biased;
+                    result = tokio::time::timeout_at(deadline, self.wait_inner(request_id)) => Some(result),
+                    () = cancel.cancelled() => None,
+                }
+            }
+            Ok(Err(e)) => return Err(e),
+            Err(elapsed) => Some(Err(elapsed)),
+        };
+
+        match waited {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => {
                 warn!(request_id, "orchestrator: inference timed out, draining");
-                self.drain_timed_out_infer(request_id).await;
+                self.drain_timed_out_infer().await;
+                self.completed.remove(&request_id);
                 Err(PipelineError::Timeout("inference timed out".into()))
             }
+            None => {
+                self.cancel_request(request_id, "cancelled by caller").await;
+                Err(PipelineError::Cancelled { request_id })
+            }
         }
     }
 
-    async fn infer_inner(
-        &mut self,
-        request_id: u64,
-        mut input_tensors: Vec<Vec<OwnedTensor>>,
-        seq_len: u32,
-    ) -> crate::error::Result<InferenceResult> {
-        let num_micro_batches = u32::try_from(input_tensors.len()).map_err(|_| {
-            PipelineError::Protocol(format!(
-                "too many micro-batches: {} exceeds u32::MAX",
-                input_tensors.len()
-            ))
-        })?;
+    /// Open a session on every stage.
+    ///
+    /// Requests run with [`Self::infer_in_session`] share stage-side state
+    /// (for example a decoder's KV cache) until [`Self::close_session`].
+    /// If a session request fails or is aborted, stages drop the session's
+    /// state and reject its later requests; close it and open a new one.
+    pub async fn open_session(&mut self) -> crate::error::Result<SessionId> {
+        self.ensure_ready("open_session")?;
+        self.require_version("opening a session")?;
+
+        let session_id = rand_request_id();
+        self.broadcast(&OrchestratorMsg::OpenSession { session_id })
+            .await?;
+        self.sessions.insert(session_id);
+        debug!(session_id, "orchestrator: session opened");
+        Ok(session_id)
+    }
 
-        if num_micro_batches == 0 {
-            return Ok(InferenceResult {
-                outputs: Vec::new(),
-            });
+    /// Close a session opened with [`Self::open_session`]; every stage
+    /// releases its state.
+    pub async fn close_session(&mut self, session_id: SessionId) -> crate::error::Result<()> {
+        self.ensure_ready("close_session")?;
+        if !self.sessions.remove(&session_id) {
+            return Err(PipelineError::Protocol(format!(
+                "unknown session {session_id}"
+            )));
         }
 
-        if seq_len > self.manifest.activation_spec.max_seq_len {
+        self.broadcast(&OrchestratorMsg::CloseSession { session_id })
+            .await?;
+        debug!(session_id, "orchestrator: session closed");
+        Ok(())
+    }
+
+    /// Run an inference request that continues `session_id`.
+    ///
+    /// Same as [`Self::infer`], except that stages run the request against
+    /// the session's state.
+    pub async fn infer_in_session(
+        &mut self,
+        session_id: SessionId,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+    ) -> crate::error::Result<InferenceResult> {
+        self.ensure_ready("infer_in_session")?;
+        if !self.sessions.contains(&session_id) {
             return Err(PipelineError::Protocol(format!(
-                "seq_len {} exceeds manifest max_seq_len {}",
-                seq_len, self.manifest.activation_spec.max_seq_len
+                "unknown session {session_id}"
             )));
         }
+        self.infer_inner(
+            Some(session_id),
+            input_tensors,
+            seq_len,
+            RequestOptions::default(),
+        )
+        .await
+    }
 
-        let data_in = self
-            .data_in
-            .as_mut()
-            .ok_or_else(|| PipelineError::Protocol("data channels not established".into()))?;
-        let data_out = self
-            .data_out
-            .as_mut()
-            .ok_or_else(|| PipelineError::Protocol("data channels not established".into()))?;
-
-        // Send StartRequest to all stages.
-        for stage in &mut self.stages {
-            let msg = OrchestratorMsg::StartRequest {
+    pub(crate) async fn infer_inner(
+        &mut self,
+        session_id: Option<SessionId>,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+        options: RequestOptions,
+    ) -> crate::error::Result<InferenceResult> {
+        let request_id = self.claim_request_id(options.request_id)?;
+        let deadline = options.deadline.map(Instant::from_std);
+        let global_deadline = Instant::now() + self.config.infer_timeout;
+        let wait_until = deadline.unwrap_or(global_deadline);
+
+        // Only this call knows the request ID; if it is dropped, the next
+        // call detaches the request.
+        self.unfinished = Some(Unfinished::Collect(vec![request_id]));
+        let result = match tokio::time::timeout_at(
+            wait_until.max(global_deadline),
+            self.submit_inner(
                 request_id,
-                num_micro_batches,
+                session_id,
+                deadline,
+                options.profile,
+                input_tensors,
                 seq_len,
-            };
-            stage
-                .control
-                .send(msg.to_bytes()?)
-                .await
-                .map_err(PipelineError::Transport)?;
-        }
-
-        debug!(
-            request_id,
-            num_micro_batches, "orchestrator: sending input tensors"
-        );
-
-        // Send input tensors to stage 0.
-        for mb_tensors in &input_tensors {
-            for t in mb_tensors {
-                data_in
-                    .send_tensor(t.as_ref())
-                    .await
-                    .map_err(PipelineError::Transport)?;
+            ),
+        )
+        .await
+        {
+            Ok(Ok(())) => {
+                match tokio::time::timeout_at(wait_until, self.wait_inner(request_id)).await {
+                    Ok(result) => missed_deadline(request_id, deadline, result),
+                    Err(_) => Err(self.expire(request_id, deadline.is_some()).await),
+                }
             }
-            data_in
-                .send(Bytes::from_static(b"END"))
-                .await
-                .map_err(PipelineError::Transport)?;
-        }
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(self.expire(request_id, false).await),
+        };
+        self.finish_call();
+        result
+    }
 
-        // SEC-705: Explicitly clear input tensor metadata after sending.
-        // OwnedTensor.data is bytes::Bytes (Arc-backed) — cannot reliably zeroize
-        // the shared allocation. Dropping releases our reference count.
-        for mb_tensors in &mut input_tensors {
-            for tensor in mb_tensors.iter_mut() {
-                tensor.name.zeroize();
-                tensor.shape.zeroize();
-            }
+    /// Handle a request whose time ran out.
+    ///
+    /// If it was the request's own deadline, only that request is
+    /// cancelled. Otherwise (`infer_timeout`) every in-flight request is
+    /// drained, in case the pipeline is stuck.
+    pub(crate) async fn expire(&mut self, request_id: u64, own_deadline: bool) -> PipelineError {
+        if own_deadline {
+            warn!(request_id, "orchestrator: request missed its deadline");
+            self.cancel_request(request_id, "deadline exceeded").await;
+            return PipelineError::DeadlineExceeded { request_id };
         }
-        drop(input_tensors);
+        warn!(request_id, "orchestrator: inference timed out, draining");
+        self.drain_timed_out_infer().await;
+        self.completed.remove(&request_id);
+        PipelineError::Timeout("inference timed out".into())
+    }
 
-        // Receive output tensors from last stage.
-        // If a stage failed, it sends an ERR sentinel on its data_out, which
-        // propagates through relays and surfaces here as a StageFailed error.
-        let output_result = receive_all_outputs(data_out, num_micro_batches).await;
-
-        match output_result {
-            Ok(outputs) => {
-                // Success: collect RequestDone confirmations from all stages.
-                // Tolerant: skip stale Pongs and wrong-request-id messages.
-                let max_bytes = self.config.max_control_message_bytes;
-                for stage in &mut self.stages {
-                    let msg =
-                        recv_stage_msg_tolerant(&mut stage.control, Some(request_id), max_bytes)
-                            .await?;
-                    match msg {
-                        StageMsg::RequestDone { request_id: rid } if rid == request_id => {
-                            debug!(stage = stage.stage_idx, "orchestrator: stage done");
-                        }
-                        StageMsg::RequestError {
-                            request_id: rid,
-                            error,
-                        } if rid == request_id => {
-                            return Err(PipelineError::RequestFailed {
-                                request_id,
-                                reason: format!("stage {} error: {}", stage.stage_idx, error),
-                            });
-                        }
-                        other => {
-                            return Err(PipelineError::Protocol(format!(
-                                "expected RequestDone/RequestError for {request_id} from stage {}, got {other:?}",
-                                stage.stage_idx
-                            )));
-                        }
-                    }
-                }
+    /// Use the caller's request ID if it is free, or generate one.
+    fn claim_request_id(&self, requested: Option<RequestId>) -> crate::error::Result<u64> {
+        let Some(request_id) = requested else {
+            return Ok(rand_request_id());
+        };
+        if self.in_flight.iter().any(|r| r.request_id == request_id)
+            || self.completed.contains_key(&request_id)
+        {
+            return Err(PipelineError::Protocol(format!(
+                "request id {request_id} is already in use"
+            )));
+        }
+        Ok(request_id)
+    }
 
-                info!(request_id, "orchestrator: inference complete");
-                Ok(InferenceResult { outputs })
-            }
-            Err(PipelineError::StageFailed { .. }) => {
-                // A stage sent an error sentinel. Read control channels for details.
-                // Use per-stage timeout so backpressure-stuck stages don't block the entire drain.
-                let drain_timeout = self.config.stage_drain_timeout;
-                let max_bytes = self.config.max_control_message_bytes;
-                for stage in &mut self.stages {
-                    match tokio::time::timeout(
-                        drain_timeout,
-                        recv_stage_msg_tolerant(&mut stage.control, Some(request_id), max_bytes),
-                    )
-                    .await
-                    {
-                        Ok(Ok(StageMsg::RequestError {
-                            request_id: rid,
-                            error,
-                        })) if rid == request_id => {
-                            return Err(PipelineError::RequestFailed {
-                                request_id,
-                                reason: format!("stage {} error: {}", stage.stage_idx, error),
-                            });
-                        }
-                        Ok(Ok(_)) => continue,
-                        Ok(Err(_)) => continue,
-                        Err(_) => {
-                            debug!(
-                                stage = stage.stage_idx,
-                                "error drain: stage stuck, skipping"
-                            );
-                            continue;
-                        }
-                    }
-                }
-                // If no stage reported an error explicitly, return generic failure.
-                Err(PipelineError::RequestFailed {
-                    request_id,
-                    reason: "stage failed (no error details on control channel)".into(),
-                })
-            }
-            Err(e) => Err(e),
+    /// Clear the dropped-call marker at the end of a call. A send that a
+    /// timeout interrupted leaves the channels unusable, so that taints.
+    fn finish_call(&mut self) {
+        if let Some(Unfinished::Send) = self.unfinished.take() {
+            warn!("orchestrator: send interrupted by a timeout, tainting pipeline");
+            self.tainted = true;
         }
     }
 
-    /// Drain stale protocol state after an inference timeout.
+    /// Submit an inference request without waiting for its outputs.
     ///
-    /// Step 1: Wait for each stage to send RequestDone/RequestError on control.
-    ///         If any stage doesn't respond within `stage_drain_timeout`, mark tainted.
-    /// Step 2: Drain remaining data_out messages until quiet.
-    ///         If data_out still has pending frames after `data_drain_timeout`, mark tainted.
-    async fn drain_timed_out_infer(&mut self, request_id: u64) {
-        let stage_drain_timeout = self.config.stage_drain_timeout;
-        let data_drain_timeout = self.config.data_drain_timeout;
-        let data_quiet_period = self.config.data_quiet_period;
-        let max_bytes = self.config.max_control_message_bytes;
-
-        // Step 1: Drain control channels.
-        for i in 0..self.stages.len() {
-            let stage_idx = self.stages[i].stage_idx;
-            let result = tokio::time::timeout(
-                stage_drain_timeout,
-                drain_control_until_request_complete(
-                    &mut self.stages[i].control,
+    /// Sends `StartRequest` to every stage and the input tensors to stage 0,
+    /// then returns the request ID to pass to [`Self::wait`]. Stages work on
+    /// requests in submission order, so up to
+    /// `OrchestratorConfig::max_in_flight` requests can occupy different
+    /// stages at the same time. Submitting beyond that limit returns
+    /// `PipelineError::TooManyInFlight`.
+    pub async fn submit(
+        &mut self,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+    ) -> crate::error::Result<RequestId> {
+        self.submit_with_options(input_tensors, seq_len, RequestOptions::default())
+            .await
+    }
+
+    /// [`Self::submit`] with per-request options.
+    ///
+    /// `options.deadline` is sent to the stages and later bounds
+    /// [`Self::wait`] for this request, as described for
+    /// [`Self::infer_with_options`]. `options.request_id` replaces the
+    /// generated ID.
+    pub async fn submit_with_options(
+        &mut self,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+        options: RequestOptions,
+    ) -> crate::error::Result<RequestId> {
+        self.ensure_ready("submit")?;
+
+        let request_id = self.claim_request_id(options.request_id)?;
+        let deadline = options.deadline.map(Instant::from_std);
+        self.submit_inner(
+            request_id,
+            None,
+            deadline,
+            options.profile,
+            input_tensors,
+            seq_len,
+        )
+        .await?;
+        Ok(request_id)
+    }
+
+    /// Wait for a request previously returned by [`Self::submit`].
+    ///
+    /// Outputs arrive in submission order, so waiting for a later request
+    /// first collects the earlier ones; their results are kept until their
+    /// own `wait` call. Subject to `OrchestratorConfig::infer_timeout`; on
+    /// timeout every in-flight request is drained and fails. A request
+    /// submitted with a deadline waits until that deadline instead and is
+    /// cancelled alone when it passes.
+    ///
+    /// Collection progress is kept on the orchestrator, so if this future is
+    /// dropped, calling `wait` again with the same ID picks up where it
+    /// stopped.
+    pub async fn wait(&mut self, request_id: RequestId) -> crate::error::Result<InferenceResult> {
+        self.reconcile_unfinished();
+        self.wait_timed(request_id).await
+    }
+
+    /// [`Self::wait`] without reconciling a dropped call, for callers that
+    /// track their own requests.
+    async fn wait_timed(&mut self, request_id: u64) -> crate::error::Result<InferenceResult> {
+        if let Some(result) = self.completed.remove(&request_id) {
+            return result;
+        }
+        self.check_ready("wait")?;
+
+        let deadline = self
+            .in_flight
+            .iter()
+            .find(|r| r.request_id == request_id)
+            .and_then(|r| r.deadline);
+        let wait_until = deadline.unwrap_or_else(|| Instant::now() + self.config.infer_timeout);
+        match tokio::time::timeout_at(wait_until, self.wait_inner(request_id)).await {
+            Ok(result) => missed_deadline(request_id, deadline, result),
+            Err(_) => Err(self.expire(request_id, deadline.is_some()).await),
+        }
+    }
+
+    /// When waiting for `request_id` gives up, and whether that is the
+    /// request's own deadline rather than `infer_timeout` after submission.
+    pub(crate) fn wait_deadline(&self, request_id: RequestId) -> (Instant, bool) {
+        match self.in_flight.iter().find(|r| r.request_id == request_id) {
+            Some(InFlightRequest {
+                deadline: Some(deadline),
+                ..
+            }) => (*deadline, true),
+            Some(request) => (request.submitted_at + self.config.infer_timeout, false),
+            None => (Instant::now() + self.config.infer_timeout, false),
+        }
+    }
+
+    /// Collect in-flight requests until `request_id` completes, with no
+    /// timeout. Pair with [`Self::wait_deadline`] and [`Self::expire`].
+    ///
+    /// Progress stays on the orchestrator, but control channels are read in
+    /// place, so only drop the future to give up on the request. Use
+    /// [`Self::receive_outputs`] to wait for outputs interruptibly.
+    pub(crate) async fn collect(
+        &mut self,
+        request_id: RequestId,
+    ) -> crate::error::Result<InferenceResult> {
+        if let Some(result) = self.completed.remove(&request_id) {
+            return result;
+        }
+        self.check_ready("wait")?;
+
+        let deadline = self
+            .in_flight
+            .iter()
+            .find(|r| r.request_id == request_id)
+            .and_then(|r| r.deadline);
+        let result = self.wait_inner(request_id).await;
+        missed_deadline(request_id, deadline, result)
+    }
+
+    /// Whether `request_id` is the oldest request in flight and still has
+    /// outputs to receive.
+    pub(crate) fn awaiting_outputs(&self, request_id: RequestId) -> bool {
+        self.in_flight.front().is_some_and(|head| {
+            head.request_id == request_id
+                && head.failure.is_none()
+                && head.received < head.num_micro_batches
+        })
+    }
+
+    /// Receive the outputs of `request_id`, if it is the oldest request in
+    /// flight, without reading control channels; [`Self::collect`] then
+    /// gathers the stages' replies. Cancel-safe once data_out is forwarded
+    /// (see [`Self::forward_data_out`]).
+    ///
+    /// An error frame is kept for `collect` to report. Any other error is
+    /// returned and the request must be given up with [`Self::retire_head`].
+    pub(crate) async fn receive_outputs(
+        &mut self,
+        request_id: RequestId,
+    ) -> crate::error::Result<()> {
+        if !self.awaiting_outputs(request_id) {
+            return Ok(());
+        }
+        match self.receive_head_outputs().await {
+            Err(PipelineError::UpstreamFailed(_)) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Run several inference requests, keeping up to
+    /// `OrchestratorConfig::max_in_flight` of them in the pipeline at once.
+    ///
+    /// Each element of `requests` is `(input_tensors, seq_len)` as for
+    /// [`Self::infer`]. Results are returned in the same order as `requests`;
+    /// a failing request does not prevent the others from completing.
+    pub async fn infer_many(
+        &mut self,
+        requests: Vec<(Vec<Vec<OwnedTensor>>, u32)>,
+    ) -> crate::error::Result<Vec<crate::error::Result<InferenceResult>>> {
+        self.ensure_ready("infer_many")?;
+
+        let total = requests.len();
+        let all_ids: Vec<u64> = (0..total).map(|_| rand_request_id()).collect();
+        self.unfinished = Some(Unfinished::Collect(all_ids.clone()));
+        let results = self.infer_many_inner(requests, all_ids).await;
+        self.finish_call();
+        results
+    }
+
+    async fn infer_many_inner(
+        &mut self,
+        requests: Vec<(Vec<Vec<OwnedTensor>>, u32)>,
+        all_ids: Vec<u64>,
+    ) -> crate::error::Result<Vec<crate::error::Result<InferenceResult>>> {
+        let total = requests.len();
+        let mut pending = requests.into_iter().zip(all_ids);
+        let mut ids: Vec<u64> = Vec::with_capacity(total);
+        let mut results = Vec::with_capacity(total);
+
+        while results.len() < total {
+            while ids.len() < total && self.in_flight.len() < self.max_in_flight() {
+                let Some(((input_tensors, seq_len), request_id)) = pending.next() else {
+                    break;
+                };
+                ids.push(request_id);
+                let submitted = if self.tainted {
+                    Err(PipelineError::Tainted)
+                } else {
+                    self.submit_inner(request_id, None, None, false, input_tensors, seq_len)
+                        .await
+                };
+                if let Err(e) = submitted {
+                    self.completed.insert(request_id, Err(e));
+                }
+            }
+
+            if ids.len() == results.len() {
+                // Every slot is held by requests submitted outside this call;
+                // retire the oldest so ours can enter the pipeline.
+                let Some(head) = self.in_flight.front().map(|r| r.request_id) else {
+                    return Err(PipelineError::Protocol(
+                        "infer_many: no request in flight to retire".into(),
+                    ));
+                };
+                let result = self.wait_timed(head).await;
+                self.stash_result(head, result);
+                continue;
+            }
+
+            let request_id = ids[results.len()];
+            results.push(self.wait_timed(request_id).await);
+        }
+
+        Ok(results)
+    }
+
+    /// Run an inference request and yield each micro-batch's outputs as the
+    /// last stage emits them.
+    ///
+    /// Unlike [`Self::infer`], which returns once every micro-batch has
+    /// arrived, the returned [`InferenceStream`] hands out
+    /// `(micro_batch, tensors)` pairs in micro-batch order. A stage failure
+    /// ends the stream with the same `PipelineError::RequestFailed` detail
+    /// that `infer` reports. `OrchestratorConfig::infer_timeout` bounds the
+    /// whole request, not each item.
+    ///
+    /// Dropping the stream early is allowed: the remaining outputs are
+    /// collected and discarded by the next call that uses the pipeline.
+    pub async fn infer_stream(
+        &mut self,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+    ) -> crate::error::Result<InferenceStream<'_, T>> {
+        self.ensure_ready("infer_stream")?;
+
+        let request_id = rand_request_id();
+        let num_micro_batches = u32::try_from(input_tensors.len()).map_err(|_| {
+            PipelineError::Protocol(format!(
+                "too many micro-batches: {} exceeds u32::MAX",
+                input_tensors.len()
+            ))
+        })?;
+        let deadline = Instant::now() + self.config.infer_timeout;
+
+        match tokio::time::timeout_at(
+            deadline,
+            self.submit_inner(request_id, None, None, false, input_tensors, seq_len),
+        )
+        .await
+        {
+            Ok(result) => result?,
+            Err(_) => {
+                warn!(request_id, "orchestrator: inference timed out, draining");
+                self.drain_timed_out_infer().await;
+                self.completed.remove(&request_id);
+                return Err(PipelineError::Timeout("inference timed out".into()));
+            }
+        }
+        // Zero micro-batches completes immediately with no outputs.
+        let finished = self.completed.remove(&request_id).is_some();
+
+        Ok(InferenceStream {
+            orch: self,
+            request_id,
+            num_micro_batches,
+            next_micro_batch: 0,
+            deadline,
+            finished,
+        })
+    }
+
+    /// Send `StartRequest` and input tensors for a new request and track it as
+    /// in flight.
+    async fn submit_inner(
+        &mut self,
+        request_id: u64,
+        session_id: Option<SessionId>,
+        deadline: Option<Instant>,
+        profile: bool,
+        mut input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+    ) -> crate::error::Result<()> {
+        let num_micro_batches = u32::try_from(input_tensors.len()).map_err(|_| {
+            PipelineError::Protocol(format!(
+                "too many micro-batches: {} exceeds u32::MAX",
+                input_tensors.len()
+            ))
+        })?;
+
+        if num_micro_batches == 0 {
+            self.completed.insert(
+                request_id,
+                Ok(InferenceResult {
+                    outputs: Vec::new(),
                     request_id,
-                    max_bytes,
-                ),
+                    stage_timings: vec![Vec::new(); self.stages.len()],
+                    layer_samples: vec![Vec::new(); self.stages.len()],
+                    total: Duration::ZERO,
+                    retries: Vec::new(),
+                }),
+            );
+            return Ok(());
+        }
+
+        if seq_len > self.manifest.activation_spec.max_seq_len {
+            return Err(PipelineError::Protocol(format!(
+                "seq_len {} exceeds manifest max_seq_len {}",
+                seq_len, self.manifest.activation_spec.max_seq_len
+            )));
+        }
+
+        if deadline.is_some() {
+            self.require_version("a request deadline")?;
+        }
+        if profile {
+            self.require_version("profiling")?;
+        }
+
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(PipelineError::DeadlineExceeded { request_id });
+        }
+
+        if self.in_flight.len() >= self.max_in_flight() {
+            return Err(PipelineError::TooManyInFlight {
+                limit: self.max_in_flight(),
+            });
+        }
+
+        if self.data_in.is_none() {
+            return Err(PipelineError::Protocol(
+                "data channels not established".into(),
+            ));
+        }
+
+        // Track the request before anything is sent, so that a timeout in the
+        // middle of submission still drains whatever the stages received.
+        self.in_flight.push_back(InFlightRequest {
+            request_id,
+            num_micro_batches,
+            deadline,
+            received: 0,
+            outputs: Vec::with_capacity(num_micro_batches as usize),
+            partial: Vec::new(),
+            failure: None,
+            submitted_at: Instant::now(),
+        });
+
+        let previous = self.unfinished.replace(Unfinished::Send);
+        let sent = self
+            .send_request(
+                request_id,
+                session_id,
+                deadline,
+                profile,
+                num_micro_batches,
+                seq_len,
+                &input_tensors,
             )
             .await;
+        self.unfinished = previous;
 
-            match result {
+        // SEC-705: Explicitly clear input tensor metadata after sending.
+        // OwnedTensor.data is bytes::Bytes (Arc-backed) — cannot reliably zeroize
+        // the shared allocation. Dropping releases our reference count.
+        for mb_tensors in &mut input_tensors {
+            for tensor in mb_tensors.iter_mut() {
+                tensor.name.zeroize();
+                tensor.shape.zeroize();
+            }
+        }
+        drop(input_tensors);
+
+        sent
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn send_request(
+        &mut self,
+        request_id: u64,
+        session_id: Option<SessionId>,
+        deadline: Option<Instant>,
+        profile: bool,
+        num_micro_batches: u32,
+        seq_len: u32,
+        input_tensors: &[Vec<OwnedTensor>],
+    ) -> crate::error::Result<()> {
+        // Send StartRequest to all stages.
+        let time_budget_ms = deadline.map(|d| {
+            let left = d.saturating_duration_since(Instant::now());
+            u64::try_from(left.as_millis()).unwrap_or(u64::MAX)
+        });
+        self.broadcast(&OrchestratorMsg::StartRequest {
+            request_id,
+            num_micro_batches,
+            seq_len,
+            session_id,
+            time_budget_ms,
+            profile,
+        })
+        .await?;
+
+        debug!(
+            request_id,
+            num_micro_batches, "orchestrator: sending input tensors"
+        );
+
+        // Send input tensors to stage 0.
+        let framing = self.data_framing();
+        let data_in = self
+            .data_in
+            .as_mut()
+            .ok_or_else(|| PipelineError::Protocol("data channels not established".into()))?;
+        for (micro_batch, mb_tensors) in (0..num_micro_batches).zip(input_tensors) {
+            send_tensors(data_in, framing, request_id, micro_batch, mb_tensors).await?;
+        }
+        Ok(())
+    }
+
+    /// Send a control message to every stage.
+    async fn broadcast(&mut self, msg: &OrchestratorMsg) -> crate::error::Result<()> {
+        let previous = self.unfinished.replace(Unfinished::Send);
+        let mut sent = Ok(());
+        for stage in &mut self.stages {
+            sent = stage.send(msg).await;
+            if sent.is_err() {
+                break;
+            }
+        }
+        self.unfinished = previous;
+        sent
+    }
+
+    /// Collect in-flight requests in order until `request_id` completes.
+    async fn wait_inner(&mut self, request_id: u64) -> crate::error::Result<InferenceResult> {
+        loop {
+            if let Some(result) = self.completed.remove(&request_id) {
+                return result;
+            }
+            let Some((rid, result)) = self.complete_next().await else {
+                return Err(PipelineError::Protocol(format!(
+                    "request {request_id} is not in flight"
+                )));
+            };
+            if rid == request_id {
+                return result;
+            }
+            self.stash_result(rid, result);
+        }
+    }
+
+    /// Keep a result for its own `wait` call, unless its caller is gone.
+    fn stash_result(&mut self, request_id: u64, result: crate::error::Result<InferenceResult>) {
+        if self.detached.remove(&request_id) {
+            debug!(
+                request_id,
+                "orchestrator: discarding result of detached request"
+            );
+        } else {
+            self.completed.insert(request_id, result);
+        }
+    }
+
+    /// Pop a finished request from the head of the in-flight queue and drop
+    /// any replies stashed for it.
+    pub(crate) fn retire_head(&mut self, request_id: u64) {
+        if let Some(head) = self.in_flight.front() {
+            if head.request_id == request_id {
+                self.in_flight.pop_front();
+            }
+        }
+        for stage in &mut self.stages {
+            stage.pending_replies.remove(&request_id);
+        }
+    }
+
+    /// Collect the outputs and stage replies for the oldest in-flight request.
+    ///
+    /// The request stays in the in-flight queue until collection finishes, so
+    /// a cancelled call leaves it visible to the timeout drain.
+    async fn complete_next(&mut self) -> Option<(u64, crate::error::Result<InferenceResult>)> {
+        let request_id = self.in_flight.front()?.request_id;
+
+        let result = self.collect_request(request_id).await;
+        self.retire_head(request_id);
+        Some((request_id, result))
+    }
+
+    async fn collect_request(&mut self, request_id: u64) -> crate::error::Result<InferenceResult> {
+        // Receive output tensors from last stage.
+        // If a stage failed, it sends an error frame on its data_out, which
+        // later stages forward and which surfaces here as UpstreamFailed.
+        match self.receive_head_outputs().await {
+            Ok(()) => {
+                let (stage_timings, layer_samples) = self.collect_request_done(request_id).await?;
+                let head = self.in_flight.front_mut().expect("head is being collected");
+                let outputs = std::mem::take(&mut head.outputs);
+                let total = head.submitted_at.elapsed();
+                info!(request_id, ?total, "orchestrator: inference complete");
+                Ok(InferenceResult {
+                    outputs,
+                    request_id,
+                    stage_timings,
+                    layer_samples,
+                    total,
+                    retries: Vec::new(),
+                })
+            }
+            Err(PipelineError::UpstreamFailed(frame)) => {
+                Err(self.request_failure(request_id, frame).await)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Receive the head request's outstanding micro-batch outputs into its
+    /// `outputs`.
+    async fn receive_head_outputs(&mut self) -> crate::error::Result<()> {
+        let framing = self.data_framing();
+        let exit_stage = self.manifest.exit_stage();
+        let data_out = self
+            .data_out
+            .as_mut()
+            .ok_or_else(|| PipelineError::Protocol("data channels not established".into()))?;
+        let Some(head) = self.in_flight.front_mut() else {
+            return Ok(());
+        };
+        if let Some(frame) = head.failure {
+            return Err(PipelineError::UpstreamFailed(frame));
+        }
+
+        while head.received < head.num_micro_batches {
+            debug!(
+                request_id = head.request_id,
+                micro_batch = head.received,
+                "orchestrator: receiving output"
+            );
+            if let Err(e) = recv_output(
+                data_out,
+                framing,
+                exit_stage,
+                &mut self.data_out_group,
+                head.request_id,
+                head.received,
+                &mut head.partial,
+            )
+            .await
+            {
+                if let PipelineError::UpstreamFailed(frame) = e {
+                    head.failure = Some(frame);
+                }
+                return Err(e);
+            }
+            head.outputs.push(std::mem::take(&mut head.partial));
+            head.received += 1;
+        }
+        Ok(())
+    }
+
+    /// Collect requests submitted before `request_id`, stashing their
+    /// results. Returns false if `request_id` is no longer in flight.
+    async fn collect_ahead_of(&mut self, request_id: u64) -> bool {
+        loop {
+            match self.in_flight.front() {
+                Some(head) if head.request_id == request_id => return true,
+                Some(_) => {
+                    if let Some((rid, result)) = self.complete_next().await {
+                        self.stash_result(rid, result);
+                    }
+                }
+                None => return false,
+            }
+        }
+    }
+
+    /// Collect RequestDone confirmations from all stages after the last
+    /// output has arrived, returning each stage's timings and layer samples.
+    /// Replies for other in-flight requests are stashed for later.
+    async fn collect_request_done(
+        &mut self,
+        request_id: u64,
+    ) -> crate::error::Result<(Vec<Vec<MicroBatchTiming>>, Vec<Vec<LayerSample>>)> {
+        let in_flight_ids: Vec<u64> = self.in_flight.iter().map(|r| r.request_id).collect();
+        let max_bytes = self.config.max_control_message_bytes;
+
+        // Gather every stage's reply before judging them, so a cancelled
+        // call keeps what it received stashed.
+        for stage in &mut self.stages {
+            await_request_reply(stage, request_id, &in_flight_ids, max_bytes).await?;
+        }
+        let mut stage_timings = Vec::with_capacity(self.stages.len());
+        let mut layer_samples = Vec::with_capacity(self.stages.len());
+        for stage in &mut self.stages {
+            match stage.pending_replies.remove(&request_id) {
+                Some(StageMsg::RequestDone {
+                    timings, layers, ..
+                }) => {
+                    debug!(stage = stage.stage_idx, "orchestrator: stage done");
+                    stage_timings.push(timings);
+                    layer_samples.push(layers);
+                }
+                Some(StageMsg::RequestError {
+                    error,
+                    code,
+                    details,
+                    ..
+                }) => {
+                    return Err(PipelineError::RequestFailed {
+                        request_id,
+                        code,
+                        reason: format!("stage {} error: {}", stage.stage_idx, error),
+                        details,
+                    });
+                }
+                other => {
+                    return Err(PipelineError::Protocol(format!(
+                        "expected RequestDone/RequestError for {request_id} from stage {}, got {other:?}",
+                        stage.stage_idx
+                    )));
+                }
+            }
+        }
+        Ok((stage_timings, layer_samples))
+    }
+
+    /// A stage sent an error frame. Read the failing stage's control
+    /// channel for details, falling back to the frame itself if that stage
+    /// does not reply within `stage_drain_timeout`.
+    ///
+    /// A version 1 `ERR` does not say where the failure started, so every
+    /// stage's control channel is read in order and the first RequestError
+    /// is reported.
+    async fn request_failure(&mut self, request_id: u64, frame: DataError) -> PipelineError {
+        let in_flight_ids: Vec<u64> = self.in_flight.iter().map(|r| r.request_id).collect();
+        let max_bytes = self.config.max_control_message_bytes;
+        let drain_timeout = self.config.stage_drain_timeout;
+        let untagged = self.data_framing() == DataFraming::Sentinel;
+
+        for stage in self
+            .stages
+            .iter_mut()
+            .filter(|stage| untagged || stage.stage_idx == frame.stage_idx)
+        {
+            match tokio::time::timeout(
+                drain_timeout,
+                await_request_reply(stage, request_id, &in_flight_ids, max_bytes),
+            )
+            .await
+            {
                 Ok(Ok(())) => {
-                    debug!(stage = stage_idx, "drain: stage finished");
+                    if let Some(StageMsg::RequestError {
+                        error,
+                        code,
+                        details,
+                        ..
+                    }) = stage.pending_replies.get(&request_id)
+                    {
+                        return PipelineError::RequestFailed {
+                            request_id,
+                            code: *code,
+                            reason: format!("stage {} error: {}", stage.stage_idx, error),
+                            details: details.clone(),
+                        };
+                    }
                 }
                 Ok(Err(e)) => {
-                    warn!(stage = stage_idx, error = %e, "drain: stage control error, tainting");
-                    self.tainted = true;
-                    return;
+                    debug!(stage = stage.stage_idx, error = %e, "error drain: control error");
                 }
                 Err(_) => {
-                    warn!(stage = stage_idx, "drain: stage stuck, tainting pipeline");
-                    self.tainted = true;
-                    return;
+                    debug!(
+                        stage = stage.stage_idx,
+                        "error drain: stage stuck, using data-plane error frame"
+                    );
+                }
+            }
+        }
+        if untagged {
+            return PipelineError::RequestFailed {
+                request_id,
+                code: frame.code,
+                reason: "a stage failed the request (no error details on control channel)".into(),
+                details: None,
+            };
+        }
+        PipelineError::RequestFailed {
+            request_id,
+            code: frame.code,
+            reason: format!(
+                "stage {} error: {} at micro-batch {}",
+                frame.stage_idx, frame.code, frame.micro_batch
+            ),
+            details: None,
+        }
+    }
+
+    /// Receive the next micro-batch of `request_id` for an `InferenceStream`.
+    ///
+    /// Requests submitted earlier are collected first and stashed. After the
+    /// last micro-batch (or a failure) the request is retired.
+    async fn stream_next(&mut self, request_id: u64) -> crate::error::Result<Vec<OwnedTensor>> {
+        if !self.collect_ahead_of(request_id).await {
+            return Err(PipelineError::Protocol(format!(
+                "request {request_id} is not in flight"
+            )));
+        }
+
+        let framing = self.data_framing();
+        let exit_stage = self.manifest.exit_stage();
+        let data_out = self
+            .data_out
+            .as_mut()
+            .ok_or_else(|| PipelineError::Protocol("data channels not established".into()))?;
+        let head = self.in_flight.front_mut().expect("head checked above");
+
+        match recv_output(
+            data_out,
+            framing,
+            exit_stage,
+            &mut self.data_out_group,
+            request_id,
+            head.received,
+            &mut head.partial,
+        )
+        .await
+        {
+            Ok(()) => {
+                let tensors = std::mem::take(&mut head.partial);
+                head.received += 1;
+                if head.received < head.num_micro_batches {
+                    return Ok(tensors);
+                }
+                let done = self.collect_request_done(request_id).await;
+                self.retire_head(request_id);
+                done?;
+                info!(request_id, "orchestrator: streamed inference complete");
+                Ok(tensors)
+            }
+            Err(PipelineError::UpstreamFailed(frame)) => {
+                let err = self.request_failure(request_id, frame).await;
+                self.retire_head(request_id);
+                Err(err)
+            }
+            Err(e) => {
+                self.retire_head(request_id);
+                Err(e)
+            }
+        }
+    }
+
+    /// Abort `request_id` on every stage and consume what it leaves behind.
+    ///
+    /// Requests ahead of it are collected normally and stashed. Then the
+    /// request's outputs are read up to its last micro-batch or an error
+    /// frame, and every stage's RequestDone/RequestError is collected.
+    /// Falls back to the timeout drain if this takes longer than
+    /// `infer_timeout`.
+    async fn cancel_request(&mut self, request_id: u64, reason: &str) {
+        info!(request_id, reason, "orchestrator: cancelling request");
+
+        let abort = OrchestratorMsg::AbortRequest {
+            request_id,
+            reason: reason.to_string(),
+        };
+        if let Err(e) = self.broadcast(&abort).await {
+            warn!(request_id, error = %e, "cancel: failed to send AbortRequest, tainting");
+            self.tainted = true;
+            return;
+        }
+
+        let timeout = self.config.infer_timeout;
+        let wound_down = tokio::time::timeout(timeout, async {
+            if !self.collect_ahead_of(request_id).await {
+                // Completed before the abort; its result is discarded below.
+                return Ok(());
+            }
+            let discarded = self.discard_head(request_id).await;
+            self.retire_head(request_id);
+            discarded
+        })
+        .await;
+        self.completed.remove(&request_id);
+
+        match wound_down {
+            Ok(Ok(())) => info!(request_id, "orchestrator: request cancelled"),
+            Ok(Err(e)) => {
+                warn!(request_id, error = %e, "cancel: failed to wind down request, tainting");
+                self.tainted = true;
+            }
+            Err(_) => {
+                warn!(
+                    request_id,
+                    "cancel: stages did not wind down in time, draining"
+                );
+                self.drain_timed_out_infer().await;
+                self.completed.remove(&request_id);
+            }
+        }
+    }
+
+    /// Read and drop the head request's remaining outputs and every stage's
+    /// reply for it.
+    async fn discard_head(&mut self, request_id: u64) -> crate::error::Result<()> {
+        match self.receive_head_outputs().await {
+            Ok(()) | Err(PipelineError::UpstreamFailed(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        let in_flight_ids: Vec<u64> = self.in_flight.iter().map(|r| r.request_id).collect();
+        let max_bytes = self.config.max_control_message_bytes;
+        for stage in &mut self.stages {
+            await_request_reply(stage, request_id, &in_flight_ids, max_bytes).await?;
+        }
+        Ok(())
+    }
+
+    /// Drain stale protocol state after an inference timeout.
+    ///
+    /// Every in-flight request is abandoned; requests other than the one that
+    /// timed out get a `Timeout` result for their own `wait` call.
+    ///
+    /// Step 1: Wait for each stage to send RequestDone/RequestError on control
+    ///         for every abandoned request. If any stage doesn't respond within
+    ///         `stage_drain_timeout`, mark tainted.
+    /// Step 2: Drain remaining data_out messages until quiet.
+    ///         If data_out still has pending frames after `data_drain_timeout`, mark tainted.
+    async fn drain_timed_out_infer(&mut self) {
+        let previous = self.unfinished.replace(Unfinished::Drain);
+        let abandoned: Vec<u64> = self.in_flight.drain(..).map(|r| r.request_id).collect();
+        for &request_id in &abandoned {
+            self.stash_result(
+                request_id,
+                Err(PipelineError::Timeout(format!(
+                    "request {request_id} abandoned after an inference timeout"
+                ))),
+            );
+        }
+
+        let drained = self.drain_abandoned(&abandoned).await;
+
+        for stage in &mut self.stages {
+            stage.pending_replies.clear();
+        }
+        if drained {
+            info!(request_ids = ?abandoned, "drain: completed successfully");
+        }
+        self.unfinished = previous;
+    }
+
+    /// Run the control and data_out drain for `abandoned` requests.
+    /// Returns false (and taints the pipeline) if the drain did not finish.
+    async fn drain_abandoned(&mut self, abandoned: &[u64]) -> bool {
+        let stage_drain_timeout = self.config.stage_drain_timeout;
+        let data_drain_timeout = self.config.data_drain_timeout;
+        let data_quiet_period = self.config.data_quiet_period;
+        let max_bytes = self.config.max_control_message_bytes;
+
+        // Step 1: Drain control channels.
+        for stage in &mut self.stages {
+            for &request_id in abandoned {
+                if stage.pending_replies.remove(&request_id).is_some() {
+                    continue;
+                }
+                let result = tokio::time::timeout(
+                    stage_drain_timeout,
+                    drain_control_until_request_complete(stage, request_id, max_bytes),
+                )
+                .await;
+
+                match result {
+                    Ok(Ok(())) => {
+                        debug!(stage = stage.stage_idx, request_id, "drain: stage finished");
+                    }
+                    Ok(Err(e)) => {
+                        warn!(stage = stage.stage_idx, error = %e, "drain: stage control error, tainting");
+                        self.tainted = true;
+                        return false;
+                    }
+                    Err(_) => {
+                        warn!(
+                            stage = stage.stage_idx,
+                            "drain: stage stuck, tainting pipeline"
+                        );
+                        self.tainted = true;
+                        return false;
+                    }
                 }
             }
         }
@@ -767,13 +2106,14 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
                 }
             })
             .await;
+            self.data_out_group = GroupState::Header;
 
             match drain_result {
                 Ok(true) => {} // Drained cleanly.
                 Ok(false) => {
                     warn!("drain: data_out transport error, tainting pipeline");
                     self.tainted = true;
-                    return;
+                    return false;
                 }
                 Err(_) => {
                     warn!(
@@ -781,26 +2121,19 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
                         "drain: data_out still has pending frames, tainting pipeline"
                     );
                     self.tainted = true;
-                    return;
+                    return false;
                 }
             }
         }
 
-        info!(request_id, "drain: completed successfully");
+        true
     }
 
     /// Send a health-check ping to all stages.
     ///
     /// Subject to `OrchestratorConfig::health_check_timeout`.
     pub async fn health_check(&mut self) -> crate::error::Result<()> {
-        if self.state != OrchestratorState::Ready {
-            return Err(PipelineError::Protocol(
-                "health_check() requires Ready state (call init() then establish_data_channels() first)".into(),
-            ));
-        }
-        if self.tainted {
-            return Err(PipelineError::Tainted);
-        }
+        self.ensure_ready("health_check")?;
 
         let timeout = self.config.health_check_timeout;
         match tokio::time::timeout(timeout, self.health_check_inner()).await {
@@ -817,18 +2150,14 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
         let seq = rand_request_id();
         let max_bytes = self.config.max_control_message_bytes;
 
-        for stage in &mut self.stages {
-            stage
-                .control
-                .send(OrchestratorMsg::Ping { seq }.to_bytes()?)
-                .await
-                .map_err(PipelineError::Transport)?;
-        }
+        self.broadcast(&OrchestratorMsg::Ping { seq }).await?;
 
-        // Tolerant reader: skip stale Pongs (wrong seq), stale RequestDone/RequestError.
+        // Tolerant reader: skip stale Pongs (wrong seq) and stale
+        // RequestDone/RequestError; stash replies for in-flight requests.
+        let in_flight_ids: Vec<u64> = self.in_flight.iter().map(|r| r.request_id).collect();
         for stage in &mut self.stages {
             loop {
-                let msg = recv_stage_msg(&mut stage.control, max_bytes).await?;
+                let msg = stage.recv(max_bytes).await?;
                 match msg {
                     StageMsg::Pong { seq: s } if s == seq => {
                         debug!(stage = stage.stage_idx, "health check OK");
@@ -843,17 +2172,21 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
                         );
                         continue;
                     }
-                    StageMsg::RequestDone { request_id } => {
+                    StageMsg::RequestDone { request_id, .. }
+                    | StageMsg::RequestError { request_id, .. }
+                        if in_flight_ids.contains(&request_id) =>
+                    {
+                        stage.pending_replies.insert(request_id, msg);
+                        continue;
+                    }
+                    StageMsg::RequestDone { request_id, .. } => {
                         debug!(
                             stage = stage.stage_idx,
                             request_id, "skipping stale RequestDone during health check"
                         );
                         continue;
                     }
-                    StageMsg::RequestError {
-                        request_id,
-                        error: _,
-                    } => {
+                    StageMsg::RequestError { request_id, .. } => {
                         debug!(
                             stage = stage.stage_idx,
                             request_id, "skipping stale RequestError during health check"
@@ -884,21 +2217,14 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
         info!("orchestrator: shutting down pipeline");
 
         for stage in &mut self.stages {
-            stage
-                .control
-                .send(OrchestratorMsg::Shutdown.to_bytes()?)
-                .await
-                .map_err(PipelineError::Transport)?;
+            stage.send(&OrchestratorMsg::Shutdown).await?;
         }
 
         let shutdown_timeout = self.config.shutdown_timeout;
         let max_bytes = self.config.max_control_message_bytes;
         for stage in &mut self.stages {
-            let result = tokio::time::timeout(
-                shutdown_timeout,
-                recv_stage_msg(&mut stage.control, max_bytes),
-            )
-            .await;
+            let result =
+                tokio::time::timeout(shutdown_timeout, recv_shutdown_ack(stage, max_bytes)).await;
             match result {
                 Ok(Ok(StageMsg::ShuttingDown { stage_idx })) if stage_idx == stage.stage_idx => {
                     info!(stage = stage_idx, "stage shut down");
@@ -932,33 +2258,184 @@ impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
             relay.abort();
         }
 
+        // Stages release session state as they shut down.
+        self.sessions.clear();
+
+        let in_flight: Vec<u64> = self.in_flight.drain(..).map(|r| r.request_id).collect();
+        for request_id in in_flight {
+            self.stash_result(request_id, Err(PipelineError::Shutdown));
+        }
+
         info!("orchestrator: shutdown complete");
         Ok(())
     }
 }
 
-/// Receive all output tensors (all micro-batches) from the data_out channel.
-async fn receive_all_outputs<T: AsyncRead + AsyncWrite + Unpin + Send>(
-    data_out: &mut SecureChannel<T>,
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + 'static> Orchestrator<T> {
+    /// Move data_out reads onto a task that forwards whole messages, making
+    /// output collection cancel-safe down to the frame. Does nothing if the
+    /// data channels are not established or are already forwarded; data
+    /// channels set up again by `recover` are read in place until this is
+    /// called again.
+    pub(crate) fn forward_data_out(&mut self) {
+        if !matches!(self.data_out, Some(DataOut::Channel(_))) {
+            return;
+        }
+        let Some(DataOut::Channel(mut channel)) = self.data_out.take() else {
+            unreachable!("data_out checked above");
+        };
+        let (tx, messages) = mpsc::channel(DATA_OUT_QUEUE_DEPTH);
+        let reader = tokio::spawn(async move {
+            loop {
+                let msg = channel.recv().await;
+                let failed = msg.is_err();
+                if tx.send(msg).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+        self.data_out = Some(DataOut::Forwarded(ForwardedDataOut { messages, reader }));
+    }
+}
+
+/// Micro-batch outputs of a single request, returned by
+/// [`Orchestrator::infer_stream`].
+///
+/// Borrows the orchestrator until it is finished or dropped. Call
+/// [`Self::next`] until it returns `None`.
+pub struct InferenceStream<'a, T: AsyncRead + AsyncWrite + Unpin + Send> {
+    orch: &'a mut Orchestrator<T>,
+    request_id: u64,
     num_micro_batches: u32,
-) -> crate::error::Result<Vec<Vec<OwnedTensor>>> {
-    let mut outputs = Vec::with_capacity(num_micro_batches as usize);
-    for mb in 0..num_micro_batches {
-        debug!(micro_batch = mb, "orchestrator: receiving output");
-        let tensors = recv_output_tensors(data_out).await?;
-        outputs.push(tensors);
-    }
-    Ok(outputs)
+    next_micro_batch: u32,
+    deadline: Instant,
+    finished: bool,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> InferenceStream<'_, T> {
+    /// The request ID sent to the stages for this request.
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
+    }
+
+    /// Number of micro-batches this stream will yield on success.
+    pub fn num_micro_batches(&self) -> u32 {
+        self.num_micro_batches
+    }
+
+    /// Wait for the next micro-batch's output tensors.
+    ///
+    /// Returns `None` once every micro-batch has been yielded or after an
+    /// error has been returned. A call that is cancelled before it returns
+    /// yields nothing; the next call picks up the same micro-batch, keeping
+    /// the tensors already received for it.
+    pub async fn next(&mut self) -> Option<crate::error::Result<(u32, Vec<OwnedTensor>)>> {
+        if self.finished {
+            return None;
+        }
+        let request_id = self.request_id;
+
+        let result =
+            match tokio::time::timeout_at(self.deadline, self.orch.stream_next(request_id)).await {
+                Ok(result) => result,
+                Err(_) => {
+                    self.finished = true;
+                    warn!(request_id, "orchestrator: inference timed out, draining");
+                    self.orch.drain_timed_out_infer().await;
+                    self.orch.completed.remove(&request_id);
+                    return Some(Err(PipelineError::Timeout("inference timed out".into())));
+                }
+            };
+
+        match result {
+            Ok(tensors) => {
+                let micro_batch = self.next_micro_batch;
+                self.next_micro_batch += 1;
+                self.finished = self.next_micro_batch == self.num_micro_batches;
+                Some(Ok((micro_batch, tensors)))
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Drop for InferenceStream<'_, T> {
+    fn drop(&mut self) {
+        let request_id = self.request_id;
+        if self
+            .orch
+            .in_flight
+            .iter()
+            .any(|r| r.request_id == request_id)
+        {
+            debug!(request_id, "orchestrator: stream dropped before completion");
+            self.orch.detached.insert(request_id);
+        }
+    }
 }
 
-/// Receive a stage message from a control channel with size and version checks.
-async fn recv_stage_msg<T: AsyncRead + AsyncWrite + Unpin + Send>(
+/// Report a request that stages failed after its deadline passed (typically
+/// because they refused it for that reason) as `DeadlineExceeded`.
+fn missed_deadline(
+    request_id: u64,
+    deadline: Option<Instant>,
+    result: crate::error::Result<InferenceResult>,
+) -> crate::error::Result<InferenceResult> {
+    match result {
+        Err(PipelineError::RequestFailed { .. })
+            if deadline.is_some_and(|d| Instant::now() >= d) =>
+        {
+            Err(PipelineError::DeadlineExceeded { request_id })
+        }
+        result => result,
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> StageHandle<T> {
+    /// Send a control message in the stage's protocol version.
+    async fn send(&mut self, msg: &OrchestratorMsg) -> crate::error::Result<()> {
+        let bytes = msg.to_bytes_versioned(self.protocol_version)?;
+        self.control
+            .send(bytes)
+            .await
+            .map_err(PipelineError::Transport)
+    }
+
+    /// Receive a stage message with size and version checks.
+    async fn recv(&mut self, max_bytes: usize) -> crate::error::Result<StageMsg> {
+        let Envelope { version, msg } = recv_stage_envelope(&mut self.control, max_bytes).await?;
+        if version != self.protocol_version {
+            return Err(PipelineError::VersionMismatch {
+                expected: self.protocol_version,
+                actual: version,
+            });
+        }
+        Ok(msg)
+    }
+}
+
+/// Whether a stage spec uses manifest features that version 1 stages
+/// ignore: graph links, pass-through tensors or tensor contracts.
+fn needs_version_2(spec: &StageSpec) -> bool {
+    !spec.upstream.is_empty()
+        || !spec.downstream.is_empty()
+        || !spec.pass_through.is_empty()
+        || !spec.inputs.is_empty()
+        || !spec.outputs.is_empty()
+}
+
+/// Receive a stage message from a control channel with a size check,
+/// leaving the version to the caller.
+async fn recv_stage_envelope<T: AsyncRead + AsyncWrite + Unpin + Send>(
     channel: &mut SecureChannel<T>,
     max_bytes: usize,
-) -> crate::error::Result<StageMsg> {
+) -> crate::error::Result<Envelope<StageMsg>> {
     let msg = channel.recv().await.map_err(PipelineError::Transport)?;
     match msg {
-        Message::Data(data) => StageMsg::from_bytes_checked(&data, max_bytes),
+        Message::Data(data) => StageMsg::envelope_from_bytes(&data, max_bytes),
         Message::Shutdown => Err(PipelineError::Shutdown),
         other => Err(PipelineError::Protocol(format!(
             "expected Data on control channel, got {other:?}"
@@ -966,57 +2443,84 @@ async fn recv_stage_msg<T: AsyncRead + AsyncWrite + Unpin + Send>(
     }
 }
 
-/// Receive a stage message, skipping stale Pongs and RequestDone/RequestError
-/// for other request IDs. Returns the first message matching `expected_request_id`
-/// (if Some) or any non-stale message.
-async fn recv_stage_msg_tolerant<T: AsyncRead + AsyncWrite + Unpin + Send>(
-    channel: &mut SecureChannel<T>,
-    expected_request_id: Option<u64>,
+/// Wait until one stage's RequestDone/RequestError reply for `request_id`
+/// is stashed in its `pending_replies`.
+///
+/// Replies for other requests in `in_flight` are stashed for their own
+/// collectors; stale Pongs and replies for requests that are no longer
+/// tracked are skipped. Cancel-safe: replies received before cancellation
+/// stay stashed.
+async fn await_request_reply<T: AsyncRead + AsyncWrite + Unpin + Send>(
+    stage: &mut StageHandle<T>,
+    request_id: u64,
+    in_flight: &[u64],
     max_bytes: usize,
-) -> crate::error::Result<StageMsg> {
-    loop {
-        let msg = recv_stage_msg(channel, max_bytes).await?;
-        match &msg {
+) -> crate::error::Result<()> {
+    while !stage.pending_replies.contains_key(&request_id) {
+        let msg = stage.recv(max_bytes).await?;
+        let rid = match &msg {
+            StageMsg::RequestDone { request_id, .. }
+            | StageMsg::RequestError { request_id, .. } => *request_id,
             // Skip stale Pongs from previous health checks.
             StageMsg::Pong { seq } => {
                 debug!(seq, "tolerant reader: skipping stale Pong");
                 continue;
             }
-            // Skip RequestDone/RequestError for other request IDs.
-            StageMsg::RequestDone { request_id } => {
-                if let Some(expected) = expected_request_id {
-                    if *request_id != expected {
-                        debug!(request_id, "tolerant reader: skipping stale RequestDone");
-                        continue;
-                    }
-                }
-                return Ok(msg);
+            other => {
+                return Err(PipelineError::Protocol(format!(
+                    "expected RequestDone/RequestError for {request_id} from stage {}, got {other:?}",
+                    stage.stage_idx
+                )));
             }
-            StageMsg::RequestError { request_id, .. } => {
-                if let Some(expected) = expected_request_id {
-                    if *request_id != expected {
-                        debug!(request_id, "tolerant reader: skipping stale RequestError");
-                        continue;
-                    }
-                }
-                return Ok(msg);
+        };
+        if rid == request_id || in_flight.contains(&rid) {
+            debug!(
+                stage = stage.stage_idx,
+                request_id = rid,
+                "tolerant reader: stashing reply"
+            );
+            stage.pending_replies.insert(rid, msg);
+        } else {
+            debug!(request_id = rid, "tolerant reader: skipping stale reply");
+        }
+    }
+    Ok(())
+}
+
+/// Wait for a stage's ShuttingDown, skipping request replies and Pongs that
+/// were already queued ahead of it.
+async fn recv_shutdown_ack<T: AsyncRead + AsyncWrite + Unpin + Send>(
+    stage: &mut StageHandle<T>,
+    max_bytes: usize,
+) -> crate::error::Result<StageMsg> {
+    loop {
+        let msg = stage.recv(max_bytes).await?;
+        match msg {
+            StageMsg::Pong { .. }
+            | StageMsg::RequestDone { .. }
+            | StageMsg::RequestError { .. } => {
+                debug!(
+                    stage = stage.stage_idx,
+                    ?msg,
+                    "shutdown: skipping queued message"
+                );
             }
-            _ => return Ok(msg),
+            other => return Ok(other),
         }
     }
 }
 
-/// Read from a control channel until we see RequestDone or RequestError for the
+/// Read from a stage's control channel until we see RequestDone or RequestError for the
 /// given request_id (or any request). Skips stale Pongs and messages for other requests.
 async fn drain_control_until_request_complete<T: AsyncRead + AsyncWrite + Unpin + Send>(
-    channel: &mut SecureChannel<T>,
+    stage: &mut StageHandle<T>,
     expected_request_id: u64,
     max_bytes: usize,
 ) -> crate::error::Result<()> {
     loop {
-        let msg = recv_stage_msg(channel, max_bytes).await?;
+        let msg = stage.recv(max_bytes).await?;
         match msg {
-            StageMsg::RequestDone { request_id } if request_id == expected_request_id => {
+            StageMsg::RequestDone { request_id, .. } if request_id == expected_request_id => {
                 return Ok(());
             }
             StageMsg::RequestError { request_id, .. } if request_id == expected_request_id => {
@@ -1036,34 +2540,161 @@ async fn drain_control_until_request_complete<T: AsyncRead + AsyncWrite + Unpin
     }
 }
 
-/// Receive tensors from data_out until END sentinel.
-/// Returns `PipelineError::StageFailed` if an error sentinel is received.
-/// Note: the error sentinel does not carry a stage index, so `stage_idx` is
-/// set to `usize::MAX` as a sentinel value indicating "unknown origin."
-async fn recv_output_tensors<T: AsyncRead + AsyncWrite + Unpin + Send>(
-    channel: &mut SecureChannel<T>,
-) -> crate::error::Result<Vec<OwnedTensor>> {
-    let mut tensors = Vec::new();
+/// Receive micro-batch `micro_batch` of `request_id` from data_out into
+/// `tensors`, in the data channels' `framing`. `exit_stage` sends
+/// data_out.
+///
+/// Cancel-safe, as [`recv_output_tensors`] and [`recv_output_until_end`].
+async fn recv_output<T: AsyncRead + AsyncWrite + Unpin + Send>(
+    channel: &mut DataOut<T>,
+    framing: DataFraming,
+    exit_stage: usize,
+    state: &mut GroupState,
+    request_id: u64,
+    micro_batch: u32,
+    tensors: &mut Vec<OwnedTensor>,
+) -> crate::error::Result<()> {
+    match framing {
+        DataFraming::Header => {
+            recv_output_tensors(channel, state, request_id, micro_batch, tensors).await
+        }
+        DataFraming::Sentinel => {
+            let failure = DataError {
+                stage_idx: exit_stage,
+                request_id,
+                micro_batch,
+                code: ErrorCode::Internal,
+            };
+            recv_output_until_end(channel, failure, tensors).await
+        }
+    }
+}
+
+/// Receive a version 1 tensor group from data_out into `tensors`: tensors
+/// up to the `END` trailer.
+///
+/// Version 1 groups are untagged, so the next group is taken as the one
+/// expected. An `ERR` carries no details, so it is reported as `failure`.
+/// Cancel-safe: tensors received before cancellation stay in `tensors`.
+async fn recv_output_until_end<T: AsyncRead + AsyncWrite + Unpin + Send>(
+    channel: &mut DataOut<T>,
+    failure: DataError,
+    tensors: &mut Vec<OwnedTensor>,
+) -> crate::error::Result<()> {
     loop {
-        let msg = channel.recv().await.map_err(PipelineError::Transport)?;
-        match msg {
+        match channel.recv().await? {
             Message::Tensor(t) => tensors.push(t),
-            Message::Data(data) if data.as_ref() == b"END" => break,
-            Message::Data(data) if data.as_ref() == ERROR_SENTINEL => {
-                return Err(PipelineError::StageFailed {
-                    stage_idx: usize::MAX,
-                    reason: "stage reported error on data channel".into(),
-                });
+            Message::Data(data) if data[..] == *END_SENTINEL => return Ok(()),
+            Message::Data(data) if data[..] == *ERROR_SENTINEL => {
+                return Err(PipelineError::UpstreamFailed(failure));
             }
             Message::Shutdown => return Err(PipelineError::Shutdown),
             other => {
                 return Err(PipelineError::Protocol(format!(
-                    "unexpected message on data_out: {other:?}"
+                    "expected tensors, END or ERR on data_out, got {other:?}"
                 )));
             }
         }
     }
-    Ok(tensors)
+}
+
+/// Receive micro-batch `micro_batch` of `request_id` from a header-framed
+/// data_out into `tensors`.
+///
+/// Groups and errors tagged with other requests are stale (left by a
+/// request that was abandoned) and are skipped. Returns
+/// `PipelineError::UpstreamFailed` with the failing stage's error if one
+/// arrives for `request_id` instead, and a protocol error if the next group
+/// of `request_id` is for a different micro-batch.
+///
+/// Cancel-safe: tensors received before cancellation stay in `tensors`, and
+/// `state` records how much of the current group is left.
+async fn recv_output_tensors<T: AsyncRead + AsyncWrite + Unpin + Send>(
+    channel: &mut DataOut<T>,
+    state: &mut GroupState,
+    request_id: u64,
+    micro_batch: u32,
+    tensors: &mut Vec<OwnedTensor>,
+) -> crate::error::Result<()> {
+    loop {
+        match *state {
+            GroupState::Reading { remaining: 0 } => {
+                *state = GroupState::Header;
+                return Ok(());
+            }
+            GroupState::Skipping { remaining: 0 } => *state = GroupState::Header,
+            GroupState::Reading { remaining } | GroupState::Skipping { remaining } => {
+                let tensor = match channel.recv().await? {
+                    Message::Tensor(t) => t,
+                    Message::Shutdown => return Err(PipelineError::Shutdown),
+                    other => {
+                        return Err(PipelineError::Protocol(format!(
+                            "expected {remaining} more tensors on data_out, got {other:?}"
+                        )));
+                    }
+                };
+                *state = match *state {
+                    GroupState::Reading { .. } => {
+                        tensors.push(tensor);
+                        GroupState::Reading {
+                            remaining: remaining - 1,
+                        }
+                    }
+                    _ => GroupState::Skipping {
+                        remaining: remaining - 1,
+                    },
+                };
+            }
+            GroupState::Header => {
+                let header = match channel.recv().await? {
+                    Message::Data(data) => DataFrame::from_bytes(&data)?,
+                    Message::Shutdown => return Err(PipelineError::Shutdown),
+                    other => {
+                        return Err(PipelineError::Protocol(format!(
+                            "expected a frame header on data_out, got {other:?}"
+                        )));
+                    }
+                };
+                match header {
+                    DataFrame::Error(e) if e.request_id == request_id => {
+                        return Err(PipelineError::UpstreamFailed(e));
+                    }
+                    DataFrame::Error(e) => {
+                        warn!(
+                            request_id,
+                            stale_request_id = e.request_id,
+                            "skipping stale error frame on data_out"
+                        );
+                    }
+                    DataFrame::Tensors {
+                        request_id: rid,
+                        micro_batch: mb,
+                        count,
+                    } if rid == request_id => {
+                        if mb != micro_batch {
+                            return Err(PipelineError::Protocol(format!(
+                                "expected micro-batch {micro_batch} of request {request_id} on data_out, got {mb}"
+                            )));
+                        }
+                        *state = GroupState::Reading { remaining: count };
+                    }
+                    DataFrame::Tensors {
+                        request_id: rid,
+                        micro_batch: mb,
+                        count,
+                    } => {
+                        warn!(
+                            request_id,
+                            stale_request_id = rid,
+                            micro_batch = mb,
+                            "skipping stale tensor group on data_out"
+                        );
+                        *state = GroupState::Skipping { remaining: count };
+                    }
+                }
+            }
+        }
+    }
 }
 
 /// Generate a unique request ID using an atomic counter seeded with the current
@@ -0,0 +1,263 @@
+use std::ops::Range;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::PartitionError;
+use crate::manifest::{ActivationSpec, ShardManifest, StageEndpoint, StageSpec};
+
+/// Measured or estimated cost of one model layer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayerCost {
+    /// Forward time of the layer for one micro-batch.
+    pub compute: Duration,
+    /// Bytes of the layer's parameters.
+    pub param_bytes: usize,
+    /// Bytes of the layer's output activations for one micro-batch.
+    pub activation_bytes: usize,
+}
+
+/// Contiguous layer ranges for each stage, from [`partition_layers`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partition {
+    /// Layer range of each stage, in stage order.
+    pub ranges: Vec<Range<usize>>,
+    /// Predicted forward time of each stage.
+    pub stage_compute: Vec<Duration>,
+    /// Predicted memory of each stage: its parameters plus its largest
+    /// output activation.
+    pub stage_memory: Vec<usize>,
+}
+
+impl Partition {
+    /// Forward time of the slowest stage, which bounds pipeline throughput.
+    pub fn bottleneck(&self) -> Duration {
+        self.stage_compute
+            .iter()
+            .copied()
+            .max()
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Predicted imbalance: how much slower the slowest stage is than the
+    /// mean, as a fraction (0.0 is perfectly balanced).
+    pub fn imbalance(&self) -> f64 {
+        let total: Duration = self.stage_compute.iter().sum();
+        if self.stage_compute.is_empty() || total.is_zero() {
+            return 0.0;
+        }
+        let mean = total.as_secs_f64() / self.stage_compute.len() as f64;
+        self.bottleneck().as_secs_f64() / mean - 1.0
+    }
+
+    /// Build a manifest with one stage per range, served at `endpoints`.
+    ///
+    /// Weight hashes and expected measurements are left empty for the
+    /// caller to fill in. The manifest is checked with
+    /// [`ShardManifest::validate`].
+    pub fn to_manifest(
+        &self,
+        model_name: impl Into<String>,
+        model_version: impl Into<String>,
+        activation_spec: ActivationSpec,
+        endpoints: Vec<StageEndpoint>,
+    ) -> std::result::Result<ShardManifest, PartitionError> {
+        if endpoints.len() != self.ranges.len() {
+            return Err(PartitionError::EndpointCountMismatch {
+                stages: self.ranges.len(),
+                endpoints: endpoints.len(),
+            });
+        }
+        let stages = self
+            .ranges
+            .iter()
+            .zip(endpoints)
+            .enumerate()
+            .map(|(stage_idx, (range, endpoint))| {
+                StageSpec::linear(stage_idx, range.clone(), endpoint)
+            })
+            .collect();
+        let manifest = ShardManifest {
+            model_name: model_name.into(),
+            model_version: model_version.into(),
+            total_layers: self.ranges.last().map_or(0, |r| r.end),
+            stages,
+            activation_spec,
+        };
+        manifest.validate()?;
+        Ok(manifest)
+    }
+}
+
+/// Split `layers` into `memory_limits.len()` contiguous, non-empty stages,
+/// minimising the slowest stage's compute.
+///
+/// Stage `i` must fit in `memory_limits[i]` bytes, counting its parameters
+/// plus its largest output activation. The search is exact; among equally
+/// slow partitions the one found first is returned.
+///
+/// Returns `PartitionError::Infeasible` if no split fits the limits.
+pub fn partition_layers(
+    layers: &[LayerCost],
+    memory_limits: &[usize],
+) -> std::result::Result<Partition, PartitionError> {
+    let n = layers.len();
+    let stages = memory_limits.len();
+    if n == 0 {
+        return Err(PartitionError::NoLayers);
+    }
+    if stages == 0 {
+        return Err(PartitionError::NoStages);
+    }
+    if stages > n {
+        return Err(PartitionError::TooManyStages { stages, layers: n });
+    }
+
+    let mut compute_prefix = vec![Duration::ZERO; n + 1];
+    let mut param_prefix = vec![0usize; n + 1];
+    for (i, layer) in layers.iter().enumerate() {
+        compute_prefix[i + 1] = compute_prefix[i] + layer.compute;
+        param_prefix[i + 1] = param_prefix[i].saturating_add(layer.param_bytes);
+    }
+    let compute = |r: &Range<usize>| compute_prefix[r.end] - compute_prefix[r.start];
+    let memory = |r: &Range<usize>| {
+        let peak = layers[r.clone()]
+            .iter()
+            .map(|l| l.activation_bytes)
+            .max()
+            .unwrap_or(0);
+        (param_prefix[r.end] - param_prefix[r.start]).saturating_add(peak)
+    };
+
+    // best[s][j]: slowest stage when the first `j` layers fill the first
+    // `s` stages; cut[s][j]: where stage `s - 1` starts in that split.
+    let mut best = vec![vec![None::<Duration>; n + 1]; stages + 1];
+    let mut cut = vec![vec![0usize; n + 1]; stages + 1];
+    best[0][0] = Some(Duration::ZERO);
+    for s in 1..=stages {
+        // Leave at least one layer for each remaining stage.
+        for j in s..=n - (stages - s) {
+            for i in (s - 1)..j {
+                let Some(before) = best[s - 1][i] else {
+                    continue;
+                };
+                if memory(&(i..j)) > memory_limits[s - 1] {
+                    continue;
+                }
+                let slowest = before.max(compute(&(i..j)));
+                if best[s][j].is_none_or(|b| slowest < b) {
+                    best[s][j] = Some(slowest);
+                    cut[s][j] = i;
+                }
+            }
+        }
+    }
+    if best[stages][n].is_none() {
+        return Err(PartitionError::Infeasible { stages });
+    }
+
+    let mut ranges = vec![0..0; stages];
+    let mut end = n;
+    for s in (1..=stages).rev() {
+        let start = cut[s][end];
+        ranges[s - 1] = start..end;
+        end = start;
+    }
+    Ok(Partition {
+        stage_compute: ranges.iter().map(compute).collect(),
+        stage_memory: ranges.iter().map(memory).collect(),
+        ranges,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::{ActivationDType, PortSpec};
+
+    fn layer(ms: u64, param_bytes: usize) -> LayerCost {
+        LayerCost {
+            compute: Duration::from_millis(ms),
+            param_bytes,
+            activation_bytes: 10,
+        }
+    }
+
+    fn endpoint(port: u16) -> StageEndpoint {
+        let tcp = |p: u16| PortSpec::Tcp {
+            addr: format!("127.0.0.1:{p}"),
+        };
+        StageEndpoint {
+            control: tcp(port),
+            data_in: tcp(port + 1),
+            data_out: tcp(port + 2),
+        }
+    }
+
+    #[test]
+    fn balances_uneven_layers() {
+        let layers: Vec<_> = [1, 1, 1, 1, 4, 4].iter().map(|&ms| layer(ms, 0)).collect();
+        let p = partition_layers(&layers, &[usize::MAX; 3]).unwrap();
+        assert_eq!(p.ranges, vec![0..4, 4..5, 5..6]);
+        assert_eq!(p.bottleneck(), Duration::from_millis(4));
+        assert!(p.imbalance().abs() < 1e-9);
+    }
+
+    #[test]
+    fn memory_limits_move_layers() {
+        let layers: Vec<_> = (0..4).map(|_| layer(1, 100)).collect();
+        // Balanced would be 2 + 2, but stage 0 only fits one layer.
+        let p = partition_layers(&layers, &[110, usize::MAX]).unwrap();
+        assert_eq!(p.ranges, vec![0..1, 1..4]);
+        assert_eq!(p.stage_memory, vec![110, 310]);
+        assert!((p.imbalance() - 0.5).abs() < 1e-9);
+
+        assert!(matches!(
+            partition_layers(&layers, &[110, 110]),
+            Err(PartitionError::Infeasible { stages: 2 })
+        ));
+    }
+
+    #[test]
+    fn rejects_degenerate_shapes() {
+        assert!(matches!(
+            partition_layers(&[], &[1]),
+            Err(PartitionError::NoLayers)
+        ));
+        assert!(matches!(
+            partition_layers(&[layer(1, 0)], &[]),
+            Err(PartitionError::NoStages)
+        ));
+        assert!(matches!(
+            partition_layers(&[layer(1, 0)], &[1, 1]),
+            Err(PartitionError::TooManyStages { .. })
+        ));
+    }
+
+    #[test]
+    fn builds_a_valid_manifest() {
+        let layers: Vec<_> = (0..12).map(|_| layer(1, 0)).collect();
+        let p = partition_layers(&layers, &[usize::MAX; 3]).unwrap();
+        let spec = ActivationSpec {
+            dtype: ActivationDType::F32,
+            hidden_dim: 8,
+            max_seq_len: 16,
+        };
+        let manifest = p
+            .to_manifest(
+                "m",
+                "1",
+                spec.clone(),
+                vec![endpoint(9000), endpoint(9010), endpoint(9020)],
+            )
+            .unwrap();
+        assert_eq!(manifest.total_layers, 12);
+        assert_eq!(manifest.stages[1].layer_start, 4);
+        assert_eq!(manifest.stages[1].layer_end, 8);
+
+        assert!(matches!(
+            p.to_manifest("m", "1", spec, vec![endpoint(9000)]),
+            Err(PartitionError::EndpointCountMismatch { .. })
+        ));
+    }
+}
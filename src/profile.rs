@@ -0,0 +1,303 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use bytes::Bytes;
+use confidential_ml_transport::{DType, OwnedTensor};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{PartitionError, ProfileError};
+use crate::manifest::{ActivationDType, ActivationSpec, ShardManifest};
+use crate::partition::{partition_layers, LayerCost, Partition};
+use crate::protocol::LayerSample;
+
+/// Per-layer costs measured by a profiling run, combined across stages.
+///
+/// Produced by [`Orchestrator::profile`](crate::Orchestrator::profile) and
+/// stored as JSON; [`layer_costs`](Self::layer_costs) feeds it straight into
+/// [`partition_layers`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelProfile {
+    pub model_name: String,
+    pub model_version: String,
+    /// Sequence length of the profiling inputs.
+    pub seq_len: u32,
+    /// One entry per model layer, in layer order.
+    pub layers: Vec<LayerProfile>,
+}
+
+/// Costs of one layer in a [`ModelProfile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayerProfile {
+    /// Global layer index.
+    pub layer: usize,
+    /// Stage that ran the layer during profiling.
+    pub stage_idx: usize,
+    /// Number of micro-batches measured.
+    pub samples: usize,
+    /// Mean forward time, in microseconds.
+    pub mean_forward_us: u64,
+    /// Slowest forward time, in microseconds.
+    pub max_forward_us: u64,
+    /// Largest output activation seen, in bytes.
+    pub output_bytes: u64,
+    /// Parameter bytes reported by the executor (zero if unknown).
+    pub param_bytes: u64,
+}
+
+impl ModelProfile {
+    /// Combine the samples each stage reported, indexed by stage as in
+    /// [`InferenceResult::layer_samples`](crate::InferenceResult::layer_samples).
+    ///
+    /// Every layer of `manifest` needs at least one sample, from the stage
+    /// that owns it. A stage that reported none at all has no per-layer
+    /// hook and fails with [`ProfileError::NoLayerSamples`].
+    pub fn from_samples(
+        manifest: &ShardManifest,
+        layer_samples: &[Vec<LayerSample>],
+        seq_len: u32,
+    ) -> std::result::Result<Self, ProfileError> {
+        if layer_samples.len() != manifest.stages.len() {
+            return Err(ProfileError::StageCountMismatch {
+                expected: manifest.stages.len(),
+                actual: layer_samples.len(),
+            });
+        }
+
+        let mut by_layer: BTreeMap<usize, (usize, Vec<&LayerSample>)> = BTreeMap::new();
+        for (spec, samples) in manifest.stages.iter().zip(layer_samples) {
+            if samples.is_empty() && spec.layer_start < spec.layer_end {
+                return Err(ProfileError::NoLayerSamples {
+                    stage_idx: spec.stage_idx,
+                });
+            }
+            for sample in samples {
+                if !(spec.layer_start..spec.layer_end).contains(&sample.layer) {
+                    return Err(ProfileError::UnknownLayer {
+                        stage_idx: spec.stage_idx,
+                        layer: sample.layer,
+                    });
+                }
+                by_layer
+                    .entry(sample.layer)
+                    .or_insert_with(|| (spec.stage_idx, Vec::new()))
+                    .1
+                    .push(sample);
+            }
+        }
+
+        let layers = (0..manifest.total_layers)
+            .map(|layer| {
+                let (stage_idx, samples) = by_layer
+                    .get(&layer)
+                    .ok_or(ProfileError::MissingLayer(layer))?;
+                let total: u64 = samples.iter().map(|s| s.forward_us).sum();
+                Ok(LayerProfile {
+                    layer,
+                    stage_idx: *stage_idx,
+                    samples: samples.len(),
+                    mean_forward_us: total / samples.len() as u64,
+                    max_forward_us: samples.iter().map(|s| s.forward_us).max().unwrap_or(0),
+                    output_bytes: samples.iter().map(|s| s.output_bytes).max().unwrap_or(0),
+                    param_bytes: samples.iter().map(|s| s.param_bytes).max().unwrap_or(0),
+                })
+            })
+            .collect::<std::result::Result<_, ProfileError>>()?;
+
+        Ok(Self {
+            model_name: manifest.model_name.clone(),
+            model_version: manifest.model_version.clone(),
+            seq_len,
+            layers,
+        })
+    }
+
+    /// Deserialize from JSON.
+    pub fn from_json(json: &str) -> std::result::Result<Self, ProfileError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Serialize to pretty JSON.
+    pub fn to_json(&self) -> std::result::Result<String, ProfileError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Read a profile file written by [`save`](Self::save).
+    pub fn load(path: impl AsRef<Path>) -> std::result::Result<Self, ProfileError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// Write the profile to `path` as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> std::result::Result<(), ProfileError> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Costs of each layer for [`partition_layers`]: mean forward time,
+    /// parameter bytes and largest output.
+    pub fn layer_costs(&self) -> Vec<LayerCost> {
+        self.layers
+            .iter()
+            .map(|l| LayerCost {
+                compute: Duration::from_micros(l.mean_forward_us),
+                param_bytes: usize::try_from(l.param_bytes).unwrap_or(usize::MAX),
+                activation_bytes: usize::try_from(l.output_bytes).unwrap_or(usize::MAX),
+            })
+            .collect()
+    }
+
+    /// Partition the profiled layers over stages with `memory_limits`.
+    pub fn partition(
+        &self,
+        memory_limits: &[usize],
+    ) -> std::result::Result<Partition, PartitionError> {
+        partition_layers(&self.layer_costs(), memory_limits)
+    }
+}
+
+/// Zero-filled activation inputs for a profiling run: `num_micro_batches`
+/// micro-batches of one `[1, seq_len, hidden_dim]` tensor named `name`.
+pub fn synthetic_inputs(
+    spec: &ActivationSpec,
+    name: &str,
+    num_micro_batches: u32,
+    seq_len: u32,
+) -> Vec<Vec<OwnedTensor>> {
+    let dtype = match spec.dtype {
+        ActivationDType::F32 => DType::F32,
+        ActivationDType::F16 => DType::F16,
+        ActivationDType::BF16 => DType::BF16,
+    };
+    let data = Bytes::from(vec![0u8; spec.tensor_bytes(1, seq_len as usize)]);
+    (0..num_micro_batches)
+        .map(|_| {
+            vec![OwnedTensor {
+                name: name.to_string(),
+                dtype,
+                shape: vec![1, seq_len, spec.hidden_dim],
+                data: data.clone(),
+            }]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::{PortSpec, StageEndpoint, StageSpec};
+
+    fn manifest(ranges: &[(usize, usize)]) -> ShardManifest {
+        let tcp = |p: u16| PortSpec::Tcp {
+            addr: format!("127.0.0.1:{p}"),
+        };
+        ShardManifest {
+            model_name: "m".into(),
+            model_version: "1".into(),
+            total_layers: ranges.last().unwrap().1,
+            stages: ranges
+                .iter()
+                .enumerate()
+                .map(|(stage_idx, &(layer_start, layer_end))| {
+                    StageSpec::linear(
+                        stage_idx,
+                        layer_start..layer_end,
+                        StageEndpoint {
+                            control: tcp(9000),
+                            data_in: tcp(9001),
+                            data_out: tcp(9002),
+                        },
+                    )
+                })
+                .collect(),
+            activation_spec: ActivationSpec {
+                dtype: ActivationDType::F32,
+                hidden_dim: 4,
+                max_seq_len: 8,
+            },
+        }
+    }
+
+    fn sample(layer: usize, forward_us: u64) -> LayerSample {
+        LayerSample {
+            layer,
+            forward_us,
+            output_bytes: 64,
+            param_bytes: 1000,
+        }
+    }
+
+    #[test]
+    fn combines_stage_samples_into_layer_costs() {
+        let m = manifest(&[(0, 2), (2, 3)]);
+        let samples = vec![
+            vec![sample(0, 10), sample(1, 30), sample(0, 20), sample(1, 50)],
+            vec![sample(2, 100), sample(2, 100)],
+        ];
+        let profile = ModelProfile::from_samples(&m, &samples, 8).unwrap();
+        assert_eq!(profile.layers.len(), 3);
+        assert_eq!(profile.layers[0].mean_forward_us, 15);
+        assert_eq!(profile.layers[1].max_forward_us, 50);
+        assert_eq!(profile.layers[2].stage_idx, 1);
+        assert_eq!(profile.layers[2].samples, 2);
+
+        let costs = profile.layer_costs();
+        assert_eq!(costs[1].compute, Duration::from_micros(40));
+        assert_eq!(costs[1].param_bytes, 1000);
+        assert_eq!(costs[1].activation_bytes, 64);
+
+        // Layer 2 is as slow as layers 0 and 1 together, plus some.
+        let p = profile.partition(&[usize::MAX; 2]).unwrap();
+        assert_eq!(p.ranges, vec![0..2, 2..3]);
+    }
+
+    #[test]
+    fn rejects_incomplete_or_misattributed_samples() {
+        let m = manifest(&[(0, 2), (2, 3)]);
+        assert!(matches!(
+            ModelProfile::from_samples(&m, &[vec![sample(0, 1)]], 8),
+            Err(ProfileError::StageCountMismatch {
+                expected: 2,
+                actual: 1
+            })
+        ));
+        assert!(matches!(
+            ModelProfile::from_samples(&m, &[vec![sample(0, 1)], vec![sample(2, 1)]], 8),
+            Err(ProfileError::MissingLayer(1))
+        ));
+        assert!(matches!(
+            ModelProfile::from_samples(&m, &[vec![sample(0, 1), sample(1, 1)], vec![]], 8),
+            Err(ProfileError::NoLayerSamples { stage_idx: 1 })
+        ));
+        assert!(matches!(
+            ModelProfile::from_samples(
+                &m,
+                &[vec![sample(0, 1), sample(1, 1)], vec![sample(0, 1)]],
+                8
+            ),
+            Err(ProfileError::UnknownLayer {
+                stage_idx: 1,
+                layer: 0
+            })
+        ));
+    }
+
+    #[test]
+    fn profile_file_roundtrips() {
+        let m = manifest(&[(0, 1)]);
+        let profile = ModelProfile::from_samples(&m, &[vec![sample(0, 7)]], 8).unwrap();
+        let path = std::env::temp_dir().join(format!("profile-{}.json", std::process::id()));
+        profile.save(&path).unwrap();
+        let loaded = ModelProfile::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, profile);
+    }
+
+    #[test]
+    fn synthetic_inputs_match_activation_spec() {
+        let spec = manifest(&[(0, 1)]).activation_spec;
+        let inputs = synthetic_inputs(&spec, "hidden", 3, 5);
+        assert_eq!(inputs.len(), 3);
+        assert_eq!(inputs[0][0].shape, vec![1, 5, 4]);
+        assert_eq!(inputs[0][0].data.len(), spec.tensor_bytes(1, 5));
+    }
+}
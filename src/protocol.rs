@@ -1,9 +1,29 @@
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 use crate::error::PipelineError;
+use crate::scheduler::ScheduleKind;
+
+/// Newest protocol version this build speaks. Incremented on wire-format
+/// changes.
+///
+/// Version 1 is the original format: the baseline control messages, and
+/// data channels that end each tensor group with `END` and replace a failed
+/// request's output with `ERR`. A version 1 stage runs one request at a
+/// time.
+///
+/// Version 2 adds version negotiation in `Init`/`Ready`, sessions, deadlines
+/// (`time_budget_ms`), profiling, per-micro-batch timings, error codes and
+/// details in `RequestError`, and [`DataFrame`] headers and typed
+/// [`DataError`]s on data channels. Control messages use the version agreed
+/// with each stage; data channels use the oldest version any stage agreed,
+/// which the orchestrator announces in `EstablishDataChannels`.
+pub const PROTOCOL_VERSION: u32 = 2;
 
-/// Current protocol version. Incremented on breaking wire-format changes.
-pub const PROTOCOL_VERSION: u32 = 1;
+/// Oldest protocol version this build still speaks.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
 
 /// Default maximum size for a control message in bytes (4 MiB).
 pub const DEFAULT_MAX_CONTROL_MESSAGE_BYTES: usize = 4 * 1024 * 1024;
@@ -11,33 +31,132 @@ pub const DEFAULT_MAX_CONTROL_MESSAGE_BYTES: usize = 4 * 1024 * 1024;
 /// Wire envelope that wraps every control message with a protocol version.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Envelope<T> {
-    /// Protocol version of the sender.
+    /// Protocol version the message is encoded in.
     pub version: u32,
     /// The inner message payload.
     pub msg: T,
 }
 
+/// Inclusive range of protocol versions a peer speaks.
+///
+/// The orchestrator advertises its range in `Init`. The stage picks the
+/// newest version both sides speak and reports it in `Ready`; every later
+/// control message on that channel is encoded in, and must carry, that
+/// version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl VersionRange {
+    /// Every version this build speaks.
+    pub const SUPPORTED: Self = Self {
+        min: MIN_PROTOCOL_VERSION,
+        max: PROTOCOL_VERSION,
+    };
+
+    /// Only `version`.
+    pub fn exactly(version: u32) -> Self {
+        Self {
+            min: version,
+            max: version,
+        }
+    }
+
+    /// Whether `version` is in the range.
+    pub fn contains(&self, version: u32) -> bool {
+        (self.min..=self.max).contains(&version)
+    }
+
+    /// Newest version in both ranges, if they overlap.
+    pub fn negotiate(&self, peer: &VersionRange) -> Option<u32> {
+        let version = self.max.min(peer.max);
+        (version >= self.min.max(peer.min)).then_some(version)
+    }
+
+    /// Check the range is non-empty and within [`Self::SUPPORTED`].
+    pub(crate) fn validate(&self) -> crate::error::Result<()> {
+        if self.min > self.max
+            || !Self::SUPPORTED.contains(self.min)
+            || !Self::SUPPORTED.contains(self.max)
+        {
+            return Err(PipelineError::Protocol(format!(
+                "protocol_versions {self} must be a non-empty range within {}",
+                Self::SUPPORTED
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for VersionRange {
+    fn default() -> Self {
+        Self::SUPPORTED
+    }
+}
+
+impl std::fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..={}", self.min, self.max)
+    }
+}
+
 /// Messages sent from the orchestrator to a stage over the control channel.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum OrchestratorMsg {
     /// Initialize stage with its spec and activation format.
+    ///
+    /// Sent in the oldest version the orchestrator speaks, so that stages
+    /// speaking only that version can read it.
     Init {
         stage_spec_json: String,
         activation_spec_json: String,
         num_stages: usize,
+        /// Schedule every stage runs; fill-drain when absent.
+        #[serde(default, skip_serializing_if = "is_fill_drain")]
+        schedule: ScheduleKind,
+        /// Protocol versions the orchestrator speaks. Absent from version 1
+        /// orchestrators, which speak only the envelope's version.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        protocol_versions: Option<VersionRange>,
     },
     /// Tell stage to accept data channel connections.
+    ///
+    /// `data_version` is the protocol version the pipeline's data channels
+    /// use, the oldest any stage agreed. Absent at version 1, where it is 1.
     EstablishDataChannels {
         has_upstream: bool,
         has_downstream: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        data_version: Option<u32>,
     },
     /// Start processing a new inference request.
+    ///
+    /// When `session_id` is set the request continues that session, and
+    /// stages run it against the session's state.
+    ///
+    /// `time_budget_ms` is the time left until the request's deadline when
+    /// the message was sent. Stages measure it from receipt, so their clocks
+    /// need not agree with the orchestrator's; they refuse or stop work on
+    /// the request once it runs out.
     StartRequest {
         request_id: u64,
         num_micro_batches: u32,
         seq_len: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        session_id: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        time_budget_ms: Option<u64>,
+        /// Report per-layer samples in `RequestDone`.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        profile: bool,
     },
+    /// Open a session whose state stages keep across requests.
+    OpenSession { session_id: u64 },
+    /// Close a session; stages release its state.
+    CloseSession { session_id: u64 },
     /// Abort an in-progress request.
     AbortRequest { request_id: u64, reason: String },
     /// Shut down the stage gracefully.
@@ -51,48 +170,303 @@ pub enum OrchestratorMsg {
 #[serde(tag = "type")]
 pub enum StageMsg {
     /// Stage has finished initialization and is ready.
-    Ready { stage_idx: usize },
+    ///
+    /// Sent in the version the stage chose from `Init`, which it also
+    /// reports in `protocol_version`. Version 1 stages omit the field.
+    Ready {
+        stage_idx: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        protocol_version: Option<u32>,
+    },
     /// Data channels have been established.
     DataChannelsReady { stage_idx: usize },
     /// Request completed successfully.
-    RequestDone { request_id: u64 },
+    ///
+    /// `timings` holds one entry per micro-batch, in processing order.
+    /// `layers` is only filled for profiled requests: one entry per layer
+    /// and micro-batch, micro-batches in processing order.
+    RequestDone {
+        request_id: u64,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        timings: Vec<MicroBatchTiming>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        layers: Vec<LayerSample>,
+    },
     /// Request failed with an error.
-    RequestError { request_id: u64, error: String },
+    ///
+    /// `code` classifies the failure; peers that do not send it report
+    /// `ErrorCode::Internal`. `details` carries the executor's own reason
+    /// when it gave one.
+    RequestError {
+        request_id: u64,
+        error: String,
+        #[serde(default, skip_serializing_if = "is_internal")]
+        code: ErrorCode,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        details: Option<String>,
+    },
     /// Health check pong.
     Pong { seq: u64 },
     /// Stage is shutting down.
     ShuttingDown { stage_idx: usize },
 }
 
-impl OrchestratorMsg {
-    /// Serialize to JSON bytes inside a versioned envelope.
+/// Time one stage spent on one micro-batch, reported in `RequestDone`.
+///
+/// Stages receive, compute and send different micro-batches concurrently,
+/// so one micro-batch's phases may overlap another's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MicroBatchTiming {
+    /// Time spent waiting for the micro-batch's input on data_in, in microseconds.
+    pub recv_wait_us: u64,
+    /// Duration of the `StageExecutor::forward` call, in microseconds.
+    pub forward_us: u64,
+    /// Time spent sending the output on data_out, in microseconds.
+    pub send_us: u64,
+}
+
+impl MicroBatchTiming {
+    /// Time spent waiting for input.
+    pub fn recv_wait(&self) -> Duration {
+        Duration::from_micros(self.recv_wait_us)
+    }
+
+    /// Time spent in the executor's forward pass.
+    pub fn forward(&self) -> Duration {
+        Duration::from_micros(self.forward_us)
+    }
+
+    /// Time spent sending output.
+    pub fn send(&self) -> Duration {
+        Duration::from_micros(self.send_us)
+    }
+}
+
+/// One layer's forward pass over one micro-batch, reported in `RequestDone`
+/// for profiled requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LayerSample {
+    /// Global layer index, as in the manifest.
+    pub layer: usize,
+    /// Forward time of the layer, in microseconds.
+    pub forward_us: u64,
+    /// Bytes of the layer's output activations.
+    pub output_bytes: u64,
+    /// Bytes of the layer's parameters; zero if the executor does not say.
+    pub param_bytes: u64,
+}
+
+impl LayerSample {
+    /// Forward time of the layer.
+    pub fn forward(&self) -> Duration {
+        Duration::from_micros(self.forward_us)
+    }
+}
+
+/// Largest [`DataFrame`] header accepted from a data channel.
+const MAX_DATA_FRAME_BYTES: usize = 1024;
+
+/// Header of a tensor group or error on a data channel, sent as a `Data`
+/// message.
+///
+/// A `Tensors` header is followed by exactly `count` `Tensor` messages.
+/// Receivers skip groups and errors tagged with a request other than the
+/// one they are reading, so frames left over from an aborted request are
+/// never read as part of the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DataFrame {
+    /// One micro-batch's tensors follow.
+    Tensors {
+        request_id: u64,
+        micro_batch: u32,
+        count: u32,
+    },
+    /// The request failed; no more of its tensors follow on this channel.
+    Error(DataError),
+}
+
+impl DataFrame {
+    /// Request the frame belongs to.
+    pub fn request_id(&self) -> u64 {
+        match self {
+            DataFrame::Tensors { request_id, .. } => *request_id,
+            DataFrame::Error(e) => e.request_id,
+        }
+    }
+
+    /// Serialize to JSON bytes.
     pub fn to_bytes(&self) -> Result<bytes::Bytes, serde_json::Error> {
-        let envelope = Envelope {
-            version: PROTOCOL_VERSION,
-            msg: self,
-        };
-        serde_json::to_vec(&envelope).map(bytes::Bytes::from)
+        serde_json::to_vec(self).map(bytes::Bytes::from)
     }
 
-    /// Deserialize from a versioned envelope, checking protocol version and size.
-    ///
-    /// Returns `PipelineError::MessageTooLarge` if `data` exceeds `max_bytes`,
-    /// `PipelineError::VersionMismatch` if the envelope version differs from
-    /// `PROTOCOL_VERSION`, or a protocol error on malformed JSON.
-    pub fn from_bytes_checked(data: &[u8], max_bytes: usize) -> crate::error::Result<Self> {
-        if data.len() > max_bytes {
+    /// Deserialize from JSON bytes, rejecting oversized headers.
+    pub fn from_bytes(data: &[u8]) -> crate::error::Result<Self> {
+        if data.len() > MAX_DATA_FRAME_BYTES {
             return Err(PipelineError::MessageTooLarge {
                 size: data.len(),
-                limit: max_bytes,
+                limit: MAX_DATA_FRAME_BYTES,
             });
         }
-        let envelope: Envelope<OrchestratorMsg> = serde_json::from_slice(data).map_err(|e| {
+        serde_json::from_slice(data).map_err(|e| {
             PipelineError::Protocol(format!(
-                "malformed orchestrator message ({} bytes): {e}",
+                "malformed data frame header ({} bytes): {e}",
                 data.len()
             ))
-        })?;
-        if envelope.version != PROTOCOL_VERSION {
+        })
+    }
+}
+
+/// Why a stage failed a request.
+///
+/// Each code is either retryable (the same request may succeed if sent
+/// again) or fatal; see [`is_retryable`](Self::is_retryable).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The executor's forward pass failed. Retryable.
+    Forward,
+    /// The executor ran out of memory or another resource. Retryable.
+    ResourceExhausted,
+    /// The request's input is invalid for the stage, e.g. `seq_len` exceeds
+    /// `max_seq_len`. Fatal.
+    InvalidInput,
+    /// Tensors broke the stage's contract or pass-through declaration. Fatal.
+    Contract,
+    /// The stage's weights do not match their declared hashes. Fatal.
+    WeightHash,
+    /// The request's session is unknown, was dropped, or failed. Fatal.
+    Session,
+    /// The request's deadline passed. Fatal.
+    DeadlineExceeded,
+    /// The orchestrator aborted the request. Retryable.
+    Aborted,
+    /// A data or control channel failed. Retryable.
+    Transport,
+    /// A peer broke the pipeline protocol. Fatal.
+    Protocol,
+    /// Any other failure. Fatal.
+    #[default]
+    Internal,
+}
+
+impl ErrorCode {
+    /// Whether a request that failed with this code may succeed if sent
+    /// again unchanged.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ErrorCode::Forward
+            | ErrorCode::ResourceExhausted
+            | ErrorCode::Aborted
+            | ErrorCode::Transport => true,
+            ErrorCode::InvalidInput
+            | ErrorCode::Contract
+            | ErrorCode::WeightHash
+            | ErrorCode::Session
+            | ErrorCode::DeadlineExceeded
+            | ErrorCode::Protocol
+            | ErrorCode::Internal => false,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ErrorCode::Forward => "forward failed",
+            ErrorCode::ResourceExhausted => "resource exhausted",
+            ErrorCode::InvalidInput => "invalid input",
+            ErrorCode::Contract => "contract violated",
+            ErrorCode::WeightHash => "weight hash mismatch",
+            ErrorCode::Session => "session unavailable",
+            ErrorCode::DeadlineExceeded => "deadline exceeded",
+            ErrorCode::Aborted => "aborted",
+            ErrorCode::Transport => "transport error",
+            ErrorCode::Protocol => "protocol error",
+            ErrorCode::Internal => "internal error",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Error a failing stage sends on its data_out channels, as a
+/// [`DataFrame::Error`], in place of the rest of a request's output.
+///
+/// A stage that fails because an upstream stage did forwards the upstream
+/// frame unchanged, so the frame that reaches the orchestrator names the
+/// stage where the failure started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataError {
+    /// Stage where the failure started.
+    pub stage_idx: usize,
+    pub request_id: u64,
+    /// First micro-batch whose output that stage did not send.
+    pub micro_batch: u32,
+    pub code: ErrorCode,
+}
+
+impl std::fmt::Display for DataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "stage {} failed request {} at micro-batch {}: {}",
+            self.stage_idx, self.request_id, self.micro_batch, self.code
+        )
+    }
+}
+
+impl OrchestratorMsg {
+    /// Oldest protocol version that can carry this message.
+    ///
+    /// Sessions, deadlines, profiling and an announced data version need
+    /// version 2. `Init` offers its version range in any version, since that
+    /// is how the version is chosen.
+    pub fn min_version(&self) -> u32 {
+        match self {
+            OrchestratorMsg::OpenSession { .. } | OrchestratorMsg::CloseSession { .. } => 2,
+            OrchestratorMsg::StartRequest {
+                session_id,
+                time_budget_ms,
+                profile,
+                ..
+            } if session_id.is_some() || time_budget_ms.is_some() || *profile => 2,
+            OrchestratorMsg::EstablishDataChannels {
+                data_version: Some(_),
+                ..
+            } => 2,
+            _ => 1,
+        }
+    }
+
+    /// Serialize to JSON bytes inside an envelope of the newest version.
+    pub fn to_bytes(&self) -> Result<bytes::Bytes, serde_json::Error> {
+        self.to_bytes_versioned(PROTOCOL_VERSION)
+    }
+
+    /// Serialize to JSON bytes inside an envelope of `version`, as
+    /// negotiated with the stage.
+    ///
+    /// Fails if the message needs a newer version (see
+    /// [`Self::min_version`]) rather than dropping what `version` cannot
+    /// carry.
+    pub fn to_bytes_versioned(&self, version: u32) -> Result<bytes::Bytes, serde_json::Error> {
+        if self.min_version() > version {
+            return Err(<serde_json::Error as serde::ser::Error>::custom(format!(
+                "message needs protocol version {}, channel speaks {version}: {self:?}",
+                self.min_version()
+            )));
+        }
+        encode(self, version)
+    }
+
+    /// Deserialize from a versioned envelope, checking protocol version and size.
+    ///
+    /// Returns `PipelineError::MessageTooLarge` if `data` exceeds `max_bytes`,
+    /// `PipelineError::VersionMismatch` if the envelope version is outside
+    /// [`VersionRange::SUPPORTED`], or a protocol error on malformed JSON.
+    pub fn from_bytes_checked(data: &[u8], max_bytes: usize) -> crate::error::Result<Self> {
+        let envelope = Self::envelope_from_bytes(data, max_bytes)?;
+        if !VersionRange::SUPPORTED.contains(envelope.version) {
             return Err(PipelineError::VersionMismatch {
                 expected: PROTOCOL_VERSION,
                 actual: envelope.version,
@@ -101,63 +475,88 @@ impl OrchestratorMsg {
         Ok(envelope.msg)
     }
 
-    /// Deserialize from bytes (legacy unversioned path, for backward compat in tests).
-    pub fn from_bytes(data: &[u8]) -> std::result::Result<Self, serde_json::Error> {
-        if data.len() > DEFAULT_MAX_CONTROL_MESSAGE_BYTES {
-            return Err(<serde_json::Error as serde::de::Error>::custom(format!(
-                "message too large: {} bytes exceeds limit of {}",
-                data.len(),
-                DEFAULT_MAX_CONTROL_MESSAGE_BYTES
+    /// Deserialize from an envelope that must be of the negotiated `version`.
+    ///
+    /// Errors as [`Self::from_bytes_checked`], with `VersionMismatch` for
+    /// any other version, and a protocol error for a message that `version`
+    /// cannot carry.
+    pub fn from_bytes_versioned(
+        data: &[u8],
+        max_bytes: usize,
+        version: u32,
+    ) -> crate::error::Result<Self> {
+        let msg = checked_version(Self::envelope_from_bytes(data, max_bytes)?, version)?;
+        if msg.min_version() > version {
+            return Err(PipelineError::Protocol(format!(
+                "message needs protocol version {}, channel speaks {version}: {msg:?}",
+                msg.min_version()
             )));
         }
+        Ok(msg)
+    }
 
-        let value: serde_json::Value = serde_json::from_slice(data)?;
-        let looks_versioned = value.get("version").is_some() || value.get("msg").is_some();
+    /// Deserialize the whole envelope without checking its version, for the
+    /// `Init` handshake that picks one.
+    pub fn envelope_from_bytes(
+        data: &[u8],
+        max_bytes: usize,
+    ) -> crate::error::Result<Envelope<Self>> {
+        decode(data, max_bytes, "orchestrator")
+    }
 
-        if looks_versioned {
-            let envelope: Envelope<OrchestratorMsg> = serde_json::from_value(value)?;
-            if envelope.version != PROTOCOL_VERSION {
-                return Err(<serde_json::Error as serde::de::Error>::custom(format!(
-                    "protocol version mismatch: expected {}, got {}",
-                    PROTOCOL_VERSION, envelope.version
-                )));
-            }
-            Ok(envelope.msg)
-        } else {
-            serde_json::from_value(value)
-        }
+    /// Deserialize from bytes (legacy unversioned path, for backward compat in tests).
+    pub fn from_bytes(data: &[u8]) -> std::result::Result<Self, serde_json::Error> {
+        decode_legacy(data)
     }
 }
 
 impl StageMsg {
-    /// Serialize to JSON bytes inside a versioned envelope.
+    /// Serialize to JSON bytes inside an envelope of the newest version.
     pub fn to_bytes(&self) -> Result<bytes::Bytes, serde_json::Error> {
-        let envelope = Envelope {
-            version: PROTOCOL_VERSION,
-            msg: self,
+        self.to_bytes_versioned(PROTOCOL_VERSION)
+    }
+
+    /// Serialize to JSON bytes inside an envelope of `version`, as
+    /// negotiated with the orchestrator.
+    ///
+    /// Version 1 has no reported version in `Ready`, timings or layer
+    /// samples in `RequestDone`, or code and details in `RequestError`;
+    /// they are left out.
+    pub fn to_bytes_versioned(&self, version: u32) -> Result<bytes::Bytes, serde_json::Error> {
+        if version >= 2 {
+            return encode(self, version);
+        }
+        let msg = match self.clone() {
+            StageMsg::Ready { stage_idx, .. } => StageMsg::Ready {
+                stage_idx,
+                protocol_version: None,
+            },
+            StageMsg::RequestDone { request_id, .. } => StageMsg::RequestDone {
+                request_id,
+                timings: Vec::new(),
+                layers: Vec::new(),
+            },
+            StageMsg::RequestError {
+                request_id, error, ..
+            } => StageMsg::RequestError {
+                request_id,
+                error,
+                code: ErrorCode::Internal,
+                details: None,
+            },
+            other => other,
         };
-        serde_json::to_vec(&envelope).map(bytes::Bytes::from)
+        encode(&msg, version)
     }
 
     /// Deserialize from a versioned envelope, checking protocol version and size.
     ///
     /// Returns `PipelineError::MessageTooLarge` if `data` exceeds `max_bytes`,
-    /// `PipelineError::VersionMismatch` if the envelope version differs from
-    /// `PROTOCOL_VERSION`, or a protocol error on malformed JSON.
+    /// `PipelineError::VersionMismatch` if the envelope version is outside
+    /// [`VersionRange::SUPPORTED`], or a protocol error on malformed JSON.
     pub fn from_bytes_checked(data: &[u8], max_bytes: usize) -> crate::error::Result<Self> {
-        if data.len() > max_bytes {
-            return Err(PipelineError::MessageTooLarge {
-                size: data.len(),
-                limit: max_bytes,
-            });
-        }
-        let envelope: Envelope<StageMsg> = serde_json::from_slice(data).map_err(|e| {
-            PipelineError::Protocol(format!(
-                "malformed stage message ({} bytes): {e}",
-                data.len()
-            ))
-        })?;
-        if envelope.version != PROTOCOL_VERSION {
+        let envelope = Self::envelope_from_bytes(data, max_bytes)?;
+        if !VersionRange::SUPPORTED.contains(envelope.version) {
             return Err(PipelineError::VersionMismatch {
                 expected: PROTOCOL_VERSION,
                 actual: envelope.version,
@@ -166,31 +565,102 @@ impl StageMsg {
         Ok(envelope.msg)
     }
 
+    /// Deserialize from an envelope that must be of the negotiated `version`.
+    ///
+    /// Errors as [`Self::from_bytes_checked`], with `VersionMismatch` for
+    /// any other version.
+    pub fn from_bytes_versioned(
+        data: &[u8],
+        max_bytes: usize,
+        version: u32,
+    ) -> crate::error::Result<Self> {
+        checked_version(Self::envelope_from_bytes(data, max_bytes)?, version)
+    }
+
+    /// Deserialize the whole envelope without checking its version, for the
+    /// `Ready` reply that reports the negotiated one.
+    pub fn envelope_from_bytes(
+        data: &[u8],
+        max_bytes: usize,
+    ) -> crate::error::Result<Envelope<Self>> {
+        decode(data, max_bytes, "stage")
+    }
+
     /// Deserialize from bytes (legacy unversioned path, for backward compat in tests).
     pub fn from_bytes(data: &[u8]) -> std::result::Result<Self, serde_json::Error> {
-        if data.len() > DEFAULT_MAX_CONTROL_MESSAGE_BYTES {
-            return Err(<serde_json::Error as serde::de::Error>::custom(format!(
-                "message too large: {} bytes exceeds limit of {}",
-                data.len(),
-                DEFAULT_MAX_CONTROL_MESSAGE_BYTES
-            )));
-        }
+        decode_legacy(data)
+    }
+}
 
-        let value: serde_json::Value = serde_json::from_slice(data)?;
-        let looks_versioned = value.get("version").is_some() || value.get("msg").is_some();
+/// `RequestError` leaves out the default code, which version 1 lacks.
+fn is_internal(code: &ErrorCode) -> bool {
+    *code == ErrorCode::Internal
+}
 
-        if looks_versioned {
-            let envelope: Envelope<StageMsg> = serde_json::from_value(value)?;
-            if envelope.version != PROTOCOL_VERSION {
-                return Err(<serde_json::Error as serde::de::Error>::custom(format!(
-                    "protocol version mismatch: expected {}, got {}",
-                    PROTOCOL_VERSION, envelope.version
-                )));
-            }
-            Ok(envelope.msg)
-        } else {
-            serde_json::from_value(value)
+/// `Init` leaves out the default schedule, so it reads as version 1 did.
+fn is_fill_drain(schedule: &ScheduleKind) -> bool {
+    *schedule == ScheduleKind::FillDrain
+}
+
+fn encode<T: Serialize>(msg: &T, version: u32) -> Result<bytes::Bytes, serde_json::Error> {
+    let envelope = Envelope { version, msg };
+    serde_json::to_vec(&envelope).map(bytes::Bytes::from)
+}
+
+/// Size-check and parse an envelope; `sender` names the peer in errors.
+fn decode<T: DeserializeOwned>(
+    data: &[u8],
+    max_bytes: usize,
+    sender: &str,
+) -> crate::error::Result<Envelope<T>> {
+    if data.len() > max_bytes {
+        return Err(PipelineError::MessageTooLarge {
+            size: data.len(),
+            limit: max_bytes,
+        });
+    }
+    serde_json::from_slice(data).map_err(|e| {
+        PipelineError::Protocol(format!(
+            "malformed {sender} message ({} bytes): {e}",
+            data.len()
+        ))
+    })
+}
+
+fn checked_version<T>(envelope: Envelope<T>, version: u32) -> crate::error::Result<T> {
+    if envelope.version != version {
+        return Err(PipelineError::VersionMismatch {
+            expected: version,
+            actual: envelope.version,
+        });
+    }
+    Ok(envelope.msg)
+}
+
+fn decode_legacy<T: DeserializeOwned>(data: &[u8]) -> std::result::Result<T, serde_json::Error> {
+    if data.len() > DEFAULT_MAX_CONTROL_MESSAGE_BYTES {
+        return Err(<serde_json::Error as serde::de::Error>::custom(format!(
+            "message too large: {} bytes exceeds limit of {}",
+            data.len(),
+            DEFAULT_MAX_CONTROL_MESSAGE_BYTES
+        )));
+    }
+
+    let value: serde_json::Value = serde_json::from_slice(data)?;
+    let looks_versioned = value.get("version").is_some() || value.get("msg").is_some();
+
+    if looks_versioned {
+        let envelope: Envelope<T> = serde_json::from_value(value)?;
+        if !VersionRange::SUPPORTED.contains(envelope.version) {
+            return Err(<serde_json::Error as serde::de::Error>::custom(format!(
+                "protocol version mismatch: expected {}, got {}",
+                VersionRange::SUPPORTED,
+                envelope.version
+            )));
         }
+        Ok(envelope.msg)
+    } else {
+        serde_json::from_value(value)
     }
 }
 
@@ -198,6 +668,41 @@ impl StageMsg {
 mod tests {
     use super::*;
 
+    #[test]
+    fn data_frame_roundtrip() {
+        let error = DataError {
+            stage_idx: 2,
+            request_id: 7,
+            micro_batch: 3,
+            code: ErrorCode::DeadlineExceeded,
+        };
+        let frames = [
+            DataFrame::Tensors {
+                request_id: 7,
+                micro_batch: 1,
+                count: 2,
+            },
+            DataFrame::Error(error),
+        ];
+        for frame in frames {
+            let bytes = frame.to_bytes().unwrap();
+            assert_eq!(DataFrame::from_bytes(&bytes).unwrap(), frame);
+            assert_eq!(frame.request_id(), 7);
+        }
+        assert_eq!(
+            error.to_string(),
+            "stage 2 failed request 7 at micro-batch 3: deadline exceeded"
+        );
+
+        assert!(DataFrame::from_bytes(b"END").is_err());
+        assert!(DataFrame::from_bytes(br#"{"type":"Tensors","request_id":0}"#).is_err());
+        let oversized = vec![b' '; MAX_DATA_FRAME_BYTES + 1];
+        assert!(matches!(
+            DataFrame::from_bytes(&oversized),
+            Err(PipelineError::MessageTooLarge { .. })
+        ));
+    }
+
     #[test]
     fn orchestrator_msg_roundtrip() {
         let msgs = vec![
@@ -205,16 +710,37 @@ mod tests {
                 stage_spec_json: r#"{"stage_idx":0}"#.into(),
                 activation_spec_json: r#"{"dtype":"F32"}"#.into(),
                 num_stages: 3,
+                schedule: ScheduleKind::Interleaved { chunks: 2 },
+                protocol_versions: Some(VersionRange::SUPPORTED),
             },
             OrchestratorMsg::EstablishDataChannels {
                 has_upstream: false,
                 has_downstream: true,
+                data_version: None,
+            },
+            OrchestratorMsg::EstablishDataChannels {
+                has_upstream: true,
+                has_downstream: true,
+                data_version: Some(1),
             },
             OrchestratorMsg::StartRequest {
                 request_id: 42,
                 num_micro_batches: 4,
                 seq_len: 128,
+                session_id: None,
+                time_budget_ms: None,
+                profile: false,
+            },
+            OrchestratorMsg::StartRequest {
+                request_id: 43,
+                num_micro_batches: 1,
+                seq_len: 1,
+                session_id: Some(7),
+                time_budget_ms: Some(250),
+                profile: true,
             },
+            OrchestratorMsg::OpenSession { session_id: 7 },
+            OrchestratorMsg::CloseSession { session_id: 7 },
             OrchestratorMsg::AbortRequest {
                 request_id: 42,
                 reason: "stage 1 failed".into(),
@@ -235,12 +761,41 @@ mod tests {
     #[test]
     fn stage_msg_roundtrip() {
         let msgs = vec![
-            StageMsg::Ready { stage_idx: 0 },
+            StageMsg::Ready {
+                stage_idx: 0,
+                protocol_version: Some(PROTOCOL_VERSION),
+            },
             StageMsg::DataChannelsReady { stage_idx: 1 },
-            StageMsg::RequestDone { request_id: 42 },
+            StageMsg::RequestDone {
+                request_id: 42,
+                timings: vec![],
+                layers: vec![],
+            },
+            StageMsg::RequestDone {
+                request_id: 43,
+                timings: vec![MicroBatchTiming {
+                    recv_wait_us: 10,
+                    forward_us: 250,
+                    send_us: 5,
+                }],
+                layers: vec![LayerSample {
+                    layer: 3,
+                    forward_us: 120,
+                    output_bytes: 4096,
+                    param_bytes: 0,
+                }],
+            },
             StageMsg::RequestError {
                 request_id: 42,
                 error: "OOM".into(),
+                code: ErrorCode::ResourceExhausted,
+                details: Some("CUDA out of memory".into()),
+            },
+            StageMsg::RequestError {
+                request_id: 43,
+                error: "aborted".into(),
+                code: ErrorCode::Aborted,
+                details: None,
             },
             StageMsg::Pong { seq: 1 },
             StageMsg::ShuttingDown { stage_idx: 2 },
@@ -254,6 +809,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn request_error_without_code_is_internal() {
+        let legacy = serde_json::json!({
+            "version": PROTOCOL_VERSION,
+            "msg": { "type": "RequestError", "request_id": 5, "error": "boom" },
+        });
+        let data = serde_json::to_vec(&legacy).unwrap();
+        match StageMsg::from_bytes_checked(&data, DEFAULT_MAX_CONTROL_MESSAGE_BYTES).unwrap() {
+            StageMsg::RequestError { code, details, .. } => {
+                assert_eq!(code, ErrorCode::Internal);
+                assert!(!code.is_retryable());
+                assert_eq!(details, None);
+            }
+            other => panic!("expected RequestError, got {other:?}"),
+        }
+
+        let bytes = StageMsg::RequestError {
+            request_id: 5,
+            error: "boom".into(),
+            code: ErrorCode::ResourceExhausted,
+            details: None,
+        }
+        .to_bytes()
+        .unwrap();
+        let raw: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(raw["msg"]["code"], "resource_exhausted");
+        assert!(raw["msg"].get("details").is_none());
+    }
+
     #[test]
     fn invalid_json_returns_error() {
         assert!(OrchestratorMsg::from_bytes(b"not json").is_err());
@@ -353,6 +937,9 @@ mod tests {
             request_id: 42,
             num_micro_batches: 4,
             seq_len: 128,
+            session_id: None,
+            time_budget_ms: None,
+            profile: false,
         };
         let data = msg.to_bytes().unwrap();
         let decoded = OrchestratorMsg::from_bytes_checked(&data, 4 * 1024 * 1024).unwrap();
@@ -361,6 +948,7 @@ mod tests {
                 request_id,
                 num_micro_batches,
                 seq_len,
+                ..
             } => {
                 assert_eq!(request_id, 42);
                 assert_eq!(num_micro_batches, 4);
@@ -370,6 +958,198 @@ mod tests {
         }
     }
 
+    #[test]
+    fn start_request_without_optional_fields_decodes() {
+        // Encoders that predate sessions and deadlines omit the fields entirely.
+        let data = br#"{"version":1,"msg":{"type":"StartRequest","request_id":9,"num_micro_batches":1,"seq_len":4}}"#;
+        let decoded = OrchestratorMsg::from_bytes_checked(data, 1024).unwrap();
+        assert!(matches!(
+            decoded,
+            OrchestratorMsg::StartRequest {
+                request_id: 9,
+                session_id: None,
+                time_budget_ms: None,
+                profile: false,
+                ..
+            }
+        ));
+
+        let bytes = decoded.to_bytes().unwrap();
+        let json = String::from_utf8_lossy(&bytes);
+        assert!(!json.contains("session_id"));
+        assert!(!json.contains("time_budget_ms"));
+        assert!(!json.contains("profile"));
+    }
+
+    #[test]
+    fn request_done_without_timings_decodes() {
+        let data = br#"{"version":1,"msg":{"type":"RequestDone","request_id":9}}"#;
+        let decoded = StageMsg::from_bytes_checked(data, 1024).unwrap();
+        assert!(matches!(
+            decoded,
+            StageMsg::RequestDone { request_id: 9, ref timings, ref layers }
+                if timings.is_empty() && layers.is_empty()
+        ));
+    }
+
+    #[test]
+    fn init_without_schedule_decodes_as_fill_drain() {
+        let data = br#"{"version":1,"msg":{"type":"Init","stage_spec_json":"{}","activation_spec_json":"{}","num_stages":2}}"#;
+        let decoded = OrchestratorMsg::from_bytes_checked(data, 1024).unwrap();
+        assert!(matches!(
+            decoded,
+            OrchestratorMsg::Init {
+                schedule: ScheduleKind::FillDrain,
+                protocol_versions: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn version_ranges_negotiate_newest_common_version() {
+        let v1 = VersionRange::exactly(1);
+        let v1_to_3 = VersionRange { min: 1, max: 3 };
+        let v2_to_4 = VersionRange { min: 2, max: 4 };
+        assert_eq!(v1.negotiate(&VersionRange::SUPPORTED), Some(1));
+        assert_eq!(v1_to_3.negotiate(&v2_to_4), Some(3));
+        assert_eq!(v2_to_4.negotiate(&v1_to_3), Some(3));
+        assert_eq!(v1.negotiate(&v2_to_4), None);
+        assert_eq!(v1_to_3.to_string(), "1..=3");
+
+        assert!(VersionRange::SUPPORTED.validate().is_ok());
+        assert!(VersionRange { min: 2, max: 1 }.validate().is_err());
+        assert!(VersionRange::exactly(PROTOCOL_VERSION + 1)
+            .validate()
+            .is_err());
+        assert!(VersionRange::exactly(0).validate().is_err());
+    }
+
+    #[test]
+    fn messages_follow_negotiated_version() {
+        for version in MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION {
+            let data = StageMsg::Pong { seq: 3 }
+                .to_bytes_versioned(version)
+                .unwrap();
+            let raw: serde_json::Value = serde_json::from_slice(&data).unwrap();
+            assert_eq!(raw["version"], version);
+            assert!(matches!(
+                StageMsg::from_bytes_versioned(&data, 1024, version),
+                Ok(StageMsg::Pong { seq: 3 })
+            ));
+            assert!(StageMsg::from_bytes_checked(&data, 1024).is_ok());
+        }
+
+        let data = OrchestratorMsg::Ping { seq: 1 }
+            .to_bytes_versioned(1)
+            .unwrap();
+        match OrchestratorMsg::from_bytes_versioned(&data, 1024, 2) {
+            Err(PipelineError::VersionMismatch { expected, actual }) => {
+                assert_eq!(expected, 2);
+                assert_eq!(actual, 1);
+            }
+            other => panic!("expected VersionMismatch, got {other:?}"),
+        }
+
+        // The handshake reads the envelope whatever its version.
+        let data = OrchestratorMsg::Shutdown.to_bytes_versioned(7).unwrap();
+        let envelope = OrchestratorMsg::envelope_from_bytes(&data, 1024).unwrap();
+        assert_eq!(envelope.version, 7);
+        assert!(matches!(envelope.msg, OrchestratorMsg::Shutdown));
+    }
+
+    #[test]
+    fn version_1_messages_use_baseline_format() {
+        let v2_only = [
+            OrchestratorMsg::OpenSession { session_id: 1 },
+            OrchestratorMsg::CloseSession { session_id: 1 },
+            OrchestratorMsg::StartRequest {
+                request_id: 1,
+                num_micro_batches: 1,
+                seq_len: 1,
+                session_id: None,
+                time_budget_ms: Some(100),
+                profile: false,
+            },
+            OrchestratorMsg::EstablishDataChannels {
+                has_upstream: true,
+                has_downstream: false,
+                data_version: Some(1),
+            },
+        ];
+        for msg in v2_only {
+            assert_eq!(msg.min_version(), 2);
+            assert!(msg.to_bytes_versioned(1).is_err());
+            let data = serde_json::to_vec(&Envelope { version: 1, msg }).unwrap();
+            assert!(matches!(
+                OrchestratorMsg::from_bytes_versioned(&data, 1024, 1),
+                Err(PipelineError::Protocol(_))
+            ));
+        }
+
+        let data = OrchestratorMsg::EstablishDataChannels {
+            has_upstream: true,
+            has_downstream: false,
+            data_version: None,
+        }
+        .to_bytes_versioned(1)
+        .unwrap();
+        assert_eq!(
+            &data[..],
+            br#"{"version":1,"msg":{"type":"EstablishDataChannels","has_upstream":true,"has_downstream":false}}"#
+        );
+
+        let stage_msgs = [
+            (
+                StageMsg::Ready {
+                    stage_idx: 1,
+                    protocol_version: Some(1),
+                },
+                r#"{"type":"Ready","stage_idx":1}"#,
+            ),
+            (
+                StageMsg::RequestDone {
+                    request_id: 4,
+                    timings: vec![MicroBatchTiming::default()],
+                    layers: vec![],
+                },
+                r#"{"type":"RequestDone","request_id":4}"#,
+            ),
+            (
+                StageMsg::RequestError {
+                    request_id: 4,
+                    error: "boom".into(),
+                    code: ErrorCode::Forward,
+                    details: Some("nan".into()),
+                },
+                r#"{"type":"RequestError","request_id":4,"error":"boom"}"#,
+            ),
+        ];
+        for (msg, baseline) in stage_msgs {
+            let data = msg.to_bytes_versioned(1).unwrap();
+            let raw: serde_json::Value = serde_json::from_slice(&data).unwrap();
+            assert_eq!(raw["version"], 1);
+            assert_eq!(
+                raw["msg"],
+                serde_json::from_str::<serde_json::Value>(baseline).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn ready_from_version_1_stage_decodes() {
+        let data = br#"{"version":1,"msg":{"type":"Ready","stage_idx":2}}"#;
+        let envelope = StageMsg::envelope_from_bytes(data, 1024).unwrap();
+        assert_eq!(envelope.version, 1);
+        assert!(matches!(
+            envelope.msg,
+            StageMsg::Ready {
+                stage_idx: 2,
+                protocol_version: None
+            }
+        ));
+    }
+
     #[test]
     fn from_bytes_checked_accepts_exact_size_limit() {
         let msg = StageMsg::Pong { seq: 1 };
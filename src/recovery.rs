@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use confidential_ml_transport::{AttestationProvider, AttestationVerifier};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tracing::{info, warn};
+
+use crate::error::PipelineError;
+use crate::manifest::ShardManifest;
+use crate::orchestrator::Orchestrator;
+use crate::relay::RelayHandle;
+
+/// Data-plane transports for one connection attempt.
+pub struct DataTransports<T> {
+    /// Orchestrator side of the entry stage's data_in (orchestrator = initiator).
+    pub data_in: T,
+    /// Orchestrator side of the exit stage's data_out (orchestrator = responder).
+    pub data_out: T,
+    /// Relays carrying the inter-stage data links, aborted on the next teardown.
+    pub relay_handles: Vec<RelayHandle>,
+}
+
+/// Opens fresh connections to the stages of a manifest, for
+/// [`Orchestrator::recover`].
+///
+/// The factory is also responsible for making the stages available again,
+/// e.g. by restarting stage processes whose previous connections were torn
+/// down. It is called once per recovery attempt.
+#[async_trait]
+pub trait TransportFactory<T>: Send {
+    /// Connect one control transport per stage, in stage order.
+    async fn connect_control(&mut self, manifest: &ShardManifest) -> crate::error::Result<Vec<T>>;
+
+    /// Connect the data transports. Called after every stage has been sent
+    /// `EstablishDataChannels`.
+    async fn connect_data(
+        &mut self,
+        manifest: &ShardManifest,
+    ) -> crate::error::Result<DataTransports<T>>;
+}
+
+/// Everything an [`OrchestratorHandle`](crate::OrchestratorHandle) needs to
+/// recover a tainted pipeline on its own. See
+/// [`OrchestratorHandle::spawn_with_recovery`](crate::OrchestratorHandle::spawn_with_recovery).
+pub struct Recovery<T> {
+    pub factory: Box<dyn TransportFactory<T>>,
+    pub provider: Arc<dyn AttestationProvider>,
+    pub verifier: Arc<dyn AttestationVerifier>,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
+    /// Rebuild the pipeline's connections, typically after it has been
+    /// tainted.
+    ///
+    /// Each attempt drops the control and data channels, aborts the relays,
+    /// then runs `init`, `send_establish_data_channels` and
+    /// `complete_data_channels` against the same manifest over transports
+    /// from `factory`. Failed attempts are retried with backoff according to
+    /// `OrchestratorConfig::recovery_policy`.
+    ///
+    /// Requests still in flight fail with `PipelineError::Shutdown` and open
+    /// sessions are lost. On success the pipeline is Ready and no longer
+    /// tainted; otherwise the last error is returned as
+    /// `PipelineError::RecoveryFailed` and the orchestrator stays tainted.
+    pub async fn recover<F>(
+        &mut self,
+        factory: &mut F,
+        provider: &dyn AttestationProvider,
+        verifier: &dyn AttestationVerifier,
+    ) -> crate::error::Result<()>
+    where
+        F: TransportFactory<T> + ?Sized,
+    {
+        let policy = self.recovery_policy().clone();
+        info!(
+            max_retries = policy.max_retries,
+            "orchestrator: recovering pipeline"
+        );
+
+        for attempt in 0..=policy.max_retries {
+            self.teardown();
+            match self.reconnect(factory, provider, verifier).await {
+                Ok(()) => {
+                    info!(attempt, "orchestrator: pipeline recovered");
+                    return Ok(());
+                }
+                Err(e) if attempt < policy.max_retries => {
+                    let delay = policy.delay_for_attempt(attempt);
+                    warn!(
+                        attempt,
+                        error = %e,
+                        delay_ms = delay.as_millis(),
+                        "orchestrator: recovery attempt failed, retrying"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => {
+                    self.teardown();
+                    self.mark_tainted();
+                    return Err(PipelineError::RecoveryFailed {
+                        attempts: attempt + 1,
+                        source: Box::new(e),
+                    });
+                }
+            }
+        }
+        unreachable!()
+    }
+
+    /// One connection attempt from the Created state.
+    async fn reconnect<F>(
+        &mut self,
+        factory: &mut F,
+        provider: &dyn AttestationProvider,
+        verifier: &dyn AttestationVerifier,
+    ) -> crate::error::Result<()>
+    where
+        F: TransportFactory<T> + ?Sized,
+    {
+        let control = factory.connect_control(self.manifest()).await?;
+        self.init(control, provider, verifier).await?;
+        self.send_establish_data_channels().await?;
+        let data = factory.connect_data(self.manifest()).await?;
+        self.complete_data_channels(
+            data.data_in,
+            data.data_out,
+            data.relay_handles,
+            provider,
+            verifier,
+        )
+        .await
+    }
+}
@@ -2,6 +2,8 @@ use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::task::JoinHandle;
 use tracing::{debug, warn};
 
+use crate::manifest::ShardManifest;
+
 /// Handle to a running relay task. Dropping it does not cancel the task;
 /// call `abort()` or `is_finished()` to manage lifecycle.
 pub struct RelayHandle {
@@ -88,6 +90,29 @@ where
     handles
 }
 
+/// Start relay links for the data links of a manifest, linear or graph.
+///
+/// Returns one relay handle per entry of [`ShardManifest::links`], in that
+/// order. The `transport_factory` is called as for [`start_relay_mesh`].
+pub async fn start_relay_graph<F, Fut, T>(
+    manifest: &ShardManifest,
+    transport_factory: F,
+) -> Vec<RelayHandle>
+where
+    F: Fn(usize, usize) -> Fut,
+    Fut: std::future::Future<Output = (T, T)>,
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let links = manifest.links();
+    let mut handles = Vec::with_capacity(links.len());
+    for (upstream, downstream) in links {
+        let (upstream_side, downstream_side) = transport_factory(upstream, downstream).await;
+        debug!(upstream, downstream, "starting relay link");
+        handles.push(start_relay_link(upstream_side, downstream_side));
+    }
+    handles
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,6 +168,44 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn relay_graph_follows_manifest_links() {
+        let json = r#"{
+            "model_name": "m", "model_version": "1", "total_layers": 3,
+            "activation_spec": {"dtype": "F32", "hidden_dim": 4, "max_seq_len": 8},
+            "stages": [
+                {"stage_idx": 0, "layer_start": 0, "layer_end": 1, "weight_hashes": [],
+                 "expected_measurements": {}, "downstream": [1, 2],
+                 "endpoint": {"control": {"type": "tcp", "addr": "127.0.0.1:9000"},
+                              "data_in": {"type": "tcp", "addr": "127.0.0.1:9001"},
+                              "data_out": {"type": "tcp", "addr": "127.0.0.1:9002"}}},
+                {"stage_idx": 1, "layer_start": 1, "layer_end": 2, "weight_hashes": [],
+                 "expected_measurements": {}, "upstream": [0], "downstream": [2],
+                 "endpoint": {"control": {"type": "tcp", "addr": "127.0.0.1:9010"},
+                              "data_in": {"type": "tcp", "addr": "127.0.0.1:9011"},
+                              "data_out": {"type": "tcp", "addr": "127.0.0.1:9012"}}},
+                {"stage_idx": 2, "layer_start": 2, "layer_end": 3, "weight_hashes": [],
+                 "expected_measurements": {}, "upstream": [0, 1],
+                 "endpoint": {"control": {"type": "tcp", "addr": "127.0.0.1:9020"},
+                              "data_in": {"type": "tcp", "addr": "127.0.0.1:9021"},
+                              "data_out": {"type": "tcp", "addr": "127.0.0.1:9022"}}}
+            ]
+        }"#;
+        let manifest = ShardManifest::from_json(json).unwrap();
+        let seen = std::sync::Mutex::new(Vec::new());
+        let handles = start_relay_graph(&manifest, |i, j| {
+            seen.lock().unwrap().push((i, j));
+            async { tokio::io::duplex(1024) }
+        })
+        .await;
+
+        assert_eq!(handles.len(), 3);
+        assert_eq!(*seen.lock().unwrap(), vec![(0, 1), (0, 2), (1, 2)]);
+        for h in &handles {
+            h.abort();
+        }
+    }
+
     #[tokio::test]
     async fn single_stage_no_relays() {
         let handles = start_relay_mesh(1, |_, _| async { tokio::io::duplex(1024) }).await;
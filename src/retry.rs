@@ -0,0 +1,198 @@
+use std::time::{Duration, Instant};
+
+use confidential_ml_transport::OwnedTensor;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tracing::warn;
+
+use crate::error::PipelineError;
+use crate::orchestrator::{InferenceResult, Orchestrator, RequestOptions};
+
+/// When and how often the orchestrator resubmits a failed request; see
+/// `OrchestratorConfig::request_retry_policy`.
+///
+/// Separate from the transport's `RetryPolicy`, which governs connecting.
+/// Only errors for which [`PipelineError::is_retryable`] holds are retried.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestRetryPolicy {
+    /// Retries after the first attempt (default: 0, i.e. disabled).
+    pub max_retries: u32,
+    /// Delay before the first retry (default: 50ms).
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between attempts (default: 2s).
+    pub max_backoff: Duration,
+    /// Factor the delay grows by with each retry (default: 2.0).
+    pub backoff_multiplier: f64,
+}
+
+impl Default for RequestRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(2),
+            backoff_multiplier: 2.0,
+        }
+    }
+}
+
+impl RequestRetryPolicy {
+    /// Default backoff with up to `max_retries` retries.
+    pub fn with_retries(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry number `attempt + 1`.
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let exponent = i32::try_from(attempt).unwrap_or(i32::MAX);
+        let delay = self.initial_backoff.as_secs_f64() * self.backoff_multiplier.powi(exponent);
+        Duration::from_secs_f64(delay.min(self.max_backoff.as_secs_f64()))
+    }
+
+    /// Backoff before retrying attempt number `attempt` (counting from 0)
+    /// after it failed with `error`, or `None` if the request should fail.
+    ///
+    /// Gives up once `max_retries` is used up, when `error` is not
+    /// retryable, when the pipeline is tainted, or when the backoff would
+    /// end past `deadline`.
+    pub(crate) fn backoff_after(
+        &self,
+        attempt: u32,
+        error: &PipelineError,
+        tainted: bool,
+        deadline: Option<Instant>,
+    ) -> Option<Duration> {
+        let backoff = self.delay_for_attempt(attempt);
+        let out_of_time = deadline.is_some_and(|deadline| Instant::now() + backoff >= deadline);
+        if attempt >= self.max_retries || !error.is_retryable() || tainted || out_of_time {
+            return None;
+        }
+        Some(backoff)
+    }
+
+    pub(crate) fn validate(&self) -> crate::error::Result<()> {
+        if !self.backoff_multiplier.is_finite() || self.backoff_multiplier < 1.0 {
+            return Err(PipelineError::Protocol(
+                "request_retry_policy.backoff_multiplier must be finite and >= 1.0".into(),
+            ));
+        }
+        if self.max_backoff < self.initial_backoff {
+            return Err(PipelineError::Protocol(
+                "request_retry_policy.max_backoff must be >= initial_backoff".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A failed attempt that was retried, listed in
+/// [`InferenceResult::retries`].
+#[derive(Debug)]
+pub struct RetryAttempt {
+    /// Why the attempt failed. Its request ID is the one the attempt was
+    /// sent under.
+    pub error: PipelineError,
+    /// How long the orchestrator waited before the next attempt.
+    pub backoff: Duration,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Orchestrator<T> {
+    /// Run a request, resubmitting it under a fresh request ID when it fails
+    /// with a retryable error.
+    ///
+    /// Gives up once `max_retries` is used up, when the pipeline is tainted,
+    /// or when the next backoff would end past `options.deadline`, and
+    /// returns the last error. Requests with a caller-supplied ID are sent
+    /// once, since a retry could not keep the ID.
+    pub(crate) async fn infer_with_retry(
+        &mut self,
+        input_tensors: Vec<Vec<OwnedTensor>>,
+        seq_len: u32,
+        options: RequestOptions,
+    ) -> crate::error::Result<InferenceResult> {
+        let policy = self.request_retry_policy().clone();
+        if policy.max_retries == 0 || options.request_id.is_some() {
+            return self
+                .infer_inner(None, input_tensors, seq_len, options)
+                .await;
+        }
+
+        let mut retries = Vec::new();
+        for attempt in 0..=policy.max_retries {
+            let error = match self
+                .infer_inner(None, input_tensors.clone(), seq_len, options.clone())
+                .await
+            {
+                Ok(mut result) => {
+                    result.retries = retries;
+                    return Ok(result);
+                }
+                Err(e) => e,
+            };
+
+            let Some(backoff) =
+                policy.backoff_after(attempt, &error, self.is_tainted(), options.deadline)
+            else {
+                return Err(error);
+            };
+
+            warn!(
+                attempt,
+                error = %error,
+                backoff_ms = backoff.as_millis(),
+                "orchestrator: request failed, retrying"
+            );
+            retries.push(RetryAttempt { error, backoff });
+            tokio::time::sleep(backoff).await;
+        }
+        unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_up_to_max() {
+        let policy = RequestRetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(35),
+            backoff_multiplier: 2.0,
+        };
+        let delays: Vec<_> = (0..4).map(|a| policy.delay_for_attempt(a)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Duration::from_millis(10),
+                Duration::from_millis(20),
+                Duration::from_millis(35),
+                Duration::from_millis(35),
+            ]
+        );
+        assert_eq!(
+            policy.delay_for_attempt(u32::MAX),
+            Duration::from_millis(35)
+        );
+    }
+
+    #[test]
+    fn validate_rejects_bad_backoff() {
+        assert!(RequestRetryPolicy::default().validate().is_ok());
+
+        let shrinking = RequestRetryPolicy {
+            backoff_multiplier: 0.5,
+            ..RequestRetryPolicy::default()
+        };
+        assert!(shrinking.validate().is_err());
+
+        let inverted = RequestRetryPolicy {
+            initial_backoff: Duration::from_secs(3),
+            ..RequestRetryPolicy::default()
+        };
+        assert!(inverted.validate().is_err());
+    }
+}
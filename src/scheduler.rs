@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use crate::error::SchedulerError;
 
 /// An operation in the pipeline schedule for a single time step.
@@ -9,6 +11,10 @@ pub enum PipeOp {
     Forward { micro_batch: u32 },
     /// Send activation tensors to the downstream stage.
     SendActivation { micro_batch: u32 },
+    /// Execute the backward pass on a micro-batch. Only training schedules
+    /// such as [`OneFOneB`] contain it; stage runtimes execute forward-only
+    /// schedules.
+    Backward { micro_batch: u32 },
     /// No work this step (pipeline bubble).
     Idle,
 }
@@ -20,21 +26,195 @@ pub struct StageSchedule {
     pub ops: Vec<Vec<PipeOp>>,
 }
 
-/// Forward-only inference schedule (fill-drain pattern).
+impl StageSchedule {
+    /// Check this stage's ops and make the first and last stage's
+    /// orchestrator I/O explicit, so the stage runtime can execute the
+    /// schedule op by op.
+    ///
+    /// Generated schedules omit `RecvActivation` on the first stage and
+    /// `SendActivation` on the last, whose data comes from and goes to the
+    /// orchestrator. The returned schedule inserts them next to the
+    /// corresponding `Forward`, so every micro-batch is received, forwarded
+    /// and sent exactly once, in that order.
+    ///
+    /// Returns `SchedulerError::InvalidSchedule` if a micro-batch is out of
+    /// range, handled out of order, more than once or not at all, if `Idle`
+    /// shares a step with other ops, or if the schedule contains `Backward`.
+    pub fn normalized(
+        &self,
+        num_stages: usize,
+        num_micro_batches: u32,
+    ) -> std::result::Result<StageSchedule, SchedulerError> {
+        let stage_idx = self.stage_idx;
+        let is_first = stage_idx == 0;
+        let is_last = stage_idx + 1 == num_stages;
+        let m = num_micro_batches as usize;
+        let invalid = |step: usize, reason: String| SchedulerError::InvalidSchedule {
+            stage_idx,
+            step,
+            reason,
+        };
+
+        let mut explicit_send = vec![false; m];
+        for op in self.ops.iter().flatten() {
+            if let PipeOp::SendActivation { micro_batch } = op {
+                if let Some(sent) = explicit_send.get_mut(*micro_batch as usize) {
+                    *sent = true;
+                }
+            }
+        }
+
+        let mut received = vec![false; m];
+        let mut forwarded = vec![false; m];
+        let mut sent = vec![false; m];
+        let mut ops = Vec::with_capacity(self.ops.len());
+        for (step, step_ops) in self.ops.iter().enumerate() {
+            if step_ops.len() > 1 && step_ops.contains(&PipeOp::Idle) {
+                return Err(invalid(step, "Idle shares a step with other ops".into()));
+            }
+            let mut out = Vec::with_capacity(step_ops.len());
+            for &op in step_ops {
+                let mb = match op {
+                    PipeOp::Idle => {
+                        out.push(op);
+                        continue;
+                    }
+                    PipeOp::Backward { .. } => {
+                        return Err(invalid(step, "backward passes are not supported".into()));
+                    }
+                    PipeOp::RecvActivation { micro_batch }
+                    | PipeOp::Forward { micro_batch }
+                    | PipeOp::SendActivation { micro_batch } => micro_batch,
+                };
+                let i = mb as usize;
+                if i >= m {
+                    return Err(invalid(
+                        step,
+                        format!("micro-batch {mb} out of range ({m} micro-batches)"),
+                    ));
+                }
+                match op {
+                    PipeOp::RecvActivation { .. } => {
+                        if received[i] {
+                            return Err(invalid(step, format!("micro-batch {mb} received twice")));
+                        }
+                        received[i] = true;
+                        out.push(op);
+                    }
+                    PipeOp::Forward { .. } => {
+                        if forwarded[i] {
+                            return Err(invalid(step, format!("micro-batch {mb} forwarded twice")));
+                        }
+                        if !received[i] {
+                            if !is_first {
+                                return Err(invalid(
+                                    step,
+                                    format!("micro-batch {mb} forwarded before it was received"),
+                                ));
+                            }
+                            received[i] = true;
+                            out.push(PipeOp::RecvActivation { micro_batch: mb });
+                        }
+                        forwarded[i] = true;
+                        out.push(op);
+                        if is_last && !explicit_send[i] {
+                            sent[i] = true;
+                            out.push(PipeOp::SendActivation { micro_batch: mb });
+                        }
+                    }
+                    PipeOp::SendActivation { .. } => {
+                        if !forwarded[i] {
+                            return Err(invalid(
+                                step,
+                                format!("micro-batch {mb} sent before it was forwarded"),
+                            ));
+                        }
+                        if sent[i] {
+                            return Err(invalid(step, format!("micro-batch {mb} sent twice")));
+                        }
+                        sent[i] = true;
+                        out.push(op);
+                    }
+                    PipeOp::Backward { .. } | PipeOp::Idle => unreachable!(),
+                }
+            }
+            ops.push(out);
+        }
+
+        let end = self.ops.len();
+        for mb in 0..m {
+            if !forwarded[mb] {
+                return Err(invalid(end, format!("micro-batch {mb} is never forwarded")));
+            }
+            if !sent[mb] {
+                return Err(invalid(end, format!("micro-batch {mb} is never sent")));
+            }
+        }
+
+        Ok(StageSchedule { stage_idx, ops })
+    }
+
+    /// Move each `RecvActivation` up to `depth` steps earlier, so a stage
+    /// receives the next inputs while `forward` runs on the current ones.
+    ///
+    /// Receives keep their order and come first in their new step, ahead
+    /// of that step's `Forward`. A step left empty becomes `Idle`. Expects a
+    /// [normalized](Self::normalized) schedule; the result is normalized
+    /// too.
+    pub fn prefetched(&self, depth: usize) -> StageSchedule {
+        let mut receives: Vec<Vec<PipeOp>> = vec![Vec::new(); self.ops.len()];
+        let mut rest: Vec<Vec<PipeOp>> = vec![Vec::new(); self.ops.len()];
+        for (step, step_ops) in self.ops.iter().enumerate() {
+            for &op in step_ops {
+                match op {
+                    PipeOp::RecvActivation { .. } => receives[step.saturating_sub(depth)].push(op),
+                    PipeOp::Idle => {}
+                    op => rest[step].push(op),
+                }
+            }
+        }
+        let ops = receives
+            .into_iter()
+            .zip(rest)
+            .map(|(mut step_ops, rest)| {
+                step_ops.extend(rest);
+                if step_ops.is_empty() {
+                    step_ops.push(PipeOp::Idle);
+                }
+                step_ops
+            })
+            .collect();
+        StageSchedule {
+            stage_idx: self.stage_idx,
+            ops,
+        }
+    }
+}
+
+/// A pipeline schedule: one [`StageSchedule`] per stage, or per virtual
+/// stage for interleaved schedules.
 ///
-/// With `p` stages and `m` micro-batches, the schedule has `m + p - 1` time steps.
-/// Bubble fraction = `(p - 1) / (m + p - 1)`.
+/// Every `Forward` and `Backward` takes one time step. With `p` stages and
+/// `m` micro-batches the forward-only fill-drain schedule has `m + p - 1`
+/// time steps and a bubble fraction of `(p - 1) / (m + p - 1)`.
 #[derive(Debug, Clone)]
 pub struct InferenceSchedule {
     pub num_stages: usize,
     pub num_micro_batches: u32,
-    /// Total time steps: m + p - 1.
+    /// Layer chunks held by each stage (1 unless interleaved).
+    ///
+    /// With `v` chunks, virtual stage `c * num_stages + s` is chunk `c` of
+    /// stage `s`, so each stage holds `v` non-adjacent slices of the model.
+    pub chunks_per_stage: usize,
+    /// Total time steps.
     pub total_steps: usize,
+    /// Indexed by virtual stage; equal to the stage index unless
+    /// `chunks_per_stage > 1`.
     pub stage_schedules: Vec<StageSchedule>,
 }
 
 impl InferenceSchedule {
-    /// Generate a forward-only fill-drain schedule.
+    /// Generate a forward-only fill-drain schedule, as [`FillDrain`] does.
     ///
     /// - Stage 0 has no `RecvActivation` (it receives input directly from the client).
     /// - The last stage has no `SendActivation` (it outputs results directly).
@@ -43,12 +223,7 @@ impl InferenceSchedule {
         num_stages: usize,
         num_micro_batches: u32,
     ) -> std::result::Result<Self, SchedulerError> {
-        if num_stages == 0 {
-            return Err(SchedulerError::ZeroStages);
-        }
-        if num_micro_batches == 0 {
-            return Err(SchedulerError::ZeroMicroBatches);
-        }
+        check_shape(num_stages, num_micro_batches)?;
 
         let p = num_stages;
         let m = num_micro_batches as usize;
@@ -84,23 +259,411 @@ impl InferenceSchedule {
         Ok(InferenceSchedule {
             num_stages,
             num_micro_batches,
+            chunks_per_stage: 1,
             total_steps,
             stage_schedules,
         })
     }
 
-    /// Bubble fraction: (p-1) / (m+p-1).
+    /// Fraction of stage time steps spent idle (pipeline bubble).
+    ///
+    /// A stage is busy in a step if any of its chunks runs `Forward` or
+    /// `Backward` then.
     pub fn bubble_fraction(&self) -> f64 {
-        if self.total_steps == 0 {
+        if self.total_steps == 0 || self.num_stages == 0 {
             return 0.0;
         }
-        (self.num_stages - 1) as f64 / self.total_steps as f64
+        let mut busy = vec![vec![false; self.total_steps]; self.num_stages];
+        for schedule in &self.stage_schedules {
+            let stage = schedule.stage_idx % self.num_stages;
+            for (step, ops) in schedule.ops.iter().enumerate() {
+                if ops
+                    .iter()
+                    .any(|op| matches!(op, PipeOp::Forward { .. } | PipeOp::Backward { .. }))
+                {
+                    busy[stage][step] = true;
+                }
+            }
+        }
+        let idle = busy.iter().flatten().filter(|b| !**b).count();
+        idle as f64 / (self.num_stages * self.total_steps) as f64
     }
 
-    /// Get the schedule for a specific stage.
+    /// Largest number of micro-batches in flight at once: started on the
+    /// first stage but not yet finished.
+    ///
+    /// A micro-batch finishes with its last `Forward` on the last stage, or
+    /// for training schedules with its `Backward` on the first stage. This
+    /// bounds the activations the pipeline holds at any time.
+    pub fn peak_in_flight(&self) -> usize {
+        let m = self.num_micro_batches as usize;
+        let mut first = vec![usize::MAX; m];
+        let mut last = vec![0; m];
+        for schedule in &self.stage_schedules {
+            for (step, ops) in schedule.ops.iter().enumerate() {
+                for op in ops {
+                    if let PipeOp::Forward { micro_batch } | PipeOp::Backward { micro_batch } = *op
+                    {
+                        let i = micro_batch as usize;
+                        if i < m {
+                            first[i] = first[i].min(step);
+                            last[i] = last[i].max(step);
+                        }
+                    }
+                }
+            }
+        }
+        // +1 where a micro-batch starts, -1 the step after it finishes.
+        let mut delta = vec![0i64; self.total_steps + 1];
+        for (&start, &end) in first.iter().zip(&last) {
+            if start <= end {
+                delta[start] += 1;
+                delta[end + 1] -= 1;
+            }
+        }
+        let mut open = 0i64;
+        let mut peak = 0i64;
+        for d in delta {
+            open += d;
+            peak = peak.max(open);
+        }
+        peak as usize
+    }
+
+    /// Get the schedule for a specific stage (or virtual stage, see
+    /// [`Self::chunks_per_stage`]).
     pub fn stage(&self, stage_idx: usize) -> Option<&StageSchedule> {
         self.stage_schedules.get(stage_idx)
     }
+
+    /// The normalized schedule a stage runtime executes for `stage_idx`.
+    ///
+    /// Stage runtimes run forward-only schedules with one layer chunk per
+    /// stage; anything else is `SchedulerError::Unsupported`. See
+    /// [`StageSchedule::normalized`] for the other checks.
+    pub fn executable_stage(
+        &self,
+        stage_idx: usize,
+    ) -> std::result::Result<StageSchedule, SchedulerError> {
+        if self.chunks_per_stage != 1 {
+            return Err(SchedulerError::Unsupported(format!(
+                "{} layer chunks per stage",
+                self.chunks_per_stage
+            )));
+        }
+        let schedule = self
+            .stage(stage_idx)
+            .ok_or_else(|| SchedulerError::InvalidSchedule {
+                stage_idx,
+                step: 0,
+                reason: format!("no schedule ({} stages)", self.num_stages),
+            })?;
+        if schedule
+            .ops
+            .iter()
+            .flatten()
+            .any(|op| matches!(op, PipeOp::Backward { .. }))
+        {
+            return Err(SchedulerError::Unsupported("backward passes".into()));
+        }
+        schedule.normalized(self.num_stages, self.num_micro_batches)
+    }
+}
+
+/// Generates pipeline schedules for a given shape.
+///
+/// Accounting comes from the generated [`InferenceSchedule`]; the provided
+/// methods are shortcuts for comparing strategies.
+pub trait ScheduleStrategy: Send + Sync {
+    /// Build the schedule for `num_stages` stages and `num_micro_batches`
+    /// micro-batches.
+    fn generate(
+        &self,
+        num_stages: usize,
+        num_micro_batches: u32,
+    ) -> std::result::Result<InferenceSchedule, SchedulerError>;
+
+    /// See [`InferenceSchedule::bubble_fraction`].
+    fn bubble_fraction(
+        &self,
+        num_stages: usize,
+        num_micro_batches: u32,
+    ) -> std::result::Result<f64, SchedulerError> {
+        Ok(self
+            .generate(num_stages, num_micro_batches)?
+            .bubble_fraction())
+    }
+
+    /// See [`InferenceSchedule::peak_in_flight`].
+    fn peak_in_flight(
+        &self,
+        num_stages: usize,
+        num_micro_batches: u32,
+    ) -> std::result::Result<usize, SchedulerError> {
+        Ok(self
+            .generate(num_stages, num_micro_batches)?
+            .peak_in_flight())
+    }
+}
+
+/// Forward-only fill-drain (GPipe-style) schedule: every stage forwards the
+/// micro-batches in order, one step behind its upstream neighbour.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FillDrain;
+
+impl ScheduleStrategy for FillDrain {
+    fn generate(
+        &self,
+        num_stages: usize,
+        num_micro_batches: u32,
+    ) -> std::result::Result<InferenceSchedule, SchedulerError> {
+        InferenceSchedule::generate(num_stages, num_micro_batches)
+    }
+}
+
+/// One-forward-one-backward (PipeDream-flush) training schedule.
+///
+/// Stage `s` runs `p - s - 1` warm-up forwards, then alternates one
+/// forward with one backward, then drains the remaining backwards. Its
+/// bubble matches fill-drain with backward passes, but at most `p`
+/// micro-batches are in flight instead of all `m`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OneFOneB;
+
+impl ScheduleStrategy for OneFOneB {
+    fn generate(
+        &self,
+        num_stages: usize,
+        num_micro_batches: u32,
+    ) -> std::result::Result<InferenceSchedule, SchedulerError> {
+        check_shape(num_stages, num_micro_batches)?;
+        let p = num_stages;
+        let m = num_micro_batches;
+        let orders = (0..p)
+            .map(|s| {
+                let warmup = ((p - s - 1) as u32).min(m);
+                let mut order: Vec<Task> = (0..warmup).map(|mb| Task::forward(s, mb)).collect();
+                for mb in 0..m {
+                    if warmup + mb < m {
+                        order.push(Task::forward(s, warmup + mb));
+                    }
+                    order.push(Task::backward(s, mb));
+                }
+                order
+            })
+            .collect();
+        Ok(lay_out(p, 1, m, orders))
+    }
+}
+
+/// Forward-only interleaved schedule over virtual stages.
+///
+/// The model is cut into `chunks * p` slices and stage `s` holds slices
+/// `s, s + p, s + 2p, ...`, so each micro-batch passes through every stage
+/// `chunks` times. Stages work through micro-batches in groups of `p`,
+/// finishing a chunk for the whole group before moving to the next. Each
+/// step is one chunk's forward, so with `m` a multiple of `p` the bubble
+/// fraction drops to `(p - 1) / (chunks * m + p - 1)`.
+#[derive(Debug, Clone, Copy)]
+pub struct Interleaved {
+    pub chunks: usize,
+}
+
+impl ScheduleStrategy for Interleaved {
+    fn generate(
+        &self,
+        num_stages: usize,
+        num_micro_batches: u32,
+    ) -> std::result::Result<InferenceSchedule, SchedulerError> {
+        check_shape(num_stages, num_micro_batches)?;
+        if self.chunks == 0 {
+            return Err(SchedulerError::ZeroChunks);
+        }
+        let p = num_stages;
+        let m = num_micro_batches;
+        let orders = (0..p)
+            .map(|s| {
+                let mut order = Vec::with_capacity(self.chunks * m as usize);
+                for group in (0..m).step_by(p) {
+                    for chunk in 0..self.chunks {
+                        for mb in group..(group + p as u32).min(m) {
+                            order.push(Task::forward(chunk * p + s, mb));
+                        }
+                    }
+                }
+                order
+            })
+            .collect();
+        Ok(lay_out(p, self.chunks, m, orders))
+    }
+}
+
+/// Selects one of the built-in [`ScheduleStrategy`] implementations, e.g.
+/// in [`OrchestratorConfig`](crate::OrchestratorConfig). Stages receive it
+/// with `Init`.
+///
+/// Stages run forward-only schedules with one layer chunk each. That rules
+/// out `OneFOneB`, a training schedule, and `Interleaved` with more than
+/// one chunk, which needs a stage to hold non-adjacent layers; both remain
+/// available to the planner and simulator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ScheduleKind {
+    /// [`FillDrain`].
+    #[default]
+    #[serde(rename = "fill_drain")]
+    FillDrain,
+    /// [`OneFOneB`].
+    #[serde(rename = "1f1b")]
+    OneFOneB,
+    /// [`Interleaved`].
+    #[serde(rename = "interleaved")]
+    Interleaved { chunks: usize },
+}
+
+impl ScheduleStrategy for ScheduleKind {
+    fn generate(
+        &self,
+        num_stages: usize,
+        num_micro_batches: u32,
+    ) -> std::result::Result<InferenceSchedule, SchedulerError> {
+        match *self {
+            ScheduleKind::FillDrain => FillDrain.generate(num_stages, num_micro_batches),
+            ScheduleKind::OneFOneB => OneFOneB.generate(num_stages, num_micro_batches),
+            ScheduleKind::Interleaved { chunks } => {
+                Interleaved { chunks }.generate(num_stages, num_micro_batches)
+            }
+        }
+    }
+}
+
+fn check_shape(
+    num_stages: usize,
+    num_micro_batches: u32,
+) -> std::result::Result<(), SchedulerError> {
+    if num_stages == 0 {
+        return Err(SchedulerError::ZeroStages);
+    }
+    if num_micro_batches == 0 {
+        return Err(SchedulerError::ZeroMicroBatches);
+    }
+    Ok(())
+}
+
+/// A forward or backward pass of one micro-batch on one virtual stage.
+#[derive(Debug, Clone, Copy)]
+struct Task {
+    virtual_stage: usize,
+    micro_batch: u32,
+    backward: bool,
+}
+
+impl Task {
+    fn forward(virtual_stage: usize, micro_batch: u32) -> Self {
+        Self {
+            virtual_stage,
+            micro_batch,
+            backward: false,
+        }
+    }
+
+    fn backward(virtual_stage: usize, micro_batch: u32) -> Self {
+        Self {
+            virtual_stage,
+            micro_batch,
+            backward: true,
+        }
+    }
+}
+
+/// Place each stage's tasks, in the given order, on one-step slots, running
+/// each as soon as its inputs are ready.
+///
+/// A forward needs the upstream virtual stage's forward of the same
+/// micro-batch; a backward needs its own forward and the downstream
+/// backward. Results become usable the step after they are produced.
+fn lay_out(
+    num_stages: usize,
+    chunks: usize,
+    num_micro_batches: u32,
+    orders: Vec<Vec<Task>>,
+) -> InferenceSchedule {
+    let virtual_stages = num_stages * chunks;
+    let m = num_micro_batches as usize;
+    let slot = |vs: usize, mb: u32| vs * m + mb as usize;
+    let mut forward_done: Vec<Option<usize>> = vec![None; virtual_stages * m];
+    let mut backward_done: Vec<Option<usize>> = vec![None; virtual_stages * m];
+    let mut placed: Vec<(usize, Task)> = Vec::new();
+    let mut next = vec![0; num_stages];
+    let mut remaining: usize = orders.iter().map(Vec::len).sum();
+
+    let mut step = 0;
+    while remaining > 0 {
+        let before_step = |done: Option<usize>| done.is_some_and(|t| t < step);
+        let mut ready = Vec::new();
+        for (stage, order) in orders.iter().enumerate() {
+            let Some(&task) = order.get(next[stage]) else {
+                continue;
+            };
+            let vs = task.virtual_stage;
+            let mb = task.micro_batch;
+            let is_ready = if task.backward {
+                before_step(forward_done[slot(vs, mb)])
+                    && (vs + 1 == virtual_stages || before_step(backward_done[slot(vs + 1, mb)]))
+            } else {
+                vs == 0 || before_step(forward_done[slot(vs - 1, mb)])
+            };
+            if is_ready {
+                ready.push((stage, task));
+            }
+        }
+        assert!(!ready.is_empty(), "schedule order deadlocks at step {step}");
+        for (stage, task) in ready {
+            let done = if task.backward {
+                &mut backward_done
+            } else {
+                &mut forward_done
+            };
+            done[slot(task.virtual_stage, task.micro_batch)] = Some(step);
+            placed.push((step, task));
+            next[stage] += 1;
+            remaining -= 1;
+        }
+        step += 1;
+    }
+
+    let total_steps = step;
+    let mut stage_schedules: Vec<StageSchedule> = (0..virtual_stages)
+        .map(|vs| StageSchedule {
+            stage_idx: vs,
+            ops: vec![vec![PipeOp::Idle]; total_steps],
+        })
+        .collect();
+    for (step, task) in placed {
+        let vs = task.virtual_stage;
+        let micro_batch = task.micro_batch;
+        let mut ops = Vec::new();
+        if task.backward {
+            ops.push(PipeOp::Backward { micro_batch });
+        } else {
+            if vs > 0 {
+                ops.push(PipeOp::RecvActivation { micro_batch });
+            }
+            ops.push(PipeOp::Forward { micro_batch });
+            if vs + 1 < virtual_stages {
+                ops.push(PipeOp::SendActivation { micro_batch });
+            }
+        }
+        stage_schedules[vs].ops[step] = ops;
+    }
+
+    InferenceSchedule {
+        num_stages,
+        num_micro_batches,
+        chunks_per_stage: chunks,
+        total_steps,
+        stage_schedules,
+    }
 }
 
 #[cfg(test)]
@@ -222,6 +785,220 @@ mod tests {
         }
     }
 
+    #[test]
+    fn normalized_makes_orchestrator_io_explicit() {
+        let s = InferenceSchedule::generate(2, 2).unwrap();
+
+        let s0 = s.stage_schedules[0].normalized(2, 2).unwrap();
+        assert_eq!(
+            s0.ops[0],
+            vec![
+                PipeOp::RecvActivation { micro_batch: 0 },
+                PipeOp::Forward { micro_batch: 0 },
+                PipeOp::SendActivation { micro_batch: 0 },
+            ]
+        );
+        assert_eq!(s0.ops[2], vec![PipeOp::Idle]);
+
+        let s1 = s.stage_schedules[1].normalized(2, 2).unwrap();
+        assert_eq!(
+            s1.ops[2],
+            vec![
+                PipeOp::RecvActivation { micro_batch: 1 },
+                PipeOp::Forward { micro_batch: 1 },
+                PipeOp::SendActivation { micro_batch: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn prefetched_moves_receives_ahead_of_forward() {
+        let s = InferenceSchedule::generate(2, 3).unwrap();
+        let s1 = s.executable_stage(1).unwrap();
+        assert_eq!(s1.prefetched(0).ops, s1.ops);
+
+        let early = s1.prefetched(1);
+        assert_eq!(
+            early.ops,
+            vec![
+                vec![PipeOp::RecvActivation { micro_batch: 0 }],
+                vec![
+                    PipeOp::RecvActivation { micro_batch: 1 },
+                    PipeOp::Forward { micro_batch: 0 },
+                    PipeOp::SendActivation { micro_batch: 0 },
+                ],
+                vec![
+                    PipeOp::RecvActivation { micro_batch: 2 },
+                    PipeOp::Forward { micro_batch: 1 },
+                    PipeOp::SendActivation { micro_batch: 1 },
+                ],
+                vec![
+                    PipeOp::Forward { micro_batch: 2 },
+                    PipeOp::SendActivation { micro_batch: 2 },
+                ],
+            ]
+        );
+        assert!(early.normalized(2, 3).is_ok());
+
+        // Every receive lands in step 0, still in order.
+        let all = s1.prefetched(10);
+        assert_eq!(
+            all.ops[0],
+            (0..3)
+                .map(|micro_batch| PipeOp::RecvActivation { micro_batch })
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            all.ops[3].last(),
+            Some(&PipeOp::SendActivation { micro_batch: 2 })
+        );
+    }
+
+    #[test]
+    fn normalized_rejects_forward_before_receive() {
+        let schedule = StageSchedule {
+            stage_idx: 1,
+            ops: vec![
+                vec![PipeOp::Forward { micro_batch: 0 }],
+                vec![PipeOp::RecvActivation { micro_batch: 0 }],
+            ],
+        };
+        assert!(matches!(
+            schedule.normalized(2, 1),
+            Err(SchedulerError::InvalidSchedule { step: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn normalized_rejects_incomplete_and_malformed_steps() {
+        let missing = StageSchedule {
+            stage_idx: 0,
+            ops: vec![vec![PipeOp::Forward { micro_batch: 0 }]],
+        };
+        assert!(matches!(
+            missing.normalized(1, 2),
+            Err(SchedulerError::InvalidSchedule { step: 1, .. })
+        ));
+
+        let idle = StageSchedule {
+            stage_idx: 0,
+            ops: vec![vec![PipeOp::Idle, PipeOp::Forward { micro_batch: 0 }]],
+        };
+        assert!(idle.normalized(1, 1).is_err());
+
+        let out_of_range = StageSchedule {
+            stage_idx: 0,
+            ops: vec![vec![PipeOp::Forward { micro_batch: 3 }]],
+        };
+        assert!(out_of_range.normalized(1, 1).is_err());
+    }
+
+    #[test]
+    fn one_f_one_b_alternates_after_warm_up() {
+        let s = OneFOneB.generate(2, 2).unwrap();
+        // F0 F1 B0 B1 on stage 0, F0 B0 F1 B1 on stage 1.
+        assert_eq!(s.total_steps, 6);
+        let s0: Vec<_> = s.stage_schedules[0]
+            .ops
+            .iter()
+            .filter(|ops| ops[0] != PipeOp::Idle)
+            .map(|ops| ops[0])
+            .collect();
+        assert_eq!(
+            s0,
+            vec![
+                PipeOp::Forward { micro_batch: 0 },
+                PipeOp::Forward { micro_batch: 1 },
+                PipeOp::Backward { micro_batch: 0 },
+                PipeOp::Backward { micro_batch: 1 },
+            ]
+        );
+        assert_eq!(
+            s.stage_schedules[1].ops[2],
+            vec![PipeOp::Backward { micro_batch: 0 }]
+        );
+    }
+
+    #[test]
+    fn one_f_one_b_bounds_in_flight_to_stage_count() {
+        for (p, m) in [(2, 8), (4, 16), (4, 3)] {
+            let s = OneFOneB.generate(p, m).unwrap();
+            let m = m as usize;
+            assert_eq!(s.total_steps, 2 * m + 2 * (p - 1), "p={p}, m={m}");
+            assert_eq!(s.peak_in_flight(), p.min(m), "p={p}, m={m}");
+            let expected = (p - 1) as f64 / (m + p - 1) as f64;
+            assert!((s.bubble_fraction() - expected).abs() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn interleaved_shrinks_bubble() {
+        let s = Interleaved { chunks: 2 }.generate(2, 4).unwrap();
+        assert_eq!(s.chunks_per_stage, 2);
+        assert_eq!(s.stage_schedules.len(), 4);
+        // 2 chunks * 4 micro-batches + 2 - 1.
+        assert_eq!(s.total_steps, 9);
+        assert!((s.bubble_fraction() - 1.0 / 9.0).abs() < 1e-10);
+        // Virtual stage 2 (stage 0's second chunk) receives from stage 1.
+        assert!(s.stage_schedules[2]
+            .ops
+            .iter()
+            .flatten()
+            .any(|op| matches!(op, PipeOp::RecvActivation { .. })));
+    }
+
+    #[test]
+    fn interleaved_with_one_chunk_is_fill_drain() {
+        let a = Interleaved { chunks: 1 }.generate(3, 5).unwrap();
+        let b = InferenceSchedule::generate(3, 5).unwrap();
+        assert_eq!(a.total_steps, b.total_steps);
+        for (x, y) in a.stage_schedules.iter().zip(&b.stage_schedules) {
+            assert_eq!(x.ops, y.ops);
+        }
+        assert!(matches!(
+            Interleaved { chunks: 0 }.generate(3, 5),
+            Err(SchedulerError::ZeroChunks)
+        ));
+    }
+
+    #[test]
+    fn fill_drain_in_flight_is_bounded_by_depth() {
+        assert_eq!(FillDrain.peak_in_flight(4, 16).unwrap(), 4);
+        assert_eq!(FillDrain.peak_in_flight(4, 2).unwrap(), 2);
+    }
+
+    #[test]
+    fn executable_stage_rejects_backward_and_chunks() {
+        let fill = ScheduleKind::FillDrain.generate(2, 2).unwrap();
+        assert!(fill.executable_stage(1).is_ok());
+        assert!(fill.executable_stage(2).is_err());
+
+        let one_f_one_b = ScheduleKind::OneFOneB.generate(2, 2).unwrap();
+        assert!(matches!(
+            one_f_one_b.executable_stage(0),
+            Err(SchedulerError::Unsupported(_))
+        ));
+
+        let interleaved = ScheduleKind::Interleaved { chunks: 2 }
+            .generate(2, 2)
+            .unwrap();
+        assert!(matches!(
+            interleaved.executable_stage(0),
+            Err(SchedulerError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn schedule_kind_json() {
+        let kind: ScheduleKind =
+            serde_json::from_str(r#"{"type":"interleaved","chunks":2}"#).unwrap();
+        assert_eq!(kind, ScheduleKind::Interleaved { chunks: 2 });
+        assert_eq!(
+            serde_json::to_string(&ScheduleKind::OneFOneB).unwrap(),
+            r#"{"type":"1f1b"}"#
+        );
+    }
+
     #[test]
     fn zero_stages_error() {
         assert!(matches!(
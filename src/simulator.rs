@@ -0,0 +1,574 @@
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use crate::error::SchedulerError;
+use crate::orchestrator::InferenceResult;
+use crate::protocol::MicroBatchTiming;
+use crate::scheduler::{InferenceSchedule, PipeOp, ScheduleStrategy};
+
+/// Cost of moving one micro-batch's activations over a link.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkCost {
+    /// Fixed cost per transfer (framing, encryption, relay hop).
+    pub latency: Duration,
+    /// Sustained throughput in bytes per second. Non-positive or infinite
+    /// values make the transfer cost `latency` alone.
+    pub bytes_per_sec: f64,
+}
+
+impl LinkCost {
+    /// Time to move `bytes` over this link.
+    pub fn transfer_time(&self, bytes: usize) -> Duration {
+        if self.bytes_per_sec > 0.0 && self.bytes_per_sec.is_finite() {
+            self.latency + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec)
+        } else {
+            self.latency
+        }
+    }
+}
+
+/// Per-stage and per-link costs for [`InferenceSchedule::simulate`].
+#[derive(Debug, Clone)]
+pub struct CostModel {
+    /// Forward time of one micro-batch on each stage, over all of the
+    /// stage's layer chunks.
+    pub forward: Vec<Duration>,
+    /// Backward time as a multiple of forward time, for training schedules
+    /// (default: 2.0).
+    pub backward_ratio: f64,
+    /// Link from each stage to the next. Entry `p - 1`, from the last stage
+    /// back to stage 0, is only used by interleaved schedules.
+    pub links: Vec<LinkCost>,
+    /// Bytes of activations per micro-batch, e.g. from
+    /// [`ActivationSpec::tensor_bytes`](crate::ActivationSpec::tensor_bytes).
+    pub activation_bytes: usize,
+}
+
+impl CostModel {
+    /// Cost model with the same link between every pair of stages.
+    pub fn new(forward: Vec<Duration>, link: LinkCost, activation_bytes: usize) -> Self {
+        let links = vec![link; forward.len()];
+        Self {
+            forward,
+            backward_ratio: 2.0,
+            links,
+            activation_bytes,
+        }
+    }
+
+    /// Calibrate a cost model from measured per-micro-batch timings, such as
+    /// [`InferenceResult::stage_timings`].
+    ///
+    /// Each stage's forward cost is its mean `forward` time and each link's
+    /// latency is the sending stage's mean `send` time.
+    pub fn from_timings(stage_timings: &[Vec<MicroBatchTiming>], activation_bytes: usize) -> Self {
+        let mean = |timings: &[MicroBatchTiming], f: fn(&MicroBatchTiming) -> Duration| {
+            match u32::try_from(timings.len()) {
+                Ok(n) if n > 0 => timings.iter().map(f).sum::<Duration>() / n,
+                _ => Duration::ZERO,
+            }
+        };
+        let forward = stage_timings
+            .iter()
+            .map(|t| mean(t, MicroBatchTiming::forward))
+            .collect();
+        let links = stage_timings
+            .iter()
+            .map(|t| LinkCost {
+                latency: mean(t, MicroBatchTiming::send),
+                bytes_per_sec: f64::INFINITY,
+            })
+            .collect();
+        Self {
+            forward,
+            backward_ratio: 2.0,
+            links,
+            activation_bytes,
+        }
+    }
+
+    /// Generate `strategy`'s schedule for this model's stages and simulate it.
+    pub fn simulate(
+        &self,
+        strategy: &dyn ScheduleStrategy,
+        num_micro_batches: u32,
+    ) -> std::result::Result<SimulationReport, SchedulerError> {
+        strategy
+            .generate(self.forward.len(), num_micro_batches)?
+            .simulate(self)
+    }
+}
+
+/// What a [`Span`] of simulated time was spent on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanKind {
+    /// Forward pass of one layer chunk on a stage.
+    Forward { chunk: usize },
+    /// Backward pass of one layer chunk on a stage.
+    Backward { chunk: usize },
+    /// Activations sent from a stage to the next.
+    SendActivation,
+    /// Gradients sent from a stage to the previous one.
+    SendGradient,
+}
+
+/// One interval of simulated work.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Span {
+    /// Stage doing the work, or sending stage for transfers.
+    pub stage: usize,
+    pub micro_batch: u32,
+    pub kind: SpanKind,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+/// Predicted timeline of one request, from [`InferenceSchedule::simulate`].
+#[derive(Debug, Clone)]
+pub struct SimulationReport {
+    pub num_stages: usize,
+    pub num_micro_batches: u32,
+    /// Time from the first forward until the last stage finishes.
+    pub makespan: Duration,
+    /// Compute time (forward and backward) per stage.
+    pub busy: Vec<Duration>,
+    /// Every compute and transfer interval, in schedule order.
+    pub spans: Vec<Span>,
+}
+
+/// Simulated and measured time for one stage, see
+/// [`SimulationReport::compare`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StageComparison {
+    pub predicted_forward: Duration,
+    pub measured_forward: Duration,
+}
+
+/// A simulation next to the timings of a real run of the same shape.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationComparison {
+    pub predicted_makespan: Duration,
+    /// The run's end-to-end `InferenceResult::total`, which also includes
+    /// the orchestrator's input and output transfers.
+    pub measured_makespan: Duration,
+    pub stages: Vec<StageComparison>,
+}
+
+impl SimulationComparison {
+    /// Relative error of the predicted makespan: `(predicted - measured) /
+    /// measured`, or 0 when nothing was measured.
+    pub fn makespan_error(&self) -> f64 {
+        let measured = self.measured_makespan.as_secs_f64();
+        if measured == 0.0 {
+            return 0.0;
+        }
+        (self.predicted_makespan.as_secs_f64() - measured) / measured
+    }
+}
+
+impl InferenceSchedule {
+    /// Predict this schedule's timeline under `costs`.
+    ///
+    /// Each stage runs its `Forward` and `Backward` ops in step order, one
+    /// at a time, as soon as their inputs have arrived. A chunk costs the
+    /// stage's forward time divided by `chunks_per_stage`. Transfers start
+    /// when the producing op ends and overlap compute, but each link
+    /// carries one transfer per direction at a time. Orchestrator input and
+    /// output transfers are not modelled.
+    pub fn simulate(
+        &self,
+        costs: &CostModel,
+    ) -> std::result::Result<SimulationReport, SchedulerError> {
+        let p = self.num_stages;
+        let chunks = self.chunks_per_stage.max(1);
+        let virtual_stages = self.stage_schedules.len();
+        let m = self.num_micro_batches as usize;
+        if costs.forward.len() != p {
+            return Err(SchedulerError::InvalidCostModel(format!(
+                "{} forward costs for {p} stages",
+                costs.forward.len()
+            )));
+        }
+        let links_needed = if chunks > 1 { p } else { p - 1 };
+        if costs.links.len() < links_needed {
+            return Err(SchedulerError::InvalidCostModel(format!(
+                "{} links, schedule needs {links_needed}",
+                costs.links.len()
+            )));
+        }
+
+        let transfer = |stage: usize| costs.links[stage].transfer_time(costs.activation_bytes);
+        let slot = |vs: usize, mb: u32| vs * m + mb as usize;
+        let missing =
+            |vs: usize, step: usize, mb: u32, what: &str| SchedulerError::InvalidSchedule {
+                stage_idx: vs,
+                step,
+                reason: format!("micro-batch {mb} {what} was produced"),
+            };
+
+        let mut stage_free = vec![Duration::ZERO; p];
+        let mut forward_link_free = vec![Duration::ZERO; p];
+        let mut backward_link_free = vec![Duration::ZERO; p];
+        let mut activation_ready: Vec<Option<Duration>> = vec![None; virtual_stages * m];
+        let mut gradient_ready: Vec<Option<Duration>> = vec![None; virtual_stages * m];
+        let mut forward_end: Vec<Option<Duration>> = vec![None; virtual_stages * m];
+        let mut busy = vec![Duration::ZERO; p];
+        let mut spans = Vec::new();
+
+        for step in 0..self.total_steps {
+            for schedule in &self.stage_schedules {
+                let vs = schedule.stage_idx;
+                let stage = vs % p;
+                let chunk = vs / p;
+                let forward_cost = costs.forward[stage] / chunks as u32;
+                for op in schedule.ops.get(step).into_iter().flatten() {
+                    match *op {
+                        PipeOp::Forward { micro_batch } => {
+                            let ready = if vs == 0 {
+                                Duration::ZERO
+                            } else {
+                                activation_ready[slot(vs, micro_batch)].ok_or_else(|| {
+                                    missing(vs, step, micro_batch, "forwarded before its input")
+                                })?
+                            };
+                            let start = stage_free[stage].max(ready);
+                            let end = start + forward_cost;
+                            stage_free[stage] = end;
+                            busy[stage] += forward_cost;
+                            forward_end[slot(vs, micro_batch)] = Some(end);
+                            spans.push(Span {
+                                stage,
+                                micro_batch,
+                                kind: SpanKind::Forward { chunk },
+                                start,
+                                end,
+                            });
+                            if vs + 1 < virtual_stages {
+                                let start = end.max(forward_link_free[stage]);
+                                let end = start + transfer(stage);
+                                forward_link_free[stage] = end;
+                                activation_ready[slot(vs + 1, micro_batch)] = Some(end);
+                                spans.push(Span {
+                                    stage,
+                                    micro_batch,
+                                    kind: SpanKind::SendActivation,
+                                    start,
+                                    end,
+                                });
+                            }
+                        }
+                        PipeOp::Backward { micro_batch } => {
+                            let forwarded =
+                                forward_end[slot(vs, micro_batch)].ok_or_else(|| {
+                                    missing(vs, step, micro_batch, "backward before its output")
+                                })?;
+                            let gradient = if vs + 1 == virtual_stages {
+                                Duration::ZERO
+                            } else {
+                                gradient_ready[slot(vs, micro_batch)].ok_or_else(|| {
+                                    missing(vs, step, micro_batch, "backward before its gradient")
+                                })?
+                            };
+                            let cost = forward_cost.mul_f64(costs.backward_ratio.max(0.0));
+                            let start = stage_free[stage].max(forwarded).max(gradient);
+                            let end = start + cost;
+                            stage_free[stage] = end;
+                            busy[stage] += cost;
+                            spans.push(Span {
+                                stage,
+                                micro_batch,
+                                kind: SpanKind::Backward { chunk },
+                                start,
+                                end,
+                            });
+                            if vs > 0 {
+                                // Gradients use the link into this stage, in reverse.
+                                let link = (vs - 1) % p;
+                                let start = end.max(backward_link_free[link]);
+                                let end = start + transfer(link);
+                                backward_link_free[link] = end;
+                                gradient_ready[slot(vs - 1, micro_batch)] = Some(end);
+                                spans.push(Span {
+                                    stage,
+                                    micro_batch,
+                                    kind: SpanKind::SendGradient,
+                                    start,
+                                    end,
+                                });
+                            }
+                        }
+                        PipeOp::RecvActivation { .. }
+                        | PipeOp::SendActivation { .. }
+                        | PipeOp::Idle => {}
+                    }
+                }
+            }
+        }
+
+        let makespan = spans.iter().map(|s| s.end).max().unwrap_or(Duration::ZERO);
+        Ok(SimulationReport {
+            num_stages: p,
+            num_micro_batches: self.num_micro_batches,
+            makespan,
+            busy,
+            spans,
+        })
+    }
+}
+
+impl SimulationReport {
+    /// Fraction of the makespan each stage spends computing.
+    pub fn utilisation(&self) -> Vec<f64> {
+        let makespan = self.makespan.as_secs_f64();
+        self.busy
+            .iter()
+            .map(|b| {
+                if makespan == 0.0 {
+                    0.0
+                } else {
+                    b.as_secs_f64() / makespan
+                }
+            })
+            .collect()
+    }
+
+    /// Fraction of stage time spent idle: one minus the mean utilisation.
+    pub fn bubble_fraction(&self) -> f64 {
+        if self.num_stages == 0 || self.makespan.is_zero() {
+            return 0.0;
+        }
+        1.0 - self.utilisation().iter().sum::<f64>() / self.num_stages as f64
+    }
+
+    /// Micro-batches completed per second, if requests run back to back.
+    pub fn throughput(&self) -> f64 {
+        if self.makespan.is_zero() {
+            return 0.0;
+        }
+        self.num_micro_batches as f64 / self.makespan.as_secs_f64()
+    }
+
+    /// Render the timeline as a text Gantt chart `width` columns wide.
+    ///
+    /// There is one row per stage and one per link carrying activations.
+    /// Forward passes and activation transfers show the micro-batch index
+    /// modulo 10, backward passes a letter (`a` for micro-batch 0) and idle
+    /// time `.`. Gradient transfers are not drawn.
+    pub fn gantt(&self, width: usize) -> String {
+        let width = width.max(1);
+        let makespan = self.makespan.as_secs_f64();
+        let column = |t: Duration| {
+            if makespan == 0.0 {
+                0
+            } else {
+                ((t.as_secs_f64() / makespan) * width as f64).round() as usize
+            }
+        };
+        let mut stages = vec![vec!['.'; width]; self.num_stages];
+        let mut links = vec![vec!['.'; width]; self.num_stages];
+        let mut link_used = vec![false; self.num_stages];
+        for span in &self.spans {
+            let (row, mark) = match span.kind {
+                SpanKind::Forward { .. } => (&mut stages[span.stage], digit(span.micro_batch)),
+                SpanKind::Backward { .. } => (&mut stages[span.stage], letter(span.micro_batch)),
+                SpanKind::SendActivation => {
+                    link_used[span.stage] = true;
+                    (&mut links[span.stage], digit(span.micro_batch))
+                }
+                SpanKind::SendGradient => continue,
+            };
+            let start = column(span.start).min(width - 1);
+            let end = column(span.end).clamp(start + 1, width);
+            for cell in &mut row[start..end] {
+                *cell = mark;
+            }
+        }
+
+        let mut out = String::new();
+        for stage in 0..self.num_stages {
+            let row: String = stages[stage].iter().collect();
+            let _ = writeln!(out, "stage {stage:<3}|{row}|");
+            if link_used[stage] {
+                let row: String = links[stage].iter().collect();
+                let _ = writeln!(out, "link {stage:<4}|{row}|");
+            }
+        }
+        let _ = writeln!(
+            out,
+            "{:>10}0{:>width$?}",
+            "",
+            self.makespan,
+            width = width - 1
+        );
+        out
+    }
+
+    /// Compare this prediction with a real run of the same schedule shape.
+    pub fn compare(&self, measured: &InferenceResult) -> SimulationComparison {
+        let mut predicted_forward = vec![Duration::ZERO; self.num_stages];
+        for span in &self.spans {
+            if let SpanKind::Forward { .. } = span.kind {
+                predicted_forward[span.stage] += span.end - span.start;
+            }
+        }
+        let stages = predicted_forward
+            .into_iter()
+            .enumerate()
+            .map(|(stage, predicted_forward)| StageComparison {
+                predicted_forward,
+                measured_forward: measured
+                    .stage_timings
+                    .get(stage)
+                    .map(|t| t.iter().map(MicroBatchTiming::forward).sum())
+                    .unwrap_or(Duration::ZERO),
+            })
+            .collect();
+        SimulationComparison {
+            predicted_makespan: self.makespan,
+            measured_makespan: measured.total,
+            stages,
+        }
+    }
+}
+
+fn digit(micro_batch: u32) -> char {
+    char::from_digit(micro_batch % 10, 10).unwrap_or('?')
+}
+
+fn letter(micro_batch: u32) -> char {
+    char::from(b'a' + (micro_batch % 26) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::{FillDrain, Interleaved, OneFOneB};
+
+    const MS: Duration = Duration::from_millis(1);
+
+    fn free_link() -> LinkCost {
+        LinkCost {
+            latency: Duration::ZERO,
+            bytes_per_sec: f64::INFINITY,
+        }
+    }
+
+    #[test]
+    fn fill_drain_matches_step_count() {
+        let costs = CostModel::new(vec![10 * MS; 3], free_link(), 0);
+        let report = costs.simulate(&FillDrain, 4).unwrap();
+        // (m + p - 1) steps of 10ms.
+        assert_eq!(report.makespan, 60 * MS);
+        assert!((report.bubble_fraction() - 2.0 / 6.0).abs() < 1e-9);
+        for u in report.utilisation() {
+            assert!((u - 40.0 / 60.0).abs() < 1e-9);
+        }
+        assert!((report.throughput() - 4.0 / 0.06).abs() < 1e-6);
+    }
+
+    #[test]
+    fn link_costs_delay_downstream_stages() {
+        let link = LinkCost {
+            latency: MS,
+            bytes_per_sec: 1e9,
+        };
+        // 1 MB at 1 GB/s: 1ms on top of the 1ms latency.
+        assert_eq!(link.transfer_time(1_000_000), 2 * MS);
+        let costs = CostModel::new(vec![10 * MS; 3], link, 1_000_000);
+        let report = costs.simulate(&FillDrain, 4).unwrap();
+        assert_eq!(report.makespan, 64 * MS);
+    }
+
+    #[test]
+    fn slowest_stage_bounds_the_pipeline() {
+        let costs = CostModel::new(vec![10 * MS, 30 * MS, 10 * MS], free_link(), 0);
+        let report = costs.simulate(&FillDrain, 4).unwrap();
+        assert_eq!(report.makespan, 140 * MS);
+        let utilisation = report.utilisation();
+        assert!(utilisation[1] > utilisation[0]);
+    }
+
+    #[test]
+    fn interleaving_shortens_the_makespan() {
+        let costs = CostModel::new(vec![10 * MS; 2], free_link(), 0);
+        let fill = costs.simulate(&FillDrain, 4).unwrap();
+        let interleaved = costs.simulate(&Interleaved { chunks: 2 }, 4).unwrap();
+        assert_eq!(fill.makespan, 50 * MS);
+        // 9 steps of one 5ms chunk.
+        assert_eq!(interleaved.makespan, 45 * MS);
+        assert!(interleaved.bubble_fraction() < fill.bubble_fraction());
+
+        let mut short = costs.clone();
+        short.links.pop();
+        assert!(matches!(
+            short.simulate(&Interleaved { chunks: 2 }, 4),
+            Err(SchedulerError::InvalidCostModel(_))
+        ));
+    }
+
+    #[test]
+    fn one_f_one_b_includes_backward_passes() {
+        let costs = CostModel::new(vec![10 * MS; 2], free_link(), 0);
+        let report = costs.simulate(&OneFOneB, 2).unwrap();
+        // Stage 0: F0 F1, stage 1: F0 B0(20ms) F1 B1, stage 0: B0 B1.
+        assert_eq!(report.makespan, 90 * MS);
+        assert!(report
+            .spans
+            .iter()
+            .any(|s| s.kind == SpanKind::SendGradient && s.stage == 1));
+    }
+
+    #[test]
+    fn gantt_draws_each_stage_and_link() {
+        let costs = CostModel::new(vec![10 * MS; 2], free_link(), 0);
+        let report = costs.simulate(&FillDrain, 2).unwrap();
+        let chart = report.gantt(30);
+        let lines: Vec<&str> = chart.lines().collect();
+        assert_eq!(lines[0], "stage 0  |00000000001111111111..........|");
+        assert!(lines[1].starts_with("link 0"));
+        assert_eq!(lines[2], "stage 1  |..........00000000001111111111|");
+        assert_eq!(lines[3], format!("{:10}0{:>29}", "", "30ms"));
+    }
+
+    #[test]
+    fn wrong_stage_count_is_rejected() {
+        let costs = CostModel::new(vec![10 * MS; 2], free_link(), 0);
+        let schedule = FillDrain.generate(3, 2).unwrap();
+        assert!(matches!(
+            schedule.simulate(&costs),
+            Err(SchedulerError::InvalidCostModel(_))
+        ));
+    }
+
+    #[test]
+    fn calibrates_from_and_compares_with_measured_timings() {
+        let timing = |forward_us| MicroBatchTiming {
+            recv_wait_us: 0,
+            forward_us,
+            send_us: 1_000,
+        };
+        let stage_timings = vec![
+            vec![timing(10_000), timing(10_000)],
+            vec![timing(20_000), timing(20_000)],
+        ];
+        let costs = CostModel::from_timings(&stage_timings, 0);
+        assert_eq!(costs.forward, vec![10 * MS, 20 * MS]);
+        assert_eq!(costs.links[0].transfer_time(1 << 20), MS);
+
+        let report = costs.simulate(&FillDrain, 2).unwrap();
+        // 10ms + 1ms link + 2 * 20ms.
+        assert_eq!(report.makespan, 51 * MS);
+        let measured = InferenceResult {
+            outputs: vec![],
+            request_id: 1,
+            stage_timings,
+            layer_samples: vec![],
+            total: 60 * MS,
+            retries: vec![],
+        };
+        let comparison = report.compare(&measured);
+        assert_eq!(comparison.stages[1].predicted_forward, 40 * MS);
+        assert_eq!(comparison.stages[1].measured_forward, 40 * MS);
+        assert!((comparison.makespan_error() + 0.15).abs() < 1e-9);
+    }
+}
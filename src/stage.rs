@@ -1,20 +1,68 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::Poll;
+use std::time::Duration;
+
 use bytes::Bytes;
 use confidential_ml_transport::{
     AttestationProvider, AttestationVerifier, Message, OwnedTensor, SecureChannel, SessionConfig,
 };
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
 use tracing::{debug, error, info, warn};
 use zeroize::Zeroize;
 
-use crate::error::PipelineError;
-use crate::executor::{ForwardOutput, RequestId, StageExecutor};
-use crate::manifest::{ActivationSpec, StageSpec};
-use crate::protocol::{OrchestratorMsg, StageMsg, DEFAULT_MAX_CONTROL_MESSAGE_BYTES};
-use crate::scheduler::{InferenceSchedule, PipeOp};
+use crate::error::{PipelineError, SchedulerError, StageError};
+use crate::executor::{ForwardOutput, RequestId, SessionId, StageExecutor};
+use crate::manifest::{check_contract, ActivationSpec, StageSpec, SymbolBindings};
+use crate::protocol::{
+    DataError, DataFrame, Envelope, ErrorCode, LayerSample, MicroBatchTiming, OrchestratorMsg,
+    StageMsg, VersionRange, DEFAULT_MAX_CONTROL_MESSAGE_BYTES, MIN_PROTOCOL_VERSION,
+    PROTOCOL_VERSION,
+};
+use crate::scheduler::{PipeOp, ScheduleKind, ScheduleStrategy};
+
+/// Stage-side state of a session opened by the orchestrator.
+enum SessionState {
+    /// The executor holds state for the session.
+    Open,
+    /// The session's state was released early (failed open, or a failed or
+    /// aborted request); its requests are rejected until it is closed.
+    Dropped(String),
+}
+
+/// Trailer ending a tensor group on a version 1 data channel.
+pub(crate) const END_SENTINEL: &[u8] = b"END";
 
-/// Sentinel bytes sent on data_out when a stage request fails.
+/// Sent in place of further tensor groups on a version 1 data channel when
+/// the request fails.
 pub(crate) const ERROR_SENTINEL: &[u8] = b"ERR";
 
+/// How tensor groups are delimited on a data channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DataFraming {
+    /// Version 1: the tensors, then an [`END_SENTINEL`] trailer. A failure
+    /// is a bare [`ERROR_SENTINEL`].
+    Sentinel,
+    /// Version 2: a [`DataFrame`] header announcing the request, the
+    /// micro-batch and the number of tensors that follow. A failure is a
+    /// [`DataFrame::Error`].
+    Header,
+}
+
+impl DataFraming {
+    /// Framing of data channels that speak protocol `version`.
+    pub(crate) fn for_version(version: u32) -> Self {
+        if version >= 2 {
+            DataFraming::Header
+        } else {
+            DataFraming::Sentinel
+        }
+    }
+}
+
 /// Configuration for a stage runtime.
 pub struct StageConfig {
     pub session_config: SessionConfig,
@@ -24,6 +72,13 @@ pub struct StageConfig {
     /// Messages exceeding this limit are rejected before deserialization.
     /// Default: 4 MiB.
     pub max_control_message_bytes: usize,
+    /// Micro-batches a stage may receive ahead of the executor, and hold
+    /// for sending behind it, so network transfers overlap `forward`
+    /// (default: 1, minimum 1).
+    pub prefetch_depth: usize,
+    /// Protocol versions the stage accepts from the orchestrator (default:
+    /// every version this build speaks).
+    pub protocol_versions: VersionRange,
 }
 
 impl Default for StageConfig {
@@ -32,6 +87,8 @@ impl Default for StageConfig {
             session_config: SessionConfig::default(),
             tcp_retry_policy: confidential_ml_transport::RetryPolicy::default(),
             max_control_message_bytes: DEFAULT_MAX_CONTROL_MESSAGE_BYTES,
+            prefetch_depth: 1,
+            protocol_versions: VersionRange::SUPPORTED,
         }
     }
 }
@@ -76,8 +133,16 @@ pub struct StageRuntime<E: StageExecutor> {
     executor: E,
     config: StageConfig,
     max_control_message_bytes: usize,
+    /// Protocol version agreed with the orchestrator in `Init`.
+    protocol_version: u32,
+    /// Protocol version of the data channels, announced in
+    /// `EstablishDataChannels`. At most `protocol_version`.
+    data_version: u32,
     stage_idx: usize,
     num_stages: usize,
+    schedule: ScheduleKind,
+    /// Set by [`Self::with_schedule`]; replaces `schedule`.
+    custom_schedule: Option<Box<dyn ScheduleStrategy>>,
     stage_spec: Option<StageSpec>,
     activation_spec: Option<ActivationSpec>,
 }
@@ -89,13 +154,28 @@ impl<E: StageExecutor> StageRuntime<E> {
             executor,
             config,
             max_control_message_bytes,
+            protocol_version: PROTOCOL_VERSION,
+            data_version: PROTOCOL_VERSION,
             stage_idx: 0,
             num_stages: 0,
+            schedule: ScheduleKind::FillDrain,
+            custom_schedule: None,
             stage_spec: None,
             activation_spec: None,
         }
     }
 
+    /// Run `schedule` instead of the [`ScheduleKind`] sent with `Init`.
+    ///
+    /// The generated schedule for this stage must still pass
+    /// [`InferenceSchedule::executable_stage`](crate::InferenceSchedule::executable_stage),
+    /// and the stage must receive and send micro-batches in the order its
+    /// neighbours send and receive them; otherwise requests fail.
+    pub fn with_schedule(mut self, schedule: impl ScheduleStrategy + 'static) -> Self {
+        self.custom_schedule = Some(Box::new(schedule));
+        self
+    }
+
     /// Run the stage, accepting connections and processing requests until shutdown.
     ///
     /// This is a convenience method that calls [`Self::run_control_phase`] followed by
@@ -133,6 +213,36 @@ impl<E: StageExecutor> StageRuntime<E> {
         .await
     }
 
+    /// [`Self::run`] for a stage in a graph manifest, with one data_in
+    /// transport per `StageSpec::upstream` stage and one data_out transport
+    /// per `StageSpec::downstream` stage, in the order listed. The entry
+    /// and exit stages take a single transport from or to the orchestrator.
+    pub async fn run_graph<CT, DI, DO>(
+        &mut self,
+        control_transport: CT,
+        data_in_transports: Vec<DI>,
+        data_out_transports: Vec<DO>,
+        provider: &dyn AttestationProvider,
+        verifier: &dyn AttestationVerifier,
+    ) -> crate::error::Result<()>
+    where
+        CT: AsyncRead + AsyncWrite + Unpin + Send,
+        DI: AsyncRead + AsyncWrite + Unpin + Send,
+        DO: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let result = self
+            .run_control_phase(control_transport, provider, verifier)
+            .await?;
+        self.run_data_phase_graph(
+            result.control,
+            data_in_transports,
+            data_out_transports,
+            provider,
+            verifier,
+        )
+        .await
+    }
+
     /// Phase 1: Accept the control channel, handle Init/Ready, and wait for
     /// EstablishDataChannels.
     ///
@@ -160,9 +270,11 @@ impl<E: StageExecutor> StageRuntime<E> {
         info!("stage: control channel established");
 
         // Wait for Init.
-        let (stage_spec, activation_spec, num_stages) = self.handle_init(&mut control).await?;
+        let (stage_spec, activation_spec, num_stages, schedule) =
+            self.handle_init(&mut control).await?;
         self.stage_idx = stage_spec.stage_idx;
         self.num_stages = num_stages;
+        self.schedule = schedule;
         self.stage_spec = Some(stage_spec.clone());
         self.activation_spec = Some(activation_spec);
 
@@ -221,13 +333,18 @@ impl<E: StageExecutor> StageRuntime<E> {
             .send(
                 StageMsg::Ready {
                     stage_idx: self.stage_idx,
+                    protocol_version: Some(self.protocol_version),
                 }
-                .to_bytes()?,
+                .to_bytes_versioned(self.protocol_version)?,
             )
             .await
             .map_err(PipelineError::Transport)?;
 
-        info!(stage = self.stage_idx, "stage: ready");
+        info!(
+            stage = self.stage_idx,
+            protocol_version = self.protocol_version,
+            "stage: ready"
+        );
 
         // Wait for EstablishDataChannels.
         let (has_upstream, has_downstream) =
@@ -246,7 +363,7 @@ impl<E: StageExecutor> StageRuntime<E> {
     /// channel is the one returned in [`ControlPhaseResult`].
     pub async fn run_data_phase<CT, DI, DO>(
         &self,
-        mut control: SecureChannel<CT>,
+        control: SecureChannel<CT>,
         data_in_transport: DI,
         data_out_transport: DO,
         provider: &dyn AttestationProvider,
@@ -257,6 +374,49 @@ impl<E: StageExecutor> StageRuntime<E> {
         DI: AsyncRead + AsyncWrite + Unpin + Send,
         DO: AsyncRead + AsyncWrite + Unpin + Send,
     {
+        self.run_data_phase_graph(
+            control,
+            vec![data_in_transport],
+            vec![data_out_transport],
+            provider,
+            verifier,
+        )
+        .await
+    }
+
+    /// [`Self::run_data_phase`] with one transport per data link, as for
+    /// [`Self::run_graph`].
+    ///
+    /// A stage with several upstreams receives one tensor group from each
+    /// per micro-batch and passes their concatenation to `forward`; its
+    /// output goes to every downstream. Handshakes and transfers on the
+    /// links run concurrently, so no ordering between stages can deadlock.
+    pub async fn run_data_phase_graph<CT, DI, DO>(
+        &self,
+        mut control: SecureChannel<CT>,
+        data_in_transports: Vec<DI>,
+        data_out_transports: Vec<DO>,
+        provider: &dyn AttestationProvider,
+        verifier: &dyn AttestationVerifier,
+    ) -> crate::error::Result<()>
+    where
+        CT: AsyncRead + AsyncWrite + Unpin + Send,
+        DI: AsyncRead + AsyncWrite + Unpin + Send,
+        DO: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let (inputs, outputs) = match self.stage_spec {
+            Some(ref spec) => (spec.upstream.len().max(1), spec.downstream.len().max(1)),
+            None => (1, 1),
+        };
+        if data_in_transports.len() != inputs || data_out_transports.len() != outputs {
+            return Err(PipelineError::Protocol(format!(
+                "stage {} needs {inputs} data_in and {outputs} data_out transports, got {} and {}",
+                self.stage_idx,
+                data_in_transports.len(),
+                data_out_transports.len()
+            )));
+        }
+
         // Build data channel config with this stage's measurements applied.
         let data_session_config = {
             let mut cfg = self.config.session_config.clone();
@@ -275,23 +435,31 @@ impl<E: StageExecutor> StageRuntime<E> {
         };
 
         // Accept data_in (responder — upstream initiates or orchestrator initiates).
-        let mut data_in = SecureChannel::accept_with_attestation(
-            data_in_transport,
-            provider,
-            verifier,
-            data_session_config.clone(),
-        )
+        let mut data_in = join_all(data_in_transports.into_iter().map(|transport| {
+            SecureChannel::accept_with_attestation(
+                transport,
+                provider,
+                verifier,
+                data_session_config.clone(),
+            )
+        }))
         .await
+        .into_iter()
+        .collect::<std::result::Result<Vec<_>, _>>()
         .map_err(PipelineError::Transport)?;
 
         // Initiate data_out (initiator — this stage connects to downstream acceptor).
-        let mut data_out = SecureChannel::connect_with_attestation(
-            data_out_transport,
-            provider,
-            verifier,
-            data_session_config,
-        )
+        let mut data_out = join_all(data_out_transports.into_iter().map(|transport| {
+            SecureChannel::connect_with_attestation(
+                transport,
+                provider,
+                verifier,
+                data_session_config.clone(),
+            )
+        }))
         .await
+        .into_iter()
+        .collect::<std::result::Result<Vec<_>, _>>()
         .map_err(PipelineError::Transport)?;
 
         // Send DataChannelsReady.
@@ -300,36 +468,60 @@ impl<E: StageExecutor> StageRuntime<E> {
                 StageMsg::DataChannelsReady {
                     stage_idx: self.stage_idx,
                 }
-                .to_bytes()?,
+                .to_bytes_versioned(self.protocol_version)?,
             )
             .await
             .map_err(PipelineError::Transport)?;
 
-        info!(stage = self.stage_idx, "stage: data channels ready");
+        info!(
+            stage = self.stage_idx,
+            data_version = self.data_version,
+            "stage: data channels ready"
+        );
 
         // Process requests until shutdown.
         self.process_loop(&mut control, &mut data_in, &mut data_out)
             .await
     }
 
+    /// Receive `Init` and agree on the protocol version used from then on.
     async fn handle_init<T: AsyncRead + AsyncWrite + Unpin + Send>(
-        &self,
+        &mut self,
         control: &mut SecureChannel<T>,
-    ) -> crate::error::Result<(StageSpec, ActivationSpec, usize)> {
-        let msg = recv_control(control, self.max_control_message_bytes).await?;
+    ) -> crate::error::Result<(StageSpec, ActivationSpec, usize, ScheduleKind)> {
+        let local = self.config.protocol_versions;
+        local.validate()?;
+        let Envelope { version, msg } =
+            recv_control_envelope(control, self.max_control_message_bytes).await?;
         match msg {
             OrchestratorMsg::Init {
                 stage_spec_json,
                 activation_spec_json,
                 num_stages,
+                schedule,
+                protocol_versions,
             } => {
+                self.protocol_version = match protocol_versions {
+                    Some(peer) => local
+                        .negotiate(&peer)
+                        .ok_or(PipelineError::NoCommonVersion { local, peer })?,
+                    // A version 1 orchestrator speaks only its envelope's version.
+                    None if local.contains(version) => version,
+                    None => {
+                        return Err(PipelineError::VersionMismatch {
+                            expected: local.max,
+                            actual: version,
+                        })
+                    }
+                };
+
                 let stage_spec: StageSpec = serde_json::from_str(&stage_spec_json)
                     .map_err(|e| PipelineError::Protocol(format!("invalid stage_spec: {e}")))?;
                 let activation_spec: ActivationSpec = serde_json::from_str(&activation_spec_json)
                     .map_err(|e| {
                     PipelineError::Protocol(format!("invalid activation_spec: {e}"))
                 })?;
-                Ok((stage_spec, activation_spec, num_stages))
+                Ok((stage_spec, activation_spec, num_stages, schedule))
             }
             other => Err(PipelineError::Protocol(format!(
                 "expected Init, got {other:?}"
@@ -337,20 +529,41 @@ impl<E: StageExecutor> StageRuntime<E> {
         }
     }
 
+    /// Wait for `EstablishDataChannels` and record the data channels'
+    /// protocol version.
     async fn wait_for_establish_data_channels<T: AsyncRead + AsyncWrite + Unpin + Send>(
-        &self,
+        &mut self,
         control: &mut SecureChannel<T>,
     ) -> crate::error::Result<(bool, bool)> {
         loop {
-            let msg = recv_control(control, self.max_control_message_bytes).await?;
+            let msg = recv_control(
+                control,
+                self.max_control_message_bytes,
+                self.protocol_version,
+            )
+            .await?;
             match msg {
                 OrchestratorMsg::EstablishDataChannels {
                     has_upstream,
                     has_downstream,
-                } => return Ok((has_upstream, has_downstream)),
+                    data_version,
+                } => {
+                    // A version 1 orchestrator announces nothing; its data
+                    // channels speak version 1 too.
+                    let data_version = data_version.unwrap_or(self.protocol_version);
+                    if !(MIN_PROTOCOL_VERSION..=self.protocol_version).contains(&data_version) {
+                        return Err(PipelineError::Protocol(format!(
+                            "data channels cannot use protocol version {data_version} \
+                             on a version {} control channel",
+                            self.protocol_version
+                        )));
+                    }
+                    self.data_version = data_version;
+                    return Ok((has_upstream, has_downstream));
+                }
                 OrchestratorMsg::Ping { seq } => {
                     control
-                        .send(StageMsg::Pong { seq }.to_bytes()?)
+                        .send(StageMsg::Pong { seq }.to_bytes_versioned(self.protocol_version)?)
                         .await
                         .map_err(PipelineError::Transport)?;
                     // Continue looping; the next message should be EstablishDataChannels.
@@ -367,22 +580,85 @@ impl<E: StageExecutor> StageRuntime<E> {
     async fn process_loop<CT, DI, DO>(
         &self,
         control: &mut SecureChannel<CT>,
-        data_in: &mut SecureChannel<DI>,
-        data_out: &mut SecureChannel<DO>,
+        data_in: &mut [SecureChannel<DI>],
+        data_out: &mut [SecureChannel<DO>],
     ) -> crate::error::Result<()>
     where
         CT: AsyncRead + AsyncWrite + Unpin + Send,
         DI: AsyncRead + AsyncWrite + Unpin + Send,
         DO: AsyncRead + AsyncWrite + Unpin + Send,
     {
+        // StartRequests that arrived while another request was running. The
+        // orchestrator may keep several requests in flight; they are served
+        // in arrival order, matching the order of frames on data_in.
+        let mut queued: VecDeque<OrchestratorMsg> = VecDeque::new();
+        let mut sessions: HashMap<SessionId, SessionState> = HashMap::new();
+        // Queued requests the orchestrator aborted before they started, with
+        // the abort reason. They are refused in turn so their input is consumed.
+        let mut aborted: HashMap<RequestId, String> = HashMap::new();
+        // Local deadlines of queued requests, measured from when their
+        // StartRequest arrived.
+        let mut deadlines: HashMap<RequestId, Instant> = HashMap::new();
+
         loop {
-            let msg = recv_control(control, self.max_control_message_bytes).await?;
+            let msg = match queued.pop_front() {
+                Some(msg) => msg,
+                None => {
+                    recv_control(
+                        control,
+                        self.max_control_message_bytes,
+                        self.protocol_version,
+                    )
+                    .await?
+                }
+            };
             match msg {
                 OrchestratorMsg::StartRequest {
                     request_id,
                     num_micro_batches,
                     seq_len,
+                    session_id,
+                    time_budget_ms,
+                    profile,
                 } => {
+                    let deadline = deadlines
+                        .remove(&request_id)
+                        .or_else(|| time_budget_ms.map(deadline_from_budget));
+
+                    let refusal = match aborted.remove(&request_id) {
+                        Some(reason) => Some((ErrorCode::Aborted, format!("aborted: {reason}"))),
+                        None if deadline.is_some_and(|d| Instant::now() >= d) => {
+                            Some((ErrorCode::DeadlineExceeded, "deadline exceeded".to_string()))
+                        }
+                        None => None,
+                    };
+                    if let Some((code, error)) = refusal {
+                        warn!(
+                            stage = self.stage_idx,
+                            request_id, error, "refusing request"
+                        );
+                        if let Some(session_id) = session_id {
+                            // Other stages may already have run it against the session.
+                            self.drop_session(
+                                &mut sessions,
+                                session_id,
+                                format!("request {request_id} refused: {error}"),
+                            )
+                            .await;
+                        }
+                        self.reject_request(
+                            control,
+                            data_in,
+                            data_out,
+                            request_id,
+                            num_micro_batches,
+                            code,
+                            error,
+                        )
+                        .await?;
+                        continue;
+                    }
+
                     if let Some(ref spec) = self.activation_spec {
                         if seq_len > spec.max_seq_len {
                             error!(
@@ -391,20 +667,46 @@ impl<E: StageExecutor> StageRuntime<E> {
                                 max = spec.max_seq_len,
                                 "seq_len exceeds max_seq_len"
                             );
-                            let _ = data_out.send(Bytes::from_static(ERROR_SENTINEL)).await;
-                            control
-                                .send(
-                                    StageMsg::RequestError {
-                                        request_id,
-                                        error: format!(
-                                            "seq_len {} exceeds max_seq_len {}",
-                                            seq_len, spec.max_seq_len
-                                        ),
-                                    }
-                                    .to_bytes()?,
-                                )
-                                .await
-                                .map_err(PipelineError::Transport)?;
+                            self.reject_request(
+                                control,
+                                data_in,
+                                data_out,
+                                request_id,
+                                num_micro_batches,
+                                ErrorCode::InvalidInput,
+                                format!(
+                                    "seq_len {} exceeds max_seq_len {}",
+                                    seq_len, spec.max_seq_len
+                                ),
+                            )
+                            .await?;
+                            continue;
+                        }
+                    }
+
+                    if let Some(session_id) = session_id {
+                        let unavailable = match sessions.get(&session_id) {
+                            Some(SessionState::Open) => None,
+                            Some(SessionState::Dropped(reason)) => {
+                                Some(format!("session {session_id} unavailable: {reason}"))
+                            }
+                            None => Some(format!("unknown session {session_id}")),
+                        };
+                        if let Some(reason) = unavailable {
+                            warn!(
+                                stage = self.stage_idx,
+                                request_id, reason, "rejecting request"
+                            );
+                            self.reject_request(
+                                control,
+                                data_in,
+                                data_out,
+                                request_id,
+                                num_micro_batches,
+                                ErrorCode::Session,
+                                reason,
+                            )
+                            .await?;
                             continue;
                         }
                     }
@@ -415,12 +717,27 @@ impl<E: StageExecutor> StageRuntime<E> {
                     );
 
                     // Run process_request with select! so AbortRequest/Ping can
-                    // be handled while the request is in progress.
+                    // be handled while the request is in progress. An abort is
+                    // cooperative: process_request stops at the next
+                    // micro-batch boundary, so no frame is left half-sent.
                     // Scoped so process_fut (which borrows data_in/data_out)
                     // is dropped before the error handler needs data_out.
+                    let abort = AtomicBool::new(false);
+                    let mut abort_reason: Option<(ErrorCode, String)> = None;
+                    let mut received = vec![0u32; data_in.len()];
+                    let mut sent = 0u32;
                     let result = {
-                        let process_fut =
-                            self.process_request(request_id, num_micro_batches, data_in, data_out);
+                        let process_fut = self.process_request(
+                            request_id,
+                            session_id,
+                            num_micro_batches,
+                            profile,
+                            &abort,
+                            &mut received,
+                            &mut sent,
+                            data_in,
+                            data_out,
+                        );
                         tokio::pin!(process_fut);
 
                         let mut early_shutdown = false;
@@ -429,22 +746,54 @@ impl<E: StageExecutor> StageRuntime<E> {
                                 res = &mut process_fut => {
                                     break res;
                                 }
-                                ctrl_msg = recv_control(control, self.max_control_message_bytes) => {
+                                () = sleep_until_deadline(deadline), if abort_reason.is_none() => {
+                                    warn!(
+                                        stage = self.stage_idx,
+                                        request_id,
+                                        "deadline exceeded — stopping after the current micro-batch"
+                                    );
+                                    abort_reason = Some((
+                                        ErrorCode::DeadlineExceeded,
+                                        "deadline exceeded".to_string(),
+                                    ));
+                                    abort.store(true, Ordering::Relaxed);
+                                }
+                                ctrl_msg = recv_control(control, self.max_control_message_bytes, self.protocol_version) => {
                                     match ctrl_msg? {
+                                        // Served in arrival order once this request ends.
+                                        queue @ (OrchestratorMsg::StartRequest { .. }
+                                        | OrchestratorMsg::OpenSession { .. }
+                                        | OrchestratorMsg::CloseSession { .. }) => {
+                                            if let OrchestratorMsg::StartRequest {
+                                                request_id: rid,
+                                                time_budget_ms: Some(budget),
+                                                ..
+                                            } = queue
+                                            {
+                                                deadlines.insert(rid, deadline_from_budget(budget));
+                                            }
+                                            queued.push_back(queue);
+                                        }
+                                        OrchestratorMsg::AbortRequest { request_id: rid, reason }
+                                            if rid != request_id =>
+                                        {
+                                            self.abort_queued(&queued, &mut aborted, rid, reason);
+                                        }
                                         OrchestratorMsg::AbortRequest { request_id: rid, reason } => {
                                             warn!(
                                                 stage = self.stage_idx,
                                                 request_id = rid, reason,
-                                                "request aborted by orchestrator — cancelling"
+                                                "request aborted by orchestrator — stopping after the current micro-batch"
                                             );
-                                            break Err(PipelineError::RequestFailed {
-                                                request_id,
-                                                reason: format!("aborted: {reason}"),
-                                            });
+                                            abort_reason.get_or_insert((
+                                                ErrorCode::Aborted,
+                                                format!("aborted: {reason}"),
+                                            ));
+                                            abort.store(true, Ordering::Relaxed);
                                         }
                                         OrchestratorMsg::Ping { seq } => {
                                             control
-                                                .send(StageMsg::Pong { seq }.to_bytes()?)
+                                                .send(StageMsg::Pong { seq }.to_bytes_versioned(self.protocol_version)?)
                                                 .await
                                                 .map_err(PipelineError::Transport)?;
                                         }
@@ -466,12 +815,13 @@ impl<E: StageExecutor> StageRuntime<E> {
                         if early_shutdown {
                             // process_fut is dropped when this block scope ends (cancelled).
                             info!(stage = self.stage_idx, "shutdown during request");
+                            self.close_all_sessions(&mut sessions).await;
                             control
                                 .send(
                                     StageMsg::ShuttingDown {
                                         stage_idx: self.stage_idx,
                                     }
-                                    .to_bytes()?,
+                                    .to_bytes_versioned(self.protocol_version)?,
                                 )
                                 .await
                                 .map_err(PipelineError::Transport)?;
@@ -482,28 +832,115 @@ impl<E: StageExecutor> StageRuntime<E> {
                     }; // process_fut dropped here — data_in/data_out borrows released.
 
                     match result {
-                        Ok(()) => {
+                        Ok((timings, layers)) => {
                             control
-                                .send(StageMsg::RequestDone { request_id }.to_bytes()?)
+                                .send(
+                                    StageMsg::RequestDone {
+                                        request_id,
+                                        timings,
+                                        layers,
+                                    }
+                                    .to_bytes_versioned(self.protocol_version)?,
+                                )
                                 .await
                                 .map_err(PipelineError::Transport)?;
                         }
                         Err(e) => {
+                            // A failure that started upstream is passed on as
+                            // is, so the orchestrator learns where it started.
+                            let frame = match &e {
+                                PipelineError::UpstreamFailed(frame) => *frame,
+                                _ => DataError {
+                                    stage_idx: self.stage_idx,
+                                    request_id,
+                                    micro_batch: sent,
+                                    code: abort_reason
+                                        .as_ref()
+                                        .map_or_else(|| error_code(&e), |(code, _)| *code),
+                                },
+                            };
+                            let e = match abort_reason {
+                                Some((code, reason)) if frame.stage_idx == self.stage_idx => {
+                                    PipelineError::RequestFailed {
+                                        request_id,
+                                        code,
+                                        reason,
+                                        details: None,
+                                    }
+                                }
+                                _ => e,
+                            };
                             error!(stage = self.stage_idx, request_id, error = %e, "request failed");
-                            if let Err(e) = data_out.send(Bytes::from_static(ERROR_SENTINEL)).await
-                            {
-                                warn!(stage = self.stage_idx, error = %e, "failed to send error sentinel on data_out");
+                            if let Some(session_id) = session_id {
+                                // The session's state may be half-updated.
+                                self.drop_session(
+                                    &mut sessions,
+                                    session_id,
+                                    format!("request {request_id} failed: {e}"),
+                                )
+                                .await;
+                            }
+                            if let Err(e) = self.send_data_errors(data_out, &frame).await {
+                                warn!(stage = self.stage_idx, error = %e, "failed to send error frame on data_out");
                             }
                             control
                                 .send(
                                     StageMsg::RequestError {
                                         request_id,
                                         error: e.to_string(),
+                                        code: frame.code,
+                                        details: error_details(&e),
                                     }
-                                    .to_bytes()?,
+                                    .to_bytes_versioned(self.protocol_version)?,
                                 )
                                 .await
                                 .map_err(PipelineError::Transport)?;
+                            // Consume the rest of this request's input so it is
+                            // not mistaken for the next request's. Upstreams
+                            // that reported an error have stopped sending.
+                            if let Err(e) = self
+                                .discard_inputs(data_in, &received, request_id, num_micro_batches)
+                                .await
+                            {
+                                warn!(stage = self.stage_idx, request_id, error = %e, "failed to discard remaining input");
+                            }
+                        }
+                    }
+                }
+                OrchestratorMsg::OpenSession { session_id } => {
+                    if sessions.contains_key(&session_id) {
+                        warn!(
+                            stage = self.stage_idx,
+                            session_id, "session already open, ignoring"
+                        );
+                        continue;
+                    }
+                    let state = match self.executor.open_session(session_id).await {
+                        Ok(()) => {
+                            debug!(stage = self.stage_idx, session_id, "session opened");
+                            SessionState::Open
+                        }
+                        Err(e) => {
+                            warn!(stage = self.stage_idx, session_id, error = %e, "session open failed");
+                            SessionState::Dropped(format!("open failed: {e}"))
+                        }
+                    };
+                    sessions.insert(session_id, state);
+                }
+                OrchestratorMsg::CloseSession { session_id } => {
+                    match sessions.remove(&session_id) {
+                        Some(SessionState::Open) => {
+                            self.executor.close_session(session_id).await;
+                            debug!(stage = self.stage_idx, session_id, "session closed");
+                        }
+                        Some(SessionState::Dropped(_)) => {
+                            debug!(stage = self.stage_idx, session_id, "dropped session closed");
+                        }
+                        None => {
+                            warn!(
+                                stage = self.stage_idx,
+                                session_id, "close received for unknown session"
+                            );
                         }
                     }
                 }
@@ -516,18 +953,19 @@ impl<E: StageExecutor> StageRuntime<E> {
                 }
                 OrchestratorMsg::Ping { seq } => {
                     control
-                        .send(StageMsg::Pong { seq }.to_bytes()?)
+                        .send(StageMsg::Pong { seq }.to_bytes_versioned(self.protocol_version)?)
                         .await
                         .map_err(PipelineError::Transport)?;
                 }
                 OrchestratorMsg::Shutdown => {
                     info!(stage = self.stage_idx, "shutting down");
+                    self.close_all_sessions(&mut sessions).await;
                     control
                         .send(
                             StageMsg::ShuttingDown {
                                 stage_idx: self.stage_idx,
                             }
-                            .to_bytes()?,
+                            .to_bytes_versioned(self.protocol_version)?,
                         )
                         .await
                         .map_err(PipelineError::Transport)?;
@@ -542,76 +980,533 @@ impl<E: StageExecutor> StageRuntime<E> {
         }
     }
 
-    async fn process_request<DI, DO>(
+    /// Mark a queued (not yet started) request as aborted.
+    ///
+    /// It is refused with RequestError when its turn comes, so its input is
+    /// still consumed in order. Aborts for unknown request IDs are ignored.
+    fn abort_queued(
+        &self,
+        queued: &VecDeque<OrchestratorMsg>,
+        aborted: &mut HashMap<RequestId, String>,
+        request_id: RequestId,
+        reason: String,
+    ) {
+        let is_queued = queued.iter().any(|msg| {
+            matches!(msg, OrchestratorMsg::StartRequest { request_id: rid, .. } if *rid == request_id)
+        });
+        if !is_queued {
+            warn!(
+                stage = self.stage_idx,
+                request_id, reason, "abort received for unknown request"
+            );
+            return;
+        }
+
+        warn!(
+            stage = self.stage_idx,
+            request_id, reason, "queued request aborted by orchestrator"
+        );
+        aborted.insert(request_id, reason);
+    }
+
+    /// Refuse a request without running it.
+    ///
+    /// Sends an error frame with `code` downstream and RequestError to the
+    /// orchestrator, then consumes the request's input from data_in so it is
+    /// not mistaken for the next request's. Input stops early if upstream
+    /// refused or failed the request too.
+    async fn reject_request<CT, DI, DO>(
         &self,
+        control: &mut SecureChannel<CT>,
+        data_in: &mut [SecureChannel<DI>],
+        data_out: &mut [SecureChannel<DO>],
         request_id: RequestId,
         num_micro_batches: u32,
-        data_in: &mut SecureChannel<DI>,
-        data_out: &mut SecureChannel<DO>,
+        code: ErrorCode,
+        error: String,
     ) -> crate::error::Result<()>
     where
+        CT: AsyncRead + AsyncWrite + Unpin + Send,
         DI: AsyncRead + AsyncWrite + Unpin + Send,
         DO: AsyncRead + AsyncWrite + Unpin + Send,
     {
-        let schedule = InferenceSchedule::generate(self.num_stages, num_micro_batches)?;
-        let stage_schedule = schedule.stage(self.stage_idx).ok_or_else(|| {
-            PipelineError::Protocol(format!("no schedule for stage {}", self.stage_idx))
-        })?;
+        let frame = DataError {
+            stage_idx: self.stage_idx,
+            request_id,
+            micro_batch: 0,
+            code,
+        };
+        let _ = self.send_data_errors(data_out, &frame).await;
+        control
+            .send(
+                StageMsg::RequestError {
+                    request_id,
+                    error,
+                    code,
+                    details: None,
+                }
+                .to_bytes_versioned(self.protocol_version)?,
+            )
+            .await
+            .map_err(PipelineError::Transport)?;
+
+        let received = vec![0; data_in.len()];
+        self.discard_inputs(data_in, &received, request_id, num_micro_batches)
+            .await
+    }
 
-        for (step, ops) in stage_schedule.ops.iter().enumerate() {
-            debug!(
+    /// Release an open session's state early, keeping a tombstone so its
+    /// later requests are rejected until the orchestrator closes it.
+    async fn drop_session(
+        &self,
+        sessions: &mut HashMap<SessionId, SessionState>,
+        session_id: SessionId,
+        reason: String,
+    ) {
+        if let Some(state @ SessionState::Open) = sessions.get_mut(&session_id) {
+            warn!(
                 stage = self.stage_idx,
-                step,
-                ops = ?ops,
-                "executing step"
+                session_id, reason, "dropping session state"
             );
+            self.executor.close_session(session_id).await;
+            *state = SessionState::Dropped(reason);
+        }
+    }
+
+    /// Release every open session before the stage exits.
+    async fn close_all_sessions(&self, sessions: &mut HashMap<SessionId, SessionState>) {
+        for (session_id, state) in sessions.drain() {
+            if let SessionState::Open = state {
+                debug!(
+                    stage = self.stage_idx,
+                    session_id, "closing session at shutdown"
+                );
+                self.executor.close_session(session_id).await;
+            }
+        }
+    }
 
-            for op in ops {
-                match op {
-                    PipeOp::RecvActivation { .. } => {}
-                    PipeOp::Forward { micro_batch } => {
-                        let inputs = recv_tensors(data_in).await?;
-
-                        let mut output: ForwardOutput = self
-                            .executor
-                            .forward(request_id, *micro_batch, inputs)
-                            .await
-                            .map_err(PipelineError::Stage)?;
-
-                        send_tensors(data_out, &output.tensors).await?;
-
-                        // SEC-705: Explicitly clear activation tensor metadata after
-                        // forwarding. OwnedTensor.data is bytes::Bytes (Arc-backed),
-                        // so we cannot reliably zeroize the underlying allocation —
-                        // other Bytes handles may share the same buffer. Dropping the
-                        // Bytes value releases our reference count; the allocator
-                        // will reclaim the memory once all references are gone.
-                        for tensor in &mut output.tensors {
-                            tensor.name.zeroize();
-                            tensor.shape.zeroize();
-                            // tensor.data: Bytes is Arc-backed — cannot zeroize
-                            // the shared allocation. Drop releases our claim.
+    /// Run one request's micro-batches by executing this stage's schedule.
+    ///
+    /// The schedule comes from the `ScheduleKind` sent with `Init`, or
+    /// [`Self::with_schedule`], and is first checked and normalized (see
+    /// [`InferenceSchedule::executable_stage`](crate::InferenceSchedule::executable_stage)),
+    /// then walked step by step: `Forward` runs the executor,
+    /// `SendActivation` hands that micro-batch's output to the sender, and
+    /// `Idle` does nothing. Steps are logical, not timed.
+    ///
+    /// Receiving and sending run concurrently with the executor.
+    /// [`StageSchedule::prefetched`](crate::StageSchedule::prefetched) first
+    /// moves each `RecvActivation` up to `StageConfig::prefetch_depth` steps
+    /// earlier, and the receive is issued at its step in that plan, never
+    /// before. At most `prefetch_depth` outputs wait to be sent, in the
+    /// order the plan sends them, so network transfers overlap `forward`
+    /// with bounded buffering. Each side stops at a frame boundary when
+    /// another fails, so neither channel is left with a half-written tensor
+    /// group.
+    ///
+    /// Checks `abort` before each micro-batch and counts fully received
+    /// input groups per data_in channel in `received`, so the caller can
+    /// consume the rest of the input after an abort or failure. Counts
+    /// outputs fully sent in `sent`, for the caller's error frame.
+    #[allow(clippy::too_many_arguments)]
+    async fn process_request<DI, DO>(
+        &self,
+        request_id: RequestId,
+        session_id: Option<SessionId>,
+        num_micro_batches: u32,
+        profile: bool,
+        abort: &AtomicBool,
+        received: &mut [u32],
+        sent: &mut u32,
+        data_in: &mut [SecureChannel<DI>],
+        data_out: &mut [SecureChannel<DO>],
+    ) -> crate::error::Result<(Vec<MicroBatchTiming>, Vec<LayerSample>)>
+    where
+        DI: AsyncRead + AsyncWrite + Unpin + Send,
+        DO: AsyncRead + AsyncWrite + Unpin + Send,
+    {
+        let depth = self.config.prefetch_depth.max(1);
+        let strategy: &dyn ScheduleStrategy = match &self.custom_schedule {
+            Some(schedule) => schedule.as_ref(),
+            None => &self.schedule,
+        };
+        let plan = strategy
+            .generate(self.num_stages, num_micro_batches)?
+            .executable_stage(self.stage_idx)?
+            .prefetched(depth);
+
+        let aborted = || PipelineError::RequestFailed {
+            request_id,
+            code: ErrorCode::Aborted,
+            reason: "aborted".into(),
+            details: None,
+        };
+        let (pass_through, contract_in, contract_out) = match self.stage_spec.as_ref() {
+            Some(spec) => (&spec.pass_through[..], &spec.inputs[..], &spec.outputs[..]),
+            None => (&[][..], &[][..], &[][..]),
+        };
+        // The receive queues stay small: the plan issues receives at most
+        // `depth` steps ahead of their forward.
+        let (receive_tx, mut receive_rx) = mpsc::unbounded_channel::<u32>();
+        let (input_tx, mut input_rx) =
+            mpsc::unbounded_channel::<(u32, Vec<OwnedTensor>, Duration)>();
+        let (output_tx, mut output_rx) =
+            mpsc::channel::<(u32, (ForwardOutput, Duration, Duration, Vec<LayerSample>))>(depth);
+
+        // Runs the issued receives in order. Stops early, without error,
+        // once the executor side has stopped.
+        let receive = async move {
+            while let Some(micro_batch) = receive_rx.recv().await {
+                if abort.load(Ordering::Relaxed) {
+                    return Err(aborted());
+                }
+                let recv_start = Instant::now();
+                let inputs = self
+                    .recv_inputs(
+                        data_in,
+                        received,
+                        request_id,
+                        micro_batch,
+                        num_micro_batches,
+                    )
+                    .await?;
+                if input_tx
+                    .send((micro_batch, inputs, recv_start.elapsed()))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+            Ok(())
+        };
+
+        // Walks the schedule. Stops early, without error, once either
+        // neighbour has stopped; that neighbour reports why.
+        let execute = async move {
+            let mut inputs: HashMap<u32, (Vec<OwnedTensor>, Duration)> = HashMap::new();
+            let mut outputs: HashMap<u32, (ForwardOutput, Duration, Duration, Vec<LayerSample>)> =
+                HashMap::new();
+            for (step, ops) in plan.ops.iter().enumerate() {
+                debug!(stage = self.stage_idx, step, ops = ?ops, "executing step");
+
+                for op in ops {
+                    match *op {
+                        PipeOp::RecvActivation { micro_batch } => {
+                            if receive_tx.send(micro_batch).is_err() {
+                                return Ok(());
+                            }
+                        }
+                        PipeOp::Idle => {}
+                        // Rejected by `executable_stage`.
+                        PipeOp::Backward { .. } => {
+                            return Err(
+                                SchedulerError::Unsupported("backward passes".into()).into()
+                            );
+                        }
+                        PipeOp::Forward { micro_batch } => {
+                            if abort.load(Ordering::Relaxed) {
+                                return Err(aborted());
+                            }
+                            let (input, recv_wait) = loop {
+                                if let Some(input) = inputs.remove(&micro_batch) {
+                                    break input;
+                                }
+                                match input_rx.recv().await {
+                                    Some((mb, tensors, wait)) => {
+                                        inputs.insert(mb, (tensors, wait));
+                                    }
+                                    None => return Ok(()),
+                                }
+                            };
+
+                            let (input, carried) =
+                                split_pass_through(pass_through, micro_batch, input)?;
+                            let mut bindings = SymbolBindings::new();
+                            check_contract(contract_in, &input, &mut bindings).map_err(
+                                |reason| StageError::InputContract {
+                                    micro_batch,
+                                    reason,
+                                },
+                            )?;
+
+                            let forward_start = Instant::now();
+                            let forwarded = match session_id {
+                                Some(session_id) => self
+                                    .executor
+                                    .forward_in_session(session_id, request_id, micro_batch, input)
+                                    .await
+                                    .map(|output| (output, Vec::new())),
+                                None if profile => {
+                                    self.executor
+                                        .forward_profiled(request_id, micro_batch, input)
+                                        .await
+                                }
+                                None => self
+                                    .executor
+                                    .forward(request_id, micro_batch, input)
+                                    .await
+                                    .map(|output| (output, Vec::new())),
+                            };
+                            let (output, layers) = forwarded.map_err(PipelineError::Stage)?;
+                            let forward = forward_start.elapsed();
+                            check_contract(contract_out, &output.tensors, &mut bindings).map_err(
+                                |reason| StageError::OutputContract {
+                                    micro_batch,
+                                    reason,
+                                },
+                            )?;
+                            let output = append_pass_through(output, carried, micro_batch)?;
+                            outputs.insert(micro_batch, (output, recv_wait, forward, layers));
+                        }
+                        PipeOp::SendActivation { micro_batch } => {
+                            let output = outputs.remove(&micro_batch).ok_or_else(|| {
+                                PipelineError::Protocol(format!(
+                                    "stage {} step {step}: no output to send for micro-batch {micro_batch}",
+                                    self.stage_idx
+                                ))
+                            })?;
+                            if output_tx.send((micro_batch, output)).await.is_err() {
+                                return Ok(());
+                            }
                         }
-                        drop(output);
                     }
-                    PipeOp::SendActivation { .. } => {}
-                    PipeOp::Idle => {}
                 }
             }
+            Ok(())
+        };
+
+        // Sends outputs in the order the schedule sends them.
+        let send = async move {
+            let mut timings = Vec::with_capacity(num_micro_batches as usize);
+            let mut samples = Vec::new();
+            while let Some((micro_batch, (mut output, recv_wait, forward, layers))) =
+                output_rx.recv().await
+            {
+                let send_start = Instant::now();
+                self.send_outputs(data_out, request_id, micro_batch, &output.tensors)
+                    .await?;
+                *sent += 1;
+                timings.push(MicroBatchTiming {
+                    recv_wait_us: micros(recv_wait),
+                    forward_us: micros(forward),
+                    send_us: micros(send_start.elapsed()),
+                });
+                samples.extend(layers);
+
+                // SEC-705: Explicitly clear activation tensor metadata after
+                // forwarding. OwnedTensor.data is bytes::Bytes (Arc-backed),
+                // so we cannot reliably zeroize the underlying allocation —
+                // other Bytes handles may share the same buffer. Dropping the
+                // Bytes value releases our reference count; the allocator
+                // will reclaim the memory once all references are gone.
+                for tensor in &mut output.tensors {
+                    tensor.name.zeroize();
+                    tensor.shape.zeroize();
+                    // tensor.data: Bytes is Arc-backed — cannot zeroize
+                    // the shared allocation. Drop releases our claim.
+                }
+                drop(output);
+            }
+            Ok((timings, samples))
+        };
+
+        let (received_all, executed_all, timings) = tokio::join!(receive, execute, send);
+        // An upstream failure must win: its error frame has already been
+        // consumed, so the caller must not wait for more input.
+        received_all?;
+        executed_all?;
+        timings
+    }
+
+    /// Framing of this stage's data channels.
+    fn data_framing(&self) -> DataFraming {
+        DataFraming::for_version(self.data_version)
+    }
+
+    /// Stages feeding each data_in channel, in channel order. The entry
+    /// stage's single channel comes from the orchestrator.
+    fn upstream_stages(&self) -> Vec<usize> {
+        match self.stage_spec {
+            Some(ref spec) if !spec.upstream.is_empty() => spec.upstream.clone(),
+            _ => vec![self.stage_idx.saturating_sub(1)],
+        }
+    }
+
+    /// Receive micro-batch `micro_batch` of `request_id` from every data_in
+    /// channel concurrently, concatenated in channel order.
+    ///
+    /// Counts each channel's groups in `received`. A channel whose upstream
+    /// reported an error counts as complete, since that upstream stops sending.
+    /// A group for a different micro-batch of the request is a protocol error.
+    async fn recv_inputs<T: AsyncRead + AsyncWrite + Unpin + Send>(
+        &self,
+        channels: &mut [SecureChannel<T>],
+        received: &mut [u32],
+        request_id: RequestId,
+        micro_batch: u32,
+        num_micro_batches: u32,
+    ) -> crate::error::Result<Vec<OwnedTensor>> {
+        let framing = self.data_framing();
+        let upstreams = self.upstream_stages();
+        let groups = join_all(
+            channels
+                .iter_mut()
+                .zip(received.iter_mut())
+                .zip(upstreams)
+                .map(|((channel, received), upstream)| async move {
+                    let group =
+                        recv_group(channel, framing, upstream, request_id, micro_batch).await;
+                    match group {
+                        Ok(_) => *received += 1,
+                        Err(PipelineError::UpstreamFailed(_)) => *received = num_micro_batches,
+                        Err(_) => {}
+                    }
+                    group
+                }),
+        )
+        .await;
+        let mut tensors = Vec::new();
+        for group in groups {
+            tensors.extend(group?);
         }
+        Ok(tensors)
+    }
+
+    /// Consume the rest of `request_id`'s input on every data_in channel,
+    /// given the groups already `received` on each.
+    async fn discard_inputs<T: AsyncRead + AsyncWrite + Unpin + Send>(
+        &self,
+        channels: &mut [SecureChannel<T>],
+        received: &[u32],
+        request_id: RequestId,
+        num_micro_batches: u32,
+    ) -> crate::error::Result<()> {
+        let framing = self.data_framing();
+        join_all(
+            channels
+                .iter_mut()
+                .zip(received)
+                .zip(self.upstream_stages())
+                .map(|((channel, &received), upstream)| {
+                    discard_input(
+                        channel,
+                        framing,
+                        upstream,
+                        request_id,
+                        received,
+                        num_micro_batches,
+                    )
+                }),
+        )
+        .await
+        .into_iter()
+        .collect()
+    }
 
-        Ok(())
+    /// Send micro-batch `micro_batch` of `request_id` to every data_out
+    /// channel concurrently.
+    async fn send_outputs<T: AsyncRead + AsyncWrite + Unpin + Send>(
+        &self,
+        channels: &mut [SecureChannel<T>],
+        request_id: RequestId,
+        micro_batch: u32,
+        tensors: &[OwnedTensor],
+    ) -> crate::error::Result<()> {
+        let framing = self.data_framing();
+        join_all(
+            channels
+                .iter_mut()
+                .map(|channel| send_tensors(channel, framing, request_id, micro_batch, tensors)),
+        )
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    /// Report a failed request on every data_out channel: `frame` with
+    /// header framing, a bare `ERR` at version 1.
+    async fn send_data_errors<T: AsyncRead + AsyncWrite + Unpin + Send>(
+        &self,
+        channels: &mut [SecureChannel<T>],
+        frame: &DataError,
+    ) -> crate::error::Result<()> {
+        let frame = match self.data_framing() {
+            DataFraming::Header => DataFrame::Error(*frame).to_bytes()?,
+            DataFraming::Sentinel => Bytes::from_static(ERROR_SENTINEL),
+        };
+        join_all(
+            channels
+                .iter_mut()
+                .map(|channel| channel.send(frame.clone())),
+        )
+        .await
+        .into_iter()
+        .collect::<std::result::Result<(), _>>()
+        .map_err(PipelineError::Transport)
     }
 }
 
+/// Take the tensors named in `pass_through` out of `inputs`, keeping their
+/// order. Every name must be present at least once.
+fn split_pass_through(
+    pass_through: &[String],
+    micro_batch: u32,
+    inputs: Vec<OwnedTensor>,
+) -> std::result::Result<(Vec<OwnedTensor>, Vec<OwnedTensor>), StageError> {
+    if pass_through.is_empty() {
+        return Ok((inputs, Vec::new()));
+    }
+    let (carried, inputs): (Vec<_>, Vec<_>) = inputs
+        .into_iter()
+        .partition(|t| pass_through.contains(&t.name));
+    if let Some(name) = pass_through
+        .iter()
+        .find(|name| !carried.iter().any(|t| &t.name == *name))
+    {
+        return Err(StageError::MissingPassThrough {
+            micro_batch,
+            name: name.clone(),
+        });
+    }
+    Ok((inputs, carried))
+}
+
+/// Append forwarded pass-through tensors after the executor's outputs. The
+/// executor must not produce a tensor of the same name.
+fn append_pass_through(
+    mut output: ForwardOutput,
+    carried: Vec<OwnedTensor>,
+    micro_batch: u32,
+) -> std::result::Result<ForwardOutput, StageError> {
+    if let Some(t) = output
+        .tensors
+        .iter()
+        .find(|t| carried.iter().any(|c| c.name == t.name))
+    {
+        return Err(StageError::PassThroughConflict {
+            micro_batch,
+            name: t.name.clone(),
+        });
+    }
+    output.tensors.extend(carried);
+    Ok(output)
+}
+
+/// Whole microseconds in `d`, saturating.
+fn micros(d: Duration) -> u64 {
+    u64::try_from(d.as_micros()).unwrap_or(u64::MAX)
+}
+
 /// Receive a control message from a SecureChannel with size and version checks.
 async fn recv_control<T: AsyncRead + AsyncWrite + Unpin + Send>(
     channel: &mut SecureChannel<T>,
     max_bytes: usize,
+    version: u32,
 ) -> crate::error::Result<OrchestratorMsg> {
     let msg = channel.recv().await.map_err(PipelineError::Transport)?;
     match msg {
-        Message::Data(data) => OrchestratorMsg::from_bytes_checked(&data, max_bytes),
+        Message::Data(data) => OrchestratorMsg::from_bytes_versioned(&data, max_bytes, version),
         Message::Shutdown => Err(PipelineError::Shutdown),
         other => Err(PipelineError::Protocol(format!(
             "expected Data on control channel, got {other:?}"
@@ -619,26 +1514,134 @@ async fn recv_control<T: AsyncRead + AsyncWrite + Unpin + Send>(
     }
 }
 
-/// Receive tensors from a data channel until END sentinel.
-async fn recv_tensors<T: AsyncRead + AsyncWrite + Unpin + Send>(
+/// Receive a control message with a size check, leaving the version to the
+/// caller.
+async fn recv_control_envelope<T: AsyncRead + AsyncWrite + Unpin + Send>(
+    channel: &mut SecureChannel<T>,
+    max_bytes: usize,
+) -> crate::error::Result<Envelope<OrchestratorMsg>> {
+    let msg = channel.recv().await.map_err(PipelineError::Transport)?;
+    match msg {
+        Message::Data(data) => OrchestratorMsg::envelope_from_bytes(&data, max_bytes),
+        Message::Shutdown => Err(PipelineError::Shutdown),
+        other => Err(PipelineError::Protocol(format!(
+            "expected Data on control channel, got {other:?}"
+        ))),
+    }
+}
+
+/// Receive micro-batch `micro_batch` of `request_id` from a data channel.
+///
+/// With header framing, groups and errors tagged with another request are
+/// left over from a request this stage already gave up on, and are
+/// skipped; a group for a different micro-batch of `request_id` is a
+/// protocol error. Version 1 groups carry no tags, so the next group is
+/// taken as the one expected. Returns `PipelineError::UpstreamFailed` if an
+/// upstream stage sent an error for `request_id` instead; a version 1 `ERR`
+/// is reported as an internal error of `upstream`, the stage feeding
+/// `channel`.
+async fn recv_group<T: AsyncRead + AsyncWrite + Unpin + Send>(
     channel: &mut SecureChannel<T>,
+    framing: DataFraming,
+    upstream: usize,
+    request_id: RequestId,
+    micro_batch: u32,
 ) -> crate::error::Result<Vec<OwnedTensor>> {
-    let mut tensors = Vec::new();
+    if framing == DataFraming::Sentinel {
+        let failure = DataError {
+            stage_idx: upstream,
+            request_id,
+            micro_batch,
+            code: ErrorCode::Internal,
+        };
+        return recv_until_end(channel, failure).await;
+    }
     loop {
         let msg = channel.recv().await.map_err(PipelineError::Transport)?;
-        match msg {
+        let header = match msg {
+            Message::Data(data) => DataFrame::from_bytes(&data)?,
+            Message::Shutdown => return Err(PipelineError::Shutdown),
+            other => {
+                return Err(PipelineError::Protocol(format!(
+                    "expected a frame header on data channel, got {other:?}"
+                )));
+            }
+        };
+        match header {
+            DataFrame::Error(e) if e.request_id == request_id => {
+                return Err(PipelineError::UpstreamFailed(e));
+            }
+            DataFrame::Error(e) => {
+                warn!(
+                    request_id,
+                    stale_request_id = e.request_id,
+                    "skipping stale error frame on data channel"
+                );
+            }
+            DataFrame::Tensors {
+                request_id: rid,
+                micro_batch: mb,
+                count,
+            } => {
+                let tensors = recv_tensors(channel, count).await?;
+                if rid != request_id {
+                    warn!(
+                        request_id,
+                        stale_request_id = rid,
+                        micro_batch = mb,
+                        "skipping stale tensor group on data channel"
+                    );
+                } else if mb != micro_batch {
+                    return Err(PipelineError::Protocol(format!(
+                        "expected micro-batch {micro_batch} of request {request_id} on data channel, got {mb}"
+                    )));
+                } else {
+                    return Ok(tensors);
+                }
+            }
+        }
+    }
+}
+
+/// Receive a version 1 tensor group: tensors up to the `END` trailer.
+///
+/// An `ERR` carries no details, so it is reported as `failure`.
+async fn recv_until_end<T: AsyncRead + AsyncWrite + Unpin + Send>(
+    channel: &mut SecureChannel<T>,
+    failure: DataError,
+) -> crate::error::Result<Vec<OwnedTensor>> {
+    let mut tensors = Vec::new();
+    loop {
+        match channel.recv().await.map_err(PipelineError::Transport)? {
             Message::Tensor(t) => tensors.push(t),
-            Message::Data(data) if data.as_ref() == b"END" => break,
-            Message::Data(data) if data.as_ref() == ERROR_SENTINEL => {
-                return Err(PipelineError::StageFailed {
-                    stage_idx: usize::MAX,
-                    reason: "upstream stage reported error".into(),
-                });
+            Message::Data(data) if data[..] == *END_SENTINEL => return Ok(tensors),
+            Message::Data(data) if data[..] == *ERROR_SENTINEL => {
+                return Err(PipelineError::UpstreamFailed(failure));
+            }
+            Message::Shutdown => return Err(PipelineError::Shutdown),
+            other => {
+                return Err(PipelineError::Protocol(format!(
+                    "expected tensors, END or ERR on data channel, got {other:?}"
+                )));
             }
+        }
+    }
+}
+
+/// Receive the `count` tensors announced by a group header.
+async fn recv_tensors<T: AsyncRead + AsyncWrite + Unpin + Send>(
+    channel: &mut SecureChannel<T>,
+    count: u32,
+) -> crate::error::Result<Vec<OwnedTensor>> {
+    let mut tensors = Vec::new();
+    for _ in 0..count {
+        match channel.recv().await.map_err(PipelineError::Transport)? {
+            Message::Tensor(t) => tensors.push(t),
             Message::Shutdown => return Err(PipelineError::Shutdown),
             other => {
                 return Err(PipelineError::Protocol(format!(
-                    "unexpected message on data channel: {other:?}"
+                    "expected {count} tensors on data channel, got {other:?} after {}",
+                    tensors.len()
                 )));
             }
         }
@@ -646,20 +1649,138 @@ async fn recv_tensors<T: AsyncRead + AsyncWrite + Unpin + Send>(
     Ok(tensors)
 }
 
-/// Send tensors followed by an END sentinel on a data channel.
-async fn send_tensors<T: AsyncRead + AsyncWrite + Unpin + Send>(
+/// Error code reported downstream for a request that failed with `e`.
+fn error_code(e: &PipelineError) -> ErrorCode {
+    match e {
+        PipelineError::Stage(e) => e.code(),
+        PipelineError::UpstreamFailed(frame) => frame.code,
+        PipelineError::RequestFailed { code, .. } => *code,
+        PipelineError::Transport(_) | PipelineError::Shutdown => ErrorCode::Transport,
+        PipelineError::Protocol(_)
+        | PipelineError::VersionMismatch { .. }
+        | PipelineError::MessageTooLarge { .. } => ErrorCode::Protocol,
+        PipelineError::DeadlineExceeded { .. } => ErrorCode::DeadlineExceeded,
+        _ => ErrorCode::Internal,
+    }
+}
+
+/// Executor-supplied detail reported with a request that failed with `e`.
+fn error_details(e: &PipelineError) -> Option<String> {
+    match e {
+        PipelineError::Stage(e) => e.details().map(str::to_owned),
+        PipelineError::RequestFailed { details, .. } => details.clone(),
+        _ => None,
+    }
+}
+
+/// Local deadline for a request whose StartRequest arrived just now.
+fn deadline_from_budget(time_budget_ms: u64) -> Instant {
+    Instant::now() + Duration::from_millis(time_budget_ms)
+}
+
+/// Sleep until `deadline`, or forever if there is none.
+async fn sleep_until_deadline(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Consume the input groups of `request_id` from micro-batch `received`
+/// up to `num_micro_batches`, stopping early if upstream reported an error.
+async fn discard_input<T: AsyncRead + AsyncWrite + Unpin + Send>(
     channel: &mut SecureChannel<T>,
+    framing: DataFraming,
+    upstream: usize,
+    request_id: RequestId,
+    received: u32,
+    num_micro_batches: u32,
+) -> crate::error::Result<()> {
+    for micro_batch in received..num_micro_batches {
+        match recv_group(channel, framing, upstream, request_id, micro_batch).await {
+            Ok(_) => {}
+            Err(PipelineError::UpstreamFailed(_)) => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Run `futures` concurrently, returning their outputs in order.
+async fn join_all<F: Future>(futures: impl IntoIterator<Item = F>) -> Vec<F::Output> {
+    let mut futures: Vec<_> = futures.into_iter().map(Box::pin).collect();
+    let mut outputs: Vec<Option<F::Output>> = futures.iter().map(|_| None).collect();
+    std::future::poll_fn(|cx| {
+        let mut pending = false;
+        for (future, output) in futures.iter_mut().zip(outputs.iter_mut()) {
+            if output.is_none() {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(value) => *output = Some(value),
+                    Poll::Pending => pending = true,
+                }
+            }
+        }
+        if pending {
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    })
+    .await;
+    outputs
+        .into_iter()
+        .map(|output| output.expect("every future completed"))
+        .collect()
+}
+
+/// Serialized `DataFrame::Tensors` header announcing `tensors`.
+fn tensor_header(
+    request_id: RequestId,
+    micro_batch: u32,
+    tensors: &[OwnedTensor],
+) -> crate::error::Result<Bytes> {
+    let count = u32::try_from(tensors.len()).map_err(|_| {
+        PipelineError::Protocol(format!(
+            "too many tensors in micro-batch {micro_batch}: {}",
+            tensors.len()
+        ))
+    })?;
+    Ok(DataFrame::Tensors {
+        request_id,
+        micro_batch,
+        count,
+    }
+    .to_bytes()?)
+}
+
+/// Send micro-batch `micro_batch` of `request_id` as one tensor group on a
+/// data channel: a header then the tensors, or at version 1 the tensors
+/// then `END`.
+pub(crate) async fn send_tensors<T: AsyncRead + AsyncWrite + Unpin + Send>(
+    channel: &mut SecureChannel<T>,
+    framing: DataFraming,
+    request_id: RequestId,
+    micro_batch: u32,
     tensors: &[OwnedTensor],
 ) -> crate::error::Result<()> {
+    if framing == DataFraming::Header {
+        let header = tensor_header(request_id, micro_batch, tensors)?;
+        channel
+            .send(header)
+            .await
+            .map_err(PipelineError::Transport)?;
+    }
     for t in tensors {
         channel
             .send_tensor(t.as_ref())
             .await
             .map_err(PipelineError::Transport)?;
     }
-    channel
-        .send(Bytes::from_static(b"END"))
-        .await
-        .map_err(PipelineError::Transport)?;
+    if framing == DataFraming::Sentinel {
+        channel
+            .send(Bytes::from_static(END_SENTINEL))
+            .await
+            .map_err(PipelineError::Transport)?;
+    }
     Ok(())
 }
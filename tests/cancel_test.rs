@@ -0,0 +1,320 @@
+#![cfg(feature = "mock")]
+
+//! Tests for caller cancellation of in-flight requests.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+
+use confidential_ml_pipeline::{
+    CancelToken, ForwardOutput, Orchestrator, OrchestratorConfig, PipelineError, RequestId,
+    StageConfig, StageError, StageExecutor, StageRuntime, StageSpec,
+};
+
+mod common;
+use common::make_test_manifest;
+
+/// Executor that sleeps per forward, counts forwards, and fails on inputs
+/// named "boom".
+struct SlowExecutor {
+    delay: Duration,
+    forwards: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl StageExecutor for SlowExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        request_id: RequestId,
+        micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        self.forwards.fetch_add(1, Ordering::SeqCst);
+        if inputs.iter().any(|t| t.name == "boom") {
+            return Err(StageError::ForwardFailed {
+                request_id,
+                micro_batch,
+                reason: "boom".into(),
+            });
+        }
+        tokio::time::sleep(self.delay).await;
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+fn make_test_tensor(name: &str) -> OwnedTensor {
+    OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![0u8; 16]),
+    }
+}
+
+fn micro_batches(prefix: &str, n: usize) -> Vec<Vec<OwnedTensor>> {
+    (0..n)
+        .map(|mb| vec![make_test_tensor(&format!("{prefix}_mb{mb}"))])
+        .collect()
+}
+
+/// Set up an N-stage duplex pipeline of `SlowExecutor`s sharing one forward counter.
+async fn setup_pipeline(
+    num_stages: usize,
+    delay: Duration,
+) -> (
+    Orchestrator<tokio::io::DuplexStream>,
+    Vec<tokio::task::JoinHandle<()>>,
+    Arc<AtomicUsize>,
+) {
+    let manifest = make_test_manifest(num_stages);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+    let forwards = Arc::new(AtomicUsize::new(0));
+
+    let mut orch_ctrls = Vec::new();
+    let mut stage_ctrls = Vec::new();
+    for _ in 0..num_stages {
+        let (orch_side, stage_side) = tokio::io::duplex(262144);
+        orch_ctrls.push(orch_side);
+        stage_ctrls.push(stage_side);
+    }
+
+    // Data links: orchestrator -> stage 0 -> ... -> stage N-1 -> orchestrator.
+    let (orch_data_in, mut next_data_in) = tokio::io::duplex(262144);
+    let mut handles = Vec::new();
+    for ctrl in stage_ctrls {
+        let (data_out, downstream_in) = tokio::io::duplex(262144);
+        let data_in = std::mem::replace(&mut next_data_in, downstream_in);
+        let executor = SlowExecutor {
+            delay,
+            forwards: forwards.clone(),
+        };
+        handles.push(tokio::spawn(async move {
+            let provider = MockProvider::new();
+            let verifier = MockVerifier::new();
+            let mut runtime = StageRuntime::new(executor, StageConfig::development());
+            let _ = runtime
+                .run(ctrl, data_in, data_out, &provider, &verifier)
+                .await;
+        }));
+    }
+    let orch_data_out = next_data_in;
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(orch_ctrls, &provider, &verifier).await.unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    (orch, handles, forwards)
+}
+
+fn assert_outputs(outputs: &[Vec<OwnedTensor>], prefix: &str, n: usize) {
+    assert_eq!(outputs.len(), n);
+    for (mb, tensors) in outputs.iter().enumerate() {
+        assert_eq!(tensors[0].name, format!("{prefix}_mb{mb}"));
+    }
+}
+
+/// Cancelling mid-request stops the stages early and leaves the pipeline
+/// ready for the next request.
+#[tokio::test]
+async fn cancel_mid_request_leaves_pipeline_usable() {
+    let (mut orch, handles, forwards) = setup_pipeline(3, Duration::from_millis(50)).await;
+
+    let cancel = CancelToken::new();
+    let trigger = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        trigger.cancel();
+    });
+
+    let err = orch
+        .infer_cancellable(micro_batches("slow", 8), 16, &cancel)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, PipelineError::Cancelled { .. }),
+        "expected Cancelled, got {err:?}"
+    );
+    assert!(!orch.is_tainted());
+    assert_eq!(orch.in_flight(), 0);
+    let done = forwards.load(Ordering::SeqCst);
+    assert!(
+        done < 3 * 8,
+        "stages kept working after abort: {done} forwards"
+    );
+
+    let result = orch.infer(micro_batches("next", 3), 16).await.unwrap();
+    assert_outputs(&result.outputs, "next", 3);
+    orch.health_check().await.unwrap();
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// An already-cancelled token returns immediately without submitting.
+#[tokio::test]
+async fn cancelled_token_submits_nothing() {
+    let (mut orch, handles, forwards) = setup_pipeline(2, Duration::ZERO).await;
+
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    let err = orch
+        .infer_cancellable(micro_batches("never", 2), 16, &cancel)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PipelineError::Cancelled { .. }));
+    assert_eq!(orch.in_flight(), 0);
+    assert_eq!(forwards.load(Ordering::SeqCst), 0);
+
+    // An uncancelled token behaves like infer.
+    let result = orch
+        .infer_cancellable(micro_batches("ok", 2), 16, &CancelToken::new())
+        .await
+        .unwrap();
+    assert_outputs(&result.outputs, "ok", 2);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// Cancelling a request queued behind another leaves the earlier request's
+/// result intact.
+#[tokio::test]
+async fn cancel_keeps_earlier_request_result() {
+    let (mut orch, handles, _forwards) = setup_pipeline(3, Duration::from_millis(30)).await;
+
+    let first = orch.submit(micro_batches("first", 4), 16).await.unwrap();
+
+    let cancel = CancelToken::new();
+    let trigger = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        trigger.cancel();
+    });
+    let err = orch
+        .infer_cancellable(micro_batches("second", 4), 16, &cancel)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PipelineError::Cancelled { .. }));
+    assert!(!orch.is_tainted());
+
+    let result = orch.wait(first).await.unwrap();
+    assert_outputs(&result.outputs, "first", 4);
+
+    let result = orch.infer(micro_batches("third", 2), 16).await.unwrap();
+    assert_outputs(&result.outputs, "third", 2);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// A stage that fails part-way through a request consumes the rest of its
+/// input, so the next request's outputs are not mixed with stale ones.
+#[tokio::test]
+async fn failed_request_input_is_consumed() {
+    let (mut orch, handles, _forwards) = setup_pipeline(3, Duration::ZERO).await;
+
+    let mut inputs = micro_batches("bad", 4);
+    inputs[1] = vec![make_test_tensor("boom")];
+    let err = orch.infer(inputs, 16).await.unwrap_err();
+    assert!(
+        matches!(err, PipelineError::RequestFailed { .. }),
+        "expected RequestFailed, got {err:?}"
+    );
+
+    let result = orch.infer(micro_batches("good", 3), 16).await.unwrap();
+    assert_outputs(&result.outputs, "good", 3);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// Dropping an `infer` future after its inputs were sent detaches the
+/// request; the next call discards its outputs instead of returning them.
+#[tokio::test]
+async fn dropped_infer_is_reconciled() {
+    let (mut orch, handles, _forwards) = setup_pipeline(3, Duration::from_millis(30)).await;
+
+    let dropped = tokio::time::timeout(
+        // Mid-collection: outputs leave the last stage every ~30ms from ~90ms.
+        Duration::from_millis(130),
+        orch.infer(micro_batches("dropped", 4), 16),
+    )
+    .await;
+    assert!(dropped.is_err(), "infer should still have been running");
+    assert_eq!(orch.in_flight(), 1);
+
+    let result = orch.infer(micro_batches("next", 2), 16).await.unwrap();
+    assert_outputs(&result.outputs, "next", 2);
+    assert!(!orch.is_tainted());
+    assert_eq!(orch.in_flight(), 0);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// A dropped `wait` keeps its progress; waiting again returns the full result.
+#[tokio::test]
+async fn dropped_wait_can_resume() {
+    let (mut orch, handles, _forwards) = setup_pipeline(2, Duration::from_millis(30)).await;
+
+    let id = orch.submit(micro_batches("resume", 4), 16).await.unwrap();
+    let dropped = tokio::time::timeout(Duration::from_millis(100), orch.wait(id)).await;
+    assert!(dropped.is_err(), "wait should still have been running");
+
+    let result = orch.wait(id).await.unwrap();
+    assert_outputs(&result.outputs, "resume", 4);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// Dropping `infer` while inputs are still being written may leave a
+/// partial frame, so the next call refuses to use the pipeline.
+#[tokio::test]
+async fn dropped_submission_taints() {
+    let (mut orch, _handles, _forwards) = setup_pipeline(2, Duration::from_millis(200)).await;
+
+    // Each input is larger than the duplex buffer, so sending blocks while
+    // stage 0 is busy with the previous micro-batch.
+    let inputs: Vec<Vec<OwnedTensor>> = (0..4)
+        .map(|mb| {
+            vec![OwnedTensor {
+                name: format!("big_mb{mb}"),
+                dtype: DType::F32,
+                shape: vec![1, 262144],
+                data: Bytes::from(vec![0u8; 1 << 20]),
+            }]
+        })
+        .collect();
+    let dropped = tokio::time::timeout(Duration::from_millis(50), orch.infer(inputs, 16)).await;
+    assert!(dropped.is_err(), "infer should still have been sending");
+
+    let err = orch.infer(micro_batches("after", 1), 16).await.unwrap_err();
+    assert!(
+        matches!(err, PipelineError::Tainted),
+        "expected Tainted, got {err:?}"
+    );
+}
@@ -0,0 +1,39 @@
+//! Fixtures shared by the integration tests.
+
+// Each test crate compiles this module and uses only part of it.
+#![allow(dead_code)]
+
+use confidential_ml_pipeline::{
+    ActivationDType, ActivationSpec, PortSpec, ShardManifest, StageEndpoint, StageSpec,
+};
+
+/// Endpoint for stage `i`. The duplex-based tests never dial it.
+pub fn test_endpoint(i: usize) -> StageEndpoint {
+    let tcp = |port: usize| PortSpec::Tcp {
+        addr: format!("127.0.0.1:{port}"),
+    };
+    StageEndpoint {
+        control: tcp(9000 + i * 10),
+        data_in: tcp(9001 + i * 10),
+        data_out: tcp(9002 + i * 10),
+    }
+}
+
+/// A linear manifest with four layers per stage.
+pub fn make_test_manifest(num_stages: usize) -> ShardManifest {
+    let stages = (0..num_stages)
+        .map(|i| StageSpec::linear(i, i * 4..(i + 1) * 4, test_endpoint(i)))
+        .collect();
+
+    ShardManifest {
+        model_name: "test-model".into(),
+        model_version: "1.0".into(),
+        total_layers: num_stages * 4,
+        stages,
+        activation_spec: ActivationSpec {
+            dtype: ActivationDType::F32,
+            hidden_dim: 4,
+            max_seq_len: 16,
+        },
+    }
+}
@@ -0,0 +1,229 @@
+#![cfg(feature = "mock")]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+use tokio::io::DuplexStream;
+
+use confidential_ml_pipeline::{
+    ActivationDType, ActivationSpec, Dim, ForwardOutput, Orchestrator, OrchestratorConfig,
+    PipelineError, RequestId, ShardManifest, StageConfig, StageError, StageExecutor, StageRuntime,
+    StageSpec, TensorDType, TensorSpec,
+};
+
+mod common;
+use common::test_endpoint;
+
+/// Stage 0 embeds `ids` [batch, seq] into `hidden` [batch, seq, 4]; stage 1
+/// reduces it to `logits` [batch, 10]. With `bad_output` set, stage 0 emits
+/// one extra sequence position on its next call.
+struct ToyExecutor {
+    first: bool,
+    bad_output: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl StageExecutor for ToyExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        _micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        let shape = &inputs[0].shape;
+        let tensor = if self.first {
+            let extra = u32::from(self.bad_output.swap(false, Ordering::SeqCst));
+            make_tensor("hidden", DType::F32, vec![shape[0], shape[1] + extra, 4])
+        } else {
+            make_tensor("logits", DType::F32, vec![shape[0], 10])
+        };
+        Ok(ForwardOutput {
+            tensors: vec![tensor],
+        })
+    }
+}
+
+fn make_tensor(name: &str, dtype: DType, shape: Vec<u32>) -> OwnedTensor {
+    let len = shape.iter().product::<u32>() as usize * dtype.element_size();
+    OwnedTensor {
+        name: name.to_string(),
+        dtype,
+        shape,
+        data: Bytes::from(vec![0u8; len]),
+    }
+}
+
+fn tensor_spec(name: &str, dtype: TensorDType, shape: &[Dim]) -> TensorSpec {
+    TensorSpec {
+        name: name.to_string(),
+        dtype,
+        shape: shape.to_vec(),
+    }
+}
+
+fn make_test_manifest() -> ShardManifest {
+    let batch = || Dim::Symbol("batch".into());
+    let seq = || Dim::Symbol("seq".into());
+    let hidden = tensor_spec("hidden", TensorDType::F32, &[batch(), seq(), Dim::Fixed(4)]);
+    let contracts = [
+        (
+            vec![tensor_spec("ids", TensorDType::U32, &[batch(), seq()])],
+            vec![hidden.clone()],
+        ),
+        (
+            vec![hidden],
+            vec![tensor_spec(
+                "logits",
+                TensorDType::F32,
+                &[batch(), Dim::Fixed(10)],
+            )],
+        ),
+    ];
+    let stages = contracts
+        .into_iter()
+        .enumerate()
+        .map(|(i, (inputs, outputs))| StageSpec {
+            inputs,
+            outputs,
+            ..StageSpec::linear(i, i * 4..(i + 1) * 4, test_endpoint(i))
+        })
+        .collect();
+
+    ShardManifest {
+        model_name: "test-model".into(),
+        model_version: "1.0".into(),
+        total_layers: 8,
+        stages,
+        activation_spec: ActivationSpec {
+            dtype: ActivationDType::F32,
+            hidden_dim: 4,
+            max_seq_len: 16,
+        },
+    }
+}
+
+/// Start the two-stage pipeline. Returns the orchestrator and the flag
+/// that corrupts stage 0's next output.
+async fn start_pipeline() -> (
+    Orchestrator<DuplexStream>,
+    Arc<AtomicBool>,
+    Vec<tokio::task::JoinHandle<()>>,
+) {
+    let manifest = make_test_manifest();
+    manifest.validate().unwrap();
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+
+    let (orch_ctrl0, stage0_ctrl) = tokio::io::duplex(65536);
+    let (orch_ctrl1, stage1_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage0_data_in) = tokio::io::duplex(65536);
+    let (stage0_data_out, stage1_data_in) = tokio::io::duplex(65536);
+    let (stage1_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+    let bad_output = Arc::new(AtomicBool::new(false));
+    let stages = [
+        (stage0_ctrl, stage0_data_in, stage0_data_out),
+        (stage1_ctrl, stage1_data_in, stage1_data_out),
+    ];
+    let mut handles = Vec::new();
+    for (i, (ctrl, data_in, data_out)) in stages.into_iter().enumerate() {
+        let executor = ToyExecutor {
+            first: i == 0,
+            bad_output: bad_output.clone(),
+        };
+        handles.push(tokio::spawn(async move {
+            let mut runtime = StageRuntime::new(executor, StageConfig::development());
+            runtime
+                .run(
+                    ctrl,
+                    data_in,
+                    data_out,
+                    &MockProvider::new(),
+                    &MockVerifier::new(),
+                )
+                .await
+                .unwrap_or_else(|e| panic!("stage {i} failed: {e}"));
+        }));
+    }
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(vec![orch_ctrl0, orch_ctrl1], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+    (orch, bad_output, handles)
+}
+
+fn expect_contract_failure(result: Result<impl std::fmt::Debug, PipelineError>, side: &str) {
+    match result {
+        Err(PipelineError::RequestFailed { reason, .. }) => {
+            assert!(reason.contains(side), "{reason}")
+        }
+        other => panic!("expected RequestFailed, got: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn tensors_matching_contracts_flow_through() {
+    let (mut orch, _bad, handles) = start_pipeline().await;
+
+    let inputs = vec![
+        vec![make_tensor("ids", DType::U32, vec![2, 5])],
+        vec![make_tensor("ids", DType::U32, vec![3, 1])],
+    ];
+    let result = orch.infer(inputs, 16).await.unwrap();
+    assert_eq!(result.outputs[0][0].shape, vec![2, 10]);
+    assert_eq!(result.outputs[1][0].shape, vec![3, 10]);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// Input with the wrong dtype is refused before `forward`, and the
+/// pipeline serves the next request.
+#[tokio::test]
+async fn input_breaking_contract_is_rejected() {
+    let (mut orch, _bad, handles) = start_pipeline().await;
+
+    let inputs = vec![vec![make_tensor("ids", DType::F32, vec![2, 5])]; 2];
+    expect_contract_failure(orch.infer(inputs, 16).await, "input");
+
+    let inputs = vec![vec![make_tensor("ids", DType::U32, vec![2, 5])]; 2];
+    let result = orch.infer(inputs, 16).await.unwrap();
+    assert_eq!(result.outputs.len(), 2);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// Output whose `seq` no longer matches the input is caught at the
+/// producing stage, not the consumer.
+#[tokio::test]
+async fn output_breaking_contract_is_rejected() {
+    let (mut orch, bad, handles) = start_pipeline().await;
+
+    bad.store(true, Ordering::SeqCst);
+    let inputs = vec![vec![make_tensor("ids", DType::U32, vec![1, 5])]; 2];
+    expect_contract_failure(orch.infer(inputs.clone(), 16).await, "output");
+
+    let result = orch.infer(inputs, 16).await.unwrap();
+    assert_eq!(result.outputs[1][0].shape, vec![1, 10]);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
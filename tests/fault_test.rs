@@ -1,17 +1,42 @@
 #![cfg(feature = "mock")]
 
-use std::collections::BTreeMap;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+use confidential_ml_transport::{
+    DType, Message, MockProvider, MockVerifier, OwnedTensor, SecureChannel, SessionConfig,
+};
+use tokio::io::DuplexStream;
 
 use confidential_ml_pipeline::{
-    ActivationDType, ActivationSpec, ForwardOutput, Orchestrator, OrchestratorConfig,
-    PipelineError, PortSpec, RequestId, ShardManifest, StageConfig, StageEndpoint, StageError,
-    StageExecutor, StageRuntime, StageSpec,
+    DataError, DataFrame, ErrorCode, ForwardOutput, Orchestrator, OrchestratorConfig,
+    OrchestratorMsg, PipelineError, RequestId, StageConfig, StageError, StageExecutor, StageMsg,
+    StageRuntime, StageSpec, PROTOCOL_VERSION,
 };
 
+mod common;
+use common::make_test_manifest;
+
+/// Identity executor: passes input tensors through unchanged.
+struct IdentityExecutor;
+
+#[async_trait]
+impl StageExecutor for IdentityExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        _micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
 /// Executor that fails on the first forward call.
 struct FailingExecutor;
 
@@ -35,39 +60,111 @@ impl StageExecutor for FailingExecutor {
     }
 }
 
-fn make_test_manifest(num_stages: usize) -> ShardManifest {
-    let stages = (0..num_stages)
-        .map(|i| StageSpec {
-            stage_idx: i,
-            layer_start: i * 4,
-            layer_end: (i + 1) * 4,
-            weight_hashes: vec![],
-            require_weight_hashes: false,
-            expected_measurements: BTreeMap::new(),
-            endpoint: StageEndpoint {
-                control: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 9100 + i * 10),
-                },
-                data_in: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 9101 + i * 10),
-                },
-                data_out: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 9102 + i * 10),
-                },
-            },
-        })
-        .collect();
-
-    ShardManifest {
-        model_name: "test-model".into(),
-        model_version: "1.0".into(),
-        total_layers: num_stages * 4,
-        stages,
-        activation_spec: ActivationSpec {
-            dtype: ActivationDType::F32,
-            hidden_dim: 4,
-            max_seq_len: 16,
-        },
+/// Executor whose forward pass fails with the error built by `error`.
+struct ErrorExecutor {
+    error: fn(RequestId, u32) -> StageError,
+}
+
+#[async_trait]
+impl StageExecutor for ErrorExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        request_id: RequestId,
+        micro_batch: u32,
+        _inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        Err((self.error)(request_id, micro_batch))
+    }
+}
+
+/// Play the stage side of `Init` and data channel setup by hand, returning
+/// the control, data_in and data_out channels.
+async fn connect_rogue_stage(
+    control: DuplexStream,
+    data_in: DuplexStream,
+    data_out: DuplexStream,
+) -> (
+    SecureChannel<DuplexStream>,
+    SecureChannel<DuplexStream>,
+    SecureChannel<DuplexStream>,
+) {
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+    let mut control = SecureChannel::accept_with_attestation(
+        control,
+        &provider,
+        &verifier,
+        SessionConfig::development(),
+    )
+    .await
+    .expect("control handshake failed");
+    let _init = control.recv().await.expect("recv Init failed");
+    control
+        .send(
+            StageMsg::Ready {
+                stage_idx: 0,
+                protocol_version: Some(PROTOCOL_VERSION),
+            }
+            .to_bytes()
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+    let _establish = control
+        .recv()
+        .await
+        .expect("recv EstablishDataChannels failed");
+
+    let data_in = SecureChannel::accept_with_attestation(
+        data_in,
+        &provider,
+        &verifier,
+        SessionConfig::development(),
+    )
+    .await
+    .expect("data_in handshake failed");
+    let data_out = SecureChannel::connect_with_attestation(
+        data_out,
+        &provider,
+        &verifier,
+        SessionConfig::development(),
+    )
+    .await
+    .expect("data_out handshake failed");
+    control
+        .send(
+            StageMsg::DataChannelsReady { stage_idx: 0 }
+                .to_bytes()
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    (control, data_in, data_out)
+}
+
+/// Read the next `StartRequest` from a rogue stage's control channel and
+/// return its request ID.
+async fn recv_start_request(control: &mut SecureChannel<DuplexStream>) -> u64 {
+    let start = match control.recv().await.expect("recv StartRequest failed") {
+        Message::Data(data) => data,
+        other => panic!("expected StartRequest, got {other:?}"),
+    };
+    match OrchestratorMsg::from_bytes(&start).unwrap() {
+        OrchestratorMsg::StartRequest { request_id, .. } => request_id,
+        other => panic!("expected StartRequest, got {other:?}"),
+    }
+}
+
+fn make_test_tensor() -> OwnedTensor {
+    OwnedTensor {
+        name: "input".into(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![0u8; 16]),
     }
 }
 
@@ -109,14 +206,7 @@ async fn stage_failure_returns_request_error() {
         .await
         .unwrap();
 
-    let input = vec![vec![OwnedTensor {
-        name: "input".into(),
-        dtype: DType::F32,
-        shape: vec![1, 4],
-        data: Bytes::from(vec![0u8; 16]),
-    }]];
-
-    let result = orch.infer(input, 16).await;
+    let result = orch.infer(vec![vec![make_test_tensor()]], 16).await;
 
     assert!(
         matches!(&result, Err(PipelineError::RequestFailed { .. })),
@@ -128,3 +218,321 @@ async fn stage_failure_returns_request_error() {
     let stage_result = stage0_handle.await.unwrap();
     assert!(stage_result.is_ok());
 }
+
+/// A failure in stage 0 of a two-stage pipeline is forwarded by stage 1,
+/// and the orchestrator names stage 0 as the failing stage.
+#[tokio::test]
+async fn upstream_failure_names_originating_stage() {
+    let manifest = make_test_manifest(2);
+
+    let (orch_ctrl0, stage0_ctrl) = tokio::io::duplex(65536);
+    let (orch_ctrl1, stage1_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage0_data_in) = tokio::io::duplex(65536);
+    let (stage0_data_out, stage1_data_in) = tokio::io::duplex(65536);
+    let (stage1_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+    let stage0_handle = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        let mut runtime = StageRuntime::new(FailingExecutor, StageConfig::development());
+        runtime
+            .run(
+                stage0_ctrl,
+                stage0_data_in,
+                stage0_data_out,
+                &provider,
+                &verifier,
+            )
+            .await
+    });
+    let stage1_handle = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        let mut runtime = StageRuntime::new(IdentityExecutor, StageConfig::development());
+        runtime
+            .run(
+                stage1_ctrl,
+                stage1_data_in,
+                stage1_data_out,
+                &provider,
+                &verifier,
+            )
+            .await
+    });
+
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(vec![orch_ctrl0, orch_ctrl1], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    let result = orch.infer(vec![vec![make_test_tensor()]], 16).await;
+    match &result {
+        Err(PipelineError::RequestFailed { reason, .. }) => {
+            assert!(reason.starts_with("stage 0 error"), "reason: {reason}");
+        }
+        other => panic!("expected RequestFailed, got: {other:?}"),
+    }
+
+    // The pipeline stays usable.
+    let result = orch.infer(vec![vec![make_test_tensor()]], 16).await;
+    assert!(
+        matches!(&result, Err(PipelineError::RequestFailed { .. })),
+        "expected RequestFailed, got: {result:?}"
+    );
+
+    orch.shutdown().await.unwrap();
+    assert!(stage0_handle.await.unwrap().is_ok());
+    assert!(stage1_handle.await.unwrap().is_ok());
+}
+
+/// The orchestrator reports the stage named in a data-plane error frame even
+/// when that stage never replies on its control channel.
+#[tokio::test]
+async fn error_frame_reported_when_control_channel_stuck() {
+    let manifest = make_test_manifest(1);
+
+    let (orch_ctrl0, stage0_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage0_data_in) = tokio::io::duplex(65536);
+    let (stage0_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+    // A stage that fails the request on the data plane but stays silent on
+    // the control channel.
+    let rogue = tokio::spawn(async move {
+        let (mut control, _data_in, mut data_out) =
+            connect_rogue_stage(stage0_ctrl, stage0_data_in, stage0_data_out).await;
+        let request_id = recv_start_request(&mut control).await;
+        let frame = DataFrame::Error(DataError {
+            stage_idx: 0,
+            request_id,
+            micro_batch: 0,
+            code: ErrorCode::Forward,
+        });
+        data_out.send(frame.to_bytes().unwrap()).await.unwrap();
+
+        // Hold the channels open without replying.
+        while control.recv().await.is_ok() {}
+    });
+
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+    let config = OrchestratorConfig {
+        stage_drain_timeout: Duration::from_millis(100),
+        ..OrchestratorConfig::development()
+    };
+    let mut orch = Orchestrator::new(config, manifest).unwrap();
+    orch.init(vec![orch_ctrl0], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        orch.infer(vec![vec![make_test_tensor()]], 16),
+    )
+    .await
+    .expect("orchestrator waited on the silent control channel");
+    match &result {
+        Err(PipelineError::RequestFailed { reason, .. }) => {
+            assert_eq!(reason, "stage 0 error: forward failed at micro-batch 0");
+        }
+        other => panic!("expected RequestFailed, got: {other:?}"),
+    }
+
+    drop(orch);
+    let _ = rogue.await;
+}
+
+/// Output groups and errors tagged with another request are skipped, and a
+/// group for the wrong micro-batch of the current request is rejected.
+#[tokio::test]
+async fn stale_data_frames_are_skipped() {
+    let manifest = make_test_manifest(1);
+
+    let (orch_ctrl0, stage0_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage0_data_in) = tokio::io::duplex(65536);
+    let (stage0_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+    let rogue = tokio::spawn(async move {
+        let (mut control, _data_in, mut data_out) =
+            connect_rogue_stage(stage0_ctrl, stage0_data_in, stage0_data_out).await;
+        let send_group = |request_id, micro_batch| DataFrame::Tensors {
+            request_id,
+            micro_batch,
+            count: 1,
+        };
+
+        // First request: a stale group and a stale error, then the real output.
+        let request_id = recv_start_request(&mut control).await;
+        let stale = request_id.wrapping_add(1000);
+        let stale_error = DataFrame::Error(DataError {
+            stage_idx: 0,
+            request_id: stale,
+            micro_batch: 0,
+            code: ErrorCode::Aborted,
+        });
+        let mut stale_tensor = make_test_tensor();
+        stale_tensor.name = "stale".into();
+        data_out
+            .send(send_group(stale, 0).to_bytes().unwrap())
+            .await
+            .unwrap();
+        data_out.send_tensor(stale_tensor.as_ref()).await.unwrap();
+        data_out
+            .send(stale_error.to_bytes().unwrap())
+            .await
+            .unwrap();
+        data_out
+            .send(send_group(request_id, 0).to_bytes().unwrap())
+            .await
+            .unwrap();
+        data_out
+            .send_tensor(make_test_tensor().as_ref())
+            .await
+            .unwrap();
+        control
+            .send(
+                StageMsg::RequestDone {
+                    request_id,
+                    timings: vec![],
+                    layers: vec![],
+                }
+                .to_bytes()
+                .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        // Second request: output tagged with the wrong micro-batch.
+        let request_id = recv_start_request(&mut control).await;
+        data_out
+            .send(send_group(request_id, 1).to_bytes().unwrap())
+            .await
+            .unwrap();
+        data_out
+            .send_tensor(make_test_tensor().as_ref())
+            .await
+            .unwrap();
+
+        while control.recv().await.is_ok() {}
+    });
+
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(vec![orch_ctrl0], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    let result = orch
+        .infer(vec![vec![make_test_tensor()]], 16)
+        .await
+        .expect("stale frames should be skipped");
+    assert_eq!(result.outputs.len(), 1);
+    assert_eq!(result.outputs[0].len(), 1);
+    assert_eq!(result.outputs[0][0].name, "input");
+
+    let result = orch.infer(vec![vec![make_test_tensor()]], 16).await;
+    assert!(
+        matches!(&result, Err(PipelineError::Protocol(reason)) if reason.contains("micro-batch 0")),
+        "expected Protocol error, got: {result:?}"
+    );
+
+    drop(orch);
+    let _ = rogue.await;
+}
+
+/// The executor's error code and reason reach the caller in
+/// `RequestFailed`, marked retryable or fatal.
+#[tokio::test]
+async fn request_failure_carries_error_code() {
+    type Case = (
+        fn(RequestId, u32) -> StageError,
+        ErrorCode,
+        bool,
+        Option<&'static str>,
+    );
+    let cases: [Case; 3] = [
+        (
+            |request_id, micro_batch| StageError::ResourceExhausted {
+                request_id,
+                micro_batch,
+                reason: "out of device memory".into(),
+            },
+            ErrorCode::ResourceExhausted,
+            true,
+            Some("out of device memory"),
+        ),
+        (
+            |request_id, micro_batch| StageError::InvalidInput {
+                request_id,
+                micro_batch,
+                reason: "token id out of range".into(),
+            },
+            ErrorCode::InvalidInput,
+            false,
+            Some("token id out of range"),
+        ),
+        (
+            |_, _| StageError::WeightHashMismatch("layer 3".into()),
+            ErrorCode::WeightHash,
+            false,
+            None,
+        ),
+    ];
+
+    for (error, expected, retryable, expected_details) in cases {
+        let manifest = make_test_manifest(1);
+        let (orch_ctrl0, stage0_ctrl) = tokio::io::duplex(65536);
+        let (orch_data_in, stage0_data_in) = tokio::io::duplex(65536);
+        let (stage0_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+        let stage0_handle = tokio::spawn(async move {
+            let provider = MockProvider::new();
+            let verifier = MockVerifier::new();
+            let mut runtime =
+                StageRuntime::new(ErrorExecutor { error }, StageConfig::development());
+            runtime
+                .run(
+                    stage0_ctrl,
+                    stage0_data_in,
+                    stage0_data_out,
+                    &provider,
+                    &verifier,
+                )
+                .await
+        });
+
+        let verifier = MockVerifier::new();
+        let provider = MockProvider::new();
+        let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+        orch.init(vec![orch_ctrl0], &provider, &verifier)
+            .await
+            .unwrap();
+        orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+            .await
+            .unwrap();
+
+        let result = orch.infer(vec![vec![make_test_tensor()]], 16).await;
+        match &result {
+            Err(PipelineError::RequestFailed { code, details, .. }) => {
+                assert_eq!(*code, expected);
+                assert_eq!(code.is_retryable(), retryable);
+                assert_eq!(details.as_deref(), expected_details);
+            }
+            other => panic!("expected RequestFailed, got: {other:?}"),
+        }
+
+        orch.shutdown().await.unwrap();
+        assert!(stage0_handle.await.unwrap().is_ok());
+    }
+}
@@ -0,0 +1,255 @@
+#![cfg(feature = "mock")]
+
+//! Tests for the autoregressive generation loop.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+
+use confidential_ml_pipeline::{
+    ForwardOutput, GenerationRequest, GreedySampler, Orchestrator, OrchestratorConfig,
+    PipelineError, RequestId, SessionId, StageConfig, StageError, StageExecutor, StageRuntime,
+    StageSpec, StopReason,
+};
+
+mod common;
+use common::make_test_manifest;
+
+const VOCAB: u32 = 8;
+
+/// Toy decoder: token IDs in, logits out, where the argmax is the last input
+/// token plus one. F32 activations pass through unchanged. Only runs inside
+/// a session.
+struct NextTokenExecutor {
+    open_sessions: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl StageExecutor for NextTokenExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        request_id: RequestId,
+        micro_batch: u32,
+        _inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        Err(StageError::ForwardFailed {
+            request_id,
+            micro_batch,
+            reason: "expected a session request".into(),
+        })
+    }
+
+    async fn open_session(&self, _session_id: SessionId) -> Result<(), StageError> {
+        self.open_sessions.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn forward_in_session(
+        &self,
+        _session_id: SessionId,
+        _request_id: RequestId,
+        _micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        let input = &inputs[0];
+        if input.dtype != DType::U32 {
+            return Ok(ForwardOutput { tensors: inputs });
+        }
+        let last = u32::from_le_bytes(input.data[input.data.len() - 4..].try_into().unwrap());
+        let mut logits = vec![0.0f32; VOCAB as usize];
+        logits[((last + 1) % VOCAB) as usize] = 1.0;
+        Ok(ForwardOutput {
+            tensors: vec![OwnedTensor {
+                name: "logits".into(),
+                dtype: DType::F32,
+                shape: vec![1, VOCAB],
+                data: Bytes::from(
+                    logits
+                        .iter()
+                        .flat_map(|v| v.to_le_bytes())
+                        .collect::<Vec<u8>>(),
+                ),
+            }],
+        })
+    }
+
+    async fn close_session(&self, _session_id: SessionId) {
+        self.open_sessions.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Set up an N-stage duplex pipeline of next-token executors.
+async fn setup_pipeline(
+    num_stages: usize,
+    open_sessions: Arc<AtomicUsize>,
+) -> (
+    Orchestrator<tokio::io::DuplexStream>,
+    Vec<tokio::task::JoinHandle<()>>,
+) {
+    let manifest = make_test_manifest(num_stages);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+
+    let mut orch_ctrls = Vec::new();
+    let mut stage_ctrls = Vec::new();
+    for _ in 0..num_stages {
+        let (orch_side, stage_side) = tokio::io::duplex(262144);
+        orch_ctrls.push(orch_side);
+        stage_ctrls.push(stage_side);
+    }
+
+    let (orch_data_in, mut next_data_in) = tokio::io::duplex(262144);
+    let mut handles = Vec::new();
+    for ctrl in stage_ctrls {
+        let (data_out, downstream_in) = tokio::io::duplex(262144);
+        let data_in = std::mem::replace(&mut next_data_in, downstream_in);
+        let executor = NextTokenExecutor {
+            open_sessions: open_sessions.clone(),
+        };
+        handles.push(tokio::spawn(async move {
+            let provider = MockProvider::new();
+            let verifier = MockVerifier::new();
+            let mut runtime = StageRuntime::new(executor, StageConfig::development());
+            runtime
+                .run(ctrl, data_in, data_out, &provider, &verifier)
+                .await
+                .unwrap();
+        }));
+    }
+    let orch_data_out = next_data_in;
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(orch_ctrls, &provider, &verifier).await.unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    (orch, handles)
+}
+
+fn prompt(ids: &[u32]) -> Vec<OwnedTensor> {
+    vec![OwnedTensor {
+        name: "input_ids".into(),
+        dtype: DType::U32,
+        shape: vec![1, ids.len() as u32],
+        data: Bytes::from(
+            ids.iter()
+                .flat_map(|v| v.to_le_bytes())
+                .collect::<Vec<u8>>(),
+        ),
+    }]
+}
+
+/// Generation stops on a stop token, which is included in the output.
+#[tokio::test]
+async fn generate_stops_on_stop_token() {
+    let open_sessions = Arc::new(AtomicUsize::new(0));
+    let (mut orch, handles) = setup_pipeline(2, open_sessions.clone()).await;
+
+    let request = GenerationRequest {
+        initial_input: prompt(&[1, 2]),
+        prompt_len: 2,
+        max_tokens: 10,
+        stop_token_ids: vec![6],
+    };
+    let out = orch
+        .generate(request, &mut GreedySampler::default())
+        .await
+        .unwrap();
+
+    assert_eq!(out.tokens, vec![3, 4, 5, 6]);
+    assert_eq!(out.stop_reason, StopReason::StopToken(6));
+    assert_eq!(out.token_latencies.len(), 4);
+    assert!(out.time_to_first_token().unwrap() <= out.total);
+
+    // The session was closed on every stage.
+    orch.health_check().await.unwrap();
+    assert_eq!(open_sessions.load(Ordering::SeqCst), 0);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// max_tokens and the manifest's max_seq_len both bound generation.
+#[tokio::test]
+async fn generate_respects_length_limits() {
+    let open_sessions = Arc::new(AtomicUsize::new(0));
+    let (mut orch, handles) = setup_pipeline(2, open_sessions.clone()).await;
+
+    let request = GenerationRequest {
+        initial_input: prompt(&[0]),
+        prompt_len: 1,
+        max_tokens: 3,
+        stop_token_ids: vec![],
+    };
+    let out = orch
+        .generate(request, &mut GreedySampler::default())
+        .await
+        .unwrap();
+    assert_eq!(out.tokens, vec![1, 2, 3]);
+    assert_eq!(out.stop_reason, StopReason::MaxTokens);
+
+    // max_seq_len is 16: steps run at seq_len 14, 15 and 16.
+    let request = GenerationRequest {
+        initial_input: prompt(&[7; 14]),
+        prompt_len: 14,
+        max_tokens: 10,
+        stop_token_ids: vec![],
+    };
+    let out = orch
+        .generate(request, &mut GreedySampler::default())
+        .await
+        .unwrap();
+    assert_eq!(out.tokens, vec![0, 1, 2]);
+    assert_eq!(out.stop_reason, StopReason::MaxSeqLen);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// A sampler error ends generation and still closes the session.
+#[tokio::test]
+async fn sampler_error_closes_session() {
+    let open_sessions = Arc::new(AtomicUsize::new(0));
+    let (mut orch, handles) = setup_pipeline(2, open_sessions.clone()).await;
+
+    let mut calls = 0;
+    let mut sampler = |_: &[OwnedTensor]| {
+        calls += 1;
+        if calls == 2 {
+            Err(PipelineError::Protocol("sampler gave up".into()))
+        } else {
+            Ok(1)
+        }
+    };
+    let request = GenerationRequest {
+        initial_input: prompt(&[0]),
+        prompt_len: 1,
+        max_tokens: 5,
+        stop_token_ids: vec![],
+    };
+    let err = orch.generate(request, &mut sampler).await.unwrap_err();
+    assert!(
+        matches!(err, PipelineError::Protocol(_)),
+        "expected Protocol, got {err:?}"
+    );
+
+    orch.health_check().await.unwrap();
+    assert_eq!(open_sessions.load(Ordering::SeqCst), 0);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
@@ -0,0 +1,233 @@
+#![cfg(feature = "mock")]
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+use tokio::io::DuplexStream;
+
+use confidential_ml_pipeline::{
+    start_relay_graph, ActivationDType, ActivationSpec, ForwardOutput, Orchestrator,
+    OrchestratorConfig, PipelineError, RequestId, ShardManifest, StageConfig, StageError,
+    StageExecutor, StageRuntime, StageSpec,
+};
+
+mod common;
+use common::test_endpoint;
+
+/// Appends a one-element tensor named after its stage to whatever it receives.
+struct TagExecutor {
+    tag: String,
+    fail_next: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl StageExecutor for TagExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        micro_batch: u32,
+        mut inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        if micro_batch == 1 && self.fail_next.swap(false, Ordering::SeqCst) {
+            return Err(StageError::ForwardFailed {
+                request_id: 0,
+                micro_batch,
+                reason: format!("{} failed", self.tag),
+            });
+        }
+        inputs.push(OwnedTensor {
+            name: self.tag.clone(),
+            dtype: DType::U8,
+            shape: vec![1],
+            data: Bytes::from(vec![micro_batch as u8]),
+        });
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+/// Diamond: stage 0 fans out to stages 1 and 2, which both feed stage 3.
+fn make_diamond_manifest() -> ShardManifest {
+    let links: [(&[usize], &[usize]); 4] =
+        [(&[], &[1, 2]), (&[0], &[3]), (&[0], &[3]), (&[1, 2], &[])];
+    let stages = links
+        .iter()
+        .enumerate()
+        .map(|(i, (upstream, downstream))| StageSpec {
+            upstream: upstream.to_vec(),
+            downstream: downstream.to_vec(),
+            ..StageSpec::linear(i, i * 2..(i + 1) * 2, test_endpoint(i))
+        })
+        .collect();
+
+    ShardManifest {
+        model_name: "diamond".into(),
+        model_version: "1.0".into(),
+        total_layers: 8,
+        stages,
+        activation_spec: ActivationSpec {
+            dtype: ActivationDType::F32,
+            hidden_dim: 4,
+            max_seq_len: 16,
+        },
+    }
+}
+
+fn make_input() -> OwnedTensor {
+    OwnedTensor {
+        name: "x".into(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![0u8; 16]),
+    }
+}
+
+/// Stage-side (data-out, data-in) ends of each relayed link, keyed by
+/// (from, to).
+type LinkEnds = HashMap<(usize, usize), (DuplexStream, DuplexStream)>;
+
+/// Start the diamond pipeline with every inter-stage link going through a
+/// host relay. Returns the orchestrator and a flag that makes stage 2 fail
+/// its next micro-batch 1.
+async fn start_diamond() -> (
+    Orchestrator<DuplexStream>,
+    Arc<AtomicBool>,
+    Vec<tokio::task::JoinHandle<()>>,
+) {
+    let manifest = make_diamond_manifest();
+    manifest.validate().unwrap();
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+
+    let ends: Arc<Mutex<LinkEnds>> = Arc::default();
+    let relays = start_relay_graph(&manifest, |from, to| {
+        let ends = ends.clone();
+        async move {
+            let (stage_out, relay_up) = tokio::io::duplex(65536);
+            let (relay_down, stage_in) = tokio::io::duplex(65536);
+            ends.lock()
+                .unwrap()
+                .insert((from, to), (stage_out, stage_in));
+            (relay_up, relay_down)
+        }
+    })
+    .await;
+    let mut ends = std::mem::take(&mut *ends.lock().unwrap());
+
+    let (orch_data_in, entry_in) = tokio::io::duplex(65536);
+    let (exit_out, orch_data_out) = tokio::io::duplex(65536);
+    let mut data_in: Vec<Vec<DuplexStream>> = (0..4).map(|_| vec![]).collect();
+    let mut data_out: Vec<Vec<DuplexStream>> = (0..4).map(|_| vec![]).collect();
+    data_in[0].push(entry_in);
+    for (from, to) in manifest.links() {
+        let (out, inp) = ends.remove(&(from, to)).unwrap();
+        data_out[from].push(out);
+        data_in[to].push(inp);
+    }
+    data_out[3].push(exit_out);
+
+    let fail_next = Arc::new(AtomicBool::new(false));
+    let mut orch_ctrls = Vec::new();
+    let mut handles = Vec::new();
+    for (i, (din, dout)) in data_in.into_iter().zip(data_out).enumerate() {
+        let (orch_ctrl, stage_ctrl) = tokio::io::duplex(65536);
+        orch_ctrls.push(orch_ctrl);
+        let executor = TagExecutor {
+            tag: format!("s{i}"),
+            fail_next: if i == 2 {
+                fail_next.clone()
+            } else {
+                Arc::new(AtomicBool::new(false))
+            },
+        };
+        handles.push(tokio::spawn(async move {
+            let mut runtime = StageRuntime::new(executor, StageConfig::development());
+            runtime
+                .run_graph(
+                    stage_ctrl,
+                    din,
+                    dout,
+                    &MockProvider::new(),
+                    &MockVerifier::new(),
+                )
+                .await
+                .unwrap_or_else(|e| panic!("stage {i} failed: {e}"));
+        }));
+    }
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(orch_ctrls, &provider, &verifier).await.unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, relays, &provider, &verifier)
+        .await
+        .unwrap();
+    (orch, fail_next, handles)
+}
+
+fn names(tensors: &[OwnedTensor]) -> Vec<&str> {
+    tensors.iter().map(|t| t.name.as_str()).collect()
+}
+
+/// Both branches see stage 0's output, and stage 3 receives their outputs
+/// concatenated in `upstream` order.
+#[tokio::test]
+async fn diamond_fans_out_and_in() {
+    let (mut orch, _fail, handles) = start_diamond().await;
+    orch.health_check().await.unwrap();
+
+    let inputs = (0..3).map(|_| vec![make_input()]).collect();
+    let result = orch.infer(inputs, 16).await.unwrap();
+
+    assert_eq!(result.outputs.len(), 3);
+    for (mb, out) in result.outputs.iter().enumerate() {
+        assert_eq!(
+            names(out),
+            vec!["x", "s0", "s1", "x", "s0", "s2", "s3"],
+            "micro-batch {mb}"
+        );
+        assert!(out
+            .iter()
+            .filter(|t| t.name != "x")
+            .all(|t| t.data[..] == [mb as u8]));
+    }
+    assert_eq!(result.stage_timings.len(), 4);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// A failure on one branch fails the request, and stage 3 discards what the
+/// other branch already sent so the next request starts clean.
+#[tokio::test]
+async fn diamond_branch_failure_leaves_pipeline_usable() {
+    let (mut orch, fail, handles) = start_diamond().await;
+
+    fail.store(true, Ordering::SeqCst);
+    let inputs = (0..3).map(|_| vec![make_input()]).collect();
+    let result = orch.infer(inputs, 16).await;
+    assert!(
+        matches!(&result, Err(PipelineError::RequestFailed { .. })),
+        "expected RequestFailed, got: {result:?}"
+    );
+
+    let inputs = (0..2).map(|_| vec![make_input()]).collect();
+    let result = orch.infer(inputs, 16).await.unwrap();
+    assert_eq!(result.outputs.len(), 2);
+    assert_eq!(
+        names(&result.outputs[1]),
+        vec!["x", "s0", "s1", "x", "s0", "s2", "s3"]
+    );
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
@@ -0,0 +1,371 @@
+#![cfg(feature = "mock")]
+
+//! Tests for driving an orchestrator through a shared `OrchestratorHandle`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+
+use confidential_ml_pipeline::{
+    ForwardOutput, Orchestrator, OrchestratorConfig, OrchestratorHandle, PipelineError, RequestId,
+    StageConfig, StageError, StageExecutor, StageRuntime, StageSpec,
+};
+
+mod common;
+use common::make_test_manifest;
+
+/// Executor that sleeps for a fixed duration, then passes inputs through.
+struct DelayExecutor(Duration);
+
+#[async_trait]
+impl StageExecutor for DelayExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        _micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        tokio::time::sleep(self.0).await;
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+fn make_test_tensor(name: &str) -> OwnedTensor {
+    OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![0u8; 16]),
+    }
+}
+
+/// Set up an N-stage duplex pipeline where every stage sleeps `delay` per forward.
+async fn setup_pipeline(
+    num_stages: usize,
+    delay: Duration,
+    config: OrchestratorConfig,
+) -> (
+    Orchestrator<tokio::io::DuplexStream>,
+    Vec<tokio::task::JoinHandle<()>>,
+) {
+    let manifest = make_test_manifest(num_stages);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+
+    let mut orch_ctrls = Vec::new();
+    let mut stage_ctrls = Vec::new();
+    for _ in 0..num_stages {
+        let (orch_side, stage_side) = tokio::io::duplex(262144);
+        orch_ctrls.push(orch_side);
+        stage_ctrls.push(stage_side);
+    }
+
+    // Data links: orchestrator -> stage 0 -> ... -> stage N-1 -> orchestrator.
+    let (orch_data_in, mut next_data_in) = tokio::io::duplex(262144);
+    let mut handles = Vec::new();
+    for ctrl in stage_ctrls {
+        let (data_out, downstream_in) = tokio::io::duplex(262144);
+        let data_in = std::mem::replace(&mut next_data_in, downstream_in);
+        handles.push(tokio::spawn(async move {
+            let provider = MockProvider::new();
+            let verifier = MockVerifier::new();
+            let mut runtime = StageRuntime::new(DelayExecutor(delay), StageConfig::development());
+            let _ = runtime
+                .run(ctrl, data_in, data_out, &provider, &verifier)
+                .await;
+        }));
+    }
+    // The far end of the last stage's data_out is the orchestrator's data_out.
+    let orch_data_out = next_data_in;
+
+    let mut orch = Orchestrator::new(config, manifest).unwrap();
+    orch.init(orch_ctrls, &provider, &verifier).await.unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    (orch, handles)
+}
+
+fn assert_send_sync_clone<T: Send + Sync + Clone + 'static>() {}
+
+#[test]
+fn handle_is_send_sync_clone() {
+    assert_send_sync_clone::<OrchestratorHandle>();
+}
+
+/// Many tasks sharing cloned handles each get their own outputs back.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_infers_through_cloned_handles() {
+    let (orch, stages) = setup_pipeline(
+        3,
+        Duration::from_millis(5),
+        OrchestratorConfig::development(),
+    )
+    .await;
+    let (handle, task) = OrchestratorHandle::spawn(orch);
+
+    let callers: Vec<_> = (0..16)
+        .map(|i| {
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                let name = format!("caller{i}");
+                let result = handle
+                    .infer(vec![vec![make_test_tensor(&name)]], 16)
+                    .await
+                    .unwrap_or_else(|e| panic!("caller {i} failed: {e}"));
+                assert_eq!(result.outputs[0][0].name, name);
+            })
+        })
+        .collect();
+    for c in callers {
+        c.await.unwrap();
+    }
+
+    handle.health_check().await.unwrap();
+    let status = handle.status().await.unwrap();
+    assert_eq!(status.in_flight, 0);
+    assert!(!status.tainted);
+
+    handle.shutdown().await.unwrap();
+    let orch = task.await.unwrap();
+    assert_eq!(orch.in_flight(), 0);
+    for h in stages {
+        h.await.unwrap();
+    }
+}
+
+/// Requests arriving while others are in flight share the pipeline with them.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn handle_keeps_requests_in_flight_together() {
+    let (orch, stages) = setup_pipeline(
+        3,
+        Duration::from_millis(100),
+        OrchestratorConfig::development(),
+    )
+    .await;
+    let (handle, task) = OrchestratorHandle::spawn(orch);
+
+    let start = tokio::time::Instant::now();
+    let callers: Vec<_> = (0..4)
+        .map(|i| {
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                handle
+                    .infer(vec![vec![make_test_tensor(&format!("r{i}"))]], 16)
+                    .await
+            })
+        })
+        .collect();
+    for c in callers {
+        c.await.unwrap().unwrap();
+    }
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed < Duration::from_millis(1000),
+        "expected pipelined execution, took {elapsed:?}"
+    );
+
+    handle.shutdown().await.unwrap();
+    task.await.unwrap();
+    for h in stages {
+        h.await.unwrap();
+    }
+}
+
+/// After shutdown every handle reports Shutdown instead of hanging.
+#[tokio::test]
+async fn calls_after_shutdown_fail() {
+    let (orch, stages) = setup_pipeline(2, Duration::ZERO, OrchestratorConfig::development()).await;
+    let (handle, task) = OrchestratorHandle::spawn(orch);
+    let other = handle.clone();
+
+    handle.shutdown().await.unwrap();
+    task.await.unwrap();
+
+    let err = other
+        .infer(vec![vec![make_test_tensor("late")]], 16)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, PipelineError::Shutdown),
+        "expected Shutdown, got {err:?}"
+    );
+    assert!(matches!(other.status().await, Err(PipelineError::Shutdown)));
+    for h in stages {
+        h.await.unwrap();
+    }
+}
+
+/// Request errors reach the caller; the task keeps serving afterwards.
+#[tokio::test]
+async fn request_errors_are_returned_to_caller() {
+    let (orch, stages) = setup_pipeline(2, Duration::ZERO, OrchestratorConfig::development()).await;
+    let (handle, task) = OrchestratorHandle::spawn(orch);
+
+    // seq_len above the manifest's max_seq_len is rejected before submission.
+    let err = handle
+        .infer(vec![vec![make_test_tensor("too_long")]], 1024)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, PipelineError::Protocol(_)),
+        "expected Protocol, got {err:?}"
+    );
+
+    let ok = handle
+        .infer(vec![vec![make_test_tensor("fine")]], 16)
+        .await
+        .unwrap();
+    assert_eq!(ok.outputs[0][0].name, "fine");
+
+    // Dropping the last handle stops the task and hands the orchestrator back.
+    drop(handle);
+    let mut orch = task.await.unwrap();
+    orch.shutdown().await.unwrap();
+    for h in stages {
+        h.await.unwrap();
+    }
+}
+
+/// Status queries and new requests are served while a slow request is in
+/// flight, instead of waiting for it to finish.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn commands_are_served_while_requests_are_in_flight() {
+    let (orch, stages) = setup_pipeline(
+        2,
+        Duration::from_millis(300),
+        OrchestratorConfig::development(),
+    )
+    .await;
+    let (handle, task) = OrchestratorHandle::spawn(orch);
+
+    let spawn_infer = |name: &'static str| {
+        let handle = handle.clone();
+        tokio::spawn(async move { handle.infer(vec![vec![make_test_tensor(name)]], 16).await })
+    };
+
+    let first = spawn_infer("first");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let start = tokio::time::Instant::now();
+    let status = handle.status().await.unwrap();
+    assert!(
+        start.elapsed() < Duration::from_millis(200),
+        "status waited for the in-flight request: {:?}",
+        start.elapsed()
+    );
+    assert_eq!(status.in_flight, 1);
+
+    let second = spawn_infer("second");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let status = handle.status().await.unwrap();
+    assert_eq!(status.in_flight, 2, "second request was not submitted");
+    handle.health_check().await.unwrap();
+
+    assert_eq!(first.await.unwrap().unwrap().outputs[0][0].name, "first");
+    assert_eq!(second.await.unwrap().unwrap().outputs[0][0].name, "second");
+
+    handle.shutdown().await.unwrap();
+    task.await.unwrap();
+    for h in stages {
+        h.await.unwrap();
+    }
+}
+
+/// A 256 KiB tensor whose bytes depend on `seed`, so a frame that loses or
+/// gains bytes on the way shows up as a mismatch.
+fn make_large_tensor(name: &str, seed: u8) -> OwnedTensor {
+    let data: Vec<u8> = (0..256 * 1024)
+        .map(|i: usize| (i % 251) as u8 ^ seed)
+        .collect();
+    OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 64 * 1024],
+        data: Bytes::from(data),
+    }
+}
+
+/// A steady stream of commands while large, multi-frame outputs are being
+/// received neither corrupts those outputs nor desynchronises data_out.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn commands_do_not_interrupt_large_outputs() {
+    let (orch, stages) = setup_pipeline(
+        2,
+        Duration::from_millis(2),
+        OrchestratorConfig::development(),
+    )
+    .await;
+    let (handle, task) = OrchestratorHandle::spawn(orch);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let chatter = {
+        let handle = handle.clone();
+        let stop = stop.clone();
+        tokio::spawn(async move {
+            let mut sent = 0u32;
+            while !stop.load(Ordering::Relaxed) {
+                handle.status().await.unwrap();
+                if sent % 16 == 0 {
+                    handle.health_check().await.unwrap();
+                }
+                sent += 1;
+            }
+            sent
+        })
+    };
+
+    let callers: Vec<_> = (0..8u8)
+        .map(|i| {
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                let inputs: Vec<Vec<OwnedTensor>> = (0..3u8)
+                    .map(|mb| {
+                        vec![
+                            make_large_tensor(&format!("r{i}m{mb}a"), i ^ mb),
+                            make_large_tensor(&format!("r{i}m{mb}b"), !(i ^ mb)),
+                        ]
+                    })
+                    .collect();
+                let result = handle.infer(inputs.clone(), 16).await.unwrap();
+                assert_eq!(result.outputs.len(), inputs.len());
+                for (got, sent) in result.outputs.iter().zip(&inputs) {
+                    assert_eq!(got.len(), sent.len());
+                    for (g, s) in got.iter().zip(sent) {
+                        assert_eq!(g.name, s.name);
+                        assert!(g.data == s.data, "{} came back altered", s.name);
+                    }
+                }
+            })
+        })
+        .collect();
+    for c in callers {
+        c.await.unwrap();
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    let sent = chatter.await.unwrap();
+    assert!(
+        sent > 8,
+        "only {sent} commands were sent during the requests"
+    );
+
+    let status = handle.status().await.unwrap();
+    assert_eq!(status.in_flight, 0);
+    assert!(!status.tainted);
+
+    handle.shutdown().await.unwrap();
+    task.await.unwrap();
+    for h in stages {
+        h.await.unwrap();
+    }
+}
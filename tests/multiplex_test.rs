@@ -0,0 +1,239 @@
+#![cfg(feature = "mock")]
+
+//! Tests for keeping several inference requests in flight at once.
+
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+
+use confidential_ml_pipeline::{
+    ForwardOutput, Orchestrator, OrchestratorConfig, PipelineError, RequestId, StageConfig,
+    StageError, StageExecutor, StageRuntime, StageSpec,
+};
+
+mod common;
+use common::make_test_manifest;
+
+/// Executor that sleeps for a fixed duration, then passes inputs through.
+struct DelayExecutor(Duration);
+
+#[async_trait]
+impl StageExecutor for DelayExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        _micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        tokio::time::sleep(self.0).await;
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+fn make_test_tensor(name: &str) -> OwnedTensor {
+    OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![0u8; 16]),
+    }
+}
+
+/// Set up an N-stage duplex pipeline where every stage sleeps `delay` per forward.
+async fn setup_pipeline(
+    num_stages: usize,
+    delay: Duration,
+    config: OrchestratorConfig,
+) -> (
+    Orchestrator<tokio::io::DuplexStream>,
+    Vec<tokio::task::JoinHandle<()>>,
+) {
+    let manifest = make_test_manifest(num_stages);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+
+    let mut orch_ctrls = Vec::new();
+    let mut stage_ctrls = Vec::new();
+    for _ in 0..num_stages {
+        let (orch_side, stage_side) = tokio::io::duplex(262144);
+        orch_ctrls.push(orch_side);
+        stage_ctrls.push(stage_side);
+    }
+
+    // Data links: orchestrator -> stage 0 -> ... -> stage N-1 -> orchestrator.
+    let (orch_data_in, mut next_data_in) = tokio::io::duplex(262144);
+    let mut handles = Vec::new();
+    for ctrl in stage_ctrls {
+        let (data_out, downstream_in) = tokio::io::duplex(262144);
+        let data_in = std::mem::replace(&mut next_data_in, downstream_in);
+        handles.push(tokio::spawn(async move {
+            let provider = MockProvider::new();
+            let verifier = MockVerifier::new();
+            let mut runtime = StageRuntime::new(DelayExecutor(delay), StageConfig::development());
+            let _ = runtime
+                .run(ctrl, data_in, data_out, &provider, &verifier)
+                .await;
+        }));
+    }
+    // The far end of the last stage's data_out is the orchestrator's data_out.
+    let orch_data_out = next_data_in;
+
+    let mut orch = Orchestrator::new(config, manifest).unwrap();
+    orch.init(orch_ctrls, &provider, &verifier).await.unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    (orch, handles)
+}
+
+/// Results from infer_many come back in request order with the right outputs.
+#[tokio::test]
+async fn infer_many_preserves_request_order() {
+    let (mut orch, handles) =
+        setup_pipeline(3, Duration::ZERO, OrchestratorConfig::development()).await;
+
+    let requests = (0..10)
+        .map(|i| {
+            let mbs = (0..(i % 3 + 1))
+                .map(|mb| vec![make_test_tensor(&format!("req{i}_mb{mb}"))])
+                .collect();
+            (mbs, 16)
+        })
+        .collect();
+
+    let results = orch.infer_many(requests).await.unwrap();
+    assert_eq!(results.len(), 10);
+    for (i, result) in results.into_iter().enumerate() {
+        let result = result.unwrap_or_else(|e| panic!("request {i} failed: {e}"));
+        assert_eq!(result.outputs.len(), i % 3 + 1);
+        for (mb, tensors) in result.outputs.iter().enumerate() {
+            assert_eq!(tensors[0].name, format!("req{i}_mb{mb}"));
+        }
+    }
+    assert_eq!(orch.in_flight(), 0);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// Waiting out of submission order returns each caller its own result.
+#[tokio::test]
+async fn wait_out_of_order_matches_request_ids() {
+    let (mut orch, handles) =
+        setup_pipeline(2, Duration::ZERO, OrchestratorConfig::development()).await;
+
+    let a = orch
+        .submit(vec![vec![make_test_tensor("a")]], 16)
+        .await
+        .unwrap();
+    let b = orch
+        .submit(vec![vec![make_test_tensor("b")]], 16)
+        .await
+        .unwrap();
+    let c = orch
+        .submit(vec![vec![make_test_tensor("c")]], 16)
+        .await
+        .unwrap();
+    assert_eq!(orch.in_flight(), 3);
+
+    // Health check with requests in flight must not swallow their replies.
+    orch.health_check().await.unwrap();
+
+    let rc = orch.wait(c).await.unwrap();
+    assert_eq!(rc.outputs[0][0].name, "c");
+    assert_eq!(orch.in_flight(), 0);
+
+    let ra = orch.wait(a).await.unwrap();
+    assert_eq!(ra.outputs[0][0].name, "a");
+    let rb = orch.wait(b).await.unwrap();
+    assert_eq!(rb.outputs[0][0].name, "b");
+
+    // A plain infer still works afterwards.
+    let r = orch
+        .infer(vec![vec![make_test_tensor("d")]], 16)
+        .await
+        .unwrap();
+    assert_eq!(r.outputs[0][0].name, "d");
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// Submitting beyond max_in_flight is rejected without touching the pipeline.
+#[tokio::test]
+async fn submit_beyond_limit_is_rejected() {
+    let config = OrchestratorConfig {
+        max_in_flight: 2,
+        ..OrchestratorConfig::development()
+    };
+    let (mut orch, handles) = setup_pipeline(2, Duration::ZERO, config).await;
+
+    let a = orch
+        .submit(vec![vec![make_test_tensor("a")]], 16)
+        .await
+        .unwrap();
+    let b = orch
+        .submit(vec![vec![make_test_tensor("b")]], 16)
+        .await
+        .unwrap();
+    let err = orch
+        .submit(vec![vec![make_test_tensor("c")]], 16)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, PipelineError::TooManyInFlight { limit: 2 }),
+        "expected TooManyInFlight, got {err:?}"
+    );
+
+    assert_eq!(orch.wait(a).await.unwrap().outputs[0][0].name, "a");
+    assert_eq!(orch.wait(b).await.unwrap().outputs[0][0].name, "b");
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// With several requests in flight, stages overlap: 4 requests through 3
+/// stages of 100ms each take ~600ms instead of ~1200ms sequentially.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn in_flight_requests_overlap_across_stages() {
+    let (mut orch, handles) = setup_pipeline(
+        3,
+        Duration::from_millis(100),
+        OrchestratorConfig::development(),
+    )
+    .await;
+
+    let requests = (0..4)
+        .map(|i| (vec![vec![make_test_tensor(&format!("r{i}"))]], 16))
+        .collect();
+
+    let start = Instant::now();
+    let results = orch.infer_many(requests).await.unwrap();
+    let elapsed = start.elapsed();
+
+    for result in &results {
+        assert!(result.is_ok(), "request failed: {result:?}");
+    }
+    assert!(
+        elapsed < Duration::from_millis(1000),
+        "expected pipelined execution, took {elapsed:?}"
+    );
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
@@ -0,0 +1,296 @@
+#![cfg(feature = "mock")]
+
+//! Tests for per-request options: deadlines, priority and caller request IDs.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+
+use confidential_ml_pipeline::{
+    ForwardOutput, Orchestrator, OrchestratorConfig, OrchestratorHandle, PipelineError, RequestId,
+    RequestOptions, StageConfig, StageError, StageExecutor, StageRuntime, StageSpec,
+};
+
+mod common;
+use common::make_test_manifest;
+
+/// Executor that sleeps per forward and counts forwards.
+struct SlowExecutor {
+    delay: Duration,
+    forwards: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl StageExecutor for SlowExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        _micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        self.forwards.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+fn make_test_tensor(name: &str) -> OwnedTensor {
+    OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![0u8; 16]),
+    }
+}
+
+fn micro_batches(prefix: &str, n: usize) -> Vec<Vec<OwnedTensor>> {
+    (0..n)
+        .map(|mb| vec![make_test_tensor(&format!("{prefix}_mb{mb}"))])
+        .collect()
+}
+
+/// Set up an N-stage duplex pipeline of `SlowExecutor`s sharing one forward counter.
+async fn setup_pipeline(
+    num_stages: usize,
+    delay: Duration,
+    config: OrchestratorConfig,
+) -> (
+    Orchestrator<tokio::io::DuplexStream>,
+    Vec<tokio::task::JoinHandle<()>>,
+    Arc<AtomicUsize>,
+) {
+    let manifest = make_test_manifest(num_stages);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+    let forwards = Arc::new(AtomicUsize::new(0));
+
+    let mut orch_ctrls = Vec::new();
+    let mut stage_ctrls = Vec::new();
+    for _ in 0..num_stages {
+        let (orch_side, stage_side) = tokio::io::duplex(262144);
+        orch_ctrls.push(orch_side);
+        stage_ctrls.push(stage_side);
+    }
+
+    // Data links: orchestrator -> stage 0 -> ... -> stage N-1 -> orchestrator.
+    let (orch_data_in, mut next_data_in) = tokio::io::duplex(262144);
+    let mut handles = Vec::new();
+    for ctrl in stage_ctrls {
+        let (data_out, downstream_in) = tokio::io::duplex(262144);
+        let data_in = std::mem::replace(&mut next_data_in, downstream_in);
+        let executor = SlowExecutor {
+            delay,
+            forwards: forwards.clone(),
+        };
+        handles.push(tokio::spawn(async move {
+            let provider = MockProvider::new();
+            let verifier = MockVerifier::new();
+            let mut runtime = StageRuntime::new(executor, StageConfig::development());
+            let _ = runtime
+                .run(ctrl, data_in, data_out, &provider, &verifier)
+                .await;
+        }));
+    }
+    let orch_data_out = next_data_in;
+
+    let mut orch = Orchestrator::new(config, manifest).unwrap();
+    orch.init(orch_ctrls, &provider, &verifier).await.unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    (orch, handles, forwards)
+}
+
+fn assert_outputs(outputs: &[Vec<OwnedTensor>], prefix: &str, n: usize) {
+    assert_eq!(outputs.len(), n);
+    for (mb, tensors) in outputs.iter().enumerate() {
+        assert_eq!(tensors[0].name, format!("{prefix}_mb{mb}"));
+    }
+}
+
+/// A missed deadline cancels only that request; stages stop early and the
+/// pipeline stays usable.
+#[tokio::test]
+async fn missed_deadline_cancels_request() {
+    let (mut orch, handles, forwards) = setup_pipeline(
+        3,
+        Duration::from_millis(50),
+        OrchestratorConfig::development(),
+    )
+    .await;
+
+    let err = orch
+        .infer_with_options(
+            micro_batches("slow", 8),
+            16,
+            RequestOptions::with_timeout(Duration::from_millis(150)),
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, PipelineError::DeadlineExceeded { .. }),
+        "expected DeadlineExceeded, got {err:?}"
+    );
+    assert!(!orch.is_tainted());
+    let done = forwards.load(Ordering::SeqCst);
+    assert!(done < 3 * 8, "stages ignored the deadline: {done} forwards");
+
+    let result = orch
+        .infer_with_options(
+            micro_batches("next", 2),
+            16,
+            RequestOptions::with_timeout(Duration::from_secs(5)),
+        )
+        .await
+        .unwrap();
+    assert_outputs(&result.outputs, "next", 2);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// A request whose deadline passes while it is queued behind another is
+/// refused by every stage without running.
+#[tokio::test]
+async fn stages_refuse_expired_queued_request() {
+    let (mut orch, handles, forwards) = setup_pipeline(
+        3,
+        Duration::from_millis(50),
+        OrchestratorConfig::development(),
+    )
+    .await;
+
+    let long = orch.submit(micro_batches("long", 4), 16).await.unwrap();
+    let short = orch
+        .submit_with_options(
+            micro_batches("short", 2),
+            16,
+            RequestOptions::with_timeout(Duration::from_millis(60)),
+        )
+        .await
+        .unwrap();
+
+    let result = orch.wait(long).await.unwrap();
+    assert_outputs(&result.outputs, "long", 4);
+    let err = orch.wait(short).await.unwrap_err();
+    assert!(
+        matches!(err, PipelineError::DeadlineExceeded { request_id } if request_id == short),
+        "expected DeadlineExceeded, got {err:?}"
+    );
+    assert_eq!(forwards.load(Ordering::SeqCst), 3 * 4);
+    assert!(!orch.is_tainted());
+
+    // A deadline that has already passed is refused before anything is sent.
+    let err = orch
+        .infer_with_options(
+            micro_batches("late", 1),
+            16,
+            RequestOptions::with_timeout(Duration::ZERO),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PipelineError::DeadlineExceeded { .. }));
+    assert_eq!(orch.in_flight(), 0);
+    assert_eq!(forwards.load(Ordering::SeqCst), 3 * 4);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// A caller-supplied request ID is used as-is and must be unique.
+#[tokio::test]
+async fn caller_request_id() {
+    let (mut orch, handles, _forwards) =
+        setup_pipeline(2, Duration::ZERO, OrchestratorConfig::development()).await;
+
+    let options = RequestOptions {
+        request_id: Some(4242),
+        ..RequestOptions::default()
+    };
+    let id = orch
+        .submit_with_options(micro_batches("mine", 1), 16, options.clone())
+        .await
+        .unwrap();
+    assert_eq!(id, 4242);
+
+    let err = orch
+        .submit_with_options(micro_batches("dup", 1), 16, options.clone())
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, PipelineError::Protocol(ref msg) if msg.contains("already in use")),
+        "expected duplicate ID error, got {err:?}"
+    );
+
+    let result = orch.wait(4242).await.unwrap();
+    assert_outputs(&result.outputs, "mine", 1);
+
+    // Free again once collected.
+    let result = orch
+        .infer_with_options(micro_batches("again", 1), 16, options)
+        .await
+        .unwrap();
+    assert_outputs(&result.outputs, "again", 1);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// Through a handle, a high-priority request waiting for a slot starts
+/// before an earlier low-priority one.
+#[tokio::test]
+async fn handle_starts_higher_priority_first() {
+    let config = OrchestratorConfig {
+        max_in_flight: 1,
+        ..OrchestratorConfig::development()
+    };
+    let (orch, stage_handles, _forwards) =
+        setup_pipeline(2, Duration::from_millis(50), config).await;
+    let (handle, task) = OrchestratorHandle::spawn(orch);
+    let finished = Arc::new(Mutex::new(Vec::new()));
+
+    let mut callers = Vec::new();
+    for (name, priority) in [("first", 0), ("low", 0), ("high", 5)] {
+        let handle = handle.clone();
+        let finished = finished.clone();
+        callers.push(tokio::spawn(async move {
+            let options = RequestOptions {
+                priority,
+                ..RequestOptions::default()
+            };
+            let result = handle
+                .infer_with_options(micro_batches(name, 1), 16, options)
+                .await
+                .unwrap();
+            assert_outputs(&result.outputs, name, 1);
+            finished.lock().unwrap().push(name);
+        }));
+        // Let "first" occupy the only slot before the others queue up.
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    for caller in callers {
+        caller.await.unwrap();
+    }
+    assert_eq!(*finished.lock().unwrap(), vec!["first", "high", "low"]);
+
+    handle.shutdown().await.unwrap();
+    task.await.unwrap();
+    for h in stage_handles {
+        h.await.unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+#![cfg(feature = "mock")]
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+use tokio::io::DuplexStream;
+
+use confidential_ml_pipeline::{
+    ActivationDType, ActivationSpec, ForwardOutput, Orchestrator, OrchestratorConfig,
+    PipelineError, RequestId, ShardManifest, StageConfig, StageError, StageExecutor, StageRuntime,
+    StageSpec,
+};
+
+mod common;
+use common::test_endpoint;
+
+/// Identity executor that fails if it sees a "mask" tensor when it should
+/// not, or misses one when it should. Optionally emits its own "mask".
+struct MaskExecutor {
+    expect_mask: bool,
+    emit_mask: bool,
+}
+
+#[async_trait]
+impl StageExecutor for MaskExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        request_id: RequestId,
+        micro_batch: u32,
+        mut inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        let has_mask = inputs.iter().any(|t| t.name == "mask");
+        if has_mask != self.expect_mask {
+            return Err(StageError::ForwardFailed {
+                request_id,
+                micro_batch,
+                reason: format!("mask present: {has_mask}"),
+            });
+        }
+        if self.emit_mask {
+            inputs.push(make_tensor("mask", 0));
+        }
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+fn make_tensor(name: &str, fill: u8) -> OwnedTensor {
+    OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![fill; 16]),
+    }
+}
+
+fn make_test_manifest(pass_through: &[&[&str]]) -> ShardManifest {
+    let stages = pass_through
+        .iter()
+        .enumerate()
+        .map(|(i, names)| StageSpec {
+            pass_through: names.iter().map(|n| n.to_string()).collect(),
+            ..StageSpec::linear(i, i * 4..(i + 1) * 4, test_endpoint(i))
+        })
+        .collect();
+
+    ShardManifest {
+        model_name: "test-model".into(),
+        model_version: "1.0".into(),
+        total_layers: pass_through.len() * 4,
+        stages,
+        activation_spec: ActivationSpec {
+            dtype: ActivationDType::F32,
+            hidden_dim: 4,
+            max_seq_len: 16,
+        },
+    }
+}
+
+/// Start a linear pipeline with one executor per manifest stage.
+async fn start_pipeline(
+    manifest: ShardManifest,
+    executors: Vec<MaskExecutor>,
+) -> (Orchestrator<DuplexStream>, Vec<tokio::task::JoinHandle<()>>) {
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+
+    // Link i carries stage i - 1's output to stage i; the first and last
+    // links end at the orchestrator.
+    let (mut senders, mut receivers): (Vec<_>, Vec<_>) = (0..=executors.len())
+        .map(|_| tokio::io::duplex(65536))
+        .unzip();
+    let orch_data_out = receivers.pop().unwrap();
+    let orch_data_in = senders.remove(0);
+
+    let mut orch_ctrls = Vec::new();
+    let mut handles = Vec::new();
+    for (i, ((executor, data_in), data_out)) in executors
+        .into_iter()
+        .zip(receivers)
+        .zip(senders)
+        .enumerate()
+    {
+        let (orch_ctrl, stage_ctrl) = tokio::io::duplex(65536);
+        orch_ctrls.push(orch_ctrl);
+        handles.push(tokio::spawn(async move {
+            let mut runtime = StageRuntime::new(executor, StageConfig::development());
+            runtime
+                .run(
+                    stage_ctrl,
+                    data_in,
+                    data_out,
+                    &MockProvider::new(),
+                    &MockVerifier::new(),
+                )
+                .await
+                .unwrap_or_else(|e| panic!("stage {i} failed: {e}"));
+        }));
+    }
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(orch_ctrls, &provider, &verifier).await.unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+    (orch, handles)
+}
+
+fn executors(expect_mask: &[bool]) -> Vec<MaskExecutor> {
+    expect_mask
+        .iter()
+        .map(|&expect_mask| MaskExecutor {
+            expect_mask,
+            emit_mask: false,
+        })
+        .collect()
+}
+
+/// Stage 0's "mask" input reaches stage 3 without stages 1 and 2 seeing it.
+#[tokio::test]
+async fn pass_through_skips_intermediate_executors() {
+    let manifest = make_test_manifest(&[&[], &["mask"], &["mask"], &[]]);
+    let (mut orch, handles) =
+        start_pipeline(manifest, executors(&[true, false, false, true])).await;
+
+    let inputs = (0..3)
+        .map(|mb| vec![make_tensor("hidden", 0), make_tensor("mask", mb + 1)])
+        .collect();
+    let result = orch.infer(inputs, 16).await.unwrap();
+
+    assert_eq!(result.outputs.len(), 3);
+    for (mb, out) in result.outputs.iter().enumerate() {
+        let names: Vec<_> = out.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["hidden", "mask"]);
+        assert_eq!(out[1].data, Bytes::from(vec![mb as u8 + 1; 16]));
+    }
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// A stage whose declared pass-through tensor does not arrive fails the
+/// request, and the pipeline serves the next one.
+#[tokio::test]
+async fn missing_pass_through_fails_request() {
+    let manifest = make_test_manifest(&[&["mask"], &[]]);
+    let (mut orch, handles) = start_pipeline(manifest, executors(&[false, true])).await;
+
+    let result = orch
+        .infer(vec![vec![make_tensor("hidden", 0)]; 2], 16)
+        .await;
+    match result {
+        Err(PipelineError::RequestFailed { reason, .. }) => {
+            assert!(reason.contains("pass-through"), "{reason}")
+        }
+        other => panic!("expected RequestFailed, got: {other:?}"),
+    }
+
+    let inputs = vec![vec![make_tensor("hidden", 0), make_tensor("mask", 1)]; 2];
+    let result = orch.infer(inputs, 16).await.unwrap();
+    assert_eq!(result.outputs[1].len(), 2);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// An executor may not produce a tensor its stage passes through.
+#[tokio::test]
+async fn executor_output_conflicting_with_pass_through_fails() {
+    let manifest = make_test_manifest(&[&["mask"]]);
+    let executor = MaskExecutor {
+        expect_mask: false,
+        emit_mask: true,
+    };
+    let (mut orch, handles) = start_pipeline(manifest, vec![executor]).await;
+
+    let inputs = vec![vec![make_tensor("hidden", 0), make_tensor("mask", 1)]];
+    let result = orch.infer(inputs, 16).await;
+    assert!(
+        matches!(&result, Err(PipelineError::RequestFailed { reason, .. }) if reason.contains("already has")),
+        "expected RequestFailed, got: {result:?}"
+    );
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
@@ -1,17 +1,22 @@
 #![cfg(feature = "mock")]
 
-use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
 
 use confidential_ml_pipeline::{
-    ActivationDType, ActivationSpec, ForwardOutput, Orchestrator, OrchestratorConfig, PortSpec,
-    RequestId, ShardManifest, StageConfig, StageEndpoint, StageError, StageExecutor, StageRuntime,
-    StageSpec,
+    merge_outputs, synthetic_inputs, CostModel, FillDrain, ForwardOutput, InferenceSchedule,
+    LayerSample, LinkCost, MicroBatchPlanner, ModelProfile, Orchestrator, OrchestratorConfig,
+    PipeOp, PipelineError, ProfileError, RequestId, ScheduleKind, ScheduleStrategy, SchedulerError,
+    StageConfig, StageError, StageExecutor, StageRuntime, StageSchedule, StageSpec,
 };
 
+mod common;
+use common::make_test_manifest;
+
 /// Identity executor: passes input tensors through unchanged.
 struct IdentityExecutor;
 
@@ -31,42 +36,6 @@ impl StageExecutor for IdentityExecutor {
     }
 }
 
-fn make_test_manifest(num_stages: usize) -> ShardManifest {
-    let stages = (0..num_stages)
-        .map(|i| StageSpec {
-            stage_idx: i,
-            layer_start: i * 4,
-            layer_end: (i + 1) * 4,
-            weight_hashes: vec![],
-            require_weight_hashes: false,
-            expected_measurements: BTreeMap::new(),
-            endpoint: StageEndpoint {
-                control: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 9000 + i * 10),
-                },
-                data_in: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 9001 + i * 10),
-                },
-                data_out: PortSpec::Tcp {
-                    addr: format!("127.0.0.1:{}", 9002 + i * 10),
-                },
-            },
-        })
-        .collect();
-
-    ShardManifest {
-        model_name: "test-model".into(),
-        model_version: "1.0".into(),
-        total_layers: num_stages * 4,
-        stages,
-        activation_spec: ActivationSpec {
-            dtype: ActivationDType::F32,
-            hidden_dim: 4,
-            max_seq_len: 16,
-        },
-    }
-}
-
 fn make_test_tensor(name: &str) -> OwnedTensor {
     OwnedTensor {
         name: name.to_string(),
@@ -233,6 +202,449 @@ async fn two_stage_two_micro_batches() {
     stage1_handle.await.unwrap();
 }
 
+/// Executor that sleeps for a fixed time per forward pass.
+struct DelayExecutor(Duration);
+
+#[async_trait]
+impl StageExecutor for DelayExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        _micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        tokio::time::sleep(self.0).await;
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+/// Stages report per-micro-batch timings, which show where time was spent.
+#[tokio::test]
+async fn inference_result_reports_stage_timings() {
+    let manifest = make_test_manifest(2);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+    let slow = Duration::from_millis(30);
+
+    let (orch_ctrl0, stage0_ctrl) = tokio::io::duplex(65536);
+    let (orch_ctrl1, stage1_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage0_data_in) = tokio::io::duplex(65536);
+    let (stage0_data_out, stage1_data_in) = tokio::io::duplex(65536);
+    let (stage1_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+    let stage0_handle = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        let mut runtime = StageRuntime::new(IdentityExecutor, StageConfig::development());
+        runtime
+            .run(
+                stage0_ctrl,
+                stage0_data_in,
+                stage0_data_out,
+                &provider,
+                &verifier,
+            )
+            .await
+            .unwrap();
+    });
+
+    let stage1_handle = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        let mut runtime = StageRuntime::new(DelayExecutor(slow), StageConfig::development());
+        runtime
+            .run(
+                stage1_ctrl,
+                stage1_data_in,
+                stage1_data_out,
+                &provider,
+                &verifier,
+            )
+            .await
+            .unwrap();
+    });
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(vec![orch_ctrl0, orch_ctrl1], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    let input = vec![vec![make_test_tensor("mb0")], vec![make_test_tensor("mb1")]];
+    let request_id = orch.submit(input, 16).await.unwrap();
+    let result = orch.wait(request_id).await.unwrap();
+
+    assert_eq!(result.request_id, request_id);
+    assert_eq!(result.stage_timings.len(), 2);
+    for timings in &result.stage_timings {
+        assert_eq!(timings.len(), 2);
+    }
+    for mb in 0..2 {
+        assert!(result.stage_timings[0][mb].forward() < slow);
+        assert!(result.stage_timings[1][mb].forward() >= slow);
+    }
+    assert!(result.total >= slow * 2);
+
+    // The timings calibrate a simulation that predicts the run.
+    let report = CostModel::from_timings(&result.stage_timings, 0)
+        .simulate(&FillDrain, 2)
+        .unwrap();
+    assert!(report.makespan >= slow * 2);
+    let comparison = report.compare(&result);
+    assert!(comparison.stages[1].predicted_forward >= slow * 2);
+    assert!(
+        comparison.makespan_error().abs() < 0.5,
+        "predicted {:?}, measured {:?}",
+        comparison.predicted_makespan,
+        comparison.measured_makespan
+    );
+
+    orch.shutdown().await.unwrap();
+    stage0_handle.await.unwrap();
+    stage1_handle.await.unwrap();
+}
+
+/// Executor whose first micro-batch waits for `gate` before returning.
+struct GatedExecutor {
+    gate: Arc<tokio::sync::Notify>,
+}
+
+#[async_trait]
+impl StageExecutor for GatedExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        if micro_batch == 0 {
+            self.gate.notified().await;
+        }
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+/// A stage receives the next micro-batch while `forward` is still running
+/// on the current one.
+#[tokio::test]
+async fn stage_prefetches_next_micro_batch_during_forward() {
+    let manifest = make_test_manifest(1);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+    let gate = Arc::new(tokio::sync::Notify::new());
+
+    // A data_in buffer much smaller than one micro-batch: the orchestrator
+    // can only finish sending micro-batch 1 if the stage is reading it.
+    let (orch_ctrl, stage_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage_data_in) = tokio::io::duplex(4096);
+    let (stage_data_out, orch_data_out) = tokio::io::duplex(262144);
+
+    let executor = GatedExecutor { gate: gate.clone() };
+    let stage_handle = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        let mut runtime = StageRuntime::new(executor, StageConfig::development());
+        runtime
+            .run(
+                stage_ctrl,
+                stage_data_in,
+                stage_data_out,
+                &provider,
+                &verifier,
+            )
+            .await
+            .unwrap();
+    });
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(vec![orch_ctrl], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    let large = |name: &str| OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 16384],
+        data: Bytes::from(vec![0u8; 65536]),
+    };
+    let input = vec![vec![large("mb0")], vec![large("mb1")]];
+    let request_id = tokio::time::timeout(Duration::from_secs(5), orch.submit(input, 16))
+        .await
+        .expect("stage did not read micro-batch 1 while forward ran on micro-batch 0")
+        .unwrap();
+
+    gate.notify_one();
+    let result = orch.wait(request_id).await.unwrap();
+    assert_eq!(result.outputs.len(), 2);
+    assert_eq!(result.outputs[1][0].name, "mb1");
+
+    orch.shutdown().await.unwrap();
+    stage_handle.await.unwrap();
+}
+
+/// A stage receives no input before the step its prefetched schedule
+/// issues it at: while `forward` is held on micro-batch 0, only the
+/// micro-batches received at step 0 may leave the orchestrator.
+#[tokio::test]
+async fn stage_receives_no_earlier_than_its_schedule() {
+    let plan = InferenceSchedule::generate(1, 3)
+        .unwrap()
+        .executable_stage(0)
+        .unwrap()
+        .prefetched(StageConfig::development().prefetch_depth);
+    let issued_at_step_0 = plan.ops[0]
+        .iter()
+        .filter(|op| matches!(op, PipeOp::RecvActivation { .. }))
+        .count();
+    assert_eq!(issued_at_step_0, 2);
+
+    let manifest = make_test_manifest(1);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+    let gate = Arc::new(tokio::sync::Notify::new());
+
+    // As above, a data_in buffer much smaller than one micro-batch makes
+    // the orchestrator's sends track the stage's receives.
+    let (orch_ctrl, stage_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage_data_in) = tokio::io::duplex(4096);
+    let (stage_data_out, orch_data_out) = tokio::io::duplex(262144);
+
+    let executor = GatedExecutor { gate: gate.clone() };
+    let stage_handle = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        let mut runtime = StageRuntime::new(executor, StageConfig::development());
+        runtime
+            .run(
+                stage_ctrl,
+                stage_data_in,
+                stage_data_out,
+                &provider,
+                &verifier,
+            )
+            .await
+            .unwrap();
+    });
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(vec![orch_ctrl], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    let large = |name: &str| OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 16384],
+        data: Bytes::from(vec![0u8; 65536]),
+    };
+    let input = vec![vec![large("mb0")], vec![large("mb1")], vec![large("mb2")]];
+    let request_id = {
+        let submit = orch.submit(input, 16);
+        tokio::pin!(submit);
+        tokio::select! {
+            _ = &mut submit => panic!("stage received micro-batch 2 before step 1"),
+            () = tokio::time::sleep(Duration::from_millis(300)) => {}
+        }
+
+        gate.notify_one();
+        tokio::time::timeout(Duration::from_secs(5), submit)
+            .await
+            .expect("stage did not receive micro-batch 2 at step 1")
+            .unwrap()
+    };
+    let result = orch.wait(request_id).await.unwrap();
+    assert_eq!(result.outputs.len(), 3);
+    assert_eq!(result.outputs[2][0].name, "mb2");
+
+    orch.shutdown().await.unwrap();
+    stage_handle.await.unwrap();
+}
+
+/// Single-stage schedule for three micro-batches that forwards 1 before 0
+/// and holds receiving 2 and sending 1 until after forwards that need a
+/// gate to be released.
+struct ReorderedSchedule;
+
+impl ScheduleStrategy for ReorderedSchedule {
+    fn generate(
+        &self,
+        num_stages: usize,
+        num_micro_batches: u32,
+    ) -> Result<InferenceSchedule, SchedulerError> {
+        assert_eq!((num_stages, num_micro_batches), (1, 3));
+        let ops = vec![
+            vec![
+                PipeOp::RecvActivation { micro_batch: 0 },
+                PipeOp::RecvActivation { micro_batch: 1 },
+            ],
+            vec![PipeOp::Forward { micro_batch: 1 }],
+            vec![PipeOp::Forward { micro_batch: 0 }],
+            vec![PipeOp::SendActivation { micro_batch: 0 }],
+            vec![PipeOp::RecvActivation { micro_batch: 2 }],
+            vec![PipeOp::Forward { micro_batch: 2 }],
+            vec![
+                PipeOp::SendActivation { micro_batch: 1 },
+                PipeOp::SendActivation { micro_batch: 2 },
+            ],
+        ];
+        Ok(InferenceSchedule {
+            num_stages,
+            num_micro_batches,
+            chunks_per_stage: 1,
+            total_steps: ops.len(),
+            stage_schedules: vec![StageSchedule { stage_idx: 0, ops }],
+        })
+    }
+}
+
+/// Executor that logs the order of forwards, holding micro-batches 0 and 2
+/// until their gates are released.
+struct OrderLoggingExecutor {
+    forwards: Arc<Mutex<Vec<u32>>>,
+    gate0: Arc<tokio::sync::Notify>,
+    gate2: Arc<tokio::sync::Notify>,
+}
+
+#[async_trait]
+impl StageExecutor for OrderLoggingExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        self.forwards.lock().unwrap().push(micro_batch);
+        match micro_batch {
+            0 => self.gate0.notified().await,
+            2 => self.gate2.notified().await,
+            _ => {}
+        }
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+/// A stage runs a custom schedule op by op: it forwards out of micro-batch
+/// order, receives micro-batch 2 only after forwarding 0, and sends
+/// micro-batch 0 before forwarding 2 but 1 only after it.
+#[tokio::test]
+async fn stage_follows_custom_schedule_order() {
+    let manifest = make_test_manifest(1);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+    let forwards = Arc::new(Mutex::new(Vec::new()));
+    let gate0 = Arc::new(tokio::sync::Notify::new());
+    let gate2 = Arc::new(tokio::sync::Notify::new());
+
+    // A data_in buffer much smaller than one micro-batch makes the
+    // orchestrator's sends track the stage's receives.
+    let (orch_ctrl, stage_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage_data_in) = tokio::io::duplex(4096);
+    let (stage_data_out, orch_data_out) = tokio::io::duplex(262144);
+
+    let executor = OrderLoggingExecutor {
+        forwards: forwards.clone(),
+        gate0: gate0.clone(),
+        gate2: gate2.clone(),
+    };
+    let stage_handle = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        // Receive 2 moves up one step, to the step that sends 0.
+        let config = StageConfig {
+            prefetch_depth: 1,
+            ..StageConfig::development()
+        };
+        let mut runtime = StageRuntime::new(executor, config).with_schedule(ReorderedSchedule);
+        runtime
+            .run(
+                stage_ctrl,
+                stage_data_in,
+                stage_data_out,
+                &provider,
+                &verifier,
+            )
+            .await
+            .unwrap();
+    });
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(vec![orch_ctrl], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    let large = |name: &str| OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 16384],
+        data: Bytes::from(vec![0u8; 65536]),
+    };
+    let input = vec![vec![large("mb0")], vec![large("mb1")], vec![large("mb2")]];
+    let mut stream = {
+        let submit = orch.infer_stream(input, 16);
+        tokio::pin!(submit);
+        tokio::select! {
+            _ = &mut submit => panic!("stage received micro-batch 2 before forwarding 0"),
+            () = tokio::time::sleep(Duration::from_millis(300)) => {}
+        }
+        assert_eq!(*forwards.lock().unwrap(), [1, 0]);
+
+        gate0.notify_one();
+        tokio::time::timeout(Duration::from_secs(5), submit)
+            .await
+            .expect("stage did not receive micro-batch 2 after forwarding 0")
+            .unwrap()
+    };
+
+    let (mb, tensors) = tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("stage did not send micro-batch 0 before forwarding 2")
+        .unwrap()
+        .unwrap();
+    assert_eq!((mb, tensors[0].name.as_str()), (0, "mb0"));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(300), stream.next())
+            .await
+            .is_err(),
+        "stage sent micro-batch 1 before forwarding 2"
+    );
+    assert_eq!(*forwards.lock().unwrap(), [1, 0, 2]);
+
+    gate2.notify_one();
+    for expected in 1..3 {
+        let (mb, tensors) = stream.next().await.unwrap().unwrap();
+        assert_eq!(mb, expected);
+        assert_eq!(tensors[0].name, format!("mb{expected}"));
+    }
+    assert!(stream.next().await.is_none());
+    drop(stream);
+
+    orch.shutdown().await.unwrap();
+    stage_handle.await.unwrap();
+}
+
 /// 10 sequential inference requests through a 2-stage duplex pipeline.
 #[tokio::test]
 async fn sequential_inference_ten_requests() {
@@ -538,3 +950,312 @@ async fn dev_config_with_explicit_require_measurements_rejects() {
     drop(orch);
     let _ = stage0_handle.await;
 }
+
+/// Schedules the stages cannot execute are rejected with the configuration,
+/// before any connection.
+#[test]
+fn orchestrator_rejects_non_executable_schedules() {
+    for schedule in [
+        ScheduleKind::OneFOneB,
+        ScheduleKind::Interleaved { chunks: 2 },
+    ] {
+        let config = OrchestratorConfig {
+            schedule,
+            ..OrchestratorConfig::development()
+        };
+        let err = Orchestrator::<tokio::io::DuplexStream>::new(config, make_test_manifest(2))
+            .err()
+            .expect("schedule should be rejected");
+        assert!(
+            matches!(
+                err,
+                PipelineError::Scheduler(SchedulerError::Unsupported(_))
+            ),
+            "got {err:?}"
+        );
+    }
+
+    let config = OrchestratorConfig {
+        schedule: ScheduleKind::Interleaved { chunks: 1 },
+        ..OrchestratorConfig::development()
+    };
+    assert!(Orchestrator::<tokio::io::DuplexStream>::new(config, make_test_manifest(2)).is_ok());
+}
+
+/// A whole batch is split by the planner, run, and merged back intact.
+#[tokio::test]
+async fn planned_batch_roundtrips_through_pipeline() {
+    let manifest = make_test_manifest(2);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+
+    let (orch_ctrl0, stage0_ctrl) = tokio::io::duplex(65536);
+    let (orch_ctrl1, stage1_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage0_data_in) = tokio::io::duplex(65536);
+    let (stage0_data_out, stage1_data_in) = tokio::io::duplex(65536);
+    let (stage1_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+    let mut stage_handles = Vec::new();
+    for (ctrl, data_in, data_out) in [
+        (stage0_ctrl, stage0_data_in, stage0_data_out),
+        (stage1_ctrl, stage1_data_in, stage1_data_out),
+    ] {
+        stage_handles.push(tokio::spawn(async move {
+            let provider = MockProvider::new();
+            let verifier = MockVerifier::new();
+            let mut runtime = StageRuntime::new(IdentityExecutor, StageConfig::development());
+            runtime
+                .run(ctrl, data_in, data_out, &provider, &verifier)
+                .await
+                .unwrap();
+        }));
+    }
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(vec![orch_ctrl0, orch_ctrl1], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    let batch = OwnedTensor {
+        name: "x".into(),
+        dtype: DType::F32,
+        shape: vec![12, 4],
+        data: Bytes::from((0..192).map(|b| b as u8).collect::<Vec<u8>>()),
+    };
+    let link = LinkCost {
+        latency: Duration::from_millis(1),
+        bytes_per_sec: f64::INFINITY,
+    };
+    let mut planner = MicroBatchPlanner::new(
+        CostModel::new(vec![Duration::from_millis(20); 2], link, 192),
+        usize::MAX,
+    );
+    planner.overhead = Duration::from_millis(1);
+    let (plan, micro_batches) = planner.split(std::slice::from_ref(&batch)).unwrap();
+    assert!(plan.num_micro_batches > 1);
+    assert_eq!(micro_batches.len(), plan.num_micro_batches as usize);
+
+    let result = orch.infer(micro_batches, 16).await.unwrap();
+    let merged = merge_outputs(result.outputs).unwrap();
+    assert_eq!(merged.len(), 1);
+    assert_eq!(merged[0].shape, batch.shape);
+    assert_eq!(merged[0].data, batch.data);
+
+    orch.shutdown().await.unwrap();
+    for handle in stage_handles {
+        handle.await.unwrap();
+    }
+}
+
+/// Executor that reports made-up per-layer costs when profiled: layer `i`
+/// takes `(i + 1) * 100` microseconds and holds 1000 bytes of parameters.
+#[derive(Default)]
+struct LayerTimedExecutor {
+    layers: std::ops::Range<usize>,
+}
+
+#[async_trait]
+impl StageExecutor for LayerTimedExecutor {
+    async fn init(&mut self, stage_spec: &StageSpec) -> Result<(), StageError> {
+        self.layers = stage_spec.layer_start..stage_spec.layer_end;
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        _micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        Ok(ForwardOutput { tensors: inputs })
+    }
+
+    async fn forward_profiled(
+        &self,
+        request_id: RequestId,
+        micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<(ForwardOutput, Vec<LayerSample>), StageError> {
+        let output = self.forward(request_id, micro_batch, inputs).await?;
+        let output_bytes: usize = output.tensors.iter().map(|t| t.data.len()).sum();
+        let samples = self
+            .layers
+            .clone()
+            .map(|layer| LayerSample {
+                layer,
+                forward_us: (layer as u64 + 1) * 100,
+                output_bytes: output_bytes as u64,
+                param_bytes: 1000,
+            })
+            .collect();
+        Ok((output, samples))
+    }
+}
+
+/// A profiling run combines per-layer samples from every stage into a
+/// profile file that partitions the model.
+#[tokio::test]
+async fn profile_run_feeds_layer_partitioning() {
+    let manifest = make_test_manifest(2);
+    let spec = manifest.activation_spec.clone();
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+
+    let (orch_ctrl0, stage0_ctrl) = tokio::io::duplex(65536);
+    let (orch_ctrl1, stage1_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage0_data_in) = tokio::io::duplex(65536);
+    let (stage0_data_out, stage1_data_in) = tokio::io::duplex(65536);
+    let (stage1_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+    let stage0 = tokio::spawn(async move {
+        let mut runtime =
+            StageRuntime::new(LayerTimedExecutor::default(), StageConfig::development());
+        runtime
+            .run(
+                stage0_ctrl,
+                stage0_data_in,
+                stage0_data_out,
+                &MockProvider::new(),
+                &MockVerifier::new(),
+            )
+            .await
+            .unwrap();
+    });
+    let stage1 = tokio::spawn(async move {
+        let mut runtime =
+            StageRuntime::new(LayerTimedExecutor::default(), StageConfig::development());
+        runtime
+            .run(
+                stage1_ctrl,
+                stage1_data_in,
+                stage1_data_out,
+                &MockProvider::new(),
+                &MockVerifier::new(),
+            )
+            .await
+            .unwrap();
+    });
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(vec![orch_ctrl0, orch_ctrl1], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    // Ordinary requests carry no layer samples.
+    let result = orch
+        .infer(vec![vec![make_test_tensor("x")]], 1)
+        .await
+        .unwrap();
+    assert!(result.layer_samples.iter().all(Vec::is_empty));
+
+    let profile = orch
+        .profile(synthetic_inputs(&spec, "hidden", 3, 8), 8)
+        .await
+        .unwrap();
+    assert_eq!(profile.model_name, "test-model");
+    assert_eq!(profile.layers.len(), 8);
+    let activation_bytes = spec.tensor_bytes(1, 8) as u64;
+    for layer in &profile.layers {
+        assert_eq!(layer.samples, 3);
+        assert_eq!(layer.output_bytes, activation_bytes);
+    }
+    assert_eq!(profile.layers[2].mean_forward_us, 300);
+    assert_eq!(profile.layers[2].param_bytes, 1000);
+    assert_eq!(profile.layers[6].stage_idx, 1);
+    assert_eq!(profile.layers[6].mean_forward_us, 700);
+
+    let path = std::env::temp_dir().join(format!("model-profile-{}.json", std::process::id()));
+    profile.save(&path).unwrap();
+    let loaded = ModelProfile::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, profile);
+
+    // Early layers are cheap, so the balanced split gives stage 0 more of them.
+    let partition = loaded.partition(&[usize::MAX; 2]).unwrap();
+    assert!((5..=6).contains(&partition.ranges[0].end), "{partition:?}");
+
+    orch.shutdown().await.unwrap();
+    stage0.await.unwrap();
+    stage1.await.unwrap();
+}
+
+/// A stage whose executor does not time its layers fails the profile
+/// instead of reporting made-up per-layer costs.
+#[tokio::test]
+async fn profile_refuses_stage_without_layer_hook() {
+    let manifest = make_test_manifest(2);
+    let spec = manifest.activation_spec.clone();
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+
+    let (orch_ctrl0, stage0_ctrl) = tokio::io::duplex(65536);
+    let (orch_ctrl1, stage1_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage0_data_in) = tokio::io::duplex(65536);
+    let (stage0_data_out, stage1_data_in) = tokio::io::duplex(65536);
+    let (stage1_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+    // Stage 0 times its own layers; stage 1 relies on the default hook.
+    let stage0 = tokio::spawn(async move {
+        let mut runtime =
+            StageRuntime::new(LayerTimedExecutor::default(), StageConfig::development());
+        runtime
+            .run(
+                stage0_ctrl,
+                stage0_data_in,
+                stage0_data_out,
+                &MockProvider::new(),
+                &MockVerifier::new(),
+            )
+            .await
+            .unwrap();
+    });
+    let stage1 = tokio::spawn(async move {
+        let executor = DelayExecutor(Duration::from_millis(4));
+        let mut runtime = StageRuntime::new(executor, StageConfig::development());
+        runtime
+            .run(
+                stage1_ctrl,
+                stage1_data_in,
+                stage1_data_out,
+                &MockProvider::new(),
+                &MockVerifier::new(),
+            )
+            .await
+            .unwrap();
+    });
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(vec![orch_ctrl0, orch_ctrl1], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    let err = orch
+        .profile(synthetic_inputs(&spec, "hidden", 2, 8), 8)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            PipelineError::Profile(ProfileError::NoLayerSamples { stage_idx: 1 })
+        ),
+        "{err}"
+    );
+
+    // The request itself succeeded, so the pipeline stays usable.
+    orch.infer(vec![vec![make_test_tensor("x")]], 1)
+        .await
+        .unwrap();
+
+    orch.shutdown().await.unwrap();
+    stage0.await.unwrap();
+    stage1.await.unwrap();
+}
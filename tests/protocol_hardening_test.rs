@@ -13,7 +13,7 @@ use confidential_ml_transport::{
 use confidential_ml_pipeline::{
     ActivationDType, ActivationSpec, ForwardOutput, Orchestrator, OrchestratorConfig,
     PipelineError, PortSpec, RequestId, ShardManifest, StageConfig, StageEndpoint, StageError,
-    StageExecutor, StageRuntime, StageSpec, PROTOCOL_VERSION,
+    StageExecutor, StageRuntime, StageSpec, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
 };
 
 /// Identity executor: passes input tensors through unchanged.
@@ -44,6 +44,11 @@ fn make_test_manifest(num_stages: usize) -> ShardManifest {
             weight_hashes: vec![],
             require_weight_hashes: false,
             expected_measurements: BTreeMap::new(),
+            upstream: vec![],
+            downstream: vec![],
+            pass_through: vec![],
+            inputs: vec![],
+            outputs: vec![],
             endpoint: StageEndpoint {
                 control: PortSpec::Tcp {
                     addr: format!("127.0.0.1:{}", 9400 + i * 10),
@@ -392,10 +397,12 @@ async fn stage_rejects_truncated_json() {
     );
 }
 
-/// Verify that protocol version constant is 1 and is embedded in serialized messages.
+/// Verify the protocol version constants: version 2 negotiates, version 1 is
+/// still spoken.
 #[test]
-fn protocol_version_is_one() {
-    assert_eq!(PROTOCOL_VERSION, 1);
+fn protocol_versions_are_one_to_two() {
+    assert_eq!(PROTOCOL_VERSION, 2);
+    assert_eq!(MIN_PROTOCOL_VERSION, 1);
 }
 
 /// Verify that max_control_message_bytes config validation rejects zero.
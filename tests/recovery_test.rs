@@ -0,0 +1,285 @@
+#![cfg(feature = "mock")]
+
+//! Tests for recovering a tainted pipeline through a transport factory.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor, RetryPolicy};
+use tokio::io::DuplexStream;
+use tokio::task::JoinHandle;
+
+use confidential_ml_pipeline::{
+    DataTransports, ForwardOutput, Orchestrator, OrchestratorConfig, OrchestratorHandle,
+    PipelineError, Recovery, RequestId, ShardManifest, StageConfig, StageError, StageExecutor,
+    StageRuntime, StageSpec, TransportFactory,
+};
+
+mod common;
+use common::make_test_manifest;
+
+/// Executor that echoes its inputs, but never finishes a micro-batch whose
+/// first tensor is named "hang".
+struct HangingExecutor;
+
+#[async_trait]
+impl StageExecutor for HangingExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        _micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        if inputs.first().is_some_and(|t| t.name == "hang") {
+            tokio::time::sleep(Duration::from_secs(3600)).await;
+        }
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+fn make_test_tensor(name: &str) -> OwnedTensor {
+    OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![0u8; 16]),
+    }
+}
+
+/// Factory that starts a fresh set of duplex-connected stages on every
+/// `connect_control`, after refusing the first `failures` attempts.
+struct DuplexFactory {
+    failures: u32,
+    connects: Arc<AtomicUsize>,
+    stage_tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    data: Option<(DuplexStream, DuplexStream)>,
+}
+
+impl DuplexFactory {
+    fn new(failures: u32) -> Self {
+        Self {
+            failures,
+            connects: Arc::new(AtomicUsize::new(0)),
+            stage_tasks: Arc::new(Mutex::new(Vec::new())),
+            data: None,
+        }
+    }
+}
+
+#[async_trait]
+impl TransportFactory<DuplexStream> for DuplexFactory {
+    async fn connect_control(
+        &mut self,
+        manifest: &ShardManifest,
+    ) -> confidential_ml_pipeline::Result<Vec<DuplexStream>> {
+        if self.failures > 0 {
+            self.failures -= 1;
+            return Err(PipelineError::Io(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                "stages not up yet",
+            )));
+        }
+        self.connects.fetch_add(1, Ordering::SeqCst);
+
+        let mut orch_ctrls = Vec::new();
+        let (orch_data_in, mut next_data_in) = tokio::io::duplex(262144);
+        let mut tasks = self.stage_tasks.lock().unwrap();
+        for _ in &manifest.stages {
+            let (orch_ctrl, ctrl) = tokio::io::duplex(262144);
+            orch_ctrls.push(orch_ctrl);
+            let (data_out, downstream_in) = tokio::io::duplex(262144);
+            let data_in = std::mem::replace(&mut next_data_in, downstream_in);
+            tasks.push(tokio::spawn(async move {
+                let provider = MockProvider::new();
+                let verifier = MockVerifier::new();
+                let mut runtime = StageRuntime::new(HangingExecutor, StageConfig::development());
+                let _ = runtime
+                    .run(ctrl, data_in, data_out, &provider, &verifier)
+                    .await;
+            }));
+        }
+        self.data = Some((orch_data_in, next_data_in));
+        Ok(orch_ctrls)
+    }
+
+    async fn connect_data(
+        &mut self,
+        _manifest: &ShardManifest,
+    ) -> confidential_ml_pipeline::Result<DataTransports<DuplexStream>> {
+        let (data_in, data_out) = self
+            .data
+            .take()
+            .ok_or_else(|| PipelineError::Protocol("connect_control not called".into()))?;
+        Ok(DataTransports {
+            data_in,
+            data_out,
+            relay_handles: vec![],
+        })
+    }
+}
+
+/// Config whose hung requests taint the pipeline quickly, with fast retries.
+fn tainting_config(max_retries: u32) -> OrchestratorConfig {
+    OrchestratorConfig {
+        infer_timeout: Duration::from_millis(100),
+        stage_drain_timeout: Duration::from_millis(100),
+        recovery_policy: RetryPolicy {
+            max_retries,
+            initial_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+            backoff_multiplier: 2.0,
+        },
+        ..OrchestratorConfig::development()
+    }
+}
+
+async fn setup_pipeline(
+    num_stages: usize,
+    config: OrchestratorConfig,
+    factory: &mut DuplexFactory,
+) -> Orchestrator<DuplexStream> {
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+    let mut orch = Orchestrator::new(config, make_test_manifest(num_stages)).unwrap();
+    let control = factory.connect_control(orch.manifest()).await.unwrap();
+    orch.init(control, &provider, &verifier).await.unwrap();
+    orch.send_establish_data_channels().await.unwrap();
+    let data = factory.connect_data(orch.manifest()).await.unwrap();
+    orch.complete_data_channels(
+        data.data_in,
+        data.data_out,
+        data.relay_handles,
+        &provider,
+        &verifier,
+    )
+    .await
+    .unwrap();
+    orch
+}
+
+fn abort_all(tasks: &Mutex<Vec<JoinHandle<()>>>) {
+    for task in tasks.lock().unwrap().drain(..) {
+        task.abort();
+    }
+}
+
+/// A tainted pipeline is rebuilt over new transports and serves requests again.
+#[tokio::test]
+async fn recover_after_taint() {
+    let mut factory = DuplexFactory::new(0);
+    let mut orch = setup_pipeline(2, tainting_config(2), &mut factory).await;
+
+    let err = orch
+        .infer(vec![vec![make_test_tensor("hang")]], 16)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PipelineError::Timeout(_)), "got {err:?}");
+    assert!(orch.is_tainted());
+    assert!(matches!(
+        orch.infer(vec![vec![make_test_tensor("x")]], 16).await,
+        Err(PipelineError::Tainted)
+    ));
+
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+    orch.recover(&mut factory, &provider, &verifier)
+        .await
+        .unwrap();
+    assert!(!orch.is_tainted());
+    assert_eq!(factory.connects.load(Ordering::SeqCst), 2);
+
+    let result = orch
+        .infer(vec![vec![make_test_tensor("after")]], 16)
+        .await
+        .unwrap();
+    assert_eq!(result.outputs[0][0].name, "after");
+
+    orch.shutdown().await.unwrap();
+    abort_all(&factory.stage_tasks);
+}
+
+/// Recovery retries failed attempts up to the policy's limit, then leaves
+/// the pipeline tainted.
+#[tokio::test]
+async fn recover_gives_up_after_max_retries() {
+    let mut factory = DuplexFactory::new(0);
+    let mut orch = setup_pipeline(2, tainting_config(2), &mut factory).await;
+
+    let _ = orch.infer(vec![vec![make_test_tensor("hang")]], 16).await;
+    assert!(orch.is_tainted());
+
+    factory.failures = 3;
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+    let err = orch
+        .recover(&mut factory, &provider, &verifier)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, PipelineError::RecoveryFailed { attempts: 3, .. }),
+        "got {err:?}"
+    );
+    assert!(orch.is_tainted());
+    assert!(matches!(
+        orch.infer(vec![vec![make_test_tensor("x")]], 16).await,
+        Err(PipelineError::Tainted)
+    ));
+
+    // The stages come back; a later attempt succeeds.
+    orch.recover(&mut factory, &provider, &verifier)
+        .await
+        .unwrap();
+    let result = orch
+        .infer(vec![vec![make_test_tensor("back")]], 16)
+        .await
+        .unwrap();
+    assert_eq!(result.outputs[0][0].name, "back");
+
+    orch.shutdown().await.unwrap();
+    abort_all(&factory.stage_tasks);
+}
+
+/// A handle spawned with recovery rebuilds the pipeline before the next
+/// command, retrying past a failed attempt.
+#[tokio::test]
+async fn handle_recovers_tainted_pipeline() {
+    let mut factory = DuplexFactory::new(0);
+    let orch = setup_pipeline(3, tainting_config(2), &mut factory).await;
+    factory.failures = 1;
+    let connects = factory.connects.clone();
+    let stage_tasks = factory.stage_tasks.clone();
+
+    let (handle, task) = OrchestratorHandle::spawn_with_recovery(
+        orch,
+        Recovery {
+            factory: Box::new(factory),
+            provider: Arc::new(MockProvider::new()),
+            verifier: Arc::new(MockVerifier::new()),
+        },
+    );
+
+    let err = handle
+        .infer(vec![vec![make_test_tensor("hang")]], 16)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PipelineError::Timeout(_)), "got {err:?}");
+
+    let result = handle
+        .infer(vec![vec![make_test_tensor("after")]], 16)
+        .await
+        .unwrap();
+    assert_eq!(result.outputs[0][0].name, "after");
+    assert_eq!(connects.load(Ordering::SeqCst), 2);
+    assert!(!handle.status().await.unwrap().tainted);
+
+    handle.shutdown().await.unwrap();
+    task.await.unwrap();
+    abort_all(&stage_tasks);
+}
@@ -196,6 +196,11 @@ fn make_manifest() -> ShardManifest {
             weight_hashes: vec![],
             require_weight_hashes: false,
             expected_measurements: BTreeMap::new(),
+            upstream: vec![],
+            downstream: vec![],
+            pass_through: vec![],
+            inputs: vec![],
+            outputs: vec![],
             endpoint: StageEndpoint {
                 control: PortSpec::Tcp {
                     addr: format!("127.0.0.1:{}", 9000 + i * 10),
@@ -482,7 +487,7 @@ async fn activation_values_not_recoverable() {
 
 /// Forward direction has tensor frames with expected structure:
 /// - At least 2 encrypted Tensor frames (input_ids + hidden_states activation)
-/// - Data frames for END sentinels
+/// - Data frames for tensor group headers
 #[tokio::test]
 async fn forward_frame_structure() {
     let cap = run_pipeline_with_capture().await;
@@ -497,14 +502,14 @@ async fn forward_frame_structure() {
         .filter(|(info, _)| info.msg_type == FrameType::Data)
         .count();
 
-    // Stage 0 sends: tensor(input_ids_doubled) + tensor(hidden_states_doubled) + END sentinel
+    // Stage 0 sends: group header + tensor(input_ids_doubled) + tensor(hidden_states_doubled)
     assert!(
         tensor_count >= 2,
         "expected at least 2 tensor frames in forward direction, got {tensor_count}"
     );
     assert!(
         data_count >= 1,
-        "expected at least 1 data frame (END sentinel) in forward direction, got {data_count}"
+        "expected at least 1 data frame (group header) in forward direction, got {data_count}"
     );
 }
 
@@ -517,7 +522,7 @@ async fn aead_overhead_bounded() {
 
     // Raw payload size: input_ids (20 bytes) + hidden_states (15360 bytes) = 15380 bytes
     // Each stage doubles, so stage 0 output = same sizes (20 + 15360 bytes of raw tensor data).
-    // Plus END sentinel, plus handshake, plus headers + AEAD tags.
+    // Plus group header, plus handshake, plus headers + AEAD tags.
     let raw_tensor_bytes = 20 + 15360; // input_ids + hidden_states
 
     // The total should be larger than raw (overhead from headers + AEAD + handshake)
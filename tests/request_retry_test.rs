@@ -0,0 +1,341 @@
+#![cfg(feature = "mock")]
+
+//! Tests for automatic resubmission of failed requests.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+
+use confidential_ml_pipeline::{
+    ErrorCode, ForwardOutput, Orchestrator, OrchestratorConfig, OrchestratorHandle, PipelineError,
+    RequestId, RequestOptions, RequestRetryPolicy, StageConfig, StageError, StageExecutor,
+    StageRuntime, StageSpec,
+};
+
+mod common;
+use common::make_test_manifest;
+
+/// Executor that fails its first `failures` forwards with `error`, then
+/// passes inputs through. Counts every forward.
+struct FlakyExecutor {
+    failures: usize,
+    error: fn(RequestId, u32) -> StageError,
+    forwards: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl StageExecutor for FlakyExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        request_id: RequestId,
+        micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        if self.forwards.fetch_add(1, Ordering::SeqCst) < self.failures {
+            return Err((self.error)(request_id, micro_batch));
+        }
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+fn out_of_memory(request_id: RequestId, micro_batch: u32) -> StageError {
+    StageError::ResourceExhausted {
+        request_id,
+        micro_batch,
+        reason: "out of device memory".into(),
+    }
+}
+
+fn bad_input(request_id: RequestId, micro_batch: u32) -> StageError {
+    StageError::InvalidInput {
+        request_id,
+        micro_batch,
+        reason: "token id out of range".into(),
+    }
+}
+
+fn make_test_tensor() -> OwnedTensor {
+    OwnedTensor {
+        name: "input".to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![0u8; 16]),
+    }
+}
+
+fn retrying_config(max_retries: u32) -> OrchestratorConfig {
+    OrchestratorConfig {
+        request_retry_policy: RequestRetryPolicy {
+            initial_backoff: Duration::from_millis(10),
+            ..RequestRetryPolicy::with_retries(max_retries)
+        },
+        ..OrchestratorConfig::development()
+    }
+}
+
+/// Set up a 2-stage duplex pipeline whose second stage is a `FlakyExecutor`.
+async fn setup_pipeline(
+    config: OrchestratorConfig,
+    failures: usize,
+    error: fn(RequestId, u32) -> StageError,
+) -> (
+    Orchestrator<tokio::io::DuplexStream>,
+    Vec<tokio::task::JoinHandle<()>>,
+    Arc<AtomicUsize>,
+) {
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+    let forwards = Arc::new(AtomicUsize::new(0));
+
+    let mut orch_ctrls = Vec::new();
+    let mut stage_ctrls = Vec::new();
+    for _ in 0..2 {
+        let (orch_side, stage_side) = tokio::io::duplex(65536);
+        orch_ctrls.push(orch_side);
+        stage_ctrls.push(stage_side);
+    }
+
+    // Data links: orchestrator -> stage 0 -> stage 1 -> orchestrator.
+    let (orch_data_in, mut next_data_in) = tokio::io::duplex(65536);
+    let mut handles = Vec::new();
+    for (i, ctrl) in stage_ctrls.into_iter().enumerate() {
+        let (data_out, downstream_in) = tokio::io::duplex(65536);
+        let data_in = std::mem::replace(&mut next_data_in, downstream_in);
+        let executor = FlakyExecutor {
+            failures: if i == 1 { failures } else { 0 },
+            error,
+            forwards: if i == 1 {
+                forwards.clone()
+            } else {
+                Arc::new(AtomicUsize::new(0))
+            },
+        };
+        handles.push(tokio::spawn(async move {
+            let provider = MockProvider::new();
+            let verifier = MockVerifier::new();
+            let mut runtime = StageRuntime::new(executor, StageConfig::development());
+            let _ = runtime
+                .run(ctrl, data_in, data_out, &provider, &verifier)
+                .await;
+        }));
+    }
+    let orch_data_out = next_data_in;
+
+    let mut orch = Orchestrator::new(config, make_test_manifest(2)).unwrap();
+    orch.init(orch_ctrls, &provider, &verifier).await.unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    (orch, handles, forwards)
+}
+
+async fn finish(
+    mut orch: Orchestrator<tokio::io::DuplexStream>,
+    handles: Vec<tokio::task::JoinHandle<()>>,
+) {
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// A retryable stage failure is resubmitted under a new request ID and the
+/// failed attempt is reported in the result.
+#[tokio::test]
+async fn transient_failure_is_retried() {
+    let (mut orch, handles, forwards) = setup_pipeline(retrying_config(2), 1, out_of_memory).await;
+
+    let result = orch
+        .infer(vec![vec![make_test_tensor()]], 16)
+        .await
+        .unwrap();
+    assert_eq!(result.outputs.len(), 1);
+    assert_eq!(forwards.load(Ordering::SeqCst), 2);
+    assert_eq!(result.retries.len(), 1);
+    match &result.retries[0].error {
+        PipelineError::RequestFailed {
+            request_id, code, ..
+        } => {
+            assert_eq!(*code, ErrorCode::ResourceExhausted);
+            assert_ne!(*request_id, result.request_id);
+        }
+        other => panic!("expected RequestFailed, got: {other:?}"),
+    }
+    assert_eq!(result.retries[0].backoff, Duration::from_millis(10));
+    assert!(!orch.is_tainted());
+
+    finish(orch, handles).await;
+}
+
+/// A fatal stage failure is returned after one attempt.
+#[tokio::test]
+async fn fatal_failure_is_not_retried() {
+    let (mut orch, handles, forwards) = setup_pipeline(retrying_config(2), 1, bad_input).await;
+
+    let err = orch
+        .infer(vec![vec![make_test_tensor()]], 16)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            PipelineError::RequestFailed {
+                code: ErrorCode::InvalidInput,
+                ..
+            }
+        ),
+        "expected InvalidInput, got {err:?}"
+    );
+    assert!(!err.is_retryable());
+    assert_eq!(forwards.load(Ordering::SeqCst), 1);
+
+    finish(orch, handles).await;
+}
+
+/// Once `max_retries` is used up the last error is returned.
+#[tokio::test]
+async fn retries_are_bounded() {
+    let (mut orch, handles, forwards) =
+        setup_pipeline(retrying_config(2), usize::MAX, out_of_memory).await;
+
+    let err = orch
+        .infer(vec![vec![make_test_tensor()]], 16)
+        .await
+        .unwrap_err();
+    assert!(err.is_retryable(), "expected retryable error, got {err:?}");
+    assert_eq!(forwards.load(Ordering::SeqCst), 3);
+
+    // The pipeline is still usable after the failed attempts.
+    assert!(!orch.is_tainted());
+    orch.health_check().await.unwrap();
+
+    finish(orch, handles).await;
+}
+
+/// Without a retry policy a retryable failure reaches the caller.
+#[tokio::test]
+async fn retries_are_opt_in() {
+    let (mut orch, handles, forwards) =
+        setup_pipeline(OrchestratorConfig::development(), 1, out_of_memory).await;
+
+    let err = orch
+        .infer(vec![vec![make_test_tensor()]], 16)
+        .await
+        .unwrap_err();
+    assert!(err.is_retryable(), "expected retryable error, got {err:?}");
+    assert_eq!(forwards.load(Ordering::SeqCst), 1);
+
+    let result = orch
+        .infer(vec![vec![make_test_tensor()]], 16)
+        .await
+        .unwrap();
+    assert!(result.retries.is_empty());
+
+    finish(orch, handles).await;
+}
+
+/// No retry is attempted when its backoff would end past the deadline.
+#[tokio::test]
+async fn retry_respects_deadline() {
+    let config = OrchestratorConfig {
+        request_retry_policy: RequestRetryPolicy {
+            initial_backoff: Duration::from_secs(1),
+            ..RequestRetryPolicy::with_retries(2)
+        },
+        ..OrchestratorConfig::development()
+    };
+    let (mut orch, handles, forwards) = setup_pipeline(config, 1, out_of_memory).await;
+
+    let started = std::time::Instant::now();
+    let err = orch
+        .infer_with_options(
+            vec![vec![make_test_tensor()]],
+            16,
+            RequestOptions::with_timeout(Duration::from_millis(500)),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.is_retryable(), "expected retryable error, got {err:?}");
+    assert!(started.elapsed() < Duration::from_millis(500));
+    assert_eq!(forwards.load(Ordering::SeqCst), 1);
+
+    finish(orch, handles).await;
+}
+
+/// A caller-supplied request ID is never resubmitted under another ID.
+#[tokio::test]
+async fn caller_request_id_is_not_retried() {
+    let (mut orch, handles, forwards) = setup_pipeline(retrying_config(2), 1, out_of_memory).await;
+
+    let options = RequestOptions {
+        request_id: Some(42),
+        ..RequestOptions::default()
+    };
+    let err = orch
+        .infer_with_options(vec![vec![make_test_tensor()]], 16, options)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, PipelineError::RequestFailed { request_id: 42, .. }),
+        "expected RequestFailed for request 42, got {err:?}"
+    );
+    assert_eq!(forwards.load(Ordering::SeqCst), 1);
+
+    finish(orch, handles).await;
+}
+
+/// Infers through an `OrchestratorHandle` follow the retry policy too, and
+/// the handle keeps answering other commands while a retry backs off.
+#[tokio::test]
+async fn handle_retries_transient_failures() {
+    let config = OrchestratorConfig {
+        request_retry_policy: RequestRetryPolicy {
+            initial_backoff: Duration::from_millis(300),
+            ..RequestRetryPolicy::with_retries(2)
+        },
+        ..OrchestratorConfig::development()
+    };
+    let (orch, handles, forwards) = setup_pipeline(config, 1, out_of_memory).await;
+    let (handle, task) = OrchestratorHandle::spawn(orch);
+
+    let infer = tokio::spawn({
+        let handle = handle.clone();
+        async move { handle.infer(vec![vec![make_test_tensor()]], 16).await }
+    });
+
+    // Wait for the first attempt to fail; the retry is then backing off.
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while forwards.load(Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap();
+    let status = tokio::time::timeout(Duration::from_millis(200), handle.status())
+        .await
+        .expect("status must not wait for the backoff")
+        .unwrap();
+    assert_eq!(status.queued + status.in_flight, 1);
+
+    let result = infer.await.unwrap().unwrap();
+    assert_eq!(result.outputs.len(), 1);
+    assert_eq!(forwards.load(Ordering::SeqCst), 2);
+    assert_eq!(result.retries.len(), 1);
+    assert!(result.retries[0].error.is_retryable());
+    assert_eq!(result.retries[0].backoff, Duration::from_millis(300));
+
+    handle.shutdown().await.unwrap();
+    task.await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
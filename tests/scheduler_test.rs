@@ -1,4 +1,7 @@
-use confidential_ml_pipeline::{InferenceSchedule, PipeOp, SchedulerError};
+use confidential_ml_pipeline::{
+    FillDrain, InferenceSchedule, Interleaved, OneFOneB, PipeOp, ScheduleKind, ScheduleStrategy,
+    SchedulerError,
+};
 
 #[test]
 fn single_stage_single_batch() {
@@ -137,3 +140,75 @@ fn forward_order_preserved() {
         }
     }
 }
+
+#[test]
+fn generated_schedules_normalize_to_full_io() {
+    for p in 1..=5 {
+        for m in 1..=10u32 {
+            let s = InferenceSchedule::generate(p, m).unwrap();
+            for stage_idx in 0..p {
+                let plan = s.stage(stage_idx).unwrap().normalized(p, m).unwrap();
+                let count =
+                    |f: fn(&PipeOp) -> bool| plan.ops.iter().flatten().filter(|op| f(op)).count();
+                assert_eq!(
+                    count(|op| matches!(op, PipeOp::RecvActivation { .. })),
+                    m as usize
+                );
+                assert_eq!(count(|op| matches!(op, PipeOp::Forward { .. })), m as usize);
+                assert_eq!(
+                    count(|op| matches!(op, PipeOp::SendActivation { .. })),
+                    m as usize
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn every_strategy_runs_each_micro_batch_once_per_virtual_stage() {
+    let strategies = [
+        ScheduleKind::FillDrain,
+        ScheduleKind::OneFOneB,
+        ScheduleKind::Interleaved { chunks: 2 },
+        ScheduleKind::Interleaved { chunks: 3 },
+    ];
+    for kind in strategies {
+        for p in 1..=4 {
+            for m in 1..=9u32 {
+                let s = kind.generate(p, m).unwrap();
+                assert_eq!(s.stage_schedules.len(), p * s.chunks_per_stage);
+                for schedule in &s.stage_schedules {
+                    let mut forwards: Vec<u32> = schedule
+                        .ops
+                        .iter()
+                        .flatten()
+                        .filter_map(|op| match op {
+                            PipeOp::Forward { micro_batch } => Some(*micro_batch),
+                            _ => None,
+                        })
+                        .collect();
+                    forwards.sort();
+                    assert_eq!(forwards, (0..m).collect::<Vec<_>>(), "{kind:?} p={p} m={m}");
+                }
+                assert!(s.peak_in_flight() <= m as usize);
+                let bubble = s.bubble_fraction();
+                assert!((0.0..1.0).contains(&bubble), "{kind:?} p={p} m={m}");
+            }
+        }
+    }
+}
+
+#[test]
+fn strategy_accounting_trade_offs() {
+    let (p, m) = (4, 16);
+    // 1F1B keeps fill-drain's bubble but bounds activations to the depth.
+    assert!(
+        (OneFOneB.bubble_fraction(p, m).unwrap() - FillDrain.bubble_fraction(p, m).unwrap()).abs()
+            < 1e-10
+    );
+    assert_eq!(OneFOneB.peak_in_flight(p, m).unwrap(), p);
+    // Interleaving divides the bubble roughly by the chunk count.
+    let interleaved = Interleaved { chunks: 2 }.bubble_fraction(p, m).unwrap();
+    assert!((interleaved - 3.0 / 35.0).abs() < 1e-10);
+    assert!(interleaved < FillDrain.bubble_fraction(p, m).unwrap());
+}
@@ -0,0 +1,288 @@
+#![cfg(feature = "mock")]
+
+//! Tests for session lifecycle across stages.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+
+use confidential_ml_pipeline::{
+    ForwardOutput, Orchestrator, OrchestratorConfig, PipelineError, RequestId, SessionId,
+    StageConfig, StageError, StageExecutor, StageRuntime, StageSpec,
+};
+
+mod common;
+use common::make_test_manifest;
+
+/// Session lifecycle events recorded by every stage, e.g. `"open 1 @0"`.
+type EventLog = Arc<Mutex<Vec<String>>>;
+
+/// Executor that counts the requests seen per session and appends the count
+/// to each tensor name. Inputs named `boom` fail on `fail_stage`.
+struct CountingExecutor {
+    stage_idx: usize,
+    fail_stage: Option<usize>,
+    counts: Mutex<HashMap<SessionId, u32>>,
+    events: EventLog,
+}
+
+#[async_trait]
+impl StageExecutor for CountingExecutor {
+    async fn init(&mut self, stage_spec: &StageSpec) -> Result<(), StageError> {
+        self.stage_idx = stage_spec.stage_idx;
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        _micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        Ok(ForwardOutput {
+            tensors: rename(inputs, "-"),
+        })
+    }
+
+    async fn open_session(&self, session_id: SessionId) -> Result<(), StageError> {
+        self.counts.lock().unwrap().insert(session_id, 0);
+        self.log(format!("open {session_id}"));
+        Ok(())
+    }
+
+    async fn forward_in_session(
+        &self,
+        session_id: SessionId,
+        request_id: RequestId,
+        micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        if self.fail_stage == Some(self.stage_idx) && inputs[0].name.starts_with("boom") {
+            return Err(StageError::ForwardFailed {
+                request_id,
+                micro_batch,
+                reason: "intentional test failure".into(),
+            });
+        }
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts
+            .get_mut(&session_id)
+            .ok_or_else(|| StageError::SessionFailed {
+                session_id,
+                reason: "no state".into(),
+            })?;
+        *count += 1;
+        Ok(ForwardOutput {
+            tensors: rename(inputs, &count.to_string()),
+        })
+    }
+
+    async fn close_session(&self, session_id: SessionId) {
+        self.counts.lock().unwrap().remove(&session_id);
+        self.log(format!("close {session_id}"));
+    }
+}
+
+impl CountingExecutor {
+    fn log(&self, event: String) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("{event} @{}", self.stage_idx));
+    }
+}
+
+fn rename(mut tensors: Vec<OwnedTensor>, suffix: &str) -> Vec<OwnedTensor> {
+    for t in &mut tensors {
+        t.name = format!("{}:{suffix}", t.name);
+    }
+    tensors
+}
+
+fn make_test_tensor(name: &str) -> OwnedTensor {
+    OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![0u8; 16]),
+    }
+}
+
+/// Set up an N-stage duplex pipeline of counting executors sharing `events`.
+async fn setup_pipeline(
+    num_stages: usize,
+    fail_stage: Option<usize>,
+    events: EventLog,
+) -> (
+    Orchestrator<tokio::io::DuplexStream>,
+    Vec<tokio::task::JoinHandle<()>>,
+) {
+    let manifest = make_test_manifest(num_stages);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+
+    let mut orch_ctrls = Vec::new();
+    let mut stage_ctrls = Vec::new();
+    for _ in 0..num_stages {
+        let (orch_side, stage_side) = tokio::io::duplex(262144);
+        orch_ctrls.push(orch_side);
+        stage_ctrls.push(stage_side);
+    }
+
+    let (orch_data_in, mut next_data_in) = tokio::io::duplex(262144);
+    let mut handles = Vec::new();
+    for ctrl in stage_ctrls {
+        let (data_out, downstream_in) = tokio::io::duplex(262144);
+        let data_in = std::mem::replace(&mut next_data_in, downstream_in);
+        let executor = CountingExecutor {
+            stage_idx: 0,
+            fail_stage,
+            counts: Mutex::new(HashMap::new()),
+            events: events.clone(),
+        };
+        handles.push(tokio::spawn(async move {
+            let provider = MockProvider::new();
+            let verifier = MockVerifier::new();
+            let mut runtime = StageRuntime::new(executor, StageConfig::development());
+            runtime
+                .run(ctrl, data_in, data_out, &provider, &verifier)
+                .await
+                .unwrap();
+        }));
+    }
+    let orch_data_out = next_data_in;
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(orch_ctrls, &provider, &verifier).await.unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    (orch, handles)
+}
+
+fn input(name: &str) -> Vec<Vec<OwnedTensor>> {
+    vec![vec![make_test_tensor(name)]]
+}
+
+fn count_events(events: &EventLog, prefix: &str) -> usize {
+    events
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|e| e.starts_with(prefix))
+        .count()
+}
+
+/// Session state carries across requests and is released on close.
+#[tokio::test]
+async fn session_state_persists_until_close() {
+    let events = EventLog::default();
+    let (mut orch, handles) = setup_pipeline(2, None, events.clone()).await;
+
+    let session = orch.open_session().await.unwrap();
+    let r = orch
+        .infer_in_session(session, input("a"), 16)
+        .await
+        .unwrap();
+    assert_eq!(r.outputs[0][0].name, "a:1:1");
+    let r = orch
+        .infer_in_session(session, input("b"), 16)
+        .await
+        .unwrap();
+    assert_eq!(r.outputs[0][0].name, "b:2:2");
+
+    // Requests outside the session do not touch its state.
+    let r = orch.infer(input("c"), 16).await.unwrap();
+    assert_eq!(r.outputs[0][0].name, "c:-:-");
+
+    orch.close_session(session).await.unwrap();
+    // Control messages are handled in order, so the ping follows the close.
+    orch.health_check().await.unwrap();
+    assert_eq!(count_events(&events, &format!("open {session}")), 2);
+    assert_eq!(count_events(&events, &format!("close {session}")), 2);
+
+    let err = orch
+        .infer_in_session(session, input("d"), 16)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, PipelineError::Protocol(_)),
+        "expected Protocol, got {err:?}"
+    );
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// A failed session request drops the session on the failing stage; its
+/// later requests are rejected and the pipeline stays usable.
+#[tokio::test]
+async fn failed_request_drops_session_state() {
+    let events = EventLog::default();
+    let (mut orch, handles) = setup_pipeline(2, Some(1), events.clone()).await;
+
+    let session = orch.open_session().await.unwrap();
+    orch.infer_in_session(session, input("a"), 16)
+        .await
+        .unwrap();
+
+    let err = orch
+        .infer_in_session(session, input("boom"), 16)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, PipelineError::RequestFailed { reason, .. } if reason.contains("intentional")),
+        "expected RequestFailed, got {err:?}"
+    );
+    assert_eq!(count_events(&events, &format!("close {session} @1")), 1);
+
+    let err = orch
+        .infer_in_session(session, input("b"), 16)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, PipelineError::RequestFailed { reason, .. } if reason.contains("unavailable")),
+        "expected RequestFailed, got {err:?}"
+    );
+
+    orch.close_session(session).await.unwrap();
+
+    // A fresh session and plain requests still work.
+    let fresh = orch.open_session().await.unwrap();
+    let r = orch.infer_in_session(fresh, input("x"), 16).await.unwrap();
+    assert_eq!(r.outputs[0][0].name, "x:1:1");
+    let r = orch.infer(input("y"), 16).await.unwrap();
+    assert_eq!(r.outputs[0][0].name, "y:-:-");
+
+    orch.health_check().await.unwrap();
+    assert_eq!(count_events(&events, &format!("close {session}")), 2);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// Sessions still open at shutdown are released by every stage.
+#[tokio::test]
+async fn shutdown_closes_open_sessions() {
+    let events = EventLog::default();
+    let (mut orch, handles) = setup_pipeline(3, None, events.clone()).await;
+
+    let a = orch.open_session().await.unwrap();
+    let b = orch.open_session().await.unwrap();
+    orch.infer_in_session(a, input("a"), 16).await.unwrap();
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+    assert_eq!(count_events(&events, &format!("close {a}")), 3);
+    assert_eq!(count_events(&events, &format!("close {b}")), 3);
+}
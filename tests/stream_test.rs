@@ -0,0 +1,239 @@
+#![cfg(feature = "mock")]
+
+//! Tests for streaming micro-batch outputs with `Orchestrator::infer_stream`.
+
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{DType, MockProvider, MockVerifier, OwnedTensor};
+
+use confidential_ml_pipeline::{
+    ForwardOutput, Orchestrator, OrchestratorConfig, PipelineError, RequestId, StageConfig,
+    StageError, StageExecutor, StageRuntime, StageSpec,
+};
+
+mod common;
+use common::make_test_manifest;
+
+/// Executor that sleeps per micro-batch, then passes inputs through, failing
+/// on `fail_at` if set.
+#[derive(Clone, Copy)]
+struct StreamExecutor {
+    delay: Duration,
+    fail_at: Option<u32>,
+}
+
+#[async_trait]
+impl StageExecutor for StreamExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        request_id: RequestId,
+        micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        tokio::time::sleep(self.delay).await;
+        if self.fail_at == Some(micro_batch) {
+            return Err(StageError::ForwardFailed {
+                request_id,
+                micro_batch,
+                reason: "intentional test failure".into(),
+            });
+        }
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+fn make_test_tensor(name: &str) -> OwnedTensor {
+    OwnedTensor {
+        name: name.to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![0u8; 16]),
+    }
+}
+
+/// Set up an N-stage duplex pipeline running `executor` on every stage.
+async fn setup_pipeline(
+    num_stages: usize,
+    executor: StreamExecutor,
+) -> (
+    Orchestrator<tokio::io::DuplexStream>,
+    Vec<tokio::task::JoinHandle<()>>,
+) {
+    let manifest = make_test_manifest(num_stages);
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+
+    let mut orch_ctrls = Vec::new();
+    let mut stage_ctrls = Vec::new();
+    for _ in 0..num_stages {
+        let (orch_side, stage_side) = tokio::io::duplex(262144);
+        orch_ctrls.push(orch_side);
+        stage_ctrls.push(stage_side);
+    }
+
+    let (orch_data_in, mut next_data_in) = tokio::io::duplex(262144);
+    let mut handles = Vec::new();
+    for ctrl in stage_ctrls {
+        let (data_out, downstream_in) = tokio::io::duplex(262144);
+        let data_in = std::mem::replace(&mut next_data_in, downstream_in);
+        handles.push(tokio::spawn(async move {
+            let provider = MockProvider::new();
+            let verifier = MockVerifier::new();
+            let mut runtime = StageRuntime::new(executor, StageConfig::development());
+            let _ = runtime
+                .run(ctrl, data_in, data_out, &provider, &verifier)
+                .await;
+        }));
+    }
+    let orch_data_out = next_data_in;
+
+    let mut orch = Orchestrator::new(OrchestratorConfig::development(), manifest).unwrap();
+    orch.init(orch_ctrls, &provider, &verifier).await.unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+
+    (orch, handles)
+}
+
+fn micro_batches(prefix: &str, n: usize) -> Vec<Vec<OwnedTensor>> {
+    (0..n)
+        .map(|mb| vec![make_test_tensor(&format!("{prefix}_mb{mb}"))])
+        .collect()
+}
+
+/// Each micro-batch is yielded as soon as it leaves the last stage.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn stream_yields_micro_batches_as_they_arrive() {
+    let executor = StreamExecutor {
+        delay: Duration::from_millis(50),
+        fail_at: None,
+    };
+    let (mut orch, handles) = setup_pipeline(2, executor).await;
+
+    let start = Instant::now();
+    let mut stream = orch.infer_stream(micro_batches("s", 4), 16).await.unwrap();
+    assert_eq!(stream.num_micro_batches(), 4);
+
+    let mut arrivals = Vec::new();
+    while let Some(item) = stream.next().await {
+        let (mb, tensors) = item.unwrap();
+        assert_eq!(mb as usize, arrivals.len());
+        assert_eq!(tensors[0].name, format!("s_mb{mb}"));
+        arrivals.push(start.elapsed());
+    }
+    assert_eq!(arrivals.len(), 4);
+    assert!(stream.next().await.is_none());
+    drop(stream);
+
+    // The first micro-batch arrives well before the last one.
+    assert!(
+        arrivals[0] + Duration::from_millis(100) < arrivals[3],
+        "expected incremental delivery, got {arrivals:?}"
+    );
+    assert_eq!(orch.in_flight(), 0);
+
+    let r = orch.infer(micro_batches("after", 1), 16).await.unwrap();
+    assert_eq!(r.outputs[0][0].name, "after_mb0");
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// A stage failure mid-stream ends it with the control-channel error detail.
+#[tokio::test]
+async fn stream_failure_reports_request_failed() {
+    let executor = StreamExecutor {
+        delay: Duration::ZERO,
+        fail_at: Some(1),
+    };
+    let (mut orch, handles) = setup_pipeline(1, executor).await;
+
+    let mut stream = orch.infer_stream(micro_batches("f", 3), 16).await.unwrap();
+
+    let (mb, tensors) = stream.next().await.unwrap().unwrap();
+    assert_eq!(mb, 0);
+    assert_eq!(tensors[0].name, "f_mb0");
+
+    let err = stream.next().await.unwrap().unwrap_err();
+    match err {
+        PipelineError::RequestFailed { reason, .. } => {
+            assert!(
+                reason.contains("intentional test failure"),
+                "missing stage detail: {reason}"
+            );
+        }
+        other => panic!("expected RequestFailed, got {other:?}"),
+    }
+    assert!(stream.next().await.is_none());
+    drop(stream);
+    assert_eq!(orch.in_flight(), 0);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// Dropping a stream early leaves its outputs for the next call to discard.
+#[tokio::test]
+async fn dropped_stream_is_drained_by_next_call() {
+    let executor = StreamExecutor {
+        delay: Duration::ZERO,
+        fail_at: None,
+    };
+    let (mut orch, handles) = setup_pipeline(2, executor).await;
+
+    let mut stream = orch.infer_stream(micro_batches("d", 3), 16).await.unwrap();
+    let (mb, _) = stream.next().await.unwrap().unwrap();
+    assert_eq!(mb, 0);
+    drop(stream);
+    assert_eq!(orch.in_flight(), 1);
+
+    let r = orch.infer(micro_batches("next", 2), 16).await.unwrap();
+    assert_eq!(r.outputs.len(), 2);
+    assert_eq!(r.outputs[1][0].name, "next_mb1");
+    assert_eq!(orch.in_flight(), 0);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
+
+/// A `next` call that is cancelled while waiting leaves the stream usable:
+/// the next call yields the micro-batch the cancelled one was waiting for.
+#[tokio::test]
+async fn cancelled_next_resumes() {
+    let executor = StreamExecutor {
+        delay: Duration::from_millis(100),
+        fail_at: None,
+    };
+    let (mut orch, handles) = setup_pipeline(2, executor).await;
+
+    let mut stream = orch.infer_stream(micro_batches("c", 3), 16).await.unwrap();
+    let cancelled = tokio::time::timeout(Duration::from_millis(20), stream.next()).await;
+    assert!(cancelled.is_err(), "first micro-batch arrived too early");
+
+    for expected in 0..3 {
+        let (mb, tensors) = stream.next().await.unwrap().unwrap();
+        assert_eq!(mb, expected);
+        assert_eq!(tensors[0].name, format!("c_mb{mb}"));
+    }
+    assert!(stream.next().await.is_none());
+    drop(stream);
+    assert_eq!(orch.in_flight(), 0);
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap();
+    }
+}
@@ -39,6 +39,11 @@ fn make_test_manifest(num_stages: usize) -> ShardManifest {
             weight_hashes: vec![],
             require_weight_hashes: false,
             expected_measurements: BTreeMap::new(),
+            upstream: vec![],
+            downstream: vec![],
+            pass_through: vec![],
+            inputs: vec![],
+            outputs: vec![],
             endpoint: StageEndpoint {
                 control: PortSpec::Tcp {
                     addr: format!("127.0.0.1:{}", 9000 + i * 10),
@@ -57,6 +57,11 @@ fn make_manifest_with_addrs(
             weight_hashes: vec![],
             require_weight_hashes: false,
             expected_measurements: BTreeMap::new(),
+            upstream: vec![],
+            downstream: vec![],
+            pass_through: vec![],
+            inputs: vec![],
+            outputs: vec![],
             endpoint: StageEndpoint {
                 control: PortSpec::Tcp {
                     addr: ctrl.to_string(),
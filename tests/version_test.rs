@@ -0,0 +1,722 @@
+#![cfg(feature = "mock")]
+
+//! Tests for protocol version negotiation between old and new peers.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use confidential_ml_transport::{
+    DType, Message, MockProvider, MockVerifier, OwnedTensor, SecureChannel, SessionConfig,
+};
+use serde_json::{json, Value};
+use tokio::io::DuplexStream;
+
+use confidential_ml_pipeline::{
+    ErrorCode, ForwardOutput, Orchestrator, OrchestratorConfig, PipelineError, RequestId,
+    RequestOptions, StageConfig, StageError, StageExecutor, StageRuntime, StageSpec, VersionRange,
+    PROTOCOL_VERSION,
+};
+
+mod common;
+use common::make_test_manifest;
+
+/// Identity executor: passes input tensors through unchanged.
+struct IdentityExecutor;
+
+#[async_trait]
+impl StageExecutor for IdentityExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        _request_id: RequestId,
+        _micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+/// Identity executor that fails one micro-batch of one request.
+struct FailingExecutor {
+    request_id: RequestId,
+    micro_batch: u32,
+}
+
+#[async_trait]
+impl StageExecutor for FailingExecutor {
+    async fn init(&mut self, _stage_spec: &StageSpec) -> Result<(), StageError> {
+        Ok(())
+    }
+
+    async fn forward(
+        &self,
+        request_id: RequestId,
+        micro_batch: u32,
+        inputs: Vec<OwnedTensor>,
+    ) -> Result<ForwardOutput, StageError> {
+        if request_id == self.request_id && micro_batch == self.micro_batch {
+            return Err(StageError::ForwardFailed {
+                request_id,
+                micro_batch,
+                reason: "out of memory".into(),
+            });
+        }
+        Ok(ForwardOutput { tensors: inputs })
+    }
+}
+
+fn make_test_tensor() -> OwnedTensor {
+    OwnedTensor {
+        name: "input".to_string(),
+        dtype: DType::F32,
+        shape: vec![1, 4],
+        data: Bytes::from(vec![0u8; 16]),
+    }
+}
+
+/// Run one request through a 2-stage duplex pipeline whose stages accept
+/// `stage_versions[i]`, and return the versions the orchestrator agreed on.
+async fn run_pipeline(orch_versions: VersionRange, stage_versions: [VersionRange; 2]) -> Vec<u32> {
+    let verifier = MockVerifier::new();
+    let provider = MockProvider::new();
+
+    let mut orch_ctrls = Vec::new();
+    let mut stage_ctrls = Vec::new();
+    for _ in 0..2 {
+        let (orch_side, stage_side) = tokio::io::duplex(65536);
+        orch_ctrls.push(orch_side);
+        stage_ctrls.push(stage_side);
+    }
+
+    // Data links: orchestrator -> stage 0 -> stage 1 -> orchestrator.
+    let (orch_data_in, mut next_data_in) = tokio::io::duplex(65536);
+    let mut handles = Vec::new();
+    for (ctrl, protocol_versions) in stage_ctrls.into_iter().zip(stage_versions) {
+        let (data_out, downstream_in) = tokio::io::duplex(65536);
+        let data_in = std::mem::replace(&mut next_data_in, downstream_in);
+        handles.push(tokio::spawn(async move {
+            let provider = MockProvider::new();
+            let verifier = MockVerifier::new();
+            let config = StageConfig {
+                protocol_versions,
+                ..StageConfig::development()
+            };
+            let mut runtime = StageRuntime::new(IdentityExecutor, config);
+            runtime
+                .run(ctrl, data_in, data_out, &provider, &verifier)
+                .await
+        }));
+    }
+    let orch_data_out = next_data_in;
+
+    let config = OrchestratorConfig {
+        protocol_versions: orch_versions,
+        ..OrchestratorConfig::development()
+    };
+    let mut orch = Orchestrator::new(config, make_test_manifest(2)).unwrap();
+    orch.init(orch_ctrls, &provider, &verifier).await.unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+    let versions = orch.protocol_versions();
+
+    let result = orch
+        .infer(vec![vec![make_test_tensor()]], 16)
+        .await
+        .unwrap();
+    assert_eq!(result.outputs.len(), 1);
+    orch.health_check().await.unwrap();
+
+    orch.shutdown().await.unwrap();
+    for h in handles {
+        h.await.unwrap().unwrap();
+    }
+    versions
+}
+
+async fn recv_json(channel: &mut SecureChannel<DuplexStream>) -> Value {
+    match channel.recv().await.expect("recv failed") {
+        Message::Data(data) => serde_json::from_slice(&data).unwrap(),
+        other => panic!("expected Data, got {other:?}"),
+    }
+}
+
+async fn send_json(channel: &mut SecureChannel<DuplexStream>, value: Value) {
+    let data = serde_json::to_vec(&value).unwrap();
+    channel.send(Bytes::from(data)).await.expect("send failed");
+}
+
+/// Read a version 1 tensor group: tensors up to `END`, or `None` on `ERR`.
+async fn recv_v1_group(channel: &mut SecureChannel<DuplexStream>) -> Option<Vec<OwnedTensor>> {
+    let mut tensors = Vec::new();
+    loop {
+        match channel.recv().await.expect("recv failed") {
+            Message::Tensor(t) => tensors.push(t),
+            Message::Data(data) if &data[..] == b"END" => return Some(tensors),
+            Message::Data(data) if &data[..] == b"ERR" => return None,
+            other => panic!("expected tensors, END or ERR, got {other:?}"),
+        }
+    }
+}
+
+/// Send a version 1 tensor group: the tensors, then `END`.
+async fn send_v1_group(channel: &mut SecureChannel<DuplexStream>, tensors: &[OwnedTensor]) {
+    for t in tensors {
+        channel.send_tensor(t.as_ref()).await.expect("send failed");
+    }
+    channel
+        .send(Bytes::from_static(b"END"))
+        .await
+        .expect("send failed");
+}
+
+/// Two current peers agree on the newest version.
+#[tokio::test]
+async fn current_peers_use_newest_version() {
+    let versions = run_pipeline(
+        VersionRange::SUPPORTED,
+        [VersionRange::SUPPORTED, VersionRange::SUPPORTED],
+    )
+    .await;
+    assert_eq!(versions, vec![PROTOCOL_VERSION, PROTOCOL_VERSION]);
+}
+
+/// Each stage gets the newest version it speaks, so one pinned to version 1
+/// can run next to a current one.
+#[tokio::test]
+async fn stage_pinned_to_old_version() {
+    let versions = run_pipeline(
+        VersionRange::SUPPORTED,
+        [VersionRange::exactly(1), VersionRange::SUPPORTED],
+    )
+    .await;
+    assert_eq!(versions, vec![1, PROTOCOL_VERSION]);
+}
+
+/// An orchestrator pinned to version 1 drives current stages in version 1.
+#[tokio::test]
+async fn orchestrator_pinned_to_old_version() {
+    let versions = run_pipeline(
+        VersionRange::exactly(1),
+        [VersionRange::SUPPORTED, VersionRange::SUPPORTED],
+    )
+    .await;
+    assert_eq!(versions, vec![1, 1]);
+}
+
+/// A version 1 stage, which neither reads the offered range nor reports a
+/// version, is spoken to in version 1 throughout.
+#[tokio::test]
+async fn version_1_stage_with_current_orchestrator() {
+    let (orch_ctrl, stage_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage_data_in) = tokio::io::duplex(65536);
+    let (stage_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+    let old_stage = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        let mut control = SecureChannel::accept_with_attestation(
+            stage_ctrl,
+            &provider,
+            &verifier,
+            SessionConfig::development(),
+        )
+        .await
+        .expect("control handshake failed");
+
+        let init = recv_json(&mut control).await;
+        assert_eq!(init["version"], 1);
+        assert_eq!(init["msg"]["type"], "Init");
+        assert_eq!(
+            init["msg"]["protocol_versions"],
+            json!({ "min": 1, "max": PROTOCOL_VERSION })
+        );
+        send_json(
+            &mut control,
+            json!({ "version": 1, "msg": { "type": "Ready", "stage_idx": 0 } }),
+        )
+        .await;
+
+        let establish = recv_json(&mut control).await;
+        assert_eq!(establish["version"], 1);
+        assert_eq!(establish["msg"]["type"], "EstablishDataChannels");
+        let _data_in = SecureChannel::accept_with_attestation(
+            stage_data_in,
+            &provider,
+            &verifier,
+            SessionConfig::development(),
+        )
+        .await
+        .expect("data_in handshake failed");
+        let _data_out = SecureChannel::connect_with_attestation(
+            stage_data_out,
+            &provider,
+            &verifier,
+            SessionConfig::development(),
+        )
+        .await
+        .expect("data_out handshake failed");
+        send_json(
+            &mut control,
+            json!({ "version": 1, "msg": { "type": "DataChannelsReady", "stage_idx": 0 } }),
+        )
+        .await;
+
+        let ping = recv_json(&mut control).await;
+        assert_eq!(ping["version"], 1);
+        assert_eq!(ping["msg"]["type"], "Ping");
+        let seq = ping["msg"]["seq"].clone();
+        send_json(
+            &mut control,
+            json!({ "version": 1, "msg": { "type": "Pong", "seq": seq } }),
+        )
+        .await;
+
+        let shutdown = recv_json(&mut control).await;
+        assert_eq!(shutdown["version"], 1);
+        assert_eq!(shutdown["msg"]["type"], "Shutdown");
+        send_json(
+            &mut control,
+            json!({ "version": 1, "msg": { "type": "ShuttingDown", "stage_idx": 0 } }),
+        )
+        .await;
+    });
+
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+    let mut orch =
+        Orchestrator::new(OrchestratorConfig::development(), make_test_manifest(1)).unwrap();
+    orch.init(vec![orch_ctrl], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+    assert_eq!(orch.protocol_versions(), vec![1]);
+
+    orch.health_check().await.unwrap();
+    orch.shutdown().await.unwrap();
+    old_stage.await.unwrap();
+}
+
+/// A current stage answers a version 1 orchestrator, which sends no range,
+/// in version 1.
+#[tokio::test]
+async fn version_1_orchestrator_with_current_stage() {
+    let (orch_ctrl, stage_ctrl) = tokio::io::duplex(65536);
+
+    let stage = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        let mut runtime = StageRuntime::new(IdentityExecutor, StageConfig::development());
+        runtime
+            .run_control_phase(stage_ctrl, &provider, &verifier)
+            .await
+            .map(|_| ())
+    });
+
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+    let mut control = SecureChannel::connect_with_attestation(
+        orch_ctrl,
+        &provider,
+        &verifier,
+        SessionConfig::development(),
+    )
+    .await
+    .expect("control handshake failed");
+
+    let manifest = make_test_manifest(1);
+    let init = json!({
+        "version": 1,
+        "msg": {
+            "type": "Init",
+            "stage_spec_json": serde_json::to_string(&manifest.stages[0]).unwrap(),
+            "activation_spec_json": serde_json::to_string(&manifest.activation_spec).unwrap(),
+            "num_stages": 1
+        }
+    });
+    send_json(&mut control, init).await;
+
+    let ready = recv_json(&mut control).await;
+    assert_eq!(ready["version"], 1);
+    assert_eq!(ready["msg"]["type"], "Ready");
+    assert_eq!(ready["msg"]["stage_idx"], 0);
+
+    send_json(
+        &mut control,
+        json!({ "version": 1, "msg": { "type": "Ping", "seq": 5 } }),
+    )
+    .await;
+    let pong = recv_json(&mut control).await;
+    assert_eq!(pong["version"], 1);
+    assert_eq!(pong["msg"], json!({ "type": "Pong", "seq": 5 }));
+
+    send_json(
+        &mut control,
+        json!({
+            "version": 1,
+            "msg": { "type": "EstablishDataChannels", "has_upstream": false, "has_downstream": false }
+        }),
+    )
+    .await;
+    stage.await.unwrap().unwrap();
+}
+
+/// A current orchestrator runs requests on a version 1 stage in the
+/// original format: baseline control messages, `END`-terminated tensor
+/// groups, and a failure reported with a bare `ERR`. Version 2 features are
+/// refused before anything reaches the stage.
+#[tokio::test]
+async fn current_orchestrator_runs_requests_on_version_1_stage() {
+    let (orch_ctrl, stage_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage_data_in) = tokio::io::duplex(65536);
+    let (stage_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+    let old_stage = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        let mut control = SecureChannel::accept_with_attestation(
+            stage_ctrl,
+            &provider,
+            &verifier,
+            SessionConfig::development(),
+        )
+        .await
+        .expect("control handshake failed");
+
+        let init = recv_json(&mut control).await;
+        assert_eq!(init["msg"]["type"], "Init");
+        send_json(
+            &mut control,
+            json!({ "version": 1, "msg": { "type": "Ready", "stage_idx": 0 } }),
+        )
+        .await;
+
+        let establish = recv_json(&mut control).await;
+        assert_eq!(
+            establish,
+            json!({
+                "version": 1,
+                "msg": { "type": "EstablishDataChannels", "has_upstream": false, "has_downstream": false }
+            })
+        );
+        let mut data_in = SecureChannel::accept_with_attestation(
+            stage_data_in,
+            &provider,
+            &verifier,
+            SessionConfig::development(),
+        )
+        .await
+        .expect("data_in handshake failed");
+        let mut data_out = SecureChannel::connect_with_attestation(
+            stage_data_out,
+            &provider,
+            &verifier,
+            SessionConfig::development(),
+        )
+        .await
+        .expect("data_out handshake failed");
+        send_json(
+            &mut control,
+            json!({ "version": 1, "msg": { "type": "DataChannelsReady", "stage_idx": 0 } }),
+        )
+        .await;
+
+        // The first request succeeds; the second fails at its last
+        // micro-batch, so all of its input has been read.
+        for fail in [false, true] {
+            let start = recv_json(&mut control).await;
+            assert_eq!(start["version"], 1);
+            let request_id = start["msg"]["request_id"].clone();
+            assert_eq!(
+                start["msg"],
+                json!({
+                    "type": "StartRequest",
+                    "request_id": request_id,
+                    "num_micro_batches": 2,
+                    "seq_len": 16
+                })
+            );
+            for micro_batch in 0..2 {
+                let tensors = recv_v1_group(&mut data_in)
+                    .await
+                    .expect("input ended in ERR");
+                assert_eq!(tensors.len(), 1);
+                if fail && micro_batch == 1 {
+                    data_out
+                        .send(Bytes::from_static(b"ERR"))
+                        .await
+                        .expect("send failed");
+                } else {
+                    send_v1_group(&mut data_out, &tensors).await;
+                }
+            }
+            let reply = if fail {
+                json!({ "type": "RequestError", "request_id": request_id, "error": "out of memory" })
+            } else {
+                json!({ "type": "RequestDone", "request_id": request_id })
+            };
+            send_json(&mut control, json!({ "version": 1, "msg": reply })).await;
+        }
+
+        let shutdown = recv_json(&mut control).await;
+        assert_eq!(shutdown["msg"]["type"], "Shutdown");
+        send_json(
+            &mut control,
+            json!({ "version": 1, "msg": { "type": "ShuttingDown", "stage_idx": 0 } }),
+        )
+        .await;
+    });
+
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+    let mut orch =
+        Orchestrator::new(OrchestratorConfig::development(), make_test_manifest(1)).unwrap();
+    orch.init(vec![orch_ctrl], &provider, &verifier)
+        .await
+        .unwrap();
+    orch.establish_data_channels(orch_data_in, orch_data_out, vec![], &provider, &verifier)
+        .await
+        .unwrap();
+    assert_eq!(orch.max_in_flight(), 1);
+
+    let inputs = || vec![vec![make_test_tensor()], vec![make_test_tensor()]];
+    let result = orch.infer(inputs(), 16).await.unwrap();
+    assert_eq!(result.outputs.len(), 2);
+    assert!(result.outputs.iter().all(|mb| mb.len() == 1));
+
+    match orch.infer(inputs(), 16).await {
+        Err(PipelineError::RequestFailed { code, reason, .. }) => {
+            assert_eq!(code, ErrorCode::Internal);
+            assert!(reason.contains("out of memory"), "reason: {reason}");
+        }
+        other => panic!("expected RequestFailed, got {other:?}"),
+    }
+
+    let result = orch.open_session().await;
+    assert!(
+        matches!(result, Err(PipelineError::Protocol(_))),
+        "expected sessions to be refused, got {result:?}"
+    );
+    let options = RequestOptions::with_timeout(Duration::from_secs(5));
+    let result = orch.infer_with_options(inputs(), 16, options).await;
+    assert!(
+        matches!(result, Err(PipelineError::Protocol(_))),
+        "expected a deadline to be refused, got {result:?}"
+    );
+    assert!(!orch.is_tainted());
+
+    orch.shutdown().await.unwrap();
+    old_stage.await.unwrap();
+}
+
+/// A current stage serves a version 1 orchestrator in the original format,
+/// including a failed request, and refuses a version 2 field at version 1.
+#[tokio::test]
+async fn version_1_orchestrator_runs_requests_on_current_stage() {
+    let (orch_ctrl, stage_ctrl) = tokio::io::duplex(65536);
+    let (orch_data_in, stage_data_in) = tokio::io::duplex(65536);
+    let (stage_data_out, orch_data_out) = tokio::io::duplex(65536);
+
+    let stage = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        let executor = FailingExecutor {
+            request_id: 2,
+            micro_batch: 1,
+        };
+        let mut runtime = StageRuntime::new(executor, StageConfig::development());
+        runtime
+            .run(
+                stage_ctrl,
+                stage_data_in,
+                stage_data_out,
+                &provider,
+                &verifier,
+            )
+            .await
+    });
+
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+    let mut control = SecureChannel::connect_with_attestation(
+        orch_ctrl,
+        &provider,
+        &verifier,
+        SessionConfig::development(),
+    )
+    .await
+    .expect("control handshake failed");
+
+    let manifest = make_test_manifest(1);
+    send_json(
+        &mut control,
+        json!({
+            "version": 1,
+            "msg": {
+                "type": "Init",
+                "stage_spec_json": serde_json::to_string(&manifest.stages[0]).unwrap(),
+                "activation_spec_json": serde_json::to_string(&manifest.activation_spec).unwrap(),
+                "num_stages": 1
+            }
+        }),
+    )
+    .await;
+    let ready = recv_json(&mut control).await;
+    assert_eq!(
+        ready,
+        json!({ "version": 1, "msg": { "type": "Ready", "stage_idx": 0 } })
+    );
+
+    send_json(
+        &mut control,
+        json!({
+            "version": 1,
+            "msg": { "type": "EstablishDataChannels", "has_upstream": false, "has_downstream": false }
+        }),
+    )
+    .await;
+    let mut data_in = SecureChannel::connect_with_attestation(
+        orch_data_in,
+        &provider,
+        &verifier,
+        SessionConfig::development(),
+    )
+    .await
+    .expect("data_in handshake failed");
+    let mut data_out = SecureChannel::accept_with_attestation(
+        orch_data_out,
+        &provider,
+        &verifier,
+        SessionConfig::development(),
+    )
+    .await
+    .expect("data_out handshake failed");
+    let ready = recv_json(&mut control).await;
+    assert_eq!(
+        ready,
+        json!({ "version": 1, "msg": { "type": "DataChannelsReady", "stage_idx": 0 } })
+    );
+
+    for request_id in [1u64, 2] {
+        send_json(
+            &mut control,
+            json!({
+                "version": 1,
+                "msg": {
+                    "type": "StartRequest",
+                    "request_id": request_id,
+                    "num_micro_batches": 2,
+                    "seq_len": 16
+                }
+            }),
+        )
+        .await;
+        for _ in 0..2 {
+            send_v1_group(&mut data_in, &[make_test_tensor()]).await;
+        }
+
+        let first = recv_v1_group(&mut data_out)
+            .await
+            .expect("micro-batch 0 failed");
+        assert_eq!(first.len(), 1);
+        let second = recv_v1_group(&mut data_out).await;
+        let reply = recv_json(&mut control).await;
+        assert_eq!(reply["version"], 1);
+        if request_id == 1 {
+            assert_eq!(second.map(|tensors| tensors.len()), Some(1));
+            assert_eq!(
+                reply["msg"],
+                json!({ "type": "RequestDone", "request_id": 1 })
+            );
+        } else {
+            assert!(second.is_none(), "expected ERR for the failed micro-batch");
+            let msg = reply["msg"].as_object().unwrap();
+            assert_eq!(msg["type"], "RequestError");
+            assert_eq!(msg["request_id"], 2);
+            assert!(msg["error"].as_str().unwrap().contains("out of memory"));
+            assert_eq!(msg.len(), 3, "unexpected version 2 fields: {msg:?}");
+        }
+    }
+
+    // A deadline needs version 2.
+    send_json(
+        &mut control,
+        json!({
+            "version": 1,
+            "msg": {
+                "type": "StartRequest",
+                "request_id": 3,
+                "num_micro_batches": 1,
+                "seq_len": 16,
+                "time_budget_ms": 1000
+            }
+        }),
+    )
+    .await;
+    let result = stage.await.unwrap();
+    assert!(
+        matches!(result, Err(PipelineError::Protocol(_))),
+        "expected the stage to refuse a deadline, got {result:?}"
+    );
+}
+
+/// Ranges that do not overlap fail `Init` instead of guessing a version.
+#[tokio::test]
+async fn disjoint_ranges_are_rejected() {
+    let (orch_ctrl, stage_ctrl) = tokio::io::duplex(65536);
+
+    let stage = tokio::spawn(async move {
+        let provider = MockProvider::new();
+        let verifier = MockVerifier::new();
+        let config = StageConfig {
+            protocol_versions: VersionRange::exactly(2),
+            ..StageConfig::development()
+        };
+        let mut runtime = StageRuntime::new(IdentityExecutor, config);
+        runtime
+            .run_control_phase(stage_ctrl, &provider, &verifier)
+            .await
+            .map(|_| ())
+    });
+
+    let provider = MockProvider::new();
+    let verifier = MockVerifier::new();
+    let config = OrchestratorConfig {
+        protocol_versions: VersionRange::exactly(1),
+        ..OrchestratorConfig::development()
+    };
+    let mut orch = Orchestrator::new(config, make_test_manifest(1)).unwrap();
+    let result = orch.init(vec![orch_ctrl], &provider, &verifier).await;
+    assert!(result.is_err(), "init should fail, got {result:?}");
+
+    let result = stage.await.unwrap();
+    assert!(
+        matches!(
+            &result,
+            Err(PipelineError::NoCommonVersion { local, peer })
+                if *local == VersionRange::exactly(2) && *peer == VersionRange::exactly(1)
+        ),
+        "expected NoCommonVersion, got: {result:?}"
+    );
+}
+
+/// Configured ranges must lie within the versions this build speaks.
+#[test]
+fn config_rejects_unsupported_range() {
+    for protocol_versions in [
+        VersionRange::exactly(PROTOCOL_VERSION + 1),
+        VersionRange { min: 2, max: 1 },
+    ] {
+        let config = OrchestratorConfig {
+            protocol_versions,
+            ..OrchestratorConfig::development()
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -49,6 +49,11 @@ fn make_manifest_with_hashes(hashes: Vec<String>) -> ShardManifest {
             weight_hashes: hashes,
             require_weight_hashes: false,
             expected_measurements: BTreeMap::new(),
+            upstream: vec![],
+            downstream: vec![],
+            pass_through: vec![],
+            inputs: vec![],
+            outputs: vec![],
             endpoint: StageEndpoint {
                 control: PortSpec::Tcp {
                     addr: "127.0.0.1:9000".to_string(),